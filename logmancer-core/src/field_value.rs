@@ -0,0 +1,316 @@
+//! Comparison of parsed field values with the values conditions are written with.
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+use crate::models::visual_rules::FieldOperator;
+
+/// Layouts tried for timestamps without a format, after RFC 3339.
+const TIMESTAMP_LAYOUTS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// Applies `operator` to a field value and a condition value.
+pub(crate) fn field_matches(
+    operator: FieldOperator,
+    actual: &str,
+    expected: &str,
+    case_sensitive: bool,
+) -> bool {
+    match operator {
+        FieldOperator::Contains if case_sensitive => actual.contains(expected),
+        FieldOperator::Contains => actual.to_lowercase().contains(&expected.to_lowercase()),
+        _ => ordering_matches(
+            operator,
+            compare_field_values(actual, expected, case_sensitive),
+        ),
+    }
+}
+
+/// Whether `ordering`, of a value against another, satisfies a comparison `operator`;
+/// `Contains` never does.
+pub(crate) fn ordering_matches(operator: FieldOperator, ordering: Ordering) -> bool {
+    match operator {
+        FieldOperator::Eq => ordering.is_eq(),
+        FieldOperator::Ne => ordering.is_ne(),
+        FieldOperator::Lt => ordering.is_lt(),
+        FieldOperator::Le => ordering.is_le(),
+        FieldOperator::Gt => ordering.is_gt(),
+        FieldOperator::Ge => ordering.is_ge(),
+        FieldOperator::Contains => false,
+    }
+}
+
+/// Whether two values are both numbers, both log levels or both text, so that
+/// [`compare_field_values`] orders them by what they mean.
+pub(crate) fn same_kind(left: &str, right: &str) -> bool {
+    let kind = |text: &str| {
+        let text = text.trim();
+        if parse_quantity(text).is_some() {
+            0
+        } else if level_rank(text).is_some() {
+            1
+        } else {
+            2
+        }
+    };
+    kind(left) == kind(right)
+}
+
+/// Reads a timestamp with a strftime `format`, or as RFC 3339 or an ISO 8601 date and time
+/// without one. Timestamps with an offset are moved to UTC; others are taken as written.
+pub(crate) fn parse_timestamp(text: &str, format: Option<&str>) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    if let Some(format) = format {
+        return DateTime::parse_from_str(text, format)
+            .map(|timestamp| timestamp.naive_utc())
+            .or_else(|_| NaiveDateTime::parse_from_str(text, format))
+            .or_else(|_| {
+                NaiveDate::parse_from_str(text, format)
+                    .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+            })
+            .ok();
+    }
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(text) {
+        return Some(timestamp.naive_utc());
+    }
+    TIMESTAMP_LAYOUTS
+        .iter()
+        .find_map(|layout| NaiveDateTime::parse_from_str(text, layout).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })
+}
+
+/// Orders two values as numbers when both are numeric, as log levels when both name one,
+/// and as text otherwise.
+///
+/// Numbers may carry a duration unit, or several as in Go's `1m30s`; two durations compare by
+/// length, so `1.5s > 900ms`, while a bare number compares with the number of a duration as
+/// written.
+pub fn compare_field_values(left: &str, right: &str, case_sensitive: bool) -> Ordering {
+    let (left, right) = (left.trim(), right.trim());
+    if let (Some(left), Some(right)) = (parse_quantity(left), parse_quantity(right)) {
+        let (left, right) = match (left.nanos, right.nanos) {
+            (Some(left_nanos), Some(right_nanos)) => (left_nanos, right_nanos),
+            _ => (left.value, right.value),
+        };
+        return left.total_cmp(&right);
+    }
+    if let (Some(left), Some(right)) = (level_rank(left), level_rank(right)) {
+        return left.cmp(&right);
+    }
+    if case_sensitive {
+        left.cmp(right)
+    } else {
+        left.to_lowercase().cmp(&right.to_lowercase())
+    }
+}
+
+/// Splits a filter such as `>100ms` or `!=200` into its operator and value, `None` when it
+/// does not start with a comparison operator or has no value.
+pub(crate) fn parse_comparison(text: &str) -> Option<(FieldOperator, &str)> {
+    let text = text.trim_start();
+    let (operator, value) = [
+        (">=", FieldOperator::Ge),
+        ("<=", FieldOperator::Le),
+        ("!=", FieldOperator::Ne),
+        ("==", FieldOperator::Eq),
+        (">", FieldOperator::Gt),
+        ("<", FieldOperator::Lt),
+        ("=", FieldOperator::Eq),
+    ]
+    .into_iter()
+    .find_map(|(prefix, operator)| Some((operator, text.strip_prefix(prefix)?.trim())))?;
+    (!value.is_empty()).then_some((operator, value))
+}
+
+/// Value of a number or duration as one magnitude: a bare number as written and a duration in
+/// milliseconds, so `1.5s`, `1500ms` and `1500` are equal.
+///
+/// Units are whole numbers of nanoseconds, so a duration becomes milliseconds with a single
+/// rounding and `570ms` reads as exactly `570`.
+#[cfg(feature = "sql")]
+pub(crate) fn quantity_magnitude(text: &str) -> Option<f64> {
+    let quantity = parse_quantity(text.trim())?;
+    Some(match quantity.nanos {
+        Some(nanos) => nanos / NANOS_PER_MILLI,
+        None => quantity.value,
+    })
+}
+
+#[cfg(feature = "sql")]
+const NANOS_PER_MILLI: f64 = 1e6;
+
+/// Nanoseconds per duration unit.
+const DURATION_UNITS: [(&str, f64); 8] = [
+    ("ns", 1.0),
+    ("us", 1e3),
+    ("µs", 1e3),
+    ("ms", 1e6),
+    ("s", 1e9),
+    ("m", 60e9),
+    ("min", 60e9),
+    ("h", 3600e9),
+];
+
+struct Quantity {
+    /// The number as written; for a compound duration such as `1m30s`, its length in the last
+    /// unit.
+    value: f64,
+    /// Length in nanoseconds, for values written with duration units.
+    nanos: Option<f64>,
+}
+
+/// Reads a number, a number with a duration unit such as `250ms`, or a Go compound duration
+/// such as `1h0m0s` or `-2.5h10m`, whose sign applies to the whole.
+fn parse_quantity(text: &str) -> Option<Quantity> {
+    let is_unit = |character: char| character.is_alphabetic() || character == 'µ';
+    let finite = |number: &str| number.parse::<f64>().ok().filter(|value| value.is_finite());
+    let unit_start = text.find(is_unit).unwrap_or(text.len());
+    let (number, mut rest) = text.split_at(unit_start);
+    let mut value = finite(number.trim_end())?;
+    if rest.is_empty() {
+        return Some(Quantity { value, nanos: None });
+    }
+    let sign = if value.is_sign_negative() { -1.0 } else { 1.0 };
+    let mut nanos = 0.0;
+    let mut parts = 0;
+    loop {
+        let unit_end = rest
+            .find(|character: char| !is_unit(character))
+            .unwrap_or(rest.len());
+        let unit_nanos = DURATION_UNITS
+            .iter()
+            .find(|(name, _)| *name == &rest[..unit_end])?
+            .1;
+        nanos += sign * value.abs() * unit_nanos;
+        parts += 1;
+        rest = &rest[unit_end..];
+        if rest.is_empty() {
+            let value = if parts == 1 {
+                value
+            } else {
+                nanos / unit_nanos
+            };
+            return Some(Quantity {
+                value,
+                nanos: Some(nanos),
+            });
+        }
+        // Later parts are unsigned numbers, such as the `30` of `1m30s`.
+        if !rest.starts_with(|character: char| character.is_ascii_digit() || character == '.') {
+            return None;
+        }
+        let number_end = rest.find(is_unit)?;
+        value = finite(&rest[..number_end])?;
+        rest = &rest[number_end..];
+    }
+}
+
+fn level_rank(text: &str) -> Option<u8> {
+    Some(match text.to_ascii_uppercase().as_str() {
+        "TRACE" | "FINEST" | "FINER" => 0,
+        "DEBUG" | "FINE" => 1,
+        "INFO" | "NOTICE" => 2,
+        "WARN" | "WARNING" => 3,
+        "ERROR" | "ERR" | "SEVERE" => 4,
+        "CRITICAL" | "CRIT" | "FATAL" | "ALERT" | "EMERG" | "PANIC" => 5,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_compare_as_durations_numbers_levels_then_text() {
+        assert!(field_matches(FieldOperator::Gt, "1.5s", "900ms", true));
+        assert!(field_matches(FieldOperator::Gt, "750ms", "500", true));
+        assert!(field_matches(FieldOperator::Lt, "9", "10", true));
+        assert!(field_matches(FieldOperator::Ge, "error", "WARN", true));
+        assert!(!field_matches(FieldOperator::Ge, "INFO", "warning", true));
+        assert!(field_matches(FieldOperator::Eq, "Alice", "alice", false));
+        assert!(!field_matches(FieldOperator::Eq, "Alice", "alice", true));
+        assert!(field_matches(
+            FieldOperator::Contains,
+            "GET /api",
+            "/API",
+            false
+        ));
+        assert!(field_matches(FieldOperator::Ne, "10ms", "abc", true));
+        assert!(field_matches(FieldOperator::Eq, "1m30s", "90s", true));
+        assert!(field_matches(FieldOperator::Eq, "1h0m0s", "60m", true));
+        assert!(field_matches(FieldOperator::Eq, "2.5h10m", "160m", true));
+        assert!(field_matches(FieldOperator::Lt, "-1m30s", "-1m", true));
+        assert!(field_matches(FieldOperator::Gt, "1m30s", "89", true));
+        assert!(same_kind("1m30s", "250ms"));
+        assert!(!same_kind("1m30", "250ms"));
+        assert!(!same_kind("1m-30s", "250ms"));
+    }
+
+    #[cfg(feature = "sql")]
+    #[test]
+    fn durations_read_as_exact_milliseconds() {
+        assert_eq!(quantity_magnitude("570ms"), Some(570.0));
+        assert_eq!(quantity_magnitude("1.1s"), Some(1100.0));
+        assert_eq!(quantity_magnitude("570us"), Some(0.57));
+        assert_eq!(quantity_magnitude("2m"), Some(120_000.0));
+        assert_eq!(quantity_magnitude("1m30s"), Some(90_000.0));
+        assert_eq!(quantity_magnitude("1h0m0s"), Some(3_600_000.0));
+        assert_eq!(quantity_magnitude("2.5h10m"), Some(9_600_000.0));
+        assert_eq!(quantity_magnitude("-1m30.5s"), Some(-90_500.0));
+        assert_eq!(quantity_magnitude("1m30"), None);
+        assert_eq!(quantity_magnitude("42"), Some(42.0));
+        assert_eq!(quantity_magnitude("5 parsecs"), None);
+    }
+
+    #[test]
+    fn values_of_one_kind_and_timestamps_are_recognized() {
+        assert!(same_kind("1.5s", "900"));
+        assert!(same_kind("warn", "ERROR"));
+        assert!(same_kind("billing", "api"));
+        assert!(!same_kind("billing", "250ms"));
+        assert!(!same_kind("info", "3"));
+
+        let utc = |text| parse_timestamp(text, None).map(|timestamp| timestamp.to_string());
+        assert_eq!(
+            utc("2026-10-03T12:00:00+02:00").as_deref(),
+            Some("2026-10-03 10:00:00")
+        );
+        assert_eq!(
+            utc("2026-10-03 10:00:00.250").as_deref(),
+            Some("2026-10-03 10:00:00.250")
+        );
+        assert_eq!(utc("2026-10-03").as_deref(), Some("2026-10-03 00:00:00"));
+        assert_eq!(utc("10:00:00"), None);
+        assert_eq!(
+            parse_timestamp("03/Oct/2026:12:00:00 +0200", Some("%d/%b/%Y:%H:%M:%S %z"))
+                .map(|timestamp| timestamp.to_string())
+                .as_deref(),
+            Some("2026-10-03 10:00:00")
+        );
+    }
+
+    #[test]
+    fn comparison_filters_split_into_operator_and_value() {
+        assert_eq!(
+            parse_comparison(">100ms"),
+            Some((FieldOperator::Gt, "100ms"))
+        );
+        assert_eq!(
+            parse_comparison(" >= 1.5s"),
+            Some((FieldOperator::Ge, "1.5s"))
+        );
+        assert_eq!(parse_comparison("!=200"), Some((FieldOperator::Ne, "200")));
+        assert_eq!(
+            parse_comparison("==warn"),
+            Some((FieldOperator::Eq, "warn"))
+        );
+        assert_eq!(parse_comparison("=warn"), Some((FieldOperator::Eq, "warn")));
+        assert_eq!(parse_comparison(">"), None);
+        assert_eq!(parse_comparison("timeout"), None);
+    }
+}
@@ -1,7 +1,16 @@
+use crate::log_format::{LogFormat, MAX_RECORD_LINES};
+use crate::models::density::{DENSITY_RULE_SAMPLE_LINES, DensityMap};
 use crate::models::log_file::LogFile;
-use crate::models::search::{PageSearchResult, SearchMatch, SearchStatus};
-use regex::Regex;
-use std::collections::HashSet;
+use crate::models::marks::{MarkStatus, PageMarkResult};
+use crate::models::match_index::MatchIndex;
+use crate::models::quote_checkpoints::QuoteCheckpoints;
+use crate::models::rule_counts::RuleCountStatus;
+use crate::models::search::{
+    MAX_SEARCH_HITS_PAGE, PageSearchResult, SearchHit, SearchHitsPage, SearchMatch, SearchPhase,
+    SearchStatus,
+};
+use crate::search_matcher::SearchMatcher;
+use crate::visual_rules::{VisualLine, VisualRuleEvaluator};
 use std::io;
 use std::sync::RwLockReadGuard;
 
@@ -16,46 +25,130 @@ impl<'a> FileReadOps<'a> {
         FileReadOps { log_file }
     }
 
+    pub(crate) fn log_file(&self) -> &LogFile {
+        &self.log_file
+    }
+
     pub fn file_path(&self) -> String {
         self.log_file.path.clone()
     }
 
     /// Reads the line number `line_number` from the file.
     pub fn read_line(&self, line_number: usize) -> io::Result<String> {
-        if line_number >= self.log_file.index.len() {
+        Self::line_text(&self.log_file, line_number)
+    }
+
+    /// Text of line `line_number` as pages show it, for callers already holding the file.
+    pub fn line_text(log_file: &LogFile, line_number: usize) -> io::Result<String> {
+        if line_number >= log_file.index.len() {
             return Err(io::Error::new(
                 io::ErrorKind::UnexpectedEof,
                 "Unexpected end of file",
             ));
         }
 
-        let start_pos = self.log_file.index[line_number];
-        let line_size = if line_number + 1 == self.log_file.index.len() {
-            self.log_file.mmap.len() // Last line
+        let start_pos = log_file.index[line_number];
+        let line_size = if line_number + 1 == log_file.index.len() {
+            log_file.mmap.len() // Last line
         } else {
-            self.log_file.index[line_number + 1]
+            log_file.index[line_number + 1]
         }
         .saturating_sub(start_pos);
         let end_pos = line_size
             .min(LINE_MAX_BYTES) // Max line size validation
             .saturating_add(start_pos) // End position
-            .min(self.log_file.mmap.len()); // Max file size validation
+            .min(log_file.mmap.len()); // Max file size validation
 
-        let bytes = &self.log_file.mmap[start_pos..end_pos];
+        let bytes = &log_file.mmap[start_pos..end_pos];
 
         Ok(String::from_utf8_lossy(bytes).trim_end().to_owned())
     }
 
-    pub fn read_filter_line(&self, line_number: usize) -> io::Result<Option<String>> {
-        if line_number >= self.log_file.filter.len() {
-            return Ok(None);
-        }
+    /// Whether the filter has processed `line_number` and kept it.
+    pub fn is_filtered_line(&self, line_number: usize) -> bool {
+        self.log_file
+            .filter
+            .get(line_number)
+            .is_some_and(|matched| *matched)
+    }
 
-        if self.log_file.filter[line_number] {
-            Ok(Some(self.read_line(line_number)?))
-        } else {
-            Ok(None)
+    /// Whether a record of the delimited `format` is inside a quoted value after lines
+    /// `from_line..to_line`, given whether it was before them. Lines are read whole, unlike
+    /// line texts, which are cut at the maximum line size.
+    pub fn quotes_open_after(
+        log_file: &LogFile,
+        format: &LogFormat,
+        from_line: usize,
+        to_line: usize,
+        in_quotes: bool,
+    ) -> bool {
+        let end = log_file.index.len().min(to_line);
+        (from_line..end).fold(in_quotes, |in_quotes, line| {
+            let start_pos = log_file.index[line].min(log_file.mmap.len());
+            let end_pos = log_file
+                .index
+                .get(line + 1)
+                .copied()
+                .unwrap_or(log_file.mmap.len())
+                .min(log_file.mmap.len());
+            format.ends_inside_quotes(&log_file.mmap[start_pos..end_pos], in_quotes)
+        })
+    }
+
+    /// Fields of line `line_number`, whose text is `text`, as pages parse them with `format`;
+    /// none without a format.
+    pub fn line_fields(
+        log_file: &LogFile,
+        format: Option<&LogFormat>,
+        quote_checkpoints: &mut QuoteCheckpoints,
+        line_number: usize,
+        text: &str,
+    ) -> Vec<(String, String)> {
+        let Some(format) = format else {
+            return Vec::new();
+        };
+        let in_quotes = format.has_header()
+            && quote_checkpoints.opens_inside_quotes(log_file, format, line_number);
+        Self::record_fields(log_file, format, line_number, text, in_quotes).0
+    }
+
+    /// Fields of line `line_number`, whose text is `text`, parsed with `format`, and whether
+    /// the line ends inside a quoted value of a delimited record. A delimited record that
+    /// opens a quoted value is joined with the lines below it and parsed whole; the header
+    /// row and a line `in_quotes`, which continues a record above it, have no fields.
+    pub fn record_fields(
+        log_file: &LogFile,
+        format: &LogFormat,
+        line_number: usize,
+        text: &str,
+        in_quotes: bool,
+    ) -> (Vec<(String, String)>, bool) {
+        if !format.has_header() {
+            return (format.parse(text).unwrap_or_default(), false);
+        }
+        let ends_in_quotes = |line: usize, in_quotes: bool| {
+            Self::quotes_open_after(log_file, format, line, line + 1, in_quotes)
+        };
+        let ends_inside = ends_in_quotes(line_number, in_quotes);
+        if line_number == 0 || in_quotes {
+            return (Vec::new(), ends_inside);
         }
+        if !ends_inside {
+            return (format.parse(text).unwrap_or_default(), false);
+        }
+        let mut record = text.to_string();
+        let last_line = log_file.index.len().min(line_number + MAX_RECORD_LINES);
+        for next_line in line_number + 1..last_line {
+            let Ok(next_text) = Self::line_text(log_file, next_line) else {
+                break;
+            };
+            record.push('\n');
+            record.push_str(&next_text);
+            if !ends_in_quotes(next_line, true) {
+                break;
+            }
+        }
+        (format.parse(&record).unwrap_or_default(), true)
     }
 
     /// Returns the total number of lines indexed.
@@ -104,69 +197,201 @@ impl<'a> FileReadOps<'a> {
 
     pub fn page_search_result(&self, from_line: usize, to_line: usize) -> Option<PageSearchResult> {
         let session = self.log_file.search.session.as_ref()?;
-        let page_matches = session
-            .matches
-            .iter()
-            .filter(|m| m.line_index >= from_line && m.line_index < to_line)
-            .cloned()
-            .collect::<Vec<SearchMatch>>();
-
-        Some(PageSearchResult {
-            query: session.query.clone(),
-            total_matches: session.matches.len(),
-            total_matches_final: session.total_matches_final,
-            is_indexing: !matches!(session.phase, crate::models::search::SearchPhase::Ready),
-            first: session.first_match.clone(),
-            current: session.current_match().cloned(),
-            page_matches,
-        })
+        Some(session.page_result(&self.log_file, from_line..to_line))
     }
 
     pub fn page_search_result_for_lines(&self, line_indexes: &[usize]) -> Option<PageSearchResult> {
         let session = self.log_file.search.session.as_ref()?;
-        let visible_lines = line_indexes.iter().copied().collect::<HashSet<_>>();
-        let page_matches = session
-            .matches
-            .iter()
-            .filter(|m| visible_lines.contains(&m.line_index))
-            .cloned()
-            .collect::<Vec<SearchMatch>>();
+        Some(session.page_result(&self.log_file, line_indexes.iter().copied()))
+    }
 
-        Some(PageSearchResult {
+    /// Returns up to `limit` hits of the active search starting at `offset`, in file order.
+    pub fn search_hits(&self, offset: usize, limit: usize) -> io::Result<Option<SearchHitsPage>> {
+        let Some(session) = self.log_file.search.session.as_ref() else {
+            return Ok(None);
+        };
+        let matches = session.matches_from(&self.log_file, offset, limit.min(MAX_SEARCH_HITS_PAGE));
+        let mut hits = Vec::with_capacity(matches.len());
+        for search_match in matches {
+            hits.push(SearchHit {
+                line_number: search_match.line_index + 1,
+                text: self.read_line(search_match.line_index)?,
+                search_match,
+            });
+        }
+        Ok(Some(SearchHitsPage {
             query: session.query.clone(),
-            total_matches: session.matches.len(),
+            offset,
+            hits,
+            total_matches: session.index.total(),
             total_matches_final: session.total_matches_final,
-            is_indexing: !matches!(session.phase, crate::models::search::SearchPhase::Ready),
-            first: session.first_match.clone(),
-            current: session.current_match().cloned(),
-            page_matches,
-        })
+            is_indexing: !matches!(session.phase, SearchPhase::Ready),
+        }))
     }
 
-    pub fn compute_search_batch(
+    /// Counts search matches, filter matches and visually styled lines per bucket of the file.
+    pub fn density_map(
+        &self,
+        buckets: usize,
+        evaluator: &VisualRuleEvaluator,
+        format: Option<&LogFormat>,
+        quote_checkpoints: &mut QuoteCheckpoints,
+    ) -> io::Result<DensityMap> {
+        let total_lines = self.total_lines()?;
+        let rule_styles = evaluator.styles();
+        let mut map = DensityMap {
+            total_lines,
+            buckets: DensityMap::empty_buckets(total_lines, buckets, rule_styles.len()),
+            rule_styles,
+            search_active: self.log_file.search.session.is_some(),
+            filter_active: self.log_file.line_filter.is_some(),
+            rule_lines_sampled: false,
+            is_indexing: self.indexing_progress()? < 1.0 || !self.log_file.search.status().is_ready,
+        };
+        let bucket_count = map.buckets.len();
+        if bucket_count == 0 {
+            return Ok(map);
+        }
+
+        if let Some(session) = &self.log_file.search.session {
+            add_search_density(&mut map, &session.index);
+        }
+        if map.filter_active {
+            map.is_indexing |= self.filter_indexing_progress()? < 1.0;
+            for (line, matched) in self.log_file.filter.iter().enumerate() {
+                if *matched {
+                    let bucket = DensityMap::bucket_of(line, total_lines, bucket_count);
+                    map.buckets[bucket].filter_matches += 1;
+                }
+            }
+        }
+
+        if !map.rule_styles.is_empty() {
+            let stride = total_lines.div_ceil(DENSITY_RULE_SAMPLE_LINES).max(1);
+            map.rule_lines_sampled = stride > 1;
+            for line in (0..total_lines).step_by(stride) {
+                let text = self.read_line(line)?;
+                let fields =
+                    Self::line_fields(&self.log_file, format, quote_checkpoints, line, &text);
+                let visual_line = VisualLine {
+                    text: &text,
+                    number: Some(line + 1),
+                    fields: &fields,
+                };
+                if let Some(rule) = evaluator.matching_rule(visual_line) {
+                    let bucket = DensityMap::bucket_of(line, total_lines, bucket_count);
+                    map.buckets[bucket].rule_lines[rule] += stride;
+                }
+            }
+            if map.rule_lines_sampled {
+                for bucket in &mut map.buckets {
+                    let size = bucket.end_line - bucket.start_line;
+                    for count in &mut bucket.rule_lines {
+                        *count = (*count).min(size);
+                    }
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn mark_statuses(&self) -> Vec<MarkStatus> {
+        self.log_file
+            .marks
+            .marks
+            .iter()
+            .map(|mark| mark.status())
+            .collect()
+    }
+
+    pub fn page_mark_results(&self, from_line: usize, to_line: usize) -> Vec<PageMarkResult> {
+        self.log_file
+            .marks
+            .marks
+            .iter()
+            .map(|mark| mark.page_result(&self.log_file, from_line..to_line))
+            .collect()
+    }
+
+    pub fn page_mark_results_for_lines(&self, line_indexes: &[usize]) -> Vec<PageMarkResult> {
+        if self.log_file.marks.marks.is_empty() {
+            return Vec::new();
+        }
+        self.log_file
+            .marks
+            .marks
+            .iter()
+            .map(|mark| mark.page_result(&self.log_file, line_indexes.iter().copied()))
+            .collect()
+    }
+
+    pub fn rule_count_status(&self) -> Option<RuleCountStatus> {
+        self.log_file.rule_counts.status()
+    }
+
+    /// Lines of `start_line..end_line` matched by each evaluator rule, indexed like the rules,
+    /// with fields parsed by `format` as pages parse them.
+    pub fn compute_rule_count_batch(
         log_file: &LogFile,
-        query: &str,
+        evaluator: &VisualRuleEvaluator,
+        format: Option<&LogFormat>,
+        quote_checkpoints: &mut QuoteCheckpoints,
         start_line: usize,
         end_line: usize,
-    ) -> io::Result<Vec<SearchMatch>> {
-        let re = Regex::new(query).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
-        let mut batch = Vec::new();
-        for i in start_line..end_line {
-            let start_pos = log_file.index[i];
-            let end_pos = log_file.index[i + 1];
-            let line = &log_file.mmap[start_pos..end_pos];
-            if let Ok(text) = std::str::from_utf8(line) {
-                for found in re.find_iter(text) {
-                    batch.push(SearchMatch {
-                        line_index: i,
-                        start: found.start(),
-                        end: found.end(),
-                        ordinal: 0,
-                    });
-                }
+    ) -> Vec<Vec<usize>> {
+        let mut batch = vec![Vec::new(); evaluator.rule_count()];
+        for line in start_line..end_line {
+            let Ok(text) = Self::line_text(log_file, line) else {
+                break;
+            };
+            let fields = Self::line_fields(log_file, format, quote_checkpoints, line, &text);
+            let matching = evaluator.matching_rules(VisualLine {
+                text: &text,
+                number: Some(line + 1),
+                fields: &fields,
+            });
+            for (lines, _) in batch.iter_mut().zip(matching).filter(|(_, holds)| *holds) {
+                lines.push(line);
             }
         }
-        Ok(batch)
+        batch
+    }
+
+    pub fn compute_search_batch(
+        log_file: &LogFile,
+        matcher: &SearchMatcher,
+        start_line: usize,
+        end_line: usize,
+    ) -> Vec<SearchMatch> {
+        matcher.find_in_lines(log_file, start_line, end_line)
+    }
+}
+
+/// Adds the search matches per bucket. Once the index only counts blocks, each block count
+/// is spread over the buckets it overlaps in proportion to the overlap.
+fn add_search_density(map: &mut DensityMap, index: &MatchIndex) {
+    let total_lines = map.total_lines;
+    let bucket_count = map.buckets.len();
+    if let Some(line_counts) = index.line_counts() {
+        for (line, count) in line_counts {
+            let bucket = DensityMap::bucket_of(line, total_lines, bucket_count);
+            map.buckets[bucket].search_matches += count;
+        }
+        return;
+    }
+
+    for (range, count) in index.block_counts() {
+        let end = range.end.min(total_lines);
+        if range.start >= end {
+            continue;
+        }
+        let lines = end - range.start;
+        let first = DensityMap::bucket_of(range.start, total_lines, bucket_count);
+        let last = DensityMap::bucket_of(end - 1, total_lines, bucket_count);
+        for bucket in &mut map.buckets[first..=last] {
+            let overlap = bucket.end_line.min(end) - bucket.start_line.max(range.start);
+            bucket.search_matches += (count * overlap).div_ceil(lines);
+        }
     }
 }
 
@@ -219,7 +444,12 @@ mod tests {
 
         let shared = write_ops.log_file();
         let read_guard = shared.read().unwrap();
-        let batch = FileReadOps::compute_search_batch(&read_guard, "foo", 0, 3).unwrap();
+        let batch = FileReadOps::compute_search_batch(
+            &read_guard,
+            &SearchMatcher::new("foo").unwrap(),
+            0,
+            3,
+        );
 
         assert_eq!(batch.len(), 3);
         assert_eq!(batch[0].line_index, 0);
@@ -230,7 +460,7 @@ mod tests {
     }
 
     #[test]
-    fn read_filter_line_returns_none_for_unprocessed_filter_bounds() {
+    fn lines_past_the_processed_filter_bounds_are_not_filtered() {
         let path = temp_file_path("read-filter-line-unprocessed-bounds");
         let mut file = File::create(&path).unwrap();
         writeln!(file, "alpha").unwrap();
@@ -247,8 +477,8 @@ mod tests {
 
         assert_eq!(read_ops.total_lines().unwrap(), 2);
         assert_eq!(read_ops.processed_filter_lines().unwrap(), 0);
-        assert_eq!(read_ops.read_filter_line(0).unwrap(), None);
-        assert_eq!(read_ops.read_filter_line(2).unwrap(), None);
+        assert!(!read_ops.is_filtered_line(0));
+        assert!(!read_ops.is_filtered_line(2));
 
         std::fs::remove_file(path).unwrap();
     }
@@ -298,4 +528,55 @@ mod tests {
 
         std::fs::remove_file(path).unwrap();
     }
+
+    #[test]
+    fn search_hits_reflect_partial_results_while_indexing() {
+        let path = temp_file_path("search-hits-indexing");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "foo").unwrap();
+        writeln!(file, "bar foo").unwrap();
+        drop(file);
+
+        let log_file = std::sync::Arc::new(RwLock::new(
+            LogFile::new(path.to_string_lossy().into_owned()).unwrap(),
+        ));
+        let mut write_ops = FileWriteOps::new(std::sync::Arc::clone(&log_file));
+        while !write_ops.index_lines().unwrap() {}
+
+        write_ops.begin_search(1, "foo".to_string(), 1);
+        let origin_batch = {
+            let file_lock = log_file.read().unwrap();
+            FileReadOps::compute_search_batch(&file_lock, &SearchMatcher::new("foo").unwrap(), 1, 2)
+        };
+        write_ops.merge_search_batch(1, origin_batch, false);
+
+        let page = FileReadOps::new(log_file.read().unwrap())
+            .search_hits(0, 10)
+            .unwrap()
+            .unwrap();
+        assert!(page.is_indexing);
+        assert_eq!(page.total_matches, 1);
+        assert_eq!(page.hits[0].line_number, 2);
+        assert_eq!(page.hits[0].text, "bar foo");
+
+        let wrapped_batch = {
+            let file_lock = log_file.read().unwrap();
+            FileReadOps::compute_search_batch(&file_lock, &SearchMatcher::new("foo").unwrap(), 0, 1)
+        };
+        write_ops.merge_search_batch(1, wrapped_batch, true);
+        let page = FileReadOps::new(log_file.read().unwrap())
+            .search_hits(0, 10)
+            .unwrap()
+            .unwrap();
+        assert!(!page.is_indexing);
+        assert_eq!(
+            page.hits
+                .iter()
+                .map(|hit| (hit.line_number, hit.search_match.ordinal))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (2, 1)]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
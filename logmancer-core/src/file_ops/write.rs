@@ -1,5 +1,10 @@
-use crate::models::log_file::LogFile;
-use crate::models::search::{SearchMatch, SearchPhase, SearchSession};
+use crate::log_format::{LogFormat, MAX_RECORD_LINES};
+use crate::models::log_file::{LineFilter, LogFile};
+use crate::models::marks::MarkSession;
+use crate::models::rule_counts::{RuleCountScan, RuleCountSession};
+use crate::models::search::{SearchMatch, SearchScan, SearchSession};
+use crate::models::visual_rules::VisualColor;
+use crate::visual_rules::VisualRuleEvaluator;
 use memmap2::Mmap;
 use regex::Regex;
 use std::cmp::min;
@@ -62,12 +67,13 @@ impl FileWriteOps {
         }
     }
 
-    /// Sets the regex pattern and resets the filter index if a pattern is provided.
-    pub fn filter(&mut self, pattern: Option<String>) -> io::Result<()> {
-        if let Some(pat) = pattern {
+    /// Sets the line filter and resets the filter index if a filter is provided.
+    pub fn filter(&mut self, line_filter: Option<LineFilter>) -> io::Result<()> {
+        if let Some(line_filter) = line_filter {
             let mut file_lock = self.log_file.write().unwrap();
-            file_lock.regex = Some(pat);
+            file_lock.line_filter = Some(line_filter);
             file_lock.filter.clear();
+            file_lock.filter_record = None;
         }
         Ok(())
     }
@@ -75,22 +81,62 @@ impl FileWriteOps {
     /// Indexes filtered lines up to a maximun of INDEX_MAX_LINES lines. Returns false unless the end of the file is reached.
     pub fn index_filter(&mut self) -> io::Result<bool> {
         let file_lock = self.log_file.read().unwrap();
-        let pattern = match &file_lock.regex {
-            Some(pat) => pat.clone(),
+        let line_filter = match &file_lock.line_filter {
+            Some(line_filter) => line_filter.clone(),
             None => return Ok(true),
         };
-        let re =
-            Regex::new(&pattern).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let re = if line_filter.uses_regex() {
+            Some(
+                Regex::new(&line_filter.pattern)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+            )
+        } else {
+            None
+        };
 
         let start_line = file_lock.filter.len();
         let total_lines = file_lock.index.len();
         let end_line = min(total_lines.saturating_sub(1), start_line + INDEX_MAX_LINES);
+        let line = |i: usize| &file_lock.mmap[file_lock.index[i]..file_lock.index[i + 1]];
+        let matches = |text: &[u8]| {
+            std::str::from_utf8(text).is_ok_and(|text| line_filter.matches(text, re.as_ref()))
+        };
+        let mut record = file_lock.filter_record;
         let mut batch = Vec::with_capacity(end_line.saturating_sub(start_line));
         for i in start_line..end_line {
-            let start_pos = file_lock.index[i];
-            let end_pos = file_lock.index[i + 1];
-            let line = &file_lock.mmap[start_pos..end_pos];
-            let match_filter = std::str::from_utf8(line).is_ok_and(|text| re.is_match(text));
+            let match_filter = match line_filter.record_format() {
+                None => matches(line(i)),
+                Some(_) if i == 0 => false,
+                // A record is kept or left out whole, on its first line and the lines its
+                // quoted value continues on.
+                Some(format) => {
+                    let ends_in_quotes =
+                        |i: usize, in_quotes: bool| format.ends_inside_quotes(line(i), in_quotes);
+                    match record {
+                        Some(kept) => {
+                            if !ends_in_quotes(i, true) {
+                                record = None;
+                            }
+                            kept
+                        }
+                        None if ends_in_quotes(i, false) => {
+                            let last_line = total_lines.saturating_sub(1).min(i + MAX_RECORD_LINES);
+                            let mut text = line(i).trim_ascii_end().to_vec();
+                            for next_line in i + 1..last_line {
+                                text.push(b'\n');
+                                text.extend_from_slice(line(next_line).trim_ascii_end());
+                                if !ends_in_quotes(next_line, true) {
+                                    break;
+                                }
+                            }
+                            let kept = matches(&text);
+                            record = Some(kept);
+                            kept
+                        }
+                        None => matches(line(i)),
+                    }
+                }
+            };
             batch.push(match_filter);
         }
         drop(file_lock);
@@ -100,12 +146,41 @@ impl FileWriteOps {
             return Ok(false);
         }
         file_lock.filter.extend(batch);
+        file_lock.filter_record = record;
         Ok(end_line == total_lines.saturating_sub(1))
     }
 
-    pub fn begin_search(&mut self, generation: u64, query: String, origin_line: usize) {
+    /// Starts a search session and returns its first scan, `None` when the query is not a
+    /// valid regex and the session is already ready without matches.
+    pub fn begin_search(
+        &mut self,
+        generation: u64,
+        query: String,
+        origin_line: usize,
+    ) -> Option<SearchScan> {
         let mut file_lock = self.log_file.write().unwrap();
-        file_lock.search.session = Some(SearchSession::indexing(generation, query, origin_line));
+        let content_lines = file_lock.index.len().saturating_sub(1);
+        let mut session = SearchSession::indexing(generation, query, origin_line);
+        session.scanned_lines = content_lines;
+        let scan = session.matcher.clone().map(|matcher| SearchScan {
+            id: generation,
+            matcher,
+            start_line: 0,
+            end_line: content_lines,
+        });
+        file_lock.search.session = Some(session);
+        scan
+    }
+
+    /// Claims the lines indexed since the ready search session was last scanned.
+    pub fn extend_search(&mut self) -> Option<SearchScan> {
+        let mut file_lock = self.log_file.write().unwrap();
+        let content_lines = file_lock.index.len().saturating_sub(1);
+        file_lock
+            .search
+            .session
+            .as_mut()?
+            .claim_new_lines(content_lines)
     }
 
     pub fn log_file(&self) -> Arc<RwLock<LogFile>> {
@@ -115,37 +190,17 @@ impl FileWriteOps {
     pub fn merge_search_batch(
         &mut self,
         generation: u64,
-        mut batch: Vec<SearchMatch>,
+        batch: Vec<SearchMatch>,
         mark_ready: bool,
     ) -> bool {
         let mut file_lock = self.log_file.write().unwrap();
-        let Some(session) = file_lock.search.session.as_mut() else {
+        if file_lock.search.status().generation != generation {
             return false;
-        };
-        if session.generation != generation {
-            return false;
-        }
-
-        session.matches.append(&mut batch);
-        session
-            .matches
-            .sort_by_key(|m| (m.line_index, m.start, m.end));
-        for (idx, item) in session.matches.iter_mut().enumerate() {
-            item.ordinal = idx;
-        }
-
-        session.first_match = session.matches.first().cloned();
-        if session.current_ordinal.is_none() && !session.matches.is_empty() {
-            session.current_ordinal = Some(0);
         }
-        if mark_ready {
-            session.phase = SearchPhase::Ready;
-            session.total_matches_final = true;
-        } else {
-            session.phase = SearchPhase::Indexing;
-            session.total_matches_final = false;
-        }
-        true
+        with_search_session(&mut file_lock, |session, log_file| {
+            session.merge_batch(log_file, batch, mark_ready)
+        })
+        .is_some()
     }
 
     pub fn clear_search(&mut self) {
@@ -155,23 +210,206 @@ impl FileWriteOps {
 
     pub fn search_next(&mut self) {
         let mut file_lock = self.log_file.write().unwrap();
-        if let Some(session) = file_lock.search.session.as_mut() {
-            session.next();
-        }
+        with_search_session(&mut file_lock, |session, log_file| session.next(log_file));
     }
 
     pub fn search_previous(&mut self) {
         let mut file_lock = self.log_file.write().unwrap();
-        if let Some(session) = file_lock.search.session.as_mut() {
-            session.previous();
+        with_search_session(&mut file_lock, |session, log_file| {
+            session.previous(log_file)
+        });
+    }
+
+    pub fn search_select(&mut self, line_index: usize, start: usize) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        with_search_session(&mut file_lock, |session, log_file| {
+            session.select(log_file, line_index, start)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Registers a mark and returns its first scan, `None` when the query is not a valid
+    /// regex.
+    pub fn begin_mark(&mut self, id: u64, query: String, color: VisualColor) -> Option<SearchScan> {
+        let mut file_lock = self.log_file.write().unwrap();
+        let content_lines = file_lock.index.len().saturating_sub(1);
+        let mut session = SearchSession::indexing(id, query, 0);
+        session.scanned_lines = content_lines;
+        let scan = session.matcher.clone().map(|matcher| SearchScan {
+            id,
+            matcher,
+            start_line: 0,
+            end_line: content_lines,
+        });
+        file_lock
+            .marks
+            .marks
+            .push(MarkSession { id, color, session });
+        scan
+    }
+
+    /// Claims the lines indexed since each ready mark was last scanned.
+    pub fn extend_marks(&mut self) -> Vec<SearchScan> {
+        let mut file_lock = self.log_file.write().unwrap();
+        let content_lines = file_lock.index.len().saturating_sub(1);
+        file_lock
+            .marks
+            .marks
+            .iter_mut()
+            .filter_map(|mark| mark.session.claim_new_lines(content_lines))
+            .collect()
+    }
+
+    /// Merges a mark batch. Returns false once the mark has been removed.
+    pub fn merge_mark_batch(&mut self, id: u64, batch: Vec<SearchMatch>, mark_ready: bool) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        with_mark_session(&mut file_lock, id, |session, log_file| {
+            session.merge_batch(log_file, batch, mark_ready)
+        })
+        .is_some()
+    }
+
+    pub fn remove_mark(&mut self, id: u64) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        file_lock.marks.remove(id)
+    }
+
+    pub fn clear_marks(&mut self) {
+        let mut file_lock = self.log_file.write().unwrap();
+        file_lock.marks.clear();
+    }
+
+    pub fn mark_next(&mut self, id: u64) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        with_mark_session(&mut file_lock, id, |session, log_file| {
+            session.next(log_file)
+        })
+        .is_some()
+    }
+
+    pub fn mark_previous(&mut self, id: u64) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        with_mark_session(&mut file_lock, id, |session, log_file| {
+            session.previous(log_file)
+        })
+        .is_some()
+    }
+
+    /// Replaces the rule count session and returns its first scan over the indexed lines.
+    pub fn begin_rule_counts(
+        &mut self,
+        generation: u64,
+        revision: u64,
+        profile: String,
+        evaluator: Arc<VisualRuleEvaluator>,
+        format: Option<Arc<LogFormat>>,
+    ) -> RuleCountScan {
+        let mut file_lock = self.log_file.write().unwrap();
+        let content_lines = file_lock.index.len().saturating_sub(1);
+        let (session, scan) = RuleCountSession::indexing(
+            generation,
+            revision,
+            profile,
+            evaluator,
+            format,
+            content_lines,
+        );
+        file_lock.rule_counts.session = Some(session);
+        scan
+    }
+
+    /// Claims the lines indexed since the ready rule count session was last scanned.
+    pub fn extend_rule_counts(&mut self) -> Option<RuleCountScan> {
+        let mut file_lock = self.log_file.write().unwrap();
+        let content_lines = file_lock.index.len().saturating_sub(1);
+        file_lock
+            .rule_counts
+            .session
+            .as_mut()?
+            .claim_new_lines(content_lines)
+    }
+
+    /// Merges the matching lines of each rule. Returns false once `generation` is stale.
+    pub fn merge_rule_count_batch(
+        &mut self,
+        generation: u64,
+        batch: Vec<Vec<usize>>,
+        mark_ready: bool,
+    ) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        match file_lock.rule_counts.session.as_mut() {
+            Some(session) if session.generation == generation => {
+                session.merge_batch(batch, mark_ready);
+                true
+            }
+            _ => false,
         }
     }
+
+    pub fn rule_next(&mut self, rule: usize, view_start: usize) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        with_rule_count_session(&mut file_lock, |session, log_file| {
+            session.next(log_file, rule, view_start)
+        })
+        .unwrap_or(false)
+    }
+
+    pub fn rule_previous(&mut self, rule: usize, view_start: usize) -> bool {
+        let mut file_lock = self.log_file.write().unwrap();
+        with_rule_count_session(&mut file_lock, |session, log_file| {
+            session.previous(log_file, rule, view_start)
+        })
+        .unwrap_or(false)
+    }
+}
+
+/// Runs `update` on the search session while the rest of the file stays readable, since
+/// sessions recompute spans from the file. Returns `None` without a session.
+fn with_search_session<R>(
+    log_file: &mut LogFile,
+    update: impl FnOnce(&mut SearchSession, &LogFile) -> R,
+) -> Option<R> {
+    let mut session = log_file.search.session.take()?;
+    let result = update(&mut session, log_file);
+    log_file.search.session = Some(session);
+    Some(result)
+}
+
+/// Like [`with_search_session`] for the mark `id`.
+fn with_mark_session<R>(
+    log_file: &mut LogFile,
+    id: u64,
+    update: impl FnOnce(&mut SearchSession, &LogFile) -> R,
+) -> Option<R> {
+    let mut marks = std::mem::take(&mut log_file.marks.marks);
+    let result = marks
+        .iter_mut()
+        .find(|mark| mark.id == id)
+        .map(|mark| update(&mut mark.session, log_file));
+    log_file.marks.marks = marks;
+    result
+}
+
+/// Like [`with_search_session`] for the rule count session.
+fn with_rule_count_session<R>(
+    log_file: &mut LogFile,
+    update: impl FnOnce(&mut RuleCountSession, &LogFile) -> R,
+) -> Option<R> {
+    let mut session = log_file.rule_counts.session.take()?;
+    let result = update(&mut session, log_file);
+    log_file.rule_counts.session = Some(session);
+    Some(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::workers::{SearchCommand, spawn_search_worker};
+    use crate::file_ops::read::FileReadOps;
+    use crate::models::match_index::MatchIndex;
+    use crate::workers::{
+        MarkCommand, RuleCountCommand, SearchCommand, spawn_mark_worker, spawn_rule_count_worker,
+        spawn_search_worker,
+    };
     use crossbeam_channel::unbounded;
     use std::io::Write;
     use std::path::PathBuf;
@@ -215,13 +453,10 @@ mod tests {
         let (tx, rx) = unbounded::<SearchCommand>();
         spawn_search_worker(worker_ops, rx);
 
-        let generation = 1u64;
-        write_ops.begin_search(generation, "foo".to_string(), 2);
+        let scan = write_ops.begin_search(1, "foo".to_string(), 2).unwrap();
         tx.send(SearchCommand::Start {
-            generation,
-            query: "foo".to_string(),
+            scan,
             origin_line: 2,
-            indexed_lines: log_file.read().unwrap().index.len(),
         })
         .unwrap();
 
@@ -231,6 +466,149 @@ mod tests {
         assert!(status.total_matches_final);
         assert_eq!(status.total_matches, 3);
         assert_eq!(status.first.unwrap().line_index, 0);
+        assert_eq!(
+            status.current.unwrap().line_index,
+            2,
+            "wrapped matches must not move the selection away from the origin"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn search_worker_extends_ready_session_over_appended_lines() {
+        let path = temp_file_path("search-extend");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "foo-1").unwrap();
+        writeln!(file, "none").unwrap();
+        drop(file);
+
+        let log_file = Arc::new(RwLock::new(
+            LogFile::new(path.to_string_lossy().into_owned()).unwrap(),
+        ));
+        let mut write_ops = FileWriteOps::new(Arc::clone(&log_file));
+        while !write_ops.index_lines().unwrap() {}
+
+        let (tx, rx) = unbounded::<SearchCommand>();
+        spawn_search_worker(FileWriteOps::new(Arc::clone(&log_file)), rx);
+        assert!(write_ops.extend_search().is_none());
+
+        let scan = write_ops.begin_search(1, "foo".to_string(), 0).unwrap();
+        tx.send(SearchCommand::Start {
+            scan,
+            origin_line: 0,
+        })
+        .unwrap();
+        wait_until(|| log_file.read().unwrap().search.status().is_ready);
+        assert!(write_ops.extend_search().is_none(), "nothing new to scan");
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(file, "foo-2 foo").unwrap();
+        drop(file);
+        write_ops.reload().unwrap();
+        while !write_ops.index_lines().unwrap() {}
+        tx.send(SearchCommand::Extend).unwrap();
+
+        wait_until(|| log_file.read().unwrap().search.status().total_matches == 3);
+        let status = log_file.read().unwrap().search.status();
+        assert!(status.is_ready && status.total_matches_final);
+        assert_eq!(status.total_matches, 3);
+        assert_eq!(status.current.unwrap().line_index, 0);
+        assert_eq!(
+            log_file
+                .read()
+                .unwrap()
+                .search
+                .session
+                .as_ref()
+                .unwrap()
+                .scanned_lines,
+            3
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn count_only_sessions_keep_ordinals_navigation_and_hits() {
+        let path = temp_file_path("search-count-only");
+        let mut file = File::create(&path).unwrap();
+        for line in 0..3000 {
+            if line % 1000 == 999 {
+                writeln!(file, "INFO {line} INFO").unwrap();
+            } else {
+                writeln!(file, "INFO {line}").unwrap();
+            }
+        }
+        drop(file);
+
+        let log_file = Arc::new(RwLock::new(
+            LogFile::new(path.to_string_lossy().into_owned()).unwrap(),
+        ));
+        let mut write_ops = FileWriteOps::new(Arc::clone(&log_file));
+        while !write_ops.index_lines().unwrap() {}
+
+        let scan = write_ops.begin_search(1, "INFO".to_string(), 0).unwrap();
+        log_file
+            .write()
+            .unwrap()
+            .search
+            .session
+            .as_mut()
+            .unwrap()
+            .index = MatchIndex::with_budget(64);
+        let batch = {
+            let file_lock = log_file.read().unwrap();
+            FileReadOps::compute_search_batch(&file_lock, &scan.matcher, 0, scan.end_line)
+        };
+        assert!(write_ops.merge_search_batch(1, batch, true));
+
+        let status = || log_file.read().unwrap().search.status();
+        let current = || {
+            let current = status().current.unwrap();
+            (current.line_index, current.start, current.ordinal)
+        };
+        assert!(status().count_only);
+        assert_eq!(status().total_matches, 3003);
+        assert_eq!(current(), (0, 0, 0));
+
+        assert!(write_ops.search_select(999, 9));
+        assert_eq!(current(), (999, 9, 1000));
+        write_ops.search_next();
+        assert_eq!(current(), (1000, 0, 1001));
+        write_ops.search_previous();
+        write_ops.search_previous();
+        assert_eq!(current(), (999, 0, 999));
+        assert!(write_ops.search_select(0, 0));
+        write_ops.search_previous();
+        assert_eq!(
+            current(),
+            (2999, 10, 3002),
+            "previous wraps to the last match"
+        );
+        write_ops.search_next();
+        assert_eq!(current(), (0, 0, 0));
+
+        let read_ops = FileReadOps::new(log_file.read().unwrap());
+        let hits = read_ops.search_hits(1000, 2).unwrap().unwrap().hits;
+        assert_eq!(
+            hits.iter()
+                .map(|hit| (hit.line_number, hit.search_match.ordinal))
+                .collect::<Vec<_>>(),
+            vec![(1000, 1000), (1001, 1001)]
+        );
+        let page = read_ops.page_search_result(998, 1001).unwrap();
+        assert_eq!(
+            page.page_matches
+                .iter()
+                .map(|found| (found.line_index, found.ordinal))
+                .collect::<Vec<_>>(),
+            vec![(998, 998), (999, 999), (999, 1000), (1000, 1001)]
+        );
+        drop(read_ops);
 
         std::fs::remove_file(path).unwrap();
     }
@@ -267,4 +645,126 @@ mod tests {
 
         std::fs::remove_file(path).unwrap();
     }
+
+    #[test]
+    fn mark_worker_scans_whole_file_independently_from_search() {
+        let path = temp_file_path("mark-scan");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "req-1 foo").unwrap();
+        writeln!(file, "req-2").unwrap();
+        writeln!(file, "req-1 bar").unwrap();
+        drop(file);
+
+        let log_file = Arc::new(RwLock::new(
+            LogFile::new(path.to_string_lossy().into_owned()).unwrap(),
+        ));
+        let mut write_ops = FileWriteOps::new(Arc::clone(&log_file));
+        while !write_ops.index_lines().unwrap() {}
+
+        let (tx, rx) = unbounded::<MarkCommand>();
+        spawn_mark_worker(FileWriteOps::new(Arc::clone(&log_file)), rx);
+
+        write_ops.begin_search(1, "foo".to_string(), 0);
+        let scan = write_ops
+            .begin_mark(1, "req-1".to_string(), VisualColor("cyan".to_string()))
+            .unwrap();
+        tx.send(MarkCommand::Start { scan }).unwrap();
+
+        let mark_ready = || {
+            log_file.read().unwrap().marks.marks[0]
+                .session
+                .status()
+                .is_ready
+        };
+        wait_until(mark_ready);
+        let file_lock = log_file.read().unwrap();
+        let status = file_lock.marks.marks[0].status();
+        assert!(status.search.total_matches_final);
+        assert_eq!(status.search.total_matches, 2);
+        assert_eq!(status.search.current.unwrap().line_index, 0);
+        assert_eq!(file_lock.search.status().total_matches, 0);
+        drop(file_lock);
+
+        assert!(write_ops.mark_next(1));
+        assert_eq!(
+            log_file.read().unwrap().marks.marks[0]
+                .session
+                .current
+                .as_ref()
+                .unwrap()
+                .line_index,
+            2
+        );
+        assert!(write_ops.remove_mark(1));
+        assert!(!write_ops.mark_next(1));
+        assert!(!write_ops.merge_mark_batch(1, Vec::new(), true));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rule_count_worker_counts_each_rule_and_drops_stale_generations() {
+        let path = temp_file_path("rule-counts");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "ERROR timeout").unwrap();
+        writeln!(file, "INFO ok").unwrap();
+        writeln!(file, "ERROR disk").unwrap();
+        drop(file);
+
+        let log_file = Arc::new(RwLock::new(
+            LogFile::new(path.to_string_lossy().into_owned()).unwrap(),
+        ));
+        let mut write_ops = FileWriteOps::new(Arc::clone(&log_file));
+        while !write_ops.index_lines().unwrap() {}
+
+        let (tx, rx) = unbounded::<RuleCountCommand>();
+        spawn_rule_count_worker(FileWriteOps::new(Arc::clone(&log_file)), rx);
+
+        let rule = |pattern: &str| crate::models::visual_rules::VisualRule {
+            name: Some(pattern.to_string()),
+            matcher: crate::models::visual_rules::VisualMatcher::Text(pattern.to_string()),
+            case_sensitive: true,
+            style: Default::default(),
+            scope: Default::default(),
+        };
+        let evaluator = Arc::new(VisualRuleEvaluator::compile(&[
+            rule("ERROR"),
+            rule("timeout"),
+        ]));
+        let scan =
+            write_ops.begin_rule_counts(1, 1, "Default".to_string(), evaluator.clone(), None);
+        tx.send(RuleCountCommand::Start { scan }).unwrap();
+
+        let ready = || {
+            log_file
+                .read()
+                .unwrap()
+                .rule_counts
+                .status()
+                .is_some_and(|status| status.is_ready)
+        };
+        wait_until(ready);
+        let status = log_file.read().unwrap().rule_counts.status().unwrap();
+        let counts = status
+            .rules
+            .iter()
+            .map(|count| count.matched_lines)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [2, 1]);
+
+        assert!(write_ops.rule_next(0, 1));
+        assert!(write_ops.rule_next(0, 1));
+        let current = |rule: usize| {
+            log_file.read().unwrap().rule_counts.status().unwrap().rules[rule].current_line_index
+        };
+        assert_eq!(current(0), Some(0), "wraps after the last match");
+        assert!(write_ops.rule_previous(0, 1));
+        assert_eq!(current(0), Some(2));
+        assert!(!write_ops.rule_next(5, 0));
+
+        write_ops.begin_rule_counts(2, 2, "Default".to_string(), evaluator, None);
+        assert!(!write_ops.merge_rule_count_batch(1, vec![vec![0]], true));
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
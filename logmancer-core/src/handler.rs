@@ -1,9 +1,15 @@
 use crate::file_ops::read::FileReadOps;
 use crate::file_ops::write::FileWriteOps;
-use crate::models::log_file::LogFile;
+use crate::log_format::LogFormat;
+use crate::models::log_file::{LineFilter, LogFile};
+use crate::models::marks::MAX_HIGHLIGHT_MARKS;
+use crate::models::palette::is_builtin_token;
+use crate::models::visual_rules::VisualColor;
 use crate::timing::{SEARCH_INITIAL_PROGRESS_WAIT, SEARCH_PROGRESS_POLL_INTERVAL};
+use crate::visual_rules::VisualRuleEvaluator;
 use crate::workers::{
-    SearchCommand, spawn_filter_worker, spawn_reload_worker, spawn_search_worker,
+    MarkCommand, RuleCountCommand, SearchCommand, spawn_filter_worker, spawn_mark_worker,
+    spawn_reload_worker, spawn_rule_count_worker, spawn_search_worker,
 };
 use crossbeam_channel::{Sender, unbounded};
 use log::info;
@@ -14,28 +20,45 @@ use std::{io, thread, time};
 pub struct LogFileHandler {
     log_file: Arc<RwLock<LogFile>>,
     reload_sender: Sender<()>,
-    filter_sender: Sender<Option<String>>, // New sender for filter thread
+    filter_sender: Sender<Option<LineFilter>>, // New sender for filter thread
     search_sender: Sender<SearchCommand>,
     search_generation: u64,
+    mark_sender: Sender<MarkCommand>,
+    next_mark_id: u64,
+    rule_count_sender: Sender<RuleCountCommand>,
+    rule_count_generation: u64,
     write_ops: FileWriteOps,
 }
 
 impl LogFileHandler {
     pub fn new(path: String) -> io::Result<Self> {
         let (reload_sender, reload_receiver) = unbounded::<()>();
-        let (filter_sender, filter_receiver) = unbounded::<Option<String>>();
+        let (filter_sender, filter_receiver) = unbounded::<Option<LineFilter>>();
         let (search_sender, search_receiver) = unbounded::<SearchCommand>();
+        let (mark_sender, mark_receiver) = unbounded::<MarkCommand>();
+        let (rule_count_sender, rule_count_receiver) = unbounded::<RuleCountCommand>();
         let log_file = Arc::new(RwLock::new(LogFile::new(path.clone())?));
         info!("File {path} loaded");
 
         let reload_write_ops = FileWriteOps::new(Arc::clone(&log_file));
         let filter_write_ops = FileWriteOps::new(Arc::clone(&log_file));
         let search_write_ops = FileWriteOps::new(Arc::clone(&log_file));
+        let mark_write_ops = FileWriteOps::new(Arc::clone(&log_file));
+        let rule_count_write_ops = FileWriteOps::new(Arc::clone(&log_file));
         let write_ops = FileWriteOps::new(Arc::clone(&log_file));
 
-        spawn_reload_worker(reload_write_ops, reload_receiver, filter_sender.clone());
+        spawn_reload_worker(
+            reload_write_ops,
+            reload_receiver,
+            filter_sender.clone(),
+            search_sender.clone(),
+            mark_sender.clone(),
+            rule_count_sender.clone(),
+        );
         spawn_filter_worker(filter_write_ops, filter_receiver);
         spawn_search_worker(search_write_ops, search_receiver);
+        spawn_mark_worker(mark_write_ops, mark_receiver);
+        spawn_rule_count_worker(rule_count_write_ops, rule_count_receiver);
 
         reload_sender.send(()).unwrap();
 
@@ -45,6 +68,10 @@ impl LogFileHandler {
             filter_sender,
             search_sender,
             search_generation: 0,
+            mark_sender,
+            next_mark_id: 0,
+            rule_count_sender,
+            rule_count_generation: 0,
             write_ops,
         })
     }
@@ -54,8 +81,8 @@ impl LogFileHandler {
         Self::wait(500);
     }
 
-    pub fn filter(&mut self, regex: Option<String>) {
-        self.filter_sender.send(regex).unwrap(); // Send regex to filter thread
+    pub fn filter(&mut self, line_filter: Option<LineFilter>) {
+        self.filter_sender.send(line_filter).unwrap(); // Send filter to filter thread
         Self::wait(500);
     }
 
@@ -67,6 +94,12 @@ impl LogFileHandler {
         }
     }
 
+    /// Shared file, for readers that take its read lock a chunk at a time.
+    #[cfg(feature = "sql")]
+    pub(crate) fn log_file(&self) -> Arc<RwLock<LogFile>> {
+        Arc::clone(&self.log_file)
+    }
+
     pub fn read_ops(&self) -> FileReadOps<'_> {
         FileReadOps::new(self.log_file.read().unwrap())
     }
@@ -74,17 +107,11 @@ impl LogFileHandler {
     pub fn apply_search(&mut self, query: String, origin_line: usize) -> io::Result<()> {
         self.search_generation += 1;
         let generation = self.search_generation;
-        let indexed_lines = self.read_ops().total_lines()?;
-        self.write_ops
-            .begin_search(generation, query.clone(), origin_line);
-        self.search_sender
-            .send(SearchCommand::Start {
-                generation,
-                query,
-                origin_line,
-                indexed_lines,
-            })
-            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e.to_string()))?;
+        if let Some(scan) = self.write_ops.begin_search(generation, query, origin_line) {
+            self.search_sender
+                .send(SearchCommand::Start { scan, origin_line })
+                .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e.to_string()))?;
+        }
 
         self.wait_search_progress(generation);
         Ok(())
@@ -118,4 +145,106 @@ impl LogFileHandler {
     pub fn search_previous(&mut self) {
         self.write_ops.search_previous();
     }
+
+    pub fn search_select(&mut self, line_index: usize, start: usize) -> bool {
+        self.write_ops.search_select(line_index, start)
+    }
+
+    /// Starts scanning a new mark and returns its id.
+    ///
+    /// Invalid patterns, unknown palette tokens and exceeding `MAX_HIGHLIGHT_MARKS`
+    /// are reported as `InvalidInput` before anything is spawned.
+    pub fn add_mark(&mut self, query: String, color: Option<VisualColor>) -> io::Result<u64> {
+        regex::Regex::new(&query).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let color = {
+            let file_lock = self.log_file.read().unwrap();
+            if file_lock.marks.marks.len() >= MAX_HIGHLIGHT_MARKS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("at most {MAX_HIGHLIGHT_MARKS} marks may be active"),
+                ));
+            }
+            color.unwrap_or_else(|| file_lock.marks.next_color())
+        };
+        if !is_builtin_token(&color) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported palette token '{}'", color.0),
+            ));
+        }
+
+        self.next_mark_id += 1;
+        let mark_id = self.next_mark_id;
+        if let Some(scan) = self.write_ops.begin_mark(mark_id, query, color) {
+            self.mark_sender
+                .send(MarkCommand::Start { scan })
+                .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e.to_string()))?;
+        }
+        Ok(mark_id)
+    }
+
+    pub fn remove_mark(&mut self, mark_id: u64) -> bool {
+        self.write_ops.remove_mark(mark_id)
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.write_ops.clear_marks();
+    }
+
+    pub fn mark_next(&mut self, mark_id: u64) -> bool {
+        self.write_ops.mark_next(mark_id)
+    }
+
+    pub fn mark_previous(&mut self, mark_id: u64) -> bool {
+        self.write_ops.mark_previous(mark_id)
+    }
+
+    /// Restarts the per-rule counts under a new generation unless they already count the
+    /// lines of `evaluator` as published at `revision`, parsed with `format`.
+    pub fn sync_rule_counts(
+        &mut self,
+        revision: u64,
+        profile: String,
+        evaluator: Arc<VisualRuleEvaluator>,
+        format: Option<Arc<LogFormat>>,
+    ) -> io::Result<()> {
+        let format_key = |format: Option<&Arc<LogFormat>>| {
+            format.map(|format| (format.name().to_string(), format.header().to_vec()))
+        };
+        let current = {
+            let file_lock = self.log_file.read().unwrap();
+            file_lock
+                .rule_counts
+                .session
+                .as_ref()
+                .is_some_and(|session| {
+                    session.revision == revision
+                        && Arc::ptr_eq(&session.evaluator, &evaluator)
+                        && format_key(session.format.as_ref()) == format_key(format.as_ref())
+                })
+        };
+        if current {
+            return Ok(());
+        }
+
+        self.rule_count_generation += 1;
+        let scan = self.write_ops.begin_rule_counts(
+            self.rule_count_generation,
+            revision,
+            profile,
+            evaluator,
+            format,
+        );
+        self.rule_count_sender
+            .send(RuleCountCommand::Start { scan })
+            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e.to_string()))
+    }
+
+    pub fn rule_next(&mut self, rule: usize, view_start: usize) -> bool {
+        self.write_ops.rule_next(rule, view_start)
+    }
+
+    pub fn rule_previous(&mut self, rule: usize, view_start: usize) -> bool {
+        self.write_ops.rule_previous(rule, view_start)
+    }
 }
@@ -1,28 +1,75 @@
+mod field_value;
 mod file_ops;
 mod handler;
+mod log_format;
+mod log_format_manager;
 mod models;
+mod query;
 mod reader;
 mod registry;
+mod search_matcher;
+#[cfg(feature = "sql")]
+mod sql;
+mod timestamps;
 mod timing;
 mod visual_rules;
+mod visual_rules_interop;
 mod visual_rules_manager;
+mod visual_rules_migration;
 #[cfg(feature = "native-persistence")]
 mod visual_rules_store;
 mod workers;
 
+pub use field_value::compare_field_values;
+pub use log_format::{LogFormat, LogFormats, sniff_delimited_format};
+pub use log_format_manager::{LogFormatManager, LogFormatsState};
+pub use models::density::{DensityBucket, DensityMap, MAX_DENSITY_BUCKETS};
 pub use models::file_info::FileInfo;
+pub use models::log_format::{
+    DiscoveredField, FORMAT_DETECTION_SAMPLE_LINES, FieldDiscovery, FormatDetection,
+    LogFormatDefinition, LogFormatKind, LogFormatStatus, LogFormatsEnvelope, LogFormatsReport,
+    MAX_FIELD_DISCOVERY_LINES, MAX_LOG_COLUMNS, MAX_LOG_FORMAT_NAME_LENGTH,
+    MAX_LOG_FORMAT_PATTERN_LENGTH, MAX_STORED_LOG_FORMATS, MIN_FORMAT_CONFIDENCE,
+    builtin_log_formats, validate_log_format,
+};
+pub use models::marks::{MAX_HIGHLIGHT_MARKS, MarkStatus, PageMarkResult};
 pub use models::page_result::{PageLine, PageResult};
-pub use models::search::{PageSearchResult, SearchDisplayStatus, SearchMatch, SearchStatus};
+pub use models::palette::{
+    MAX_PALETTE_COLORS, MAX_PALETTE_NAME_LENGTH, MIN_PALETTE_CONTRAST, PaletteColor,
+    PaletteVariant, Rgb, VisualColorRole, VisualPalette, VisualTheme, validate_palette_color,
+};
+pub use models::rule_counts::{RuleCount, RuleCountStatus, RuleNavigation};
+pub use models::search::{
+    MAX_SEARCH_HITS_PAGE, PageSearchResult, SearchDisplayStatus, SearchHit, SearchHitsPage,
+    SearchMatch, SearchStatus,
+};
+pub use models::sql::{MAX_SQL_ROWS, SQL_LINE_COLUMN, SQL_TEXT_COLUMN, SqlResult, SqlRow};
+pub use models::time::{LineTime, TimeSettings};
 pub use models::visual_rules::{
-    LineStyleIntent, ManagedVisualRule, ValidationDiagnostic, ValidationError, ValidationReport,
-    ValidationSeverity, VisualColor, VisualMatcher, VisualRule, VisualRulesEnvelope,
+    DEFAULT_VISUAL_PROFILE, FieldOperator, FieldPredicate, GutterIcon, GutterMarker,
+    LineStyleIntent, MAX_VISUAL_CONDITION_DEPTH, MAX_VISUAL_CONDITION_NODES,
+    MAX_VISUAL_RULE_PROFILES, ManagedVisualRule, ProfileAutoSelect, StyledSpan, TextAttributes,
+    ValidatedProfile, ValidationDiagnostic, ValidationError, ValidationReport, ValidationSeverity,
+    VisualColor, VisualMatcher, VisualProfileStatus, VisualRule, VisualRuleProfile,
+    VisualRuleScope, VisualRulesEnvelope,
 };
+pub use query::{LogQuery, MAX_QUERY_LENGTH, QueryError};
 pub use reader::LogReader;
 pub use registry::LogRegistry;
-pub use visual_rules::VisualRuleEvaluator;
+#[cfg(feature = "sql")]
+pub use sql::LogTableSource;
+pub use timestamps::{TIMESTAMP_FIELDS, format_time_delta};
+pub use visual_rules::{
+    MAX_STYLED_SPANS_PER_LINE, VisualLine, VisualRuleEvaluator, VisualRuleProfiles,
+};
+pub use visual_rules_interop::{
+    MAX_VISUAL_RULES_IMPORT_SIZE, VisualRulesExport, VisualRulesFormat, VisualRulesImport,
+    export_visual_rules, import_visual_rules,
+};
 pub use visual_rules_manager::{
     SaveOutcome, SaveResult, VisualRulesError, VisualRulesManager, VisualRulesState,
 };
+pub use visual_rules_migration::{VisualRulesMigration, migrate_visual_rules};
 #[cfg(feature = "native-persistence")]
 pub use visual_rules_store::{
     AtomicFileReplacer, NativeAtomicFileReplacer, NativeVisualRulesStore, StoreCommit,
@@ -0,0 +1,836 @@
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+use crate::models::log_format::{
+    FormatDetection, LogFormatDefinition, LogFormatKind, LogFormatStatus, builtin_log_formats,
+};
+use crate::timestamps::{Zone, parse_time_zone};
+
+/// Most lines one delimited record may span; longer records are left unparsed.
+pub(crate) const MAX_RECORD_LINES: usize = 1000;
+
+/// A format ready to split lines into fields.
+#[derive(Debug)]
+pub struct LogFormat {
+    definition: LogFormatDefinition,
+    parser: LineParser,
+    /// Zone of timestamps without an offset, read from the definition's `time_zone`.
+    source_zone: Zone,
+}
+
+#[derive(Debug)]
+enum LineParser {
+    Regex(Regex),
+    JsonLines,
+    Logfmt,
+    /// Values split by `delimiter`, named by the header row; empty until a file binds it.
+    Delimited {
+        delimiter: char,
+        header: Vec<String>,
+    },
+}
+
+impl LogFormat {
+    /// Compiles a validated definition; `None` if its pattern does not compile or its time
+    /// zone is unknown.
+    pub fn compile(definition: LogFormatDefinition) -> Option<Self> {
+        let source_zone = match &definition.time_zone {
+            Some(time_zone) => parse_time_zone(time_zone)?,
+            None => Zone::default(),
+        };
+        let parser = match definition.kind {
+            LogFormatKind::Regex => LineParser::Regex(Regex::new(&definition.pattern).ok()?),
+            LogFormatKind::JsonLines => LineParser::JsonLines,
+            LogFormatKind::Logfmt => LineParser::Logfmt,
+            LogFormatKind::Csv | LogFormatKind::Tsv => LineParser::Delimited {
+                delimiter: definition.kind.delimiter()?,
+                header: Vec::new(),
+            },
+        };
+        Some(Self {
+            definition,
+            parser,
+            source_zone,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    pub fn definition(&self) -> &LogFormatDefinition {
+        &self.definition
+    }
+
+    /// Zone the format's timestamps without an offset are written in.
+    pub(crate) fn source_zone(&self) -> Zone {
+        self.source_zone
+    }
+
+    /// Whether the first line of a file is a header row rather than a log line.
+    pub fn has_header(&self) -> bool {
+        matches!(self.parser, LineParser::Delimited { .. })
+    }
+
+    /// Whether a record of a delimited format is inside a quoted value at the end of `line`,
+    /// given whether it was at its start; always `false` for other formats.
+    pub(crate) fn ends_inside_quotes(&self, line: &[u8], in_quotes: bool) -> bool {
+        match self.parser {
+            LineParser::Delimited { delimiter, .. } => {
+                ends_inside_quotes(line, delimiter, in_quotes)
+            }
+            _ => false,
+        }
+    }
+
+    /// Column names of a delimited format bound to a file's header row.
+    pub fn header(&self) -> &[String] {
+        match &self.parser {
+            LineParser::Delimited { header, .. } => header,
+            _ => &[],
+        }
+    }
+
+    /// Copy of a delimited format that names fields after `header_line`; `None` for other
+    /// formats and for a header row that does not split. Unnamed columns are numbered from 1.
+    pub fn with_header(&self, header_line: &str) -> Option<Self> {
+        let LineParser::Delimited { delimiter, .. } = self.parser else {
+            return None;
+        };
+        let header = split_delimited(header_line, delimiter)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let name = name.trim();
+                if name.is_empty() {
+                    (index + 1).to_string()
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect();
+        Some(Self {
+            definition: self.definition.clone(),
+            parser: LineParser::Delimited { delimiter, header },
+            source_zone: self.source_zone,
+        })
+    }
+
+    /// Fields of `line`, or `None` when the line does not match the format.
+    ///
+    /// Regex formats list their named captures in pattern order and leave out those that take
+    /// no part in the match. JSON Lines formats list the keys of the line's object in written
+    /// order, nested objects flattened into dotted paths; lines that are not a JSON object
+    /// have no fields. Logfmt formats list the keys of the line's pairs in written order.
+    /// Delimited formats list the values of a record under the header names; a blank line or
+    /// one that ends inside a quoted value has no fields, and records spanning lines are joined
+    /// by the reader before they are parsed.
+    pub fn parse(&self, line: &str) -> Option<Vec<(String, String)>> {
+        match &self.parser {
+            LineParser::Regex(regex) => {
+                let captures = regex.captures(line)?;
+                Some(
+                    regex
+                        .capture_names()
+                        .enumerate()
+                        .filter_map(|(index, name)| {
+                            let value = captures.get(index)?;
+                            Some((name?.to_string(), value.as_str().to_string()))
+                        })
+                        .collect(),
+                )
+            }
+            LineParser::JsonLines => {
+                let line = line.trim();
+                if !line.starts_with('{') {
+                    return None;
+                }
+                let Value::Object(object) = serde_json::from_str(line).ok()? else {
+                    return None;
+                };
+                let mut fields = Vec::with_capacity(object.len());
+                flatten_json_object("", &object, &mut fields);
+                Some(fields)
+            }
+            LineParser::Logfmt => parse_logfmt(line),
+            LineParser::Delimited { .. } if line.trim().is_empty() => None,
+            LineParser::Delimited { delimiter, header } => Some(
+                split_delimited(line.trim_end_matches(['\n', '\r']), *delimiter)?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, value)| {
+                        let name = header
+                            .get(index)
+                            .cloned()
+                            .unwrap_or_else(|| (index + 1).to_string());
+                        (name, value)
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Values of one delimited record, or `None` when its quoting is malformed or unterminated.
+///
+/// A value wrapped in double quotes may hold the delimiter, line breaks and `""` for a quote;
+/// a quote that does not start a value is read as a literal character, as in `5" disk`.
+pub(crate) fn split_delimited(record: &str, delimiter: char) -> Option<Vec<String>> {
+    let mut values = Vec::new();
+    let mut chars = record.chars().peekable();
+    loop {
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' if chars.next_if_eq(&'"').is_some() => value.push('"'),
+                    '"' => break,
+                    character => value.push(character),
+                }
+            }
+            match chars.next() {
+                None => {
+                    values.push(value);
+                    return Some(values);
+                }
+                Some(character) if character == delimiter => values.push(value),
+                Some(_) => return None,
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    None => {
+                        values.push(value);
+                        return Some(values);
+                    }
+                    Some(character) if character == delimiter => break,
+                    Some(character) => value.push(character),
+                }
+            }
+            values.push(value);
+        }
+    }
+}
+
+/// Whether a delimited record is inside a quoted value at the end of `line`, given whether it
+/// was at its start. Only the quotes [`split_delimited`] reads as opening, closing or escaping
+/// a quoted value count; a line that starts outside one starts a record.
+pub(crate) fn ends_inside_quotes(line: &[u8], delimiter: char, in_quotes: bool) -> bool {
+    let mut in_quotes = in_quotes;
+    let mut value_start = !in_quotes;
+    let mut bytes = line.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if in_quotes {
+            if byte == b'"' && bytes.next_if_eq(&&b'"').is_none() {
+                in_quotes = false;
+            }
+        } else if char::from(byte) == delimiter {
+            value_start = true;
+        } else {
+            in_quotes = value_start && byte == b'"';
+            value_start = false;
+        }
+    }
+    in_quotes
+}
+
+/// Records read from the start of a file to tell whether it is delimited.
+const SNIFF_RECORDS: usize = 20;
+
+/// Built-in delimited format for a file with the given extension and first bytes, if any.
+///
+/// A `.csv` or `.tsv` file only needs a header row with two columns or more. Any other file
+/// needs a header of distinct names that do not start with a digit, so timestamped log lines
+/// with a comma are not taken for a header, and two records below it with as many values.
+pub fn sniff_delimited_format(path: &str, sample: &str) -> Option<&'static str> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let by_extension = match extension.as_deref() {
+        Some("csv") => Some(("csv", ',')),
+        Some("tsv" | "tab") => Some(("tsv", '\t')),
+        _ => None,
+    };
+    // The sample may end in the middle of a line.
+    let sample = sample.rfind('\n').map_or(sample, |end| &sample[..end]);
+    let candidates = match by_extension {
+        Some(candidate) => vec![candidate],
+        None => vec![("tsv", '\t'), ("csv", ',')],
+    };
+    candidates.into_iter().find_map(|(name, delimiter)| {
+        let records = sample_records(sample, delimiter);
+        let header = records.first()?;
+        if header.len() < 2 {
+            return None;
+        }
+        if by_extension.is_some() {
+            return Some(name);
+        }
+        let names_header = header.iter().enumerate().all(|(index, name)| {
+            let name = name.trim();
+            !name.is_empty()
+                && name.len() <= 64
+                && !name.starts_with(|character: char| character.is_ascii_digit())
+                && !header[..index].iter().any(|other| other.trim() == name)
+        });
+        let rows = &records[1..];
+        (names_header && rows.len() >= 2 && rows.iter().all(|row| row.len() == header.len()))
+            .then_some(name)
+    })
+}
+
+/// Complete records at the start of `sample`, joining lines inside quoted values; stops at
+/// the first malformed record.
+fn sample_records(sample: &str, delimiter: char) -> Vec<Vec<String>> {
+    delimited_records(sample.lines(), delimiter)
+        .into_iter()
+        .map_while(|record| record)
+        .take(SNIFF_RECORDS)
+        .collect()
+}
+
+/// Records of `lines`, joined where a quoted value spans lines, with `None` for a malformed
+/// one; a quote left open at the end drops the last record.
+fn delimited_records<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    delimiter: char,
+) -> Vec<Option<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = String::new();
+    let mut in_quotes = false;
+    for line in lines {
+        if !record.is_empty() {
+            record.push('\n');
+        }
+        record.push_str(line);
+        in_quotes = ends_inside_quotes(line.as_bytes(), delimiter, in_quotes);
+        if in_quotes {
+            continue;
+        }
+        if !record.trim().is_empty() {
+            records.push(split_delimited(&record, delimiter));
+        }
+        record.clear();
+    }
+    records
+}
+
+/// Pairs of a logfmt line, or `None` when it has no `key=value` pair or a malformed one.
+///
+/// Values may be double-quoted with `\"`, `\\`, `\n`, `\r` and `\t` escapes; a key without
+/// `=` is a flag and reads as `true`.
+fn parse_logfmt(line: &str) -> Option<Vec<(String, String)>> {
+    let mut fields = Vec::new();
+    let mut has_pair = false;
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars
+            .next_if(|character| character.is_whitespace())
+            .is_some()
+        {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let mut key = String::new();
+        while let Some(character) =
+            chars.next_if(|character| !character.is_whitespace() && *character != '=')
+        {
+            if character == '"' {
+                return None;
+            }
+            key.push(character);
+        }
+        if key.is_empty() {
+            return None;
+        }
+        if chars.next_if_eq(&'=').is_none() {
+            fields.push((key, "true".to_string()));
+            continue;
+        }
+        has_pair = true;
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => match chars.next()? {
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        escaped @ ('"' | '\\') => value.push(escaped),
+                        other => {
+                            value.push('\\');
+                            value.push(other);
+                        }
+                    },
+                    character => value.push(character),
+                }
+            }
+            if chars
+                .peek()
+                .is_some_and(|character| !character.is_whitespace())
+            {
+                return None;
+            }
+        } else {
+            while let Some(character) = chars.next_if(|character| !character.is_whitespace()) {
+                value.push(character);
+            }
+        }
+        fields.push((key, value));
+    }
+    has_pair.then_some(fields)
+}
+
+fn flatten_json_object(
+    prefix: &str,
+    object: &Map<String, Value>,
+    fields: &mut Vec<(String, String)>,
+) {
+    for (key, value) in object {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Object(nested) if !nested.is_empty() => {
+                flatten_json_object(&path, nested, fields)
+            }
+            Value::String(text) => fields.push((path, text.clone())),
+            other => fields.push((path, other.to_string())),
+        }
+    }
+}
+
+/// The built-in formats followed by the custom ones of one published revision.
+#[derive(Debug)]
+pub struct LogFormats {
+    formats: Vec<Arc<LogFormat>>,
+}
+
+impl LogFormats {
+    pub fn compile(custom: &[LogFormatDefinition]) -> Self {
+        Self {
+            formats: builtin_log_formats()
+                .into_iter()
+                .chain(custom.iter().cloned())
+                .filter_map(LogFormat::compile)
+                .map(Arc::new)
+                .collect(),
+        }
+    }
+
+    /// Format named `name`, compared case-insensitively like profile auto-select formats.
+    pub fn get(&self, name: &str) -> Option<Arc<LogFormat>> {
+        self.formats
+            .iter()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.formats
+            .iter()
+            .map(|format| format.name().to_string())
+            .collect()
+    }
+
+    /// Format that parses the most non-blank lines of a sample taken from the `head` and
+    /// `tail` of the file at `path`, with the share it parses; `None` when none parses any.
+    ///
+    /// Delimited formats only compete when the head looks delimited, and count the records
+    /// under the header that have as many values as it, joining quoted values that span
+    /// lines. The tail may begin inside such a value, so it is also read from its first line
+    /// with an odd number of quotes, and the reading that parses more records counts. Logfmt
+    /// only counts lines that start with a pair, since lines of other formats often read as
+    /// flags, and it loses ties; other ties go to the format listed first, built-in formats
+    /// before custom ones.
+    pub fn detect(&self, path: &str, head: &[String], tail: &[String]) -> Option<FormatDetection> {
+        let non_blank = |lines: &[String]| -> Vec<String> {
+            lines
+                .iter()
+                .filter(|line| !line.trim().is_empty())
+                .cloned()
+                .collect()
+        };
+        let (head, tail) = (non_blank(head), non_blank(tail));
+        let mut head_text = head.join("\n");
+        head_text.push('\n');
+        let delimited = sniff_delimited_format(path, &head_text);
+        let mut best: Option<(FormatDetection, bool)> = None;
+        for format in &self.formats {
+            let (lines, parsed) = match &format.parser {
+                LineParser::Delimited { delimiter, .. } => {
+                    if delimited != Some(format.name()) {
+                        continue;
+                    }
+                    let Some(width) = head
+                        .first()
+                        .and_then(|header| split_delimited(header, *delimiter))
+                        .map(|header| header.len())
+                    else {
+                        continue;
+                    };
+                    let score = |lines: &[String]| {
+                        let records =
+                            delimited_records(lines.iter().map(String::as_str), *delimiter);
+                        let parsed = records
+                            .iter()
+                            .filter(|record| {
+                                record.as_ref().is_some_and(|values| values.len() == width)
+                            })
+                            .count();
+                        (records.len(), parsed)
+                    };
+                    let (head_records, head_parsed) = score(&head[1.min(head.len())..]);
+                    let resumed = tail
+                        .iter()
+                        .position(|line| line.matches('"').count() % 2 == 1)
+                        .map_or(&tail[..0], |open| &tail[open + 1..]);
+                    let (tail_records, tail_parsed) = [score(&tail), score(resumed)]
+                        .into_iter()
+                        .max_by_key(|(_, parsed)| *parsed)
+                        .unwrap_or_default();
+                    (head_records + tail_records, head_parsed + tail_parsed)
+                }
+                LineParser::Logfmt => (
+                    head.len() + tail.len(),
+                    head.iter()
+                        .chain(&tail)
+                        .filter(|line| {
+                            line.split_whitespace()
+                                .next()
+                                .is_some_and(|token| token.contains('='))
+                                && format.parse(line).is_some()
+                        })
+                        .count(),
+                ),
+                _ => (
+                    head.len() + tail.len(),
+                    head.iter()
+                        .chain(&tail)
+                        .filter(|line| format.parse(line).is_some())
+                        .count(),
+                ),
+            };
+            if parsed == 0 {
+                continue;
+            }
+            let detection = FormatDetection {
+                format: format.name().to_string(),
+                confidence: parsed as f64 / lines as f64,
+            };
+            let is_logfmt = matches!(format.parser, LineParser::Logfmt);
+            let better = match &best {
+                None => true,
+                Some((current, current_is_logfmt)) => {
+                    detection.confidence > current.confidence
+                        || (detection.confidence == current.confidence
+                            && *current_is_logfmt
+                            && !is_logfmt)
+                }
+            };
+            if better {
+                best = Some((detection, is_logfmt));
+            }
+        }
+        best.map(|(detection, _)| detection)
+    }
+
+    /// Status of a file that chose `chosen`; a chosen format that no longer exists is ignored.
+    pub fn status(&self, chosen: Option<&str>) -> LogFormatStatus {
+        LogFormatStatus {
+            active: chosen
+                .and_then(|name| self.get(name))
+                .map(|format| format.name().to_string()),
+            formats: self.names(),
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl Default for LogFormats {
+    fn default() -> Self {
+        Self::compile(&[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(format: &str, line: &str) -> Option<Vec<(String, String)>> {
+        LogFormats::default().get(format)?.parse(line)
+    }
+
+    fn field_pairs(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn builtin_formats_split_their_default_layouts() {
+        assert_eq!(
+            fields(
+                "log4j",
+                "2024-01-15 10:00:00,123 [main] WARN  com.app.Db - slow query took 250ms"
+            ),
+            field_pairs(&[
+                ("timestamp", "2024-01-15 10:00:00,123"),
+                ("thread", "main"),
+                ("level", "WARN"),
+                ("logger", "com.app.Db"),
+                ("message", "slow query took 250ms"),
+            ])
+        );
+        assert_eq!(
+            fields(
+                "python",
+                "2024-01-15 10:00:00,123 - app.db - WARNING - slow query"
+            ),
+            field_pairs(&[
+                ("timestamp", "2024-01-15 10:00:00,123"),
+                ("logger", "app.db"),
+                ("level", "WARNING"),
+                ("message", "slow query"),
+            ])
+        );
+        assert_eq!(
+            fields("go", "2024/01/15 10:00:00.123456 db.go:42: slow query"),
+            field_pairs(&[
+                ("timestamp", "2024/01/15 10:00:00.123456"),
+                ("file", "db.go:42"),
+                ("message", "slow query"),
+            ])
+        );
+        assert_eq!(
+            fields("go", "2024/01/15 10:00:00 started"),
+            field_pairs(&[("timestamp", "2024/01/15 10:00:00"), ("message", "started")])
+        );
+        assert_eq!(fields("python", "Traceback (most recent call last):"), None);
+    }
+
+    #[test]
+    fn json_lines_flatten_nested_objects_and_pass_other_lines_through() {
+        assert_eq!(
+            fields(
+                "json",
+                r#"{"level":"warn","http":{"status":503,"path":"/api"},"tags":["a"],"ok":false,"meta":{}}"#
+            ),
+            field_pairs(&[
+                ("level", "warn"),
+                ("http.status", "503"),
+                ("http.path", "/api"),
+                ("tags", r#"["a"]"#),
+                ("ok", "false"),
+                ("meta", "{}"),
+            ])
+        );
+        assert_eq!(
+            fields("json", "  {\"msg\": null}  "),
+            field_pairs(&[("msg", "null")])
+        );
+        assert_eq!(fields("json", "panic: runtime error"), None);
+        assert_eq!(fields("json", "[1, 2]"), None);
+        assert_eq!(fields("json", "{\"truncated\":"), None);
+    }
+
+    #[test]
+    fn logfmt_reads_quoted_values_escapes_and_flags() {
+        assert_eq!(
+            fields(
+                "logfmt",
+                r#"level=info msg="GET \"/api\" done\n" duration=12ms cached path= err="""#
+            ),
+            field_pairs(&[
+                ("level", "info"),
+                ("msg", "GET \"/api\" done\n"),
+                ("duration", "12ms"),
+                ("cached", "true"),
+                ("path", ""),
+                ("err", ""),
+            ])
+        );
+        assert_eq!(
+            fields("logfmt", "at=error code=H12 desc=\"Request timeout\"\r\n"),
+            field_pairs(&[
+                ("at", "error"),
+                ("code", "H12"),
+                ("desc", "Request timeout")
+            ])
+        );
+        assert_eq!(fields("logfmt", "panic: runtime error"), None);
+        assert_eq!(fields("logfmt", r#"msg="unterminated"#), None);
+        assert_eq!(fields("logfmt", r#"msg="a"b"#), None);
+    }
+
+    #[test]
+    fn delimited_formats_name_values_after_the_bound_header() {
+        let formats = LogFormats::default();
+        let csv = formats
+            .get("csv")
+            .and_then(|format| format.with_header("time,user, ,action"))
+            .unwrap();
+        assert_eq!(csv.header(), ["time", "user", "3", "action"]);
+        assert_eq!(
+            csv.parse(r#"10:00,"Doe, Jane",,"said ""hi""""#),
+            field_pairs(&[
+                ("time", "10:00"),
+                ("user", "Doe, Jane"),
+                ("3", ""),
+                ("action", r#"said "hi""#),
+            ])
+        );
+        assert_eq!(
+            csv.parse("10:01,bob,x,login,extra").unwrap()[4],
+            ("5".to_string(), "extra".to_string())
+        );
+        assert_eq!(
+            csv.parse("10:02,\"multi\nline\",,note").unwrap()[1].1,
+            "multi\nline"
+        );
+        assert_eq!(csv.parse(r#"10:03,"open"#), None);
+        assert_eq!(csv.parse(""), None);
+        assert_eq!(csv.parse("\r\n"), None);
+        let tsv = formats
+            .get("tsv")
+            .and_then(|format| format.with_header("a\tb"))
+            .unwrap();
+        assert_eq!(
+            tsv.parse("1,2\t3"),
+            field_pairs(&[("a", "1,2"), ("b", "3")])
+        );
+        assert!(formats.get("json").unwrap().with_header("a,b").is_none());
+    }
+
+    #[test]
+    fn quotes_inside_unquoted_delimited_values_are_literal() {
+        assert_eq!(
+            split_delimited(r#"a,5" disk,c"#, ','),
+            Some(vec![
+                "a".to_string(),
+                r#"5" disk"#.to_string(),
+                "c".to_string()
+            ])
+        );
+        assert_eq!(
+            split_delimited(r#"10:03,bare"quote","quoted""#, ','),
+            Some(vec![
+                "10:03".to_string(),
+                r#"bare"quote""#.to_string(),
+                "quoted".to_string()
+            ])
+        );
+        assert_eq!(split_delimited(r#""closed"junk,b"#, ','), None);
+
+        let ends_inside =
+            |line: &str, in_quotes| ends_inside_quotes(line.as_bytes(), ',', in_quotes);
+        assert!(!ends_inside(r#"monitor,27" wide,alice"#, false));
+        assert!(ends_inside(r#"cable,"1 m"#, false));
+        assert!(ends_inside(r#"said ""hi"" and"#, true));
+        assert!(!ends_inside(r#"coiled",carol"#, true));
+        assert!(ends_inside(r#"end",x,"again"#, true));
+    }
+
+    #[test]
+    fn delimited_files_are_sniffed_by_extension_or_header_and_records() {
+        let audit = "time,user,action\n10:00,alice,\"login\nfrom vpn\"\n10:01,bob,logout\n10:0";
+        assert_eq!(sniff_delimited_format("audit.log", audit), Some("csv"));
+        assert_eq!(
+            sniff_delimited_format("export.TSV", "id\tname\n"),
+            Some("tsv")
+        );
+        assert_eq!(
+            sniff_delimited_format("data.csv", "just one column\n"),
+            None
+        );
+        let log4j = "2024-01-15 10:00:00,123 INFO start\n2024-01-15 10:00:01,456 INFO ready\n2024-01-15 10:00:02,789 INFO done\n";
+        assert_eq!(sniff_delimited_format("app.log", log4j), None);
+        assert_eq!(sniff_delimited_format("app.log", "a,b\n1,2\n1,2,3\n"), None);
+    }
+
+    #[test]
+    fn detection_scores_the_head_and_tail_against_every_format() {
+        let lines = |text: &str| text.lines().map(str::to_string).collect::<Vec<_>>();
+        let formats = LogFormats::default();
+        let detect = |path: &str, head: &str, tail: &str| {
+            formats
+                .detect(path, &lines(head), &lines(tail))
+                .map(|detection| (detection.format, detection.confidence))
+        };
+
+        let syslog = "Oct  3 10:00:01 web01 sshd[812]: Accepted key\n\
+                      Oct  3 10:00:02 web01 cron: job started\n";
+        assert_eq!(
+            detect("messages", syslog, "kernel panic\n"),
+            Some(("syslog".to_string(), 2.0 / 3.0))
+        );
+        let access = r#"10.0.0.1 - - [03/Oct/2026:10:00:01 +0000] "GET / HTTP/1.1" 200 512"#;
+        assert_eq!(
+            detect("access.log", access, ""),
+            Some(("access-log".to_string(), 1.0))
+        );
+        assert_eq!(
+            detect("app.log", "level=info msg=ready\nlevel=warn msg=slow\n", ""),
+            Some(("logfmt".to_string(), 1.0))
+        );
+        assert_eq!(
+            detect("app.log", r#"{"level":"info","msg":"a=b"}"#, ""),
+            Some(("json".to_string(), 1.0))
+        );
+        assert_eq!(
+            detect(
+                "audit.csv",
+                "time,user,action\n10:00,\"Doe\nJane\",login\n",
+                "Jane\",login\n10:01,bob,logout\n"
+            ),
+            Some(("csv".to_string(), 1.0))
+        );
+        assert_eq!(detect("notes.txt", "just some words\n", ""), None);
+    }
+
+    #[test]
+    fn custom_formats_follow_the_builtins_and_are_found_case_insensitively() {
+        let formats = LogFormats::compile(&[LogFormatDefinition {
+            name: "access".to_string(),
+            kind: LogFormatKind::Regex,
+            pattern: r"^(?P<client>\S+) (?P<status>\d{3}) (?P<message>.*)$".to_string(),
+            timestamp_format: None,
+            time_zone: None,
+        }]);
+        assert_eq!(
+            formats.names(),
+            [
+                "log4j",
+                "python",
+                "go",
+                "syslog",
+                "access-log",
+                "json",
+                "logfmt",
+                "csv",
+                "tsv",
+                "access"
+            ]
+        );
+        assert_eq!(
+            formats
+                .get("Access")
+                .and_then(|format| format.parse("10.0.0.1 404 GET /")),
+            field_pairs(&[
+                ("client", "10.0.0.1"),
+                ("status", "404"),
+                ("message", "GET /"),
+            ])
+        );
+        assert_eq!(formats.status(Some("missing")).active, None);
+        assert_eq!(formats.status(Some("GO")).active.as_deref(), Some("go"));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::log_format::LogFormats;
+use crate::models::log_format::{LogFormatsEnvelope, LogFormatsReport};
+use crate::models::visual_rules::ValidationDiagnostic;
+#[cfg(feature = "native-persistence")]
+use crate::models::visual_rules::ValidationSeverity;
+use crate::visual_rules_manager::{SaveOutcome, SaveResult, VisualRulesError};
+#[cfg(feature = "native-persistence")]
+use crate::visual_rules_store::{StoreCommit, VisualRulesStore};
+use std::sync::{Arc, Mutex, RwLock};
+
+#[cfg(feature = "native-persistence")]
+const PERSISTED_SIZE_ERROR: &str = "log formats configuration exceeds 256 KiB";
+
+#[derive(Clone, Debug)]
+pub struct LogFormatsState {
+    pub revision: u64,
+    pub envelope: LogFormatsEnvelope,
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+struct ManagerState {
+    revision: u64,
+    envelope: LogFormatsEnvelope,
+    source: Option<Vec<u8>>,
+    diagnostics: Vec<ValidationDiagnostic>,
+}
+
+/// Custom log formats, loaded, validated and saved the way [`crate::VisualRulesManager`]
+/// handles visual rules: revision-checked saves through a compare-and-commit store, invalid
+/// entries dropped with a diagnostic on load and the last good formats kept when a file
+/// cannot be read.
+pub struct LogFormatManager {
+    formats: RwLock<Arc<LogFormats>>,
+    state: Mutex<ManagerState>,
+    #[cfg(feature = "native-persistence")]
+    store: Option<Arc<dyn VisualRulesStore>>,
+}
+
+impl LogFormatManager {
+    pub fn in_memory() -> Arc<Self> {
+        Arc::new(Self {
+            formats: RwLock::new(Arc::new(LogFormats::default())),
+            state: Mutex::new(ManagerState {
+                revision: 0,
+                envelope: LogFormatsEnvelope::new(Vec::new()),
+                source: None,
+                diagnostics: Vec::new(),
+            }),
+            #[cfg(feature = "native-persistence")]
+            store: None,
+        })
+    }
+
+    #[cfg(feature = "native-persistence")]
+    pub fn with_store(store: Arc<dyn VisualRulesStore>) -> Arc<Self> {
+        let manager = match Arc::try_unwrap(Self::in_memory()) {
+            Ok(manager) => manager,
+            Err(_) => unreachable!("new manager has one owner"),
+        };
+        Arc::new(Self {
+            store: Some(store),
+            ..manager
+        })
+    }
+
+    /// Built-in and custom formats of the published revision.
+    pub fn formats(&self) -> Arc<LogFormats> {
+        self.formats.read().expect("log formats lock").clone()
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.state.lock().expect("log formats state lock").revision
+    }
+
+    pub fn state(&self) -> LogFormatsState {
+        let state = self.state.lock().expect("log formats state lock");
+        LogFormatsState {
+            revision: state.revision,
+            envelope: state.envelope.clone(),
+            diagnostics: state.diagnostics.clone(),
+        }
+    }
+
+    pub fn apply_memory(
+        &self,
+        envelope: LogFormatsEnvelope,
+    ) -> Result<SaveResult, VisualRulesError> {
+        let report = envelope
+            .validate_for_save()
+            .map_err(|error| VisualRulesError::Validation(error.message))?;
+        let mut state = self.state.lock().expect("log formats state lock");
+        self.publish(&mut state, envelope, report, None);
+        Ok(SaveResult {
+            revision: state.revision,
+            outcome: SaveOutcome::Committed,
+        })
+    }
+
+    #[cfg(feature = "native-persistence")]
+    pub fn load(&self) -> Result<LogFormatsState, VisualRulesError> {
+        let mut state = self.state.lock().expect("log formats state lock");
+        let source = self
+            .store
+            .as_ref()
+            .expect("native store")
+            .read()
+            .map_err(|error| VisualRulesError::Io(error.to_string()))?;
+        let decoded = match source.as_deref() {
+            None | Some([]) => Ok(LogFormatsEnvelope::new(Vec::new())),
+            Some(bytes) if bytes.len() > LogFormatsEnvelope::MAX_PERSISTED_SIZE => {
+                Err(PERSISTED_SIZE_ERROR.to_string())
+            }
+            Some(bytes) => serde_json::from_slice::<LogFormatsEnvelope>(bytes)
+                .map_err(|error| error.to_string()),
+        };
+        match decoded.and_then(|envelope| {
+            envelope
+                .validate_for_load()
+                .map(|report| (envelope, report))
+                .map_err(|error| error.message)
+        }) {
+            Ok((envelope, report)) => self.publish(&mut state, envelope, report, source),
+            Err(message) => {
+                state.revision += 1;
+                state.source = source;
+                state.diagnostics = vec![ValidationDiagnostic {
+                    severity: ValidationSeverity::Warning,
+                    message,
+                }];
+            }
+        }
+        Ok(LogFormatsState {
+            revision: state.revision,
+            envelope: state.envelope.clone(),
+            diagnostics: state.diagnostics.clone(),
+        })
+    }
+
+    #[cfg(feature = "native-persistence")]
+    pub fn save(
+        &self,
+        base_revision: u64,
+        envelope: LogFormatsEnvelope,
+    ) -> Result<SaveResult, VisualRulesError> {
+        self.persist(base_revision, envelope, false)
+    }
+
+    #[cfg(feature = "native-persistence")]
+    pub fn replace(
+        &self,
+        base_revision: u64,
+        envelope: LogFormatsEnvelope,
+    ) -> Result<SaveResult, VisualRulesError> {
+        self.persist(base_revision, envelope, true)
+    }
+
+    #[cfg(feature = "native-persistence")]
+    fn persist(
+        &self,
+        base_revision: u64,
+        mut envelope: LogFormatsEnvelope,
+        replace: bool,
+    ) -> Result<SaveResult, VisualRulesError> {
+        envelope.schema_version = LogFormatsEnvelope::SCHEMA_VERSION;
+        let report = envelope
+            .validate_for_save()
+            .map_err(|error| VisualRulesError::Validation(error.message))?;
+        let bytes = serde_json::to_vec(&envelope)
+            .map_err(|error| VisualRulesError::Decode(error.to_string()))?;
+        if bytes.len() > LogFormatsEnvelope::MAX_PERSISTED_SIZE {
+            return Err(VisualRulesError::Validation(
+                PERSISTED_SIZE_ERROR.to_string(),
+            ));
+        }
+        let mut state = self.state.lock().expect("log formats state lock");
+        if state.revision != base_revision {
+            return Err(VisualRulesError::RevisionConflict);
+        }
+        let store = self.store.as_ref().expect("native store");
+        let commit = store
+            .compare_and_commit(state.source.as_deref(), &bytes, replace)
+            .map_err(|error| {
+                if error.kind() == std::io::ErrorKind::AlreadyExists {
+                    VisualRulesError::SourceConflict
+                } else {
+                    VisualRulesError::Io(error.to_string())
+                }
+            })?;
+        self.publish(&mut state, envelope, report, Some(bytes));
+        Ok(SaveResult {
+            revision: state.revision,
+            outcome: match commit {
+                StoreCommit::Committed => SaveOutcome::Committed,
+                StoreCommit::CommittedWithWarning(message) => {
+                    SaveOutcome::CommittedWithWarning(message)
+                }
+            },
+        })
+    }
+
+    fn publish(
+        &self,
+        state: &mut ManagerState,
+        envelope: LogFormatsEnvelope,
+        report: LogFormatsReport,
+        source: Option<Vec<u8>>,
+    ) {
+        *self.formats.write().expect("log formats lock") =
+            Arc::new(LogFormats::compile(&report.formats));
+        state.envelope = envelope;
+        state.diagnostics = report.diagnostics;
+        state.source = source;
+        state.revision += 1;
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::visual_rules::LineStyleIntent;
+
+/// Upper bound for the number of buckets a density map may be split into.
+pub const MAX_DENSITY_BUCKETS: usize = 1000;
+
+/// Above this many lines visual rules are evaluated on an evenly spaced sample and
+/// the per-bucket counts are scaled back up.
+pub const DENSITY_RULE_SAMPLE_LINES: usize = 200_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DensityBucket {
+    /// First 0-based line of the bucket.
+    pub start_line: usize,
+    /// Exclusive 0-based end line of the bucket.
+    pub end_line: usize,
+    pub search_matches: usize,
+    pub filter_matches: usize,
+    /// Lines styled by each visual rule, indexed like `DensityMap::rule_styles`.
+    pub rule_lines: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DensityMap {
+    pub total_lines: usize,
+    pub buckets: Vec<DensityBucket>,
+    /// Styles of the enabled visual rules in priority order.
+    pub rule_styles: Vec<LineStyleIntent>,
+    pub search_active: bool,
+    pub filter_active: bool,
+    /// True when `rule_lines` were extrapolated from a sample of the file.
+    pub rule_lines_sampled: bool,
+    /// True while indexing, filtering or searching may still change the counts.
+    pub is_indexing: bool,
+}
+
+impl DensityMap {
+    /// Splits `total_lines` into at most `buckets` contiguous, non-empty ranges.
+    pub fn empty_buckets(total_lines: usize, buckets: usize, rules: usize) -> Vec<DensityBucket> {
+        let buckets = buckets.clamp(1, MAX_DENSITY_BUCKETS).min(total_lines);
+        (0..buckets)
+            .map(|bucket| DensityBucket {
+                start_line: bucket * total_lines / buckets,
+                end_line: (bucket + 1) * total_lines / buckets,
+                search_matches: 0,
+                filter_matches: 0,
+                rule_lines: vec![0; rules],
+            })
+            .collect()
+    }
+
+    /// Returns the bucket holding `line`, given buckets built by `empty_buckets`.
+    pub fn bucket_of(line: usize, total_lines: usize, buckets: usize) -> usize {
+        if total_lines == 0 || buckets == 0 {
+            return 0;
+        }
+        // Bucket `b` starts at floor(b * total / n); find the last start <= line.
+        let mut bucket = ((line + 1) * buckets).div_ceil(total_lines);
+        bucket = bucket.saturating_sub(1).min(buckets - 1);
+        while bucket > 0 && bucket * total_lines / buckets > line {
+            bucket -= 1;
+        }
+        while bucket + 1 < buckets && (bucket + 1) * total_lines / buckets <= line {
+            bucket += 1;
+        }
+        bucket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DensityMap;
+
+    #[test]
+    fn buckets_cover_every_line_exactly_once() {
+        for (total_lines, requested) in [(10, 3), (7, 7), (3, 10), (1000, 64)] {
+            let buckets = DensityMap::empty_buckets(total_lines, requested, 0);
+            assert_eq!(buckets.first().unwrap().start_line, 0);
+            assert_eq!(buckets.last().unwrap().end_line, total_lines);
+            for pair in buckets.windows(2) {
+                assert_eq!(pair[0].end_line, pair[1].start_line);
+                assert!(pair[0].start_line < pair[0].end_line);
+            }
+            for line in 0..total_lines {
+                let bucket = &buckets[DensityMap::bucket_of(line, total_lines, buckets.len())];
+                assert!(bucket.start_line <= line && line < bucket.end_line);
+            }
+        }
+        assert!(DensityMap::empty_buckets(0, 10, 0).is_empty());
+    }
+}
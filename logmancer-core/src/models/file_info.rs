@@ -1,8 +1,14 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::log_format::FormatDetection;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct FileInfo {
     pub path: String,
     pub total_lines: usize,
     pub indexing_progress: f64,
+    /// Format detected when the file was opened, whether or not it was confident enough to
+    /// be applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_format: Option<FormatDetection>,
 }
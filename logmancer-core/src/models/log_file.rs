@@ -1,8 +1,101 @@
 use memmap2::Mmap;
+use regex::Regex;
 use std::fs::File;
 use std::io;
+use std::sync::Arc;
 
+use crate::field_value::{field_matches, parse_comparison};
+use crate::log_format::LogFormat;
+use crate::models::marks::MarkState;
+use crate::models::rule_counts::RuleCountState;
 use crate::models::search::SearchState;
+use crate::models::visual_rules::FieldOperator;
+use crate::query::LogQuery;
+
+/// Regex or query the filter worker keeps lines by.
+#[derive(Clone, Debug)]
+pub struct LineFilter {
+    pub pattern: String,
+    /// Field the pattern is tested against, parsed with the format; the whole line when
+    /// `None`. Lines without the field are left out.
+    pub field: Option<(String, Arc<LogFormat>)>,
+    /// Compares the field with `pattern` as a value, the way field conditions of visual
+    /// rules do, instead of matching it as a regex.
+    pub comparison: Option<FieldOperator>,
+    /// Query tested against the fields the format parses, in place of `pattern`; lines the
+    /// format does not parse are left out.
+    pub query: Option<(Arc<LogQuery>, Arc<LogFormat>)>,
+}
+
+impl LineFilter {
+    /// Keeps lines whose field matches `pattern`, or compares with it when `pattern` starts
+    /// with a comparison operator such as `>100ms` or `!=200`.
+    pub fn field(pattern: String, field: String, format: Arc<LogFormat>) -> Self {
+        let comparison = parse_comparison(&pattern);
+        Self {
+            pattern: comparison.map_or(pattern.clone(), |(_, value)| value.to_string()),
+            field: Some((field, format)),
+            comparison: comparison.map(|(operator, _)| operator),
+            query: None,
+        }
+    }
+
+    /// Keeps lines whose fields, parsed with `format`, satisfy `query`.
+    pub fn query(query: LogQuery, format: Arc<LogFormat>) -> Self {
+        Self {
+            pattern: String::new(),
+            field: None,
+            comparison: None,
+            query: Some((Arc::new(query), format)),
+        }
+    }
+
+    /// Delimited format the filter parses lines with, whose header row is never kept and whose
+    /// records may span lines; `None` when each line is tested on its own.
+    pub(crate) fn record_format(&self) -> Option<&LogFormat> {
+        let format = match (&self.query, &self.field) {
+            (Some((_, format)), _) | (None, Some((_, format))) => format,
+            (None, None) => return None,
+        };
+        format.has_header().then_some(format.as_ref())
+    }
+
+    /// Whether the filter keeps lines by a regex over `pattern`.
+    pub(crate) fn uses_regex(&self) -> bool {
+        self.comparison.is_none() && self.query.is_none()
+    }
+
+    /// Whether `line` is kept; `regex` is the compiled pattern when the filter uses one.
+    pub(crate) fn matches(&self, line: &str, regex: Option<&Regex>) -> bool {
+        if let Some((query, format)) = &self.query {
+            return format
+                .parse(line.trim_end_matches(['\n', '\r']))
+                .is_some_and(|fields| {
+                    query.matches(&fields, format.definition().timestamp_format.as_deref())
+                });
+        }
+        let Some(subject) = self.subject(line) else {
+            return false;
+        };
+        match (self.comparison, regex) {
+            (Some(operator), _) => field_matches(operator, &subject, &self.pattern, false),
+            (None, Some(regex)) => regex.is_match(&subject),
+            (None, None) => false,
+        }
+    }
+
+    /// Text of `line` the pattern is tested against, if any.
+    pub fn subject<'a>(&self, line: &'a str) -> Option<std::borrow::Cow<'a, str>> {
+        match &self.field {
+            None => Some(std::borrow::Cow::Borrowed(line)),
+            Some((field, format)) => format
+                .parse(line.trim_end_matches(['\n', '\r']))?
+                .into_iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| std::borrow::Cow::Owned(value)),
+        }
+    }
+}
 
 /// Holds mmap and index of the file. It's no thread safe.
 pub struct LogFile {
@@ -11,8 +104,13 @@ pub struct LogFile {
     pub size: u64,
     pub index: Vec<usize>,
     pub filter: Vec<bool>,
-    pub regex: Option<String>,
+    /// Whether the record the filter stopped inside was kept, while the last filtered line
+    /// leaves a quoted value of a delimited record open.
+    pub filter_record: Option<bool>,
+    pub line_filter: Option<LineFilter>,
     pub search: SearchState,
+    pub marks: MarkState,
+    pub rule_counts: RuleCountState,
 }
 
 impl LogFile {
@@ -26,8 +124,11 @@ impl LogFile {
             size: file.metadata()?.len(),
             index,
             filter: Vec::<bool>::new(),
-            regex: None,
+            filter_record: None,
+            line_filter: None,
             search: SearchState::default(),
+            marks: MarkState::default(),
+            rule_counts: RuleCountState::default(),
         })
     }
 }
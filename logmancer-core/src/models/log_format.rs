@@ -0,0 +1,421 @@
+use chrono::format::{Item, StrftimeItems};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::timestamps::parse_time_zone;
+
+use super::visual_rules::{ValidationDiagnostic, ValidationError, ValidationSeverity};
+
+pub const MAX_STORED_LOG_FORMATS: usize = 32;
+pub const MAX_LOG_FORMAT_NAME_LENGTH: usize = 32;
+pub const MAX_LOG_FORMAT_PATTERN_LENGTH: usize = 1024;
+/// Most fields a file can show as columns.
+pub const MAX_LOG_COLUMNS: usize = 16;
+/// Most lines read from the start of a file to discover its fields.
+pub const MAX_FIELD_DISCOVERY_LINES: usize = 1000;
+/// Lines read from each end of a file to detect its format when it is opened.
+pub const FORMAT_DETECTION_SAMPLE_LINES: usize = 200;
+/// Share of sampled lines the detected format must parse to be applied on open.
+pub const MIN_FORMAT_CONFIDENCE: f64 = 0.6;
+
+/// Log4j and Logback layouts like `%d [%thread] %-5level %logger - %msg`, with either a point
+/// or a comma before the milliseconds: `2024-01-15 10:00:00,123 [main] INFO  com.app - ...`.
+const LOG4J_PATTERN: &str = r"^(?P<timestamp>\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}[.,]\d{3})\s+\[(?P<thread>[^\]]*)\]\s+(?P<level>TRACE|DEBUG|INFO|WARN|ERROR|FATAL)\s+(?P<logger>\S+)\s+-\s(?P<message>.*)$";
+/// Python `logging` with `%(asctime)s - %(name)s - %(levelname)s - %(message)s`.
+const PYTHON_PATTERN: &str = r"^(?P<timestamp>\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2},\d{3}) - (?P<logger>\S+) - (?P<level>DEBUG|INFO|WARNING|ERROR|CRITICAL) - (?P<message>.*)$";
+/// Go `log` with the standard flags, optionally with microseconds and the short file name.
+const GO_PATTERN: &str = r"^(?P<timestamp>\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}(?:\.\d{1,6})?) (?:(?P<file>[\w./-]+\.go:\d+): )?(?P<message>.*)$";
+/// BSD syslog as written by rsyslog and journald forwarding: `Jan  5 10:00:00 web sshd[42]: ...`,
+/// with an optional `<priority>` prefix.
+const SYSLOG_PATTERN: &str = r"^(?:<(?P<priority>\d{1,3})>)?(?P<timestamp>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) (?P<host>\S+) (?P<app>[^\s:\[]+)(?:\[(?P<pid>\d+)\])?: (?P<message>.*)$";
+/// Common and combined access logs of Apache and nginx.
+const ACCESS_LOG_PATTERN: &str = r#"^(?P<client>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<timestamp>[^\]]+)\] "(?P<method>[A-Z]+) (?P<path>\S+)(?: (?P<protocol>[^"]+))?" (?P<status>\d{3}) (?P<size>\d+|-)(?: "(?P<referer>[^"]*)" "(?P<agent>[^"]*)")?$"#;
+
+/// How a format turns a line into fields.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormatKind {
+    /// Named captures of `pattern`.
+    #[default]
+    Regex,
+    /// One JSON object per line; nested keys become dotted paths such as `http.status`.
+    JsonLines,
+    /// `key=value` pairs such as `level=info msg="listening" duration=12ms`.
+    Logfmt,
+    /// Comma-separated values under a header row that names the fields; quoted values may
+    /// span lines.
+    Csv,
+    /// Tab-separated values, read like `Csv`.
+    Tsv,
+}
+
+impl LogFormatKind {
+    pub fn is_regex(&self) -> bool {
+        *self == Self::Regex
+    }
+
+    /// Separator of a delimited kind, whose first line is a header row.
+    pub fn delimiter(&self) -> Option<char> {
+        match self {
+            Self::Csv => Some(','),
+            Self::Tsv => Some('\t'),
+            _ => None,
+        }
+    }
+}
+
+/// How the lines of one kind of log are split into fields.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFormatDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "LogFormatKind::is_regex")]
+    pub kind: LogFormatKind,
+    /// Regex tested against each line; every named capture that takes part in a match becomes
+    /// a field of the line. Empty for formats that are not regex based.
+    #[serde(default)]
+    pub pattern: String,
+    /// `strftime` pattern the `timestamp` capture is written in, such as `%Y-%m-%d %H:%M:%S`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_format: Option<String>,
+    /// Zone the format's timestamps are written in when they carry no offset: `UTC`, an
+    /// offset such as `+02:00` or an IANA zone such as `Europe/Madrid`. UTC when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+}
+
+/// Custom log formats as persisted; the built-in ones are not stored.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFormatsEnvelope {
+    pub schema_version: u32,
+    pub formats: Vec<LogFormatDefinition>,
+}
+
+impl LogFormatsEnvelope {
+    pub const MAX_PERSISTED_SIZE: usize = 256 * 1024;
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    pub fn new(formats: Vec<LogFormatDefinition>) -> Self {
+        Self {
+            schema_version: Self::SCHEMA_VERSION,
+            formats,
+        }
+    }
+
+    pub fn validate_for_save(&self) -> Result<LogFormatsReport, ValidationError> {
+        validate_envelope(self, false)
+    }
+
+    pub fn validate_for_load(&self) -> Result<LogFormatsReport, ValidationError> {
+        validate_envelope(self, true)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct LogFormatsReport {
+    /// Custom formats that passed validation, in order.
+    pub formats: Vec<LogFormatDefinition>,
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+/// Format a file is parsed with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LogFormatStatus {
+    /// Chosen format, if it still exists; lines are not parsed without one.
+    pub active: Option<String>,
+    /// Every selectable format, built-in ones first.
+    pub formats: Vec<String>,
+    /// Field paths shown as columns, in order; empty for the plain text view.
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+/// A field seen while sampling a file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredField {
+    pub path: String,
+    /// Sampled lines that carry the field.
+    pub lines: usize,
+}
+
+/// Format that parses the largest share of the lines sampled from both ends of a file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FormatDetection {
+    pub format: String,
+    /// Share of the sampled non-blank lines the format parses, from 0 to 1.
+    pub confidence: f64,
+}
+
+impl FormatDetection {
+    /// Whether the file is opened with the format.
+    pub fn is_applied(&self) -> bool {
+        self.confidence >= MIN_FORMAT_CONFIDENCE
+    }
+}
+
+/// Fields found in the first lines of a file with its chosen format.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct FieldDiscovery {
+    pub sampled_lines: usize,
+    /// Sampled lines the format could parse; the others pass through as plain text.
+    pub parsed_lines: usize,
+    /// Fields in the order they were first seen.
+    pub fields: Vec<DiscoveredField>,
+}
+
+/// Formats that ship with Logmancer, available without being stored.
+pub fn builtin_log_formats() -> Vec<LogFormatDefinition> {
+    let mut formats: Vec<_> = [
+        ("log4j", LOG4J_PATTERN, "%Y-%m-%d %H:%M:%S%.3f"),
+        ("python", PYTHON_PATTERN, "%Y-%m-%d %H:%M:%S,%3f"),
+        ("go", GO_PATTERN, "%Y/%m/%d %H:%M:%S%.f"),
+        ("syslog", SYSLOG_PATTERN, "%b %e %H:%M:%S"),
+        ("access-log", ACCESS_LOG_PATTERN, "%d/%b/%Y:%H:%M:%S %z"),
+    ]
+    .into_iter()
+    .map(|(name, pattern, timestamp_format)| LogFormatDefinition {
+        name: name.to_string(),
+        kind: LogFormatKind::Regex,
+        pattern: pattern.to_string(),
+        timestamp_format: Some(timestamp_format.to_string()),
+        time_zone: None,
+    })
+    .collect();
+    for (name, kind) in [
+        ("json", LogFormatKind::JsonLines),
+        ("logfmt", LogFormatKind::Logfmt),
+        ("csv", LogFormatKind::Csv),
+        ("tsv", LogFormatKind::Tsv),
+    ] {
+        formats.push(LogFormatDefinition {
+            name: name.to_string(),
+            kind,
+            pattern: String::new(),
+            timestamp_format: None,
+            time_zone: None,
+        });
+    }
+    formats
+}
+
+fn validate_envelope(
+    envelope: &LogFormatsEnvelope,
+    recover_entries: bool,
+) -> Result<LogFormatsReport, ValidationError> {
+    if !(1..=LogFormatsEnvelope::SCHEMA_VERSION).contains(&envelope.schema_version) {
+        return Err(ValidationError {
+            message: "unsupported schemaVersion".to_string(),
+        });
+    }
+    if envelope.formats.len() > MAX_STORED_LOG_FORMATS {
+        return Err(ValidationError {
+            message: "at most 32 log formats may be stored".to_string(),
+        });
+    }
+    let mut report = LogFormatsReport::default();
+    for format in &envelope.formats {
+        match validate_log_format(format, &report.formats) {
+            Ok(()) => report.formats.push(format.clone()),
+            Err(message) if recover_entries => report.diagnostics.push(ValidationDiagnostic {
+                severity: ValidationSeverity::Warning,
+                message: format!("log format '{}': {}", format.name, message),
+            }),
+            Err(message) => {
+                return Err(ValidationError {
+                    message: format!("log format '{}': {}", format.name, message),
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Checks one custom format against the built-in ones and the custom formats before it.
+pub fn validate_log_format(
+    format: &LogFormatDefinition,
+    earlier: &[LogFormatDefinition],
+) -> Result<(), String> {
+    let name = format.name.as_str();
+    if name.is_empty() {
+        return Err("needs a name".to_string());
+    }
+    if name.len() > MAX_LOG_FORMAT_NAME_LENGTH {
+        return Err("name exceeds 32 characters".to_string());
+    }
+    if !name.chars().all(|character| {
+        character.is_ascii_lowercase() || character.is_ascii_digit() || character == '-'
+    }) {
+        return Err("name may only use lowercase letters, digits and '-'".to_string());
+    }
+    if builtin_log_formats()
+        .iter()
+        .any(|builtin| builtin.name == name)
+    {
+        return Err("uses a built-in format name".to_string());
+    }
+    if earlier.iter().any(|other| other.name == name) {
+        return Err("is defined more than once".to_string());
+    }
+    match format.kind {
+        LogFormatKind::Regex => validate_pattern(format)?,
+        LogFormatKind::JsonLines if !format.pattern.is_empty() => {
+            return Err("a JSON Lines format takes no pattern".to_string());
+        }
+        LogFormatKind::Logfmt if !format.pattern.is_empty() => {
+            return Err("a logfmt format takes no pattern".to_string());
+        }
+        LogFormatKind::Csv | LogFormatKind::Tsv if !format.pattern.is_empty() => {
+            return Err("a delimited format takes no pattern".to_string());
+        }
+        LogFormatKind::JsonLines
+        | LogFormatKind::Logfmt
+        | LogFormatKind::Csv
+        | LogFormatKind::Tsv => {}
+    }
+    if let Some(time_zone) = &format.time_zone
+        && parse_time_zone(time_zone).is_none()
+    {
+        return Err(format!(
+            "time zone '{time_zone}' is not UTC, an offset or an IANA zone"
+        ));
+    }
+    let Some(timestamp_format) = &format.timestamp_format else {
+        return Ok(());
+    };
+    if timestamp_format.trim().is_empty()
+        || StrftimeItems::new(timestamp_format).any(|item| matches!(item, Item::Error))
+    {
+        return Err(format!(
+            "timestamp format '{timestamp_format}' is not a strftime pattern"
+        ));
+    }
+    Ok(())
+}
+
+fn validate_pattern(format: &LogFormatDefinition) -> Result<(), String> {
+    if format.pattern.chars().count() > MAX_LOG_FORMAT_PATTERN_LENGTH {
+        return Err("pattern exceeds 1024 characters".to_string());
+    }
+    let regex = Regex::new(&format.pattern).map_err(|error| format!("invalid regex: {error}"))?;
+    if regex.capture_names().flatten().next().is_none() {
+        return Err("pattern has no named captures".to_string());
+    }
+    if format.timestamp_format.is_some()
+        && !regex
+            .capture_names()
+            .flatten()
+            .any(|name| name == "timestamp")
+    {
+        return Err("a timestamp format needs a 'timestamp' capture".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(name: &str, pattern: &str, timestamp_format: Option<&str>) -> LogFormatDefinition {
+        LogFormatDefinition {
+            name: name.to_string(),
+            kind: LogFormatKind::Regex,
+            pattern: pattern.to_string(),
+            timestamp_format: timestamp_format.map(str::to_string),
+            time_zone: None,
+        }
+    }
+
+    #[test]
+    fn builtin_formats_pass_the_checks_custom_ones_get() {
+        for builtin in builtin_log_formats() {
+            let mut renamed = builtin.clone();
+            renamed.name = format!("my-{}", builtin.name);
+            assert_eq!(
+                validate_log_format(&renamed, &[]),
+                Ok(()),
+                "{}",
+                builtin.name
+            );
+        }
+    }
+
+    #[test]
+    fn custom_formats_need_a_free_name_named_captures_and_a_valid_timestamp_format() {
+        let access = format("access", r"^(?P<client>\S+) (?P<message>.*)$", None);
+        assert_eq!(validate_log_format(&access, &[]), Ok(()));
+        let cases = [
+            (format("", "(?P<a>.)", None), "needs a name"),
+            (
+                format("Access", "(?P<a>.)", None),
+                "name may only use lowercase letters, digits and '-'",
+            ),
+            (
+                format("go", "(?P<a>.)", None),
+                "uses a built-in format name",
+            ),
+            (access.clone(), "is defined more than once"),
+            (format("x", "(", None), "invalid regex"),
+            (format("x", "(.*)", None), "pattern has no named captures"),
+            (
+                format("x", "(?P<message>.*)", Some("%Y")),
+                "a timestamp format needs a 'timestamp' capture",
+            ),
+            (
+                format("x", "(?P<timestamp>.*)", Some("%Q")),
+                "timestamp format '%Q' is not a strftime pattern",
+            ),
+        ];
+        let mut json = format("events", "(?P<message>.*)", None);
+        json.kind = LogFormatKind::JsonLines;
+        let mut logfmt = format("pairs", "(?P<message>.*)", None);
+        logfmt.kind = LogFormatKind::Logfmt;
+        let mut csv = format("audit", ",", None);
+        csv.kind = LogFormatKind::Csv;
+        let mut martian = format("mars", "(?P<timestamp>.*)", None);
+        martian.time_zone = Some("Mars/Olympus".to_string());
+        let cases = cases.into_iter().chain([
+            (
+                martian,
+                "time zone 'Mars/Olympus' is not UTC, an offset or an IANA zone",
+            ),
+            (json, "a JSON Lines format takes no pattern"),
+            (logfmt, "a logfmt format takes no pattern"),
+            (csv, "a delimited format takes no pattern"),
+        ]);
+        for (candidate, expected) in cases {
+            let error =
+                validate_log_format(&candidate, std::slice::from_ref(&access)).expect_err(expected);
+            assert!(error.starts_with(expected), "{error}");
+        }
+    }
+
+    #[test]
+    fn loads_drop_invalid_formats_that_saves_reject() {
+        let envelope = LogFormatsEnvelope::new(vec![
+            format("access", r"(?P<message>.*)", None),
+            format("broken", "(", None),
+        ]);
+        let report = envelope.validate_for_load().expect("recoverable");
+        assert_eq!(report.formats.len(), 1);
+        assert!(
+            report.diagnostics[0]
+                .message
+                .starts_with("log format 'broken': ")
+        );
+        assert!(
+            envelope
+                .validate_for_save()
+                .expect_err("invalid format")
+                .message
+                .starts_with("log format 'broken': invalid regex")
+        );
+
+        let mut future = LogFormatsEnvelope::new(Vec::new());
+        future.schema_version = 2;
+        assert_eq!(
+            future.validate_for_load().expect_err("future").message,
+            "unsupported schemaVersion"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::log_file::LogFile;
+use crate::models::search::{PageSearchResult, SearchSession, SearchStatus};
+use crate::models::visual_rules::VisualColor;
+
+/// Maximum number of marks that may be highlighted at the same time in one file.
+pub const MAX_HIGHLIGHT_MARKS: usize = 8;
+
+/// Palette tokens assigned to new marks when the caller does not pick a color.
+pub const MARK_COLOR_ROTATION: [&str; 6] = ["yellow", "cyan", "green", "orange", "purple", "blue"];
+
+/// A persistent highlight term scanned in the background like the active search.
+///
+/// The inner session reuses the search bookkeeping; its generation is the mark id.
+#[derive(Clone, Debug)]
+pub struct MarkSession {
+    pub id: u64,
+    pub color: VisualColor,
+    pub session: SearchSession,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MarkState {
+    pub marks: Vec<MarkSession>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MarkStatus {
+    pub id: u64,
+    pub color: VisualColor,
+    pub search: SearchStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PageMarkResult {
+    pub id: u64,
+    pub color: VisualColor,
+    pub search: PageSearchResult,
+}
+
+impl MarkSession {
+    pub fn status(&self) -> MarkStatus {
+        MarkStatus {
+            id: self.id,
+            color: self.color.clone(),
+            search: self.session.status(),
+        }
+    }
+
+    pub fn page_result(
+        &self,
+        log_file: &LogFile,
+        lines: impl IntoIterator<Item = usize>,
+    ) -> PageMarkResult {
+        PageMarkResult {
+            id: self.id,
+            color: self.color.clone(),
+            search: self.session.page_result(log_file, lines),
+        }
+    }
+}
+
+impl MarkState {
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.marks.len();
+        self.marks.retain(|mark| mark.id != id);
+        self.marks.len() != before
+    }
+
+    pub fn clear(&mut self) {
+        self.marks.clear();
+    }
+
+    /// Picks the first rotation color not used by another mark, cycling once all are taken.
+    pub fn next_color(&self) -> VisualColor {
+        let token = MARK_COLOR_ROTATION
+            .iter()
+            .find(|token| !self.marks.iter().any(|mark| mark.color.0 == **token))
+            .unwrap_or(&MARK_COLOR_ROTATION[self.marks.len() % MARK_COLOR_ROTATION.len()]);
+        VisualColor(token.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MarkSession, MarkState};
+    use crate::models::search::SearchSession;
+    use crate::models::visual_rules::VisualColor;
+
+    fn mark(id: u64, color: &str) -> MarkSession {
+        MarkSession {
+            id,
+            color: VisualColor(color.to_string()),
+            session: SearchSession::indexing(id, format!("term-{id}"), 0),
+        }
+    }
+
+    #[test]
+    fn next_color_skips_colors_already_used_by_marks() {
+        let mut state = MarkState::default();
+        assert_eq!(state.next_color(), VisualColor("yellow".to_string()));
+
+        state.marks.push(mark(1, "yellow"));
+        state.marks.push(mark(2, "green"));
+        assert_eq!(state.next_color(), VisualColor("cyan".to_string()));
+    }
+
+    #[test]
+    fn remove_reports_whether_the_mark_existed() {
+        let mut state = MarkState::default();
+        state.marks.push(mark(1, "yellow"));
+
+        assert!(!state.remove(7));
+        assert!(state.remove(1));
+        assert!(state.marks.is_empty());
+    }
+}
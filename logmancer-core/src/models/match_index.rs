@@ -0,0 +1,402 @@
+use std::ops::Range;
+
+/// Lines covered by one block of a [`MatchIndex`].
+pub const MATCH_BLOCK_LINES: usize = 1024;
+
+/// Bytes the matched line sets of one index may use before it falls back to counting only.
+pub const MATCH_INDEX_BUDGET_BYTES: usize = 16 * 1024 * 1024;
+
+const DENSE_WORDS: usize = MATCH_BLOCK_LINES / 64;
+const DENSE_BYTES: usize = DENSE_WORDS * 8;
+/// Sorted offsets cost two bytes per line, so past this many lines the bitmap is smaller.
+const SPARSE_MAX_LINES: usize = DENSE_BYTES / 2;
+const REPEATED_ENTRY_BYTES: usize = std::mem::size_of::<(u16, u32)>();
+
+/// Matched lines of one block, relative to the block start.
+#[derive(Clone, Debug)]
+enum BlockLines {
+    Sparse(Vec<u16>),
+    Dense(Box<[u64; DENSE_WORDS]>),
+}
+
+impl Default for BlockLines {
+    fn default() -> Self {
+        Self::Sparse(Vec::new())
+    }
+}
+
+impl BlockLines {
+    fn bytes(&self) -> usize {
+        match self {
+            Self::Sparse(offsets) => offsets.len() * 2,
+            Self::Dense(_) => DENSE_BYTES,
+        }
+    }
+
+    /// Adds `offset`, returning false when it was already present.
+    fn insert(&mut self, offset: u16) -> bool {
+        match self {
+            Self::Sparse(offsets) => {
+                let Err(position) = offsets.binary_search(&offset) else {
+                    return false;
+                };
+                offsets.insert(position, offset);
+                if offsets.len() > SPARSE_MAX_LINES {
+                    let mut words = Box::new([0_u64; DENSE_WORDS]);
+                    for offset in offsets.iter() {
+                        words[*offset as usize / 64] |= 1 << (offset % 64);
+                    }
+                    *self = Self::Dense(words);
+                }
+                true
+            }
+            Self::Dense(words) => {
+                let word = &mut words[offset as usize / 64];
+                let bit = 1 << (offset % 64);
+                let added = *word & bit == 0;
+                *word |= bit;
+                added
+            }
+        }
+    }
+
+    fn contains(&self, offset: u16) -> bool {
+        match self {
+            Self::Sparse(offsets) => offsets.binary_search(&offset).is_ok(),
+            Self::Dense(words) => words[offset as usize / 64] & (1 << (offset % 64)) != 0,
+        }
+    }
+
+    /// Number of matched lines before `offset`.
+    fn count_below(&self, offset: usize) -> usize {
+        match self {
+            Self::Sparse(offsets) => offsets.partition_point(|line| (*line as usize) < offset),
+            Self::Dense(words) => {
+                let full = words[..offset / 64]
+                    .iter()
+                    .map(|word| word.count_ones() as usize)
+                    .sum::<usize>();
+                let partial = match offset % 64 {
+                    0 => 0,
+                    bits => (words[offset / 64] & ((1 << bits) - 1)).count_ones() as usize,
+                };
+                full + partial
+            }
+        }
+    }
+
+    fn offsets(&self) -> Vec<usize> {
+        match self {
+            Self::Sparse(offsets) => offsets.iter().map(|offset| *offset as usize).collect(),
+            Self::Dense(words) => words
+                .iter()
+                .enumerate()
+                .flat_map(|(index, word)| {
+                    (0..64)
+                        .filter(move |bit| word & (1 << bit) != 0)
+                        .map(move |bit| index * 64 + bit)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct MatchBlock {
+    matches: usize,
+    lines: BlockLines,
+    /// Lines with more than one match and their match count, sorted by offset.
+    repeated: Vec<(u16, u32)>,
+}
+
+impl MatchBlock {
+    fn line_matches(&self, offset: u16) -> usize {
+        if !self.lines.contains(offset) {
+            return 0;
+        }
+        match self
+            .repeated
+            .binary_search_by_key(&offset, |(line, _)| *line)
+        {
+            Ok(position) => self.repeated[position].1 as usize,
+            Err(_) => 1,
+        }
+    }
+
+    fn matches_below(&self, offset: usize) -> usize {
+        let extra = self
+            .repeated
+            .iter()
+            .take_while(|(line, _)| (*line as usize) < offset)
+            .map(|(_, count)| *count as usize - 1)
+            .sum::<usize>();
+        self.lines.count_below(offset) + extra
+    }
+}
+
+/// Positions of the matches of one search or mark session with bounded memory.
+///
+/// The file is split into blocks of [`MATCH_BLOCK_LINES`] lines. Every block keeps its match
+/// count, plus the set of matching lines (sorted offsets, or a bitmap once that is smaller)
+/// and the count of lines matching more than once. Spans are not stored: callers recompute
+/// them for the lines they show. Once the line sets outgrow the budget they are dropped and
+/// the index only counts per block, so callers rescan a block to find its matching lines.
+#[derive(Clone, Debug)]
+pub struct MatchIndex {
+    blocks: Vec<MatchBlock>,
+    /// Matches in the blocks before each block, rebuilt after every merge.
+    block_prefix: Vec<usize>,
+    total: usize,
+    tracked_bytes: usize,
+    budget_bytes: usize,
+    count_only: bool,
+}
+
+impl Default for MatchIndex {
+    fn default() -> Self {
+        Self::with_budget(MATCH_INDEX_BUDGET_BYTES)
+    }
+}
+
+impl MatchIndex {
+    pub fn with_budget(budget_bytes: usize) -> Self {
+        Self {
+            blocks: Vec::new(),
+            block_prefix: Vec::new(),
+            total: 0,
+            tracked_bytes: 0,
+            budget_bytes,
+            count_only: false,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Whether the index lost its line sets and only counts matches per block.
+    pub fn is_count_only(&self) -> bool {
+        self.count_only
+    }
+
+    /// Records `(line, match count)` pairs. A line is recorded at most once per session.
+    pub fn add_lines(&mut self, lines: impl IntoIterator<Item = (usize, usize)>) {
+        for (line, count) in lines {
+            self.add_line(line, count);
+        }
+        self.block_prefix.clear();
+        self.block_prefix
+            .extend(self.blocks.iter().scan(0, |before, block| {
+                let prefix = *before;
+                *before += block.matches;
+                Some(prefix)
+            }));
+    }
+
+    fn add_line(&mut self, line: usize, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let block_index = line / MATCH_BLOCK_LINES;
+        let offset = (line % MATCH_BLOCK_LINES) as u16;
+        if self.blocks.len() <= block_index {
+            self.blocks
+                .resize_with(block_index + 1, MatchBlock::default);
+        }
+        let block = &mut self.blocks[block_index];
+
+        if !self.count_only {
+            let bytes_before = block.lines.bytes();
+            if !block.lines.insert(offset) {
+                return;
+            }
+            self.tracked_bytes += block.lines.bytes() - bytes_before;
+            if count > 1 {
+                let position = block.repeated.partition_point(|(line, _)| *line < offset);
+                block.repeated.insert(position, (offset, count as u32));
+                self.tracked_bytes += REPEATED_ENTRY_BYTES;
+            }
+        }
+        block.matches += count;
+        self.total += count;
+
+        if !self.count_only && self.tracked_bytes > self.budget_bytes {
+            self.drop_line_sets();
+        }
+    }
+
+    fn drop_line_sets(&mut self) {
+        for block in &mut self.blocks {
+            block.lines = BlockLines::default();
+            block.repeated = Vec::new();
+        }
+        self.tracked_bytes = 0;
+        self.count_only = true;
+    }
+
+    /// Whether `line` may hold matches: exact while lines are tracked, per block afterwards.
+    pub fn may_match(&self, line: usize) -> bool {
+        let Some(block) = self.blocks.get(line / MATCH_BLOCK_LINES) else {
+            return false;
+        };
+        if self.count_only {
+            block.matches > 0
+        } else {
+            block.lines.contains((line % MATCH_BLOCK_LINES) as u16)
+        }
+    }
+
+    /// Matches on lines before `line`, or `Err` with the matches before its block and the
+    /// block start when only blocks are counted, so the caller rescans the block up to `line`.
+    pub fn matches_before(&self, line: usize) -> Result<usize, (usize, usize)> {
+        let block_index = line / MATCH_BLOCK_LINES;
+        let Some(block) = self.blocks.get(block_index) else {
+            return Ok(self.total);
+        };
+        let before_block = self.block_prefix[block_index];
+        if self.count_only {
+            return Err((before_block, block_index * MATCH_BLOCK_LINES));
+        }
+        Ok(before_block + block.matches_below(line % MATCH_BLOCK_LINES))
+    }
+
+    /// First line of the block holding the match with ordinal `ordinal`, with the ordinal of
+    /// the first match of that block.
+    pub fn block_of_ordinal(&self, ordinal: usize) -> Option<(usize, usize)> {
+        if ordinal >= self.total {
+            return None;
+        }
+        let block_index = self
+            .block_prefix
+            .partition_point(|before| *before <= ordinal)
+            - 1;
+        Some((
+            block_index * MATCH_BLOCK_LINES,
+            self.block_prefix[block_index],
+        ))
+    }
+
+    /// Line ranges that may hold matches from `from` on, in file order: single lines while
+    /// lines are tracked, the rest of each non-empty block afterwards.
+    pub fn candidates_after(&self, from: usize) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .skip(from / MATCH_BLOCK_LINES)
+            .filter(|(_, block)| block.matches > 0)
+            .flat_map(move |(index, block)| self.block_candidates(index, block))
+            .filter_map(move |range| {
+                let start = range.start.max(from);
+                (start < range.end).then_some(start..range.end)
+            })
+    }
+
+    /// Like [`Self::candidates_after`] for the lines before `to`, walking backwards.
+    pub fn candidates_before(&self, to: usize) -> impl Iterator<Item = Range<usize>> + '_ {
+        let blocks = to.div_ceil(MATCH_BLOCK_LINES).min(self.blocks.len());
+        self.blocks[..blocks]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, block)| block.matches > 0)
+            .flat_map(move |(index, block)| self.block_candidates(index, block).into_iter().rev())
+            .filter_map(move |range| {
+                let end = range.end.min(to);
+                (range.start < end).then_some(range.start..end)
+            })
+    }
+
+    fn block_candidates(&self, index: usize, block: &MatchBlock) -> Vec<Range<usize>> {
+        let block_start = index * MATCH_BLOCK_LINES;
+        if self.count_only {
+            return std::iter::once(block_start..block_start + MATCH_BLOCK_LINES).collect();
+        }
+        block
+            .lines
+            .offsets()
+            .into_iter()
+            .map(|offset| block_start + offset..block_start + offset + 1)
+            .collect()
+    }
+
+    /// Every matching line with its match count, or `None` once only blocks are counted.
+    pub fn line_counts(&self) -> Option<impl Iterator<Item = (usize, usize)> + '_> {
+        if self.count_only {
+            return None;
+        }
+        Some(self.blocks.iter().enumerate().flat_map(|(index, block)| {
+            block.lines.offsets().into_iter().map(move |offset| {
+                (
+                    index * MATCH_BLOCK_LINES + offset,
+                    block.line_matches(offset as u16),
+                )
+            })
+        }))
+    }
+
+    /// Line range and match count of every non-empty block.
+    pub fn block_counts(&self) -> impl Iterator<Item = (Range<usize>, usize)> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.matches > 0)
+            .map(|(index, block)| {
+                let start = index * MATCH_BLOCK_LINES;
+                (start..start + MATCH_BLOCK_LINES, block.matches)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MATCH_BLOCK_LINES, MatchIndex, SPARSE_MAX_LINES};
+
+    #[test]
+    fn counts_matches_before_lines_across_sparse_and_dense_blocks() {
+        let mut index = MatchIndex::default();
+        // Block 0 stays sparse, block 1 turns into a bitmap.
+        index.add_lines([(3, 2), (10, 1)]);
+        index.add_lines((0..SPARSE_MAX_LINES + 10).map(|offset| (MATCH_BLOCK_LINES + offset, 1)));
+        index.add_lines([(0, 1)]);
+
+        assert!(!index.is_count_only());
+        assert_eq!(index.total(), 4 + SPARSE_MAX_LINES + 10);
+        assert_eq!(index.matches_before(0), Ok(0));
+        assert_eq!(index.matches_before(3), Ok(1));
+        assert_eq!(index.matches_before(4), Ok(3));
+        assert_eq!(index.matches_before(MATCH_BLOCK_LINES + 70), Ok(4 + 70));
+        assert!(index.may_match(10) && !index.may_match(11));
+        assert_eq!(
+            index.candidates_after(4).next(),
+            Some(10..11),
+            "tracked candidates are single lines"
+        );
+        assert_eq!(index.candidates_before(10).next(), Some(3..4));
+        assert_eq!(index.block_of_ordinal(3), Some((0, 0)));
+        assert_eq!(index.block_of_ordinal(4), Some((MATCH_BLOCK_LINES, 4)));
+        assert_eq!(
+            index.line_counts().unwrap().take(3).collect::<Vec<_>>(),
+            vec![(0, 1), (3, 2), (10, 1)]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_block_counts_once_the_budget_is_exceeded() {
+        let mut index = MatchIndex::with_budget(64);
+        index.add_lines((0..40).map(|line| (line * 3, 1)));
+
+        assert!(index.is_count_only());
+        assert_eq!(index.total(), 40);
+        assert!(index.line_counts().is_none());
+        assert!(index.may_match(1), "whole blocks become candidates");
+        assert_eq!(index.matches_before(5), Err((0, 0)));
+        assert_eq!(
+            index.candidates_after(5).collect::<Vec<_>>(),
+            vec![5..MATCH_BLOCK_LINES]
+        );
+        assert_eq!(
+            index.block_counts().collect::<Vec<_>>(),
+            vec![(0..MATCH_BLOCK_LINES, 40)]
+        );
+    }
+}
@@ -1,7 +1,16 @@
+pub mod density;
 pub mod file_info;
 pub mod log_file;
+pub mod log_format;
+pub mod marks;
+pub mod match_index;
 pub mod page_result;
+pub mod palette;
+pub mod quote_checkpoints;
+pub mod rule_counts;
 pub mod search;
+pub mod sql;
+pub mod time;
 pub mod visual_rules;
 
 pub use file_info::FileInfo;
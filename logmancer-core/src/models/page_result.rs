@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::marks::PageMarkResult;
 use crate::models::search::PageSearchResult;
-use crate::models::visual_rules::LineStyleIntent;
+use crate::models::time::LineTime;
+use crate::models::visual_rules::{LineStyleIntent, StyledSpan, is_false};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct PageLine {
@@ -9,6 +11,20 @@ pub struct PageLine {
     pub text: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub style: Option<LineStyleIntent>,
+    /// Spans styled by match-scoped visual rules, highest priority first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<StyledSpan>,
+    /// Fields parsed by the file's log format, in capture order; empty without a format or
+    /// when the line does not match it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<(String, String)>,
+    /// Line continues a record that starts on a line above, inside a quoted value of a
+    /// delimited file; the record's fields are on its first line.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub continuation: bool,
+    /// Timestamp read from the line's fields; `None` without a format or a timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<LineTime>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -18,6 +34,8 @@ pub struct PageResult {
     pub total_lines: usize,
     pub indexing_progress: f64,
     pub search: Option<PageSearchResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marks: Vec<PageMarkResult>,
 }
 
 impl PartialEq for PageResult {
@@ -27,6 +45,7 @@ impl PartialEq for PageResult {
             && self.total_lines == other.total_lines
             && self.indexing_progress == other.indexing_progress
             && self.search == other.search
+            && self.marks == other.marks
     }
 }
 
@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+
+use super::visual_rules::VisualColor;
+
+/// Most user-defined colors one envelope may store.
+pub const MAX_PALETTE_COLORS: usize = 32;
+pub const MAX_PALETTE_NAME_LENGTH: usize = 32;
+/// WCAG AA contrast for normal text, required of every custom color against its theme.
+pub const MIN_PALETTE_CONTRAST: f64 = 4.5;
+
+/// Colors of the built-in tokens: light foreground, light background, dark foreground and
+/// dark background.
+const BUILTIN_COLORS: [(&str, [Rgb; 4]); 8] = [
+    (
+        "red",
+        [
+            Rgb(0xb9, 0x1c, 0x1c),
+            Rgb(0xfe, 0xe2, 0xe2),
+            Rgb(0xf8, 0x71, 0x71),
+            Rgb(0x7f, 0x1d, 0x1d),
+        ],
+    ),
+    (
+        "orange",
+        [
+            Rgb(0xc2, 0x41, 0x0c),
+            Rgb(0xff, 0xed, 0xd5),
+            Rgb(0xfb, 0x92, 0x3c),
+            Rgb(0x7c, 0x2d, 0x12),
+        ],
+    ),
+    (
+        "yellow",
+        [
+            Rgb(0xa1, 0x62, 0x07),
+            Rgb(0xfe, 0xf9, 0xc3),
+            Rgb(0xfa, 0xcc, 0x15),
+            Rgb(0x71, 0x3f, 0x12),
+        ],
+    ),
+    (
+        "green",
+        [
+            Rgb(0x15, 0x80, 0x3d),
+            Rgb(0xdc, 0xfc, 0xe7),
+            Rgb(0x4a, 0xde, 0x80),
+            Rgb(0x14, 0x53, 0x2d),
+        ],
+    ),
+    (
+        "cyan",
+        [
+            Rgb(0x0e, 0x74, 0x90),
+            Rgb(0xcf, 0xfa, 0xfe),
+            Rgb(0x22, 0xd3, 0xee),
+            Rgb(0x16, 0x4e, 0x63),
+        ],
+    ),
+    (
+        "blue",
+        [
+            Rgb(0x1d, 0x4e, 0xd8),
+            Rgb(0xdb, 0xea, 0xfe),
+            Rgb(0x60, 0xa5, 0xfa),
+            Rgb(0x1e, 0x3a, 0x8a),
+        ],
+    ),
+    (
+        "purple",
+        [
+            Rgb(0x7e, 0x22, 0xce),
+            Rgb(0xf3, 0xe8, 0xff),
+            Rgb(0xc0, 0x84, 0xfc),
+            Rgb(0x58, 0x1c, 0x87),
+        ],
+    ),
+    (
+        "gray",
+        [
+            Rgb(0x4b, 0x55, 0x63),
+            Rgb(0xf3, 0xf4, 0xf6),
+            Rgb(0x9c, 0xa3, 0xaf),
+            Rgb(0x37, 0x41, 0x51),
+        ],
+    ),
+];
+
+/// User-defined color token with a foreground and background for each theme.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PaletteColor {
+    pub name: String,
+    pub light: PaletteVariant,
+    pub dark: PaletteVariant,
+}
+
+/// `#rrggbb` colors a token resolves to in one theme.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PaletteVariant {
+    pub foreground: String,
+    pub background: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum VisualTheme {
+    Light,
+    Dark,
+}
+
+impl VisualTheme {
+    /// Default text and page background the theme renders unstyled lines with.
+    pub fn base(self) -> (Rgb, Rgb) {
+        match self {
+            Self::Light => (Rgb(0x11, 0x18, 0x27), Rgb(0xff, 0xff, 0xff)),
+            Self::Dark => (Rgb(0xe5, 0xe7, 0xeb), Rgb(0x11, 0x18, 0x27)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VisualColorRole {
+    Foreground,
+    Background,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Parses `#rrggbb`.
+    pub fn parse_hex(value: &str) -> Option<Self> {
+        let digits = value.strip_prefix('#')?;
+        if digits.len() != 6 || !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |start: usize| u8::from_str_radix(&digits[start..start + 2], 16).ok();
+        Some(Self(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    /// WCAG relative luminance.
+    fn luminance(self) -> f64 {
+        let linear = |channel: u8| {
+            let value = f64::from(channel) / 255.0;
+            if value <= 0.03928 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.0) + 0.7152 * linear(self.1) + 0.0722 * linear(self.2)
+    }
+
+    /// WCAG contrast ratio, from 1 to 21.
+    pub fn contrast(self, other: Self) -> f64 {
+        let (left, right) = (self.luminance(), other.luminance());
+        (left.max(right) + 0.05) / (left.min(right) + 0.05)
+    }
+}
+
+/// Built-in tokens followed by the user-defined colors of an envelope.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VisualPalette {
+    custom: Vec<PaletteColor>,
+}
+
+impl VisualPalette {
+    /// Palette over custom colors that already passed validation.
+    pub fn new(custom: Vec<PaletteColor>) -> Self {
+        Self { custom }
+    }
+
+    pub fn custom(&self) -> &[PaletteColor] {
+        &self.custom
+    }
+
+    /// Every token rules may use besides `default`, built-ins first.
+    pub fn tokens(&self) -> Vec<&str> {
+        BUILTIN_COLORS
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(self.custom.iter().map(|color| color.name.as_str()))
+            .collect()
+    }
+
+    /// Returns true when rules may use `token`; `default` means no color.
+    pub fn knows(&self, token: &VisualColor) -> bool {
+        token.0 == "default" || self.tokens().contains(&token.0.as_str())
+    }
+
+    /// Color of `token` in `theme`; `default` and unknown tokens have none.
+    pub fn resolve(
+        &self,
+        token: &VisualColor,
+        role: VisualColorRole,
+        theme: VisualTheme,
+    ) -> Option<Rgb> {
+        let name = token.0.as_str();
+        if let Some((_, colors)) = BUILTIN_COLORS.iter().find(|(builtin, _)| *builtin == name) {
+            let index = match (theme, role) {
+                (VisualTheme::Light, VisualColorRole::Foreground) => 0,
+                (VisualTheme::Light, VisualColorRole::Background) => 1,
+                (VisualTheme::Dark, VisualColorRole::Foreground) => 2,
+                (VisualTheme::Dark, VisualColorRole::Background) => 3,
+            };
+            return Some(colors[index]);
+        }
+        let color = self.custom.iter().find(|color| color.name == name)?;
+        let variant = match theme {
+            VisualTheme::Light => &color.light,
+            VisualTheme::Dark => &color.dark,
+        };
+        Rgb::parse_hex(match role {
+            VisualColorRole::Foreground => &variant.foreground,
+            VisualColorRole::Background => &variant.background,
+        })
+    }
+}
+
+/// Returns true when `token` is a built-in palette token or `default`.
+pub(crate) fn is_builtin_token(token: &VisualColor) -> bool {
+    token.0 == "default" || BUILTIN_COLORS.iter().any(|(name, _)| *name == token.0)
+}
+
+/// Checks a custom color's name against the built-ins and `earlier` colors, and each theme's
+/// variant for readability: the foreground on the theme background, and the theme text on the
+/// background, must both reach [`MIN_PALETTE_CONTRAST`].
+pub fn validate_palette_color(
+    color: &PaletteColor,
+    earlier: &[PaletteColor],
+) -> Result<(), String> {
+    let name = color.name.as_str();
+    if name.is_empty() {
+        return Err("needs a name".to_string());
+    }
+    if name.len() > MAX_PALETTE_NAME_LENGTH {
+        return Err("name exceeds 32 characters".to_string());
+    }
+    if !name.chars().all(|character| {
+        character.is_ascii_lowercase() || character.is_ascii_digit() || character == '-'
+    }) {
+        return Err("name may only use lowercase letters, digits and '-'".to_string());
+    }
+    if is_builtin_token(&VisualColor(name.to_string())) {
+        return Err("uses a built-in token name".to_string());
+    }
+    if earlier.iter().any(|other| other.name == name) {
+        return Err("is defined more than once".to_string());
+    }
+    for (theme, variant) in [
+        (VisualTheme::Light, &color.light),
+        (VisualTheme::Dark, &color.dark),
+    ] {
+        let label = match theme {
+            VisualTheme::Light => "light",
+            VisualTheme::Dark => "dark",
+        };
+        let parse = |value: &str, role: &str| {
+            Rgb::parse_hex(value)
+                .ok_or_else(|| format!("{label} {role} '{value}' is not a #rrggbb color"))
+        };
+        let foreground = parse(&variant.foreground, "foreground")?;
+        let background = parse(&variant.background, "background")?;
+        let (text, page) = theme.base();
+        let foreground_contrast = foreground.contrast(page);
+        if foreground_contrast < MIN_PALETTE_CONTRAST {
+            return Err(format!(
+                "{label} foreground contrast {foreground_contrast:.1}:1 is below 4.5:1"
+            ));
+        }
+        let background_contrast = text.contrast(background);
+        if background_contrast < MIN_PALETTE_CONTRAST {
+            return Err(format!(
+                "{label} background contrast {background_contrast:.1}:1 is below 4.5:1"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(name: &str, light: (&str, &str), dark: (&str, &str)) -> PaletteColor {
+        PaletteColor {
+            name: name.to_string(),
+            light: PaletteVariant {
+                foreground: light.0.to_string(),
+                background: light.1.to_string(),
+            },
+            dark: PaletteVariant {
+                foreground: dark.0.to_string(),
+                background: dark.1.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn builtin_colors_meet_the_contrast_they_require_of_custom_ones() {
+        for (name, colors) in BUILTIN_COLORS {
+            let builtin = color(
+                &format!("x-{name}"),
+                (&colors[0].to_hex(), &colors[1].to_hex()),
+                (&colors[2].to_hex(), &colors[3].to_hex()),
+            );
+            assert_eq!(validate_palette_color(&builtin, &[]), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn custom_colors_resolve_per_theme_and_role_next_to_builtins() {
+        let palette = VisualPalette::new(vec![color(
+            "teal",
+            ("#0f766e", "#ccfbf1"),
+            ("#2dd4bf", "#134e4a"),
+        )]);
+        let teal = VisualColor("teal".to_string());
+        assert!(palette.knows(&teal));
+        assert_eq!(
+            palette.resolve(&teal, VisualColorRole::Background, VisualTheme::Dark),
+            Some(Rgb(0x13, 0x4e, 0x4a))
+        );
+        assert_eq!(
+            palette
+                .resolve(
+                    &VisualColor("red".to_string()),
+                    VisualColorRole::Foreground,
+                    VisualTheme::Light
+                )
+                .map(Rgb::to_hex)
+                .as_deref(),
+            Some("#b91c1c")
+        );
+        assert_eq!(palette.tokens().last(), Some(&"teal"));
+        let default = VisualColor("default".to_string());
+        assert!(palette.knows(&default));
+        assert_eq!(
+            palette.resolve(&default, VisualColorRole::Foreground, VisualTheme::Light),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_names_hex_values_and_low_contrast_are_rejected() {
+        let valid = color("teal", ("#0f766e", "#ccfbf1"), ("#2dd4bf", "#134e4a"));
+        let with_name = |name: &str| PaletteColor {
+            name: name.to_string(),
+            ..valid.clone()
+        };
+        assert_eq!(
+            validate_palette_color(&with_name("Teal"), &[]),
+            Err("name may only use lowercase letters, digits and '-'".to_string())
+        );
+        assert_eq!(
+            validate_palette_color(&with_name("red"), &[]),
+            Err("uses a built-in token name".to_string())
+        );
+        assert_eq!(
+            validate_palette_color(&valid, std::slice::from_ref(&valid)),
+            Err("is defined more than once".to_string())
+        );
+        assert_eq!(
+            validate_palette_color(
+                &color("teal", ("0f766e", "#ccfbf1"), ("#2dd4bf", "#134e4a")),
+                &[]
+            ),
+            Err("light foreground '0f766e' is not a #rrggbb color".to_string())
+        );
+        assert_eq!(
+            validate_palette_color(
+                &color("pale", ("#fde68a", "#ffffff"), ("#2dd4bf", "#134e4a")),
+                &[]
+            ),
+            Err("light foreground contrast 1.2:1 is below 4.5:1".to_string())
+        );
+        assert_eq!(
+            validate_palette_color(
+                &color("murky", ("#0f766e", "#ccfbf1"), ("#2dd4bf", "#6b7280")),
+                &[]
+            ),
+            Err("dark background contrast 3.9:1 is below 4.5:1".to_string())
+        );
+    }
+
+    #[test]
+    fn contrast_follows_wcag() {
+        let black = Rgb(0, 0, 0);
+        let white = Rgb::parse_hex("#FFFFFF").unwrap();
+        assert!((black.contrast(white) - 21.0).abs() < 1e-9);
+        assert_eq!(white.contrast(white), 1.0);
+        assert_eq!(Rgb::parse_hex("#fff"), None);
+    }
+}
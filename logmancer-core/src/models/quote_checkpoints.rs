@@ -0,0 +1,72 @@
+use crate::file_ops::read::FileReadOps;
+use crate::log_format::LogFormat;
+use crate::models::log_file::LogFile;
+
+/// Lines between the quote checkpoints of a delimited file.
+pub const QUOTE_CHECKPOINT_LINES: usize = 4096;
+
+/// Whether every `QUOTE_CHECKPOINT_LINES`th line of a delimited file starts inside a quoted
+/// value, so readers know where records begin without counting from the start.
+#[derive(Clone, Debug, Default)]
+pub struct QuoteCheckpoints {
+    checkpoints: Vec<bool>,
+    /// Last line looked up and whether it starts inside a quoted value.
+    last: Option<(usize, bool)>,
+}
+
+impl QuoteCheckpoints {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether `line` starts inside a quoted value of the `delimited` format, from the quotes
+    /// above it. A line less than a checkpoint below the last one looked up is counted from
+    /// there, so lines looked up in file order are read once. Checkpoints are only added over
+    /// indexed lines, so they stay valid while the file grows.
+    pub fn opens_inside_quotes(
+        &mut self,
+        log_file: &LogFile,
+        delimited: &LogFormat,
+        line: usize,
+    ) -> bool {
+        let in_quotes = match self.last {
+            Some((from_line, in_quotes))
+                if from_line <= line && line - from_line < QUOTE_CHECKPOINT_LINES =>
+            {
+                FileReadOps::quotes_open_after(log_file, delimited, from_line, line, in_quotes)
+            }
+            _ => self.counted_from_checkpoint(log_file, delimited, line),
+        };
+        self.last = Some((line, in_quotes));
+        in_quotes
+    }
+
+    fn counted_from_checkpoint(
+        &mut self,
+        log_file: &LogFile,
+        delimited: &LogFormat,
+        line: usize,
+    ) -> bool {
+        let indexed_lines = log_file.index.len();
+        let checkpoints = &mut self.checkpoints;
+        if checkpoints.is_empty() {
+            checkpoints.push(false);
+        }
+        while checkpoints.len() <= line / QUOTE_CHECKPOINT_LINES
+            && checkpoints.len() * QUOTE_CHECKPOINT_LINES < indexed_lines
+        {
+            let last = checkpoints.len() - 1;
+            let from_line = last * QUOTE_CHECKPOINT_LINES;
+            checkpoints.push(FileReadOps::quotes_open_after(
+                log_file,
+                delimited,
+                from_line,
+                from_line + QUOTE_CHECKPOINT_LINES,
+                checkpoints[last],
+            ));
+        }
+        let nearest = (line / QUOTE_CHECKPOINT_LINES).min(checkpoints.len() - 1);
+        let from_line = nearest * QUOTE_CHECKPOINT_LINES;
+        FileReadOps::quotes_open_after(log_file, delimited, from_line, line, checkpoints[nearest])
+    }
+}
@@ -0,0 +1,332 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::file_ops::read::FileReadOps;
+use crate::log_format::LogFormat;
+use crate::models::log_file::LogFile;
+use crate::models::match_index::{MATCH_INDEX_BUDGET_BYTES, MatchIndex};
+use crate::models::page_result::PageResult;
+use crate::models::quote_checkpoints::QuoteCheckpoints;
+use crate::models::search::SearchPhase;
+use crate::models::visual_rules::LineStyleIntent;
+use crate::visual_rules::{VisualLine, VisualRuleEvaluator};
+
+#[derive(Clone, Debug, Default)]
+pub struct RuleCountState {
+    pub session: Option<RuleCountSession>,
+}
+
+/// Lines matched by each rule of the evaluator a file renders with, scanned in the
+/// background like a search.
+///
+/// Every rule is tested on its own, so a line styled by an earlier rule still counts for
+/// the later rules it matches.
+#[derive(Clone, Debug)]
+pub struct RuleCountSession {
+    pub generation: u64,
+    /// Manager revision the evaluator was published with.
+    pub revision: u64,
+    pub profile: String,
+    pub evaluator: Arc<VisualRuleEvaluator>,
+    /// Format lines are parsed with for field conditions, as pages parse them.
+    pub format: Option<Arc<LogFormat>>,
+    /// Where delimited records begin, for rechecking candidate lines.
+    pub quote_checkpoints: QuoteCheckpoints,
+    pub phase: SearchPhase,
+    pub rules: Vec<RuleMatches>,
+    /// Lines `0..scanned_lines` are claimed by a scan; the session is extended from here as
+    /// the followed file grows.
+    pub scanned_lines: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct RuleMatches {
+    pub index: MatchIndex,
+    /// Line last reached by navigating with this rule.
+    pub current: Option<usize>,
+}
+
+/// A line range of a rule count session handed to its worker for scanning.
+#[derive(Clone, Debug)]
+pub struct RuleCountScan {
+    pub generation: u64,
+    pub evaluator: Arc<VisualRuleEvaluator>,
+    pub format: Option<Arc<LogFormat>>,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RuleCountStatus {
+    pub generation: u64,
+    pub revision: u64,
+    pub profile: String,
+    pub is_ready: bool,
+    /// Counts of the active profile rules, in rule priority order.
+    pub rules: Vec<RuleCount>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RuleCount {
+    pub name: Option<String>,
+    pub style: LineStyleIntent,
+    pub matched_lines: usize,
+    pub current_line_index: Option<usize>,
+}
+
+/// Page positioned around the line a rule navigation reached.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RuleNavigation {
+    pub rule: usize,
+    /// Current line of the rule, `None` while no scanned line matches it.
+    pub line_index: Option<usize>,
+    pub page: PageResult,
+}
+
+impl RuleCountState {
+    pub fn status(&self) -> Option<RuleCountStatus> {
+        self.session.as_ref().map(RuleCountSession::status)
+    }
+}
+
+impl RuleCountSession {
+    /// Starts a session over the first `content_lines` lines, returned as its first scan.
+    pub fn indexing(
+        generation: u64,
+        revision: u64,
+        profile: String,
+        evaluator: Arc<VisualRuleEvaluator>,
+        format: Option<Arc<LogFormat>>,
+        content_lines: usize,
+    ) -> (Self, RuleCountScan) {
+        // The rules share the memory budget of one search session.
+        let budget = MATCH_INDEX_BUDGET_BYTES / evaluator.rule_count().max(1);
+        let rules = (0..evaluator.rule_count())
+            .map(|_| RuleMatches {
+                index: MatchIndex::with_budget(budget),
+                current: None,
+            })
+            .collect();
+        let scan = RuleCountScan {
+            generation,
+            evaluator: evaluator.clone(),
+            format: format.clone(),
+            start_line: 0,
+            end_line: content_lines,
+        };
+        let session = Self {
+            generation,
+            revision,
+            profile,
+            evaluator,
+            format,
+            quote_checkpoints: QuoteCheckpoints::default(),
+            phase: SearchPhase::Indexing,
+            rules,
+            scanned_lines: content_lines,
+        };
+        (session, scan)
+    }
+
+    pub fn status(&self) -> RuleCountStatus {
+        let names = self.evaluator.names();
+        let styles = self.evaluator.styles();
+        RuleCountStatus {
+            generation: self.generation,
+            revision: self.revision,
+            profile: self.profile.clone(),
+            is_ready: matches!(self.phase, SearchPhase::Ready),
+            rules: self
+                .rules
+                .iter()
+                .zip(names.into_iter().zip(styles))
+                .map(|(matches, (name, style))| RuleCount {
+                    name,
+                    style,
+                    matched_lines: matches.index.total(),
+                    current_line_index: matches.current,
+                })
+                .collect(),
+        }
+    }
+
+    /// Merges the matching lines of each rule, indexed like the evaluator rules.
+    pub fn merge_batch(&mut self, batch: Vec<Vec<usize>>, mark_ready: bool) {
+        for (matches, lines) in self.rules.iter_mut().zip(batch) {
+            matches
+                .index
+                .add_lines(lines.into_iter().map(|line| (line, 1)));
+        }
+        self.phase = if mark_ready {
+            SearchPhase::Ready
+        } else {
+            SearchPhase::Indexing
+        };
+    }
+
+    /// Claims the lines indexed since the last scan when the session is ready, switching it
+    /// back to indexing until the extension batches are merged.
+    pub fn claim_new_lines(&mut self, content_lines: usize) -> Option<RuleCountScan> {
+        if !matches!(self.phase, SearchPhase::Ready) || content_lines <= self.scanned_lines {
+            return None;
+        }
+        let scan = RuleCountScan {
+            generation: self.generation,
+            evaluator: self.evaluator.clone(),
+            format: self.format.clone(),
+            start_line: self.scanned_lines,
+            end_line: content_lines,
+        };
+        self.scanned_lines = content_lines;
+        self.phase = SearchPhase::Indexing;
+        Some(scan)
+    }
+
+    /// Moves `rule` to its next matching line after its current one, or from `view_start`
+    /// before the first move, wrapping at the end. Returns false for an unknown rule.
+    pub fn next(&mut self, log_file: &LogFile, rule: usize, view_start: usize) -> bool {
+        let Some(current) = self.rules.get(rule).map(|matches| matches.current) else {
+            return false;
+        };
+        let from = current.map_or(view_start, |line| line + 1);
+        let mut quotes = std::mem::take(&mut self.quote_checkpoints);
+        let found = self
+            .find_after(log_file, rule, from, &mut quotes)
+            .or_else(|| self.find_after(log_file, rule, 0, &mut quotes));
+        self.quote_checkpoints = quotes;
+        self.rules[rule].current = found.or(current);
+        true
+    }
+
+    /// Like [`Self::next`] walking backwards.
+    pub fn previous(&mut self, log_file: &LogFile, rule: usize, view_start: usize) -> bool {
+        let Some(current) = self.rules.get(rule).map(|matches| matches.current) else {
+            return false;
+        };
+        let mut quotes = std::mem::take(&mut self.quote_checkpoints);
+        let found = self
+            .find_before(log_file, rule, current.unwrap_or(view_start), &mut quotes)
+            .or_else(|| self.find_before(log_file, rule, self.scanned_lines, &mut quotes));
+        self.quote_checkpoints = quotes;
+        self.rules[rule].current = found.or(current);
+        true
+    }
+
+    fn find_after(
+        &self,
+        log_file: &LogFile,
+        rule: usize,
+        from: usize,
+        quotes: &mut QuoteCheckpoints,
+    ) -> Option<usize> {
+        let end = self.scanned_lines.min(content_lines(log_file));
+        self.rules[rule]
+            .index
+            .candidates_after(from)
+            .find_map(|range| {
+                (range.start..range.end.min(end))
+                    .find(|line| self.matches(log_file, rule, *line, quotes))
+            })
+    }
+
+    fn find_before(
+        &self,
+        log_file: &LogFile,
+        rule: usize,
+        to: usize,
+        quotes: &mut QuoteCheckpoints,
+    ) -> Option<usize> {
+        let end = self.scanned_lines.min(content_lines(log_file));
+        let delimited = self
+            .format
+            .as_ref()
+            .is_some_and(|format| format.has_header());
+        self.rules[rule]
+            .index
+            .candidates_before(to)
+            .find_map(|range| {
+                let mut lines = range.start..range.end.min(end);
+                if delimited {
+                    // Where delimited records begin is only known walking down the file.
+                    lines
+                        .filter(|line| self.matches(log_file, rule, *line, quotes))
+                        .last()
+                } else {
+                    lines.rfind(|line| self.matches(log_file, rule, *line, quotes))
+                }
+            })
+    }
+
+    /// Rechecks a candidate line, since count-only indexes only know which blocks match.
+    fn matches(
+        &self,
+        log_file: &LogFile,
+        rule: usize,
+        line: usize,
+        quotes: &mut QuoteCheckpoints,
+    ) -> bool {
+        FileReadOps::line_text(log_file, line).is_ok_and(|text| {
+            let fields =
+                FileReadOps::line_fields(log_file, self.format.as_deref(), quotes, line, &text);
+            self.evaluator.rule_matches(
+                rule,
+                VisualLine {
+                    text: &text,
+                    number: Some(line + 1),
+                    fields: &fields,
+                },
+            )
+        })
+    }
+}
+
+fn content_lines(log_file: &LogFile) -> usize {
+    log_file.index.len().saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RuleCountSession;
+    use crate::models::search::SearchPhase;
+    use crate::models::visual_rules::{LineStyleIntent, VisualMatcher, VisualRule};
+    use crate::visual_rules::VisualRuleEvaluator;
+    use std::sync::Arc;
+
+    fn rule(name: &str, pattern: &str) -> VisualRule {
+        VisualRule {
+            name: Some(name.to_string()),
+            matcher: VisualMatcher::Text(pattern.to_string()),
+            case_sensitive: false,
+            style: LineStyleIntent::default(),
+            scope: Default::default(),
+        }
+    }
+
+    #[test]
+    fn batches_count_every_rule_independently_and_extend_once_ready() {
+        let evaluator = Arc::new(VisualRuleEvaluator::compile(&[
+            rule("errors", "error"),
+            rule("timeouts", "timeout"),
+        ]));
+        let (mut session, scan) =
+            RuleCountSession::indexing(3, 7, "Default".into(), evaluator, None, 10);
+        assert_eq!((scan.start_line, scan.end_line), (0, 10));
+        assert!(session.claim_new_lines(12).is_none(), "not ready yet");
+
+        session.merge_batch(vec![vec![1, 4], vec![4]], false);
+        session.merge_batch(vec![vec![8]], true);
+        let status = session.status();
+        assert!(status.is_ready);
+        assert_eq!((status.generation, status.revision), (3, 7));
+        let counts = status
+            .rules
+            .iter()
+            .map(|count| (count.name.as_deref(), count.matched_lines))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [(Some("errors"), 3), (Some("timeouts"), 1)]);
+
+        let extension = session.claim_new_lines(12).unwrap();
+        assert_eq!((extension.start_line, extension.end_line), (10, 12));
+        assert_eq!(session.phase, SearchPhase::Indexing);
+    }
+}
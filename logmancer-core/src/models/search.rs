@@ -1,4 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::models::log_file::LogFile;
+use crate::models::match_index::MatchIndex;
+use crate::search_matcher::SearchMatcher;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct SearchMatch {
@@ -19,6 +24,29 @@ pub struct PageSearchResult {
     pub page_matches: Vec<SearchMatch>,
 }
 
+/// Maximum number of hits returned by one "find all" page.
+pub const MAX_SEARCH_HITS_PAGE: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SearchHit {
+    /// 1-based line number, like `PageLine::number`.
+    pub line_number: usize,
+    pub text: String,
+    pub search_match: SearchMatch,
+}
+
+/// A window of the session matches in file order, read under one lock so it is
+/// self-consistent even while the worker is still merging batches.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SearchHitsPage {
+    pub query: String,
+    pub offset: usize,
+    pub hits: Vec<SearchHit>,
+    pub total_matches: usize,
+    pub total_matches_final: bool,
+    pub is_indexing: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct SearchDisplayStatus {
     pub query: String,
@@ -40,9 +68,25 @@ pub struct SearchSession {
     pub origin_line: usize,
     pub phase: SearchPhase,
     pub total_matches_final: bool,
-    pub matches: Vec<SearchMatch>,
+    /// Compiled query, `None` when it is not a valid regex.
+    pub matcher: Option<Arc<SearchMatcher>>,
+    pub index: MatchIndex,
     pub first_match: Option<SearchMatch>,
-    pub current_ordinal: Option<usize>,
+    /// Current match; its ordinal is refreshed whenever a batch is merged.
+    pub current: Option<SearchMatch>,
+    /// Lines `0..scanned_lines` are covered once the session is ready; the session is
+    /// extended from here as the followed file grows.
+    pub scanned_lines: usize,
+}
+
+/// A line range of a search or mark session handed to its worker for scanning.
+#[derive(Clone, Debug)]
+pub struct SearchScan {
+    /// Search generation or mark id, used to reject stale merges.
+    pub id: u64,
+    pub matcher: Arc<SearchMatcher>,
+    pub start_line: usize,
+    pub end_line: usize,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
@@ -61,6 +105,10 @@ pub struct SearchStatus {
     pub is_ready: bool,
     pub total_matches: usize,
     pub total_matches_final: bool,
+    /// Match positions are no longer tracked because the query matches too often; counts
+    /// and navigation still work by rescanning the blocks around the current match.
+    #[serde(default)]
+    pub count_only: bool,
     pub first: Option<SearchMatch>,
     pub current: Option<SearchMatch>,
 }
@@ -96,17 +144,7 @@ impl SearchState {
 
     pub fn status(&self) -> SearchStatus {
         match &self.session {
-            Some(session) => SearchStatus {
-                query: Some(session.query.clone()),
-                generation: session.generation,
-                origin_line: Some(session.origin_line),
-                phase: session.phase.clone(),
-                is_ready: matches!(session.phase, SearchPhase::Ready),
-                total_matches: session.matches.len(),
-                total_matches_final: session.total_matches_final,
-                first: session.first_match.clone(),
-                current: session.current_match().cloned(),
-            },
+            Some(session) => session.status(),
             None => SearchStatus {
                 query: None,
                 generation: 0,
@@ -115,6 +153,7 @@ impl SearchState {
                 is_ready: true,
                 total_matches: 0,
                 total_matches_final: true,
+                count_only: false,
                 first: None,
                 current: None,
             },
@@ -123,40 +162,303 @@ impl SearchState {
 }
 
 impl SearchSession {
+    /// Starts a session; a query that is not a valid regex yields a ready session without
+    /// matches.
     pub fn indexing(generation: u64, query: String, origin_line: usize) -> Self {
+        let matcher = SearchMatcher::new(&query).ok().map(Arc::new);
+        let valid = matcher.is_some();
         Self {
             generation,
             query,
             origin_line,
-            phase: SearchPhase::Indexing,
-            total_matches_final: false,
-            matches: Vec::new(),
+            phase: if valid {
+                SearchPhase::Indexing
+            } else {
+                SearchPhase::Ready
+            },
+            total_matches_final: !valid,
+            matcher,
+            index: MatchIndex::default(),
             first_match: None,
-            current_ordinal: None,
+            current: None,
+            scanned_lines: 0,
         }
     }
 
-    pub fn current_match(&self) -> Option<&SearchMatch> {
-        self.current_ordinal.and_then(|idx| self.matches.get(idx))
+    pub fn status(&self) -> SearchStatus {
+        SearchStatus {
+            query: Some(self.query.clone()),
+            generation: self.generation,
+            origin_line: Some(self.origin_line),
+            phase: self.phase.clone(),
+            is_ready: matches!(self.phase, SearchPhase::Ready),
+            total_matches: self.index.total(),
+            total_matches_final: self.total_matches_final,
+            count_only: self.index.is_count_only(),
+            first: self.first_match.clone(),
+            current: self.current.clone(),
+        }
     }
 
-    pub fn next(&mut self) {
-        if self.matches.is_empty() {
-            self.current_ordinal = None;
-            return;
+    /// Builds the page metadata for `lines`, recomputing the spans of the matching ones.
+    pub fn page_result(
+        &self,
+        log_file: &LogFile,
+        lines: impl IntoIterator<Item = usize>,
+    ) -> PageSearchResult {
+        let mut page_matches = Vec::new();
+        // Ordinal of the first match on the line after the previous one, so consecutive
+        // lines do not each recount the matches before them.
+        let mut next_line_ordinal: Option<(usize, usize)> = None;
+        for line in lines {
+            let found = self.line_matches(log_file, line);
+            let continues = next_line_ordinal.filter(|(next, _)| *next == line);
+            if found.is_empty() {
+                next_line_ordinal = continues.map(|(_, ordinal)| (line + 1, ordinal));
+                continue;
+            }
+            let base = match continues {
+                Some((_, ordinal)) => ordinal,
+                None => self.matches_before_line(log_file, line),
+            };
+            next_line_ordinal = Some((line + 1, base + found.len()));
+            page_matches.extend(
+                found
+                    .into_iter()
+                    .enumerate()
+                    .map(|(offset, found)| SearchMatch {
+                        ordinal: base + offset,
+                        ..found
+                    }),
+            );
+        }
+
+        PageSearchResult {
+            query: self.query.clone(),
+            total_matches: self.index.total(),
+            total_matches_final: self.total_matches_final,
+            is_indexing: !matches!(self.phase, SearchPhase::Ready),
+            first: self.first_match.clone(),
+            current: self.current.clone(),
+            page_matches,
+        }
+    }
+
+    /// Returns up to `limit` matches starting at ordinal `offset`, in file order.
+    pub fn matches_from(
+        &self,
+        log_file: &LogFile,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<SearchMatch> {
+        let Some((block_start, mut ordinal)) = self.index.block_of_ordinal(offset) else {
+            return Vec::new();
+        };
+        let mut window = Vec::new();
+        for range in self.index.candidates_after(block_start) {
+            for found in self.find_in_range(log_file, range) {
+                if ordinal >= offset {
+                    window.push(SearchMatch { ordinal, ..found });
+                    if window.len() == limit {
+                        return window;
+                    }
+                }
+                ordinal += 1;
+            }
+        }
+        window
+    }
+
+    /// Merges a worker batch in file order. The current match is tracked by position, so
+    /// batches that land before it (the wrapped part of the scan) do not move the selection.
+    pub fn merge_batch(&mut self, log_file: &LogFile, batch: Vec<SearchMatch>, mark_ready: bool) {
+        let first_found = batch.first().cloned();
+        let batch_first = batch
+            .iter()
+            .min_by_key(|found| (found.line_index, found.start))
+            .cloned();
+        self.index.add_lines(line_counts(&batch));
+
+        if let Some(batch_first) = batch_first {
+            let earlier = self.first_match.as_ref().is_none_or(|first| {
+                (batch_first.line_index, batch_first.start) < (first.line_index, first.start)
+            });
+            if earlier {
+                self.first_match = Some(SearchMatch {
+                    ordinal: 0,
+                    ..batch_first
+                });
+            }
+        }
+        self.current = self
+            .current
+            .take()
+            .or(first_found)
+            .map(|current| self.with_ordinal(log_file, current));
+
+        if mark_ready {
+            self.phase = SearchPhase::Ready;
+            self.total_matches_final = true;
+        } else {
+            self.phase = SearchPhase::Indexing;
+            self.total_matches_final = false;
+        }
+    }
+
+    /// Claims the lines indexed since the last scan when the session is ready, switching it
+    /// back to indexing until the extension batches are merged.
+    pub fn claim_new_lines(&mut self, content_lines: usize) -> Option<SearchScan> {
+        let matcher = self.matcher.clone()?;
+        if !matches!(self.phase, SearchPhase::Ready) || content_lines <= self.scanned_lines {
+            return None;
+        }
+        let scan = SearchScan {
+            id: self.generation,
+            matcher,
+            start_line: self.scanned_lines,
+            end_line: content_lines,
+        };
+        self.scanned_lines = content_lines;
+        self.phase = SearchPhase::Indexing;
+        self.total_matches_final = false;
+        Some(scan)
+    }
+
+    /// Makes the match at `line_index`/`start` current. Ordinals shift while indexing,
+    /// so matches are looked up by position rather than by ordinal.
+    pub fn select(&mut self, log_file: &LogFile, line_index: usize, start: usize) -> bool {
+        let found = self
+            .line_matches(log_file, line_index)
+            .into_iter()
+            .find(|found| found.start == start);
+        match found {
+            Some(found) => {
+                self.current = Some(self.with_ordinal(log_file, found));
+                true
+            }
+            None => false,
         }
-        let current = self.current_ordinal.unwrap_or(0);
-        self.current_ordinal = Some((current + 1) % self.matches.len());
     }
 
-    pub fn previous(&mut self) {
-        if self.matches.is_empty() {
-            self.current_ordinal = None;
+    pub fn next(&mut self, log_file: &LogFile) {
+        let Some(current) = self.current.take() else {
+            self.current = self.first_match.clone();
             return;
+        };
+        self.current = self
+            .find_after(log_file, current.line_index, current.start)
+            .map(|found| SearchMatch {
+                ordinal: current.ordinal + 1,
+                ..found
+            })
+            .or_else(|| self.first_match.clone());
+    }
+
+    pub fn previous(&mut self, log_file: &LogFile) {
+        let current = self.current.take();
+        let (line_index, start, ordinal) = current
+            .map(|current| (current.line_index, current.start, current.ordinal))
+            .unwrap_or((0, 0, 0));
+        let found = match self.find_before(log_file, line_index, start) {
+            Some(found) => Some(SearchMatch {
+                ordinal: ordinal.saturating_sub(1),
+                ..found
+            }),
+            None => self
+                .find_before(log_file, content_lines(log_file), 0)
+                .map(|found| SearchMatch {
+                    ordinal: self.index.total().saturating_sub(1),
+                    ..found
+                }),
+        };
+        self.current = found;
+    }
+
+    /// Matches of `line` with their spans recomputed; ordinals are left at zero.
+    fn line_matches(&self, log_file: &LogFile, line: usize) -> Vec<SearchMatch> {
+        if !self.index.may_match(line) {
+            return Vec::new();
         }
-        let current = self.current_ordinal.unwrap_or(0);
-        self.current_ordinal = Some((current + self.matches.len() - 1) % self.matches.len());
+        self.find_in_range(log_file, line..line + 1)
     }
+
+    fn find_in_range(&self, log_file: &LogFile, range: std::ops::Range<usize>) -> Vec<SearchMatch> {
+        let end = range.end.min(content_lines(log_file));
+        match &self.matcher {
+            Some(matcher) if range.start < end => matcher.find_in_lines(log_file, range.start, end),
+            _ => Vec::new(),
+        }
+    }
+
+    fn matches_before_line(&self, log_file: &LogFile, line: usize) -> usize {
+        match self.index.matches_before(line) {
+            Ok(before) => before,
+            Err((before_block, block_start)) => {
+                before_block + self.find_in_range(log_file, block_start..line).len()
+            }
+        }
+    }
+
+    fn with_ordinal(&self, log_file: &LogFile, found: SearchMatch) -> SearchMatch {
+        let before_in_line = self
+            .line_matches(log_file, found.line_index)
+            .iter()
+            .filter(|other| other.start < found.start)
+            .count();
+        SearchMatch {
+            ordinal: self.matches_before_line(log_file, found.line_index) + before_in_line,
+            ..found
+        }
+    }
+
+    fn find_after(
+        &self,
+        log_file: &LogFile,
+        line_index: usize,
+        start: usize,
+    ) -> Option<SearchMatch> {
+        if let Some(found) = self
+            .line_matches(log_file, line_index)
+            .into_iter()
+            .find(|found| found.start > start)
+        {
+            return Some(found);
+        }
+        self.index
+            .candidates_after(line_index + 1)
+            .find_map(|range| self.find_in_range(log_file, range).into_iter().next())
+    }
+
+    fn find_before(
+        &self,
+        log_file: &LogFile,
+        line_index: usize,
+        start: usize,
+    ) -> Option<SearchMatch> {
+        if let Some(found) = self
+            .line_matches(log_file, line_index)
+            .into_iter()
+            .rev()
+            .find(|found| found.start < start)
+        {
+            return Some(found);
+        }
+        self.index
+            .candidates_before(line_index)
+            .find_map(|range| self.find_in_range(log_file, range).pop())
+    }
+}
+
+fn content_lines(log_file: &LogFile) -> usize {
+    log_file.index.len().saturating_sub(1)
+}
+
+/// Collapses a batch sorted by line into per-line match counts.
+fn line_counts(batch: &[SearchMatch]) -> Vec<(usize, usize)> {
+    batch
+        .chunk_by(|left, right| left.line_index == right.line_index)
+        .map(|line| (line[0].line_index, line.len()))
+        .collect()
 }
 
 #[cfg(test)]
@@ -202,6 +504,7 @@ mod tests {
             is_ready: true,
             total_matches: 0,
             total_matches_final: true,
+            count_only: false,
             first: None,
             current: None,
         };
@@ -219,6 +522,7 @@ mod tests {
             is_ready: false,
             total_matches: 0,
             total_matches_final: false,
+            count_only: false,
             first: None,
             current: None,
         };
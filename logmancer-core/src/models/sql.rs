@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Most rows one SQL query returns.
+pub const MAX_SQL_ROWS: usize = 1000;
+/// Column of the `log` table holding each row's line number, counted from 1.
+pub const SQL_LINE_COLUMN: &str = "log_line";
+/// Column of the `log` table holding each row's text.
+pub const SQL_TEXT_COLUMN: &str = "log_text";
+
+/// Result set of a SQL query over the `log` table of a file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SqlResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<SqlRow>,
+    /// Whether rows past `MAX_SQL_ROWS` were left out.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SqlRow {
+    /// Source line of the row, when the query selects `log_line`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// One value per column: null, a number or text.
+    pub values: Vec<serde_json::Value>,
+}
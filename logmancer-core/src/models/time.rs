@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Timestamp of a line, read with its log format and shown in the display time zone, and the
+/// time elapsed since earlier lines.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LineTime {
+    /// Timestamp to the millisecond, such as `2026-10-03 12:00:00.250`.
+    pub timestamp: String,
+    /// Milliseconds since the nearest line above with a timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since_previous_ms: Option<i64>,
+    /// Milliseconds since the timestamp of the reference line; negative above it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since_reference_ms: Option<i64>,
+}
+
+/// Time zone and reference line timestamps of a file are shown with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TimeSettings {
+    /// `UTC` or an offset from it such as `+02:00`.
+    pub time_zone: String,
+    /// Line, counted from 1, that reference deltas are measured from.
+    #[serde(default)]
+    pub reference_line: Option<usize>,
+    /// Timestamp of the reference line in the display time zone.
+    #[serde(default)]
+    pub reference_timestamp: Option<String>,
+}
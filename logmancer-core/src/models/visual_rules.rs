@@ -1,16 +1,90 @@
 use serde::{Deserialize, Serialize};
 
+use super::palette::{MAX_PALETTE_COLORS, PaletteColor, VisualPalette, validate_palette_color};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct VisualRule {
+    /// Name of the managed rule, used to label per-rule counts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub matcher: VisualMatcher,
     pub case_sensitive: bool,
     pub style: LineStyleIntent,
+    #[serde(default)]
+    pub scope: VisualRuleScope,
 }
 
+/// Condition a line must meet for a rule to apply.
+///
+/// `Text` and `Regex` are the plain matchers every schema version knows; the other variants
+/// form a condition tree and need schema version 4.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum VisualMatcher {
     Text(String),
     Regex(String),
+    /// Lines numbered `first..=last`, counting from 1; without `last` the range runs to the
+    /// end of the file.
+    Lines {
+        first: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last: Option<usize>,
+    },
+    /// Predicate over a parsed field; lines without parsed fields never match it.
+    Field(FieldPredicate),
+    /// Every condition holds.
+    All(Vec<VisualMatcher>),
+    /// At least one condition holds.
+    Any(Vec<VisualMatcher>),
+    Not(Box<VisualMatcher>),
+}
+
+impl VisualMatcher {
+    /// True for condition trees and the line and field conditions they are built from.
+    pub fn is_compound(&self) -> bool {
+        !matches!(self, Self::Text(_) | Self::Regex(_))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldPredicate {
+    pub field: String,
+    pub operator: FieldOperator,
+    pub value: String,
+}
+
+/// Comparison of a field with a predicate value.
+///
+/// Ordering operators compare numbers (with an optional duration unit) numerically and log
+/// levels by severity, and fall back to comparing text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FieldOperator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+/// Part of a matching line that a rule styles.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub enum VisualRuleScope {
+    /// The whole row; the first matching line rule wins.
+    #[default]
+    Line,
+    /// Every matched span.
+    Match,
+    /// The listed capture groups of every match of a regex rule, numbered from 1.
+    Groups(Vec<usize>),
+}
+
+impl VisualRuleScope {
+    pub fn is_line(&self) -> bool {
+        matches!(self, Self::Line)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -21,31 +95,105 @@ pub enum VisualMatcher {
 /// terminal styles, or any other UI-specific color representation.
 pub struct VisualColor(pub String);
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
 pub struct LineStyleIntent {
     pub foreground: Option<VisualColor>,
     pub background: Option<VisualColor>,
+    #[serde(default, skip_serializing_if = "TextAttributes::is_empty")]
+    pub attributes: TextAttributes,
+    /// Marker drawn beside the line number; only line-scoped rules may set it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gutter: Option<GutterMarker>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TextAttributes {
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub underline: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strikethrough: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dim: bool,
+}
+
+impl TextAttributes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+pub(crate) fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Gutter marker shown for lines styled by a rule.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GutterMarker {
+    pub icon: GutterIcon,
+    /// Palette token of the marker; UIs fall back to the rule foreground when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<VisualColor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GutterIcon {
+    Dot,
+    Flag,
+    Star,
+    Warning,
+    Error,
+}
+
+/// Style a match-scoped rule applies to `start..end`, byte offsets into `PageLine::text`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StyledSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: LineStyleIntent,
 }
 
 pub const MAX_STORED_VISUAL_RULES: usize = 100;
 pub const MAX_ENABLED_VISUAL_RULES: usize = 50;
 pub const MAX_VISUAL_RULE_PATTERN_LENGTH: usize = 512;
 pub const MAX_VISUAL_RULE_NAME_LENGTH: usize = 80;
+pub const MAX_VISUAL_RULE_PROFILES: usize = 16;
+/// Deepest nesting of `All`, `Any` and `Not` conditions.
+pub const MAX_VISUAL_CONDITION_DEPTH: usize = 8;
+/// Most conditions, leaves and groups alike, in one rule.
+pub const MAX_VISUAL_CONDITION_NODES: usize = 32;
+/// Name under which the top-level `rules` of an envelope are listed next to its profiles.
+pub const DEFAULT_VISUAL_PROFILE: &str = "Default";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct VisualRulesEnvelope {
     pub schema_version: u32,
     pub rules: Vec<ManagedVisualRule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<VisualRuleProfile>,
+    /// Custom color tokens rules may use next to the built-in ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<PaletteColor>,
 }
 
 impl VisualRulesEnvelope {
     pub const MAX_PERSISTED_SIZE: usize = 256 * 1024;
+    /// Version written by this build; version 2 added text attributes and gutter markers,
+    /// version 3 added profiles, version 4 compound conditions and version 5 the palette.
+    pub const SCHEMA_VERSION: u32 = 5;
 
     pub fn new(rules: Vec<ManagedVisualRule>) -> Self {
         Self {
-            schema_version: 1,
+            schema_version: Self::SCHEMA_VERSION,
             rules,
+            profiles: Vec::new(),
+            palette: Vec::new(),
         }
     }
 
@@ -66,21 +214,68 @@ pub struct ManagedVisualRule {
     pub matcher: VisualMatcher,
     pub case_sensitive: bool,
     pub style: LineStyleIntent,
+    #[serde(default, skip_serializing_if = "VisualRuleScope::is_line")]
+    pub scope: VisualRuleScope,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Named rule set used instead of the top-level rules on the files it is active for.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualRuleProfile {
+    pub name: String,
+    pub rules: Vec<ManagedVisualRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_select: Option<ProfileAutoSelect>,
+}
+
+/// Condition that makes a profile the automatic choice for a file; every set field must match.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileAutoSelect {
+    /// Glob over the file path, or over the file name when it has no `/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_glob: Option<String>,
+    /// Detected log format name, compared case-insensitively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+/// Profile a file renders with, and why.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VisualProfileStatus {
+    pub active: String,
+    /// Profile chosen for this file, if it still exists.
+    pub pinned: Option<String>,
+    /// Profile the auto-select conditions pick for this file.
+    pub auto_selected: Option<String>,
+    /// Every selectable profile, starting with the default one.
+    pub profiles: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct ValidationReport {
     pub evaluator_rules: Vec<VisualRule>,
+    pub profiles: Vec<ValidatedProfile>,
+    /// Custom colors that passed validation.
+    pub palette: Vec<PaletteColor>,
     pub diagnostics: Vec<ValidationDiagnostic>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidatedProfile {
+    pub name: String,
+    pub auto_select: Option<ProfileAutoSelect>,
+    pub evaluator_rules: Vec<VisualRule>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct ValidationDiagnostic {
     pub severity: ValidationSeverity,
     pub message: String,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub enum ValidationSeverity {
     Warning,
     Error,
@@ -103,50 +298,218 @@ fn validate_envelope(
     envelope: &VisualRulesEnvelope,
     recover_entries: bool,
 ) -> Result<ValidationReport, ValidationError> {
-    if envelope.schema_version != 1 {
+    if !(1..=VisualRulesEnvelope::SCHEMA_VERSION).contains(&envelope.schema_version) {
         return Err(ValidationError {
             message: "unsupported schemaVersion".to_string(),
         });
     }
-    if envelope.rules.len() > MAX_STORED_VISUAL_RULES {
+    if !envelope.profiles.is_empty() && envelope.schema_version < 3 {
         return Err(ValidationError {
-            message: "at most 100 rules may be stored".to_string(),
+            message: "profiles require schemaVersion 3".to_string(),
+        });
+    }
+    if !envelope.palette.is_empty() && envelope.schema_version < 5 {
+        return Err(ValidationError {
+            message: "palette requires schemaVersion 5".to_string(),
+        });
+    }
+    if envelope.palette.len() > MAX_PALETTE_COLORS {
+        return Err(ValidationError {
+            message: "at most 32 palette colors may be stored".to_string(),
         });
     }
-    if envelope.rules.iter().filter(|rule| rule.enabled).count() > MAX_ENABLED_VISUAL_RULES {
+    if envelope.profiles.len() > MAX_VISUAL_RULE_PROFILES {
         return Err(ValidationError {
-            message: "at most 50 rules may be enabled".to_string(),
+            message: "at most 16 profiles may be stored".to_string(),
+        });
+    }
+    let stored_rules = envelope.rules.len()
+        + envelope
+            .profiles
+            .iter()
+            .map(|profile| profile.rules.len())
+            .sum::<usize>();
+    if stored_rules > MAX_STORED_VISUAL_RULES {
+        return Err(ValidationError {
+            message: "at most 100 rules may be stored".to_string(),
         });
     }
 
-    let mut evaluator_rules = Vec::new();
     let mut diagnostics = Vec::new();
-    for (index, rule) in envelope.rules.iter().enumerate() {
-        match validate_rule(rule) {
+    let mut palette = Vec::with_capacity(envelope.palette.len());
+    for color in &envelope.palette {
+        match validate_palette_color(color, &palette) {
+            Ok(()) => palette.push(color.clone()),
+            Err(message) if recover_entries => diagnostics.push(ValidationDiagnostic {
+                severity: ValidationSeverity::Warning,
+                message: format!("palette color '{}': {}", color.name, message),
+            }),
+            Err(message) => {
+                return Err(ValidationError {
+                    message: format!("palette color '{}': {}", color.name, message),
+                });
+            }
+        }
+    }
+    let tokens = VisualPalette::new(palette);
+    let evaluator_rules = validate_rules(
+        &envelope.rules,
+        &tokens,
+        envelope.schema_version,
+        recover_entries,
+        "",
+        &mut diagnostics,
+    )?;
+    let mut profiles = Vec::with_capacity(envelope.profiles.len());
+    for (index, profile) in envelope.profiles.iter().enumerate() {
+        let prefix = format!("profile '{}' ", profile.name);
+        validate_profile(profile, &envelope.profiles[..index]).map_err(|message| {
+            ValidationError {
+                message: format!("{prefix}{message}"),
+            }
+        })?;
+        profiles.push(ValidatedProfile {
+            name: profile.name.clone(),
+            auto_select: profile.auto_select.clone(),
+            evaluator_rules: validate_rules(
+                &profile.rules,
+                &tokens,
+                envelope.schema_version,
+                recover_entries,
+                &prefix,
+                &mut diagnostics,
+            )?,
+        });
+    }
+    Ok(ValidationReport {
+        evaluator_rules,
+        profiles,
+        palette: tokens.custom().to_vec(),
+        diagnostics,
+    })
+}
+
+fn validate_rules(
+    rules: &[ManagedVisualRule],
+    palette: &VisualPalette,
+    schema_version: u32,
+    recover_entries: bool,
+    prefix: &str,
+    diagnostics: &mut Vec<ValidationDiagnostic>,
+) -> Result<Vec<VisualRule>, ValidationError> {
+    if rules.iter().filter(|rule| rule.enabled).count() > MAX_ENABLED_VISUAL_RULES {
+        return Err(ValidationError {
+            message: format!("{prefix}at most 50 rules may be enabled"),
+        });
+    }
+    let mut evaluator_rules = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        let validation = if schema_version < 2 && uses_schema_2_styles(rule) {
+            Err("text attributes and gutter markers require schemaVersion 2".to_string())
+        } else if schema_version < 4 && rule.matcher.is_compound() {
+            Err("compound conditions require schemaVersion 4".to_string())
+        } else {
+            validate_rule(rule, palette)
+        };
+        match validation {
             Ok(()) if rule.enabled => evaluator_rules.push(VisualRule {
+                name: rule.name.clone(),
                 matcher: rule.matcher.clone(),
                 case_sensitive: rule.case_sensitive,
                 style: rule.style.clone(),
+                scope: rule.scope.clone(),
             }),
             Ok(()) => {}
             Err(message) if recover_entries => diagnostics.push(ValidationDiagnostic {
                 severity: ValidationSeverity::Warning,
-                message: format!("rule {}: {}", index + 1, message),
+                message: format!("{prefix}rule {}: {}", index + 1, message),
             }),
             Err(message) => {
                 return Err(ValidationError {
-                    message: format!("rule {}: {}", index + 1, message),
+                    message: format!("{prefix}rule {}: {}", index + 1, message),
                 });
             }
         }
     }
-    Ok(ValidationReport {
-        evaluator_rules,
-        diagnostics,
-    })
+    Ok(evaluator_rules)
 }
 
-fn validate_rule(rule: &ManagedVisualRule) -> Result<(), String> {
+fn validate_profile(
+    profile: &VisualRuleProfile,
+    earlier: &[VisualRuleProfile],
+) -> Result<(), String> {
+    let name = profile.name.trim();
+    if name.is_empty() {
+        return Err("needs a name".to_string());
+    }
+    if name.chars().count() > MAX_VISUAL_RULE_NAME_LENGTH {
+        return Err("name exceeds 80 characters".to_string());
+    }
+    if name.eq_ignore_ascii_case(DEFAULT_VISUAL_PROFILE) {
+        return Err("uses the reserved default profile name".to_string());
+    }
+    if earlier
+        .iter()
+        .any(|other| other.name.trim().eq_ignore_ascii_case(name))
+    {
+        return Err("is defined more than once".to_string());
+    }
+    let Some(auto_select) = &profile.auto_select else {
+        return Ok(());
+    };
+    if auto_select.path_glob.is_none() && auto_select.format.is_none() {
+        return Err("auto-select needs a path glob or a format".to_string());
+    }
+    if let Some(glob) = &auto_select.path_glob {
+        if glob.chars().count() > MAX_VISUAL_RULE_PATTERN_LENGTH {
+            return Err("path glob exceeds 512 characters".to_string());
+        }
+        compile_path_glob(glob).map_err(|_| "invalid path glob".to_string())?;
+    }
+    Ok(())
+}
+
+/// Compiles a path glob: `**` spans directories, `*` and `?` stay within one path segment
+/// and `{a,b}` matches either alternative.
+pub(crate) fn compile_path_glob(glob: &str) -> Result<regex::Regex, String> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    let mut open_braces = 0usize;
+    while let Some(character) = chars.next() {
+        match character {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '{' => {
+                open_braces += 1;
+                pattern.push_str("(?:");
+            }
+            '}' if open_braces > 0 => {
+                open_braces -= 1;
+                pattern.push(')');
+            }
+            ',' if open_braces > 0 => pattern.push('|'),
+            other => pattern.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    if open_braces > 0 {
+        return Err("unclosed brace".to_string());
+    }
+    pattern.push('$');
+    regex::Regex::new(&pattern).map_err(|error| error.to_string())
+}
+
+fn uses_schema_2_styles(rule: &ManagedVisualRule) -> bool {
+    !rule.style.attributes.is_empty() || rule.style.gutter.is_some()
+}
+
+pub(crate) fn validate_rule(
+    rule: &ManagedVisualRule,
+    palette: &VisualPalette,
+) -> Result<(), String> {
     if rule
         .name
         .as_ref()
@@ -154,30 +517,115 @@ fn validate_rule(rule: &ManagedVisualRule) -> Result<(), String> {
     {
         return Err("name exceeds 80 characters".to_string());
     }
-    let pattern = match &rule.matcher {
-        VisualMatcher::Text(pattern) | VisualMatcher::Regex(pattern) => pattern,
-    };
-    if pattern.chars().count() > MAX_VISUAL_RULE_PATTERN_LENGTH {
-        return Err("pattern exceeds 512 characters".to_string());
-    }
-    for color in [&rule.style.foreground, &rule.style.background]
-        .into_iter()
-        .flatten()
+    let mut conditions = 0;
+    validate_matcher(&rule.matcher, rule.case_sensitive, 0, &mut conditions)?;
+    let gutter_color = rule
+        .style
+        .gutter
+        .as_ref()
+        .and_then(|marker| marker.color.as_ref());
+    for color in [
+        rule.style.foreground.as_ref(),
+        rule.style.background.as_ref(),
+        gutter_color,
+    ]
+    .into_iter()
+    .flatten()
     {
-        if !matches!(
-            color.0.as_str(),
-            "default" | "red" | "orange" | "yellow" | "green" | "cyan" | "blue" | "purple" | "gray"
-        ) {
+        if !palette.knows(color) {
             return Err(format!("unsupported palette token '{}'", color.0));
         }
     }
-    if let VisualMatcher::Regex(pattern) = &rule.matcher
-        && regex::RegexBuilder::new(pattern)
-            .case_insensitive(!rule.case_sensitive)
-            .build()
-            .is_err()
-    {
-        return Err("invalid regex".to_string());
+    if rule.style.attributes.bold && rule.style.attributes.dim {
+        // Terminals share one intensity attribute between the two.
+        return Err("bold and dim cannot be combined".to_string());
+    }
+    if rule.style.gutter.is_some() && !rule.scope.is_line() {
+        return Err("gutter markers require line scope".to_string());
+    }
+    if let VisualRuleScope::Groups(groups) = &rule.scope {
+        let VisualMatcher::Regex(pattern) = &rule.matcher else {
+            return Err("capture groups require a regex matcher".to_string());
+        };
+        let regex = compile_rule_regex(pattern, rule.case_sensitive)?;
+        if groups.is_empty() {
+            return Err("capture group scope lists no groups".to_string());
+        }
+        if let Some(group) = groups
+            .iter()
+            .find(|group| **group == 0 || **group >= regex.captures_len())
+        {
+            return Err(format!("capture group {group} does not exist"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_matcher(
+    matcher: &VisualMatcher,
+    case_sensitive: bool,
+    depth: usize,
+    conditions: &mut usize,
+) -> Result<(), String> {
+    *conditions += 1;
+    if *conditions > MAX_VISUAL_CONDITION_NODES {
+        return Err("conditions exceed 32 entries".to_string());
+    }
+    match matcher {
+        VisualMatcher::Text(pattern) => validate_pattern_length(pattern),
+        VisualMatcher::Regex(pattern) => {
+            validate_pattern_length(pattern)?;
+            compile_rule_regex(pattern, case_sensitive).map(|_| ())
+        }
+        VisualMatcher::Lines { first, last } => {
+            if *first == 0 {
+                return Err("line ranges start at line 1".to_string());
+            }
+            if last.is_some_and(|last| last < *first) {
+                return Err("line range ends before it starts".to_string());
+            }
+            Ok(())
+        }
+        VisualMatcher::Field(predicate) => {
+            let field = predicate.field.trim();
+            if field.is_empty() {
+                return Err("field condition needs a field name".to_string());
+            }
+            if field.chars().count() > MAX_VISUAL_RULE_NAME_LENGTH {
+                return Err("field name exceeds 80 characters".to_string());
+            }
+            validate_pattern_length(&predicate.value)
+        }
+        VisualMatcher::All(children) | VisualMatcher::Any(children) => {
+            if depth == MAX_VISUAL_CONDITION_DEPTH {
+                return Err("conditions are nested more than 8 levels deep".to_string());
+            }
+            if children.is_empty() {
+                return Err("condition group is empty".to_string());
+            }
+            children.iter().try_for_each(|child| {
+                validate_matcher(child, case_sensitive, depth + 1, conditions)
+            })
+        }
+        VisualMatcher::Not(child) => {
+            if depth == MAX_VISUAL_CONDITION_DEPTH {
+                return Err("conditions are nested more than 8 levels deep".to_string());
+            }
+            validate_matcher(child, case_sensitive, depth + 1, conditions)
+        }
+    }
+}
+
+fn validate_pattern_length(pattern: &str) -> Result<(), String> {
+    if pattern.chars().count() > MAX_VISUAL_RULE_PATTERN_LENGTH {
+        return Err("pattern exceeds 512 characters".to_string());
     }
     Ok(())
 }
+
+fn compile_rule_regex(pattern: &str, case_sensitive: bool) -> Result<regex::Regex, String> {
+    regex::RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|_| "invalid regex".to_string())
+}
@@ -0,0 +1,507 @@
+//! Field queries such as `level >= WARN and duration > 250ms and message ~ /timeout/`,
+//! tested against the fields a log format parses from a line.
+
+use std::fmt;
+
+use chrono::NaiveDateTime;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::field_value::{field_matches, ordering_matches, parse_timestamp, same_kind};
+use crate::models::visual_rules::FieldOperator;
+
+/// Longest query accepted, in characters.
+pub const MAX_QUERY_LENGTH: usize = 4096;
+/// Deepest nesting of parentheses and `not`.
+const MAX_QUERY_DEPTH: usize = 32;
+
+/// Comparison operators, longest first so that `>=` is not read as `>`.
+const OPERATORS: [(&str, Operator); 9] = [
+    ("!~", Operator::NotMatches),
+    (">=", Operator::Compare(FieldOperator::Ge)),
+    ("<=", Operator::Compare(FieldOperator::Le)),
+    ("!=", Operator::Compare(FieldOperator::Ne)),
+    ("==", Operator::Compare(FieldOperator::Eq)),
+    ("~", Operator::Matches),
+    (">", Operator::Compare(FieldOperator::Gt)),
+    ("<", Operator::Compare(FieldOperator::Lt)),
+    ("=", Operator::Compare(FieldOperator::Eq)),
+];
+
+/// A query that does not parse, with where it went wrong.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+    /// Characters before the point of the error.
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// A parsed field query.
+///
+/// Predicates compare a field with a value (`=`, `!=`, `<`, `<=`, `>`, `>=`) or match it
+/// against a regex (`~`, `!~`), and combine with `and`, `or`, `not` and parentheses; `and`
+/// binds tighter than `or`. Field names are dotted paths, or any text between backticks.
+/// Values are bare words or double-quoted strings, and regexes are written `/.../`, with an
+/// `i` after them to ignore case, or as a quoted string.
+///
+/// Comparisons are typed by the value: numbers and durations compare by magnitude, log
+/// levels by severity, and dates and times as instants, read with the format's timestamp
+/// layout on the field side. Ordering a field whose value is of another type never holds,
+/// while `=` and `!=` fall back to comparing text, ignoring case. A line without the field
+/// satisfies no predicate on it.
+#[derive(Clone, Debug)]
+pub struct LogQuery {
+    expression: Expression,
+}
+
+#[derive(Clone, Debug)]
+enum Expression {
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+    Not(Box<Expression>),
+    Compare {
+        field: String,
+        operator: FieldOperator,
+        value: String,
+        timestamp: Option<NaiveDateTime>,
+    },
+    Matches {
+        field: String,
+        regex: Regex,
+        negated: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Operator {
+    Compare(FieldOperator),
+    Matches,
+    NotMatches,
+}
+
+impl LogQuery {
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        if text.chars().count() > MAX_QUERY_LENGTH {
+            return Err(QueryError {
+                message: format!("query exceeds {MAX_QUERY_LENGTH} characters"),
+                position: 0,
+            });
+        }
+        let mut parser = Parser {
+            text,
+            offset: 0,
+            depth: 0,
+        };
+        parser.skip_whitespace();
+        if parser.at_end() {
+            return Err(parser.error("query is empty"));
+        }
+        let expression = parser.parse_or()?;
+        parser.skip_whitespace();
+        if !parser.at_end() {
+            return Err(parser.error("expected 'and', 'or' or the end of the query"));
+        }
+        Ok(Self { expression })
+    }
+
+    /// Whether a line with `fields` satisfies the query; `timestamp_format` is the strftime
+    /// layout of the format's timestamps, if it has one.
+    pub fn matches(&self, fields: &[(String, String)], timestamp_format: Option<&str>) -> bool {
+        self.expression.evaluate(fields, timestamp_format)
+    }
+}
+
+impl Expression {
+    fn evaluate(&self, fields: &[(String, String)], timestamp_format: Option<&str>) -> bool {
+        let value_of = |field: &str| {
+            fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value.as_str())
+        };
+        match self {
+            Expression::And(expressions) => expressions
+                .iter()
+                .all(|expression| expression.evaluate(fields, timestamp_format)),
+            Expression::Or(expressions) => expressions
+                .iter()
+                .any(|expression| expression.evaluate(fields, timestamp_format)),
+            Expression::Not(expression) => !expression.evaluate(fields, timestamp_format),
+            Expression::Compare {
+                field,
+                operator,
+                value,
+                timestamp,
+            } => {
+                let Some(actual) = value_of(field) else {
+                    return false;
+                };
+                if let Some(expected) = timestamp
+                    && let Some(actual) = parse_timestamp(actual, timestamp_format)
+                {
+                    return ordering_matches(*operator, actual.cmp(expected));
+                }
+                let ordered = !matches!(operator, FieldOperator::Eq | FieldOperator::Ne);
+                if ordered && (timestamp.is_some() || !same_kind(actual, value)) {
+                    return false;
+                }
+                field_matches(*operator, actual, value, false)
+            }
+            Expression::Matches {
+                field,
+                regex,
+                negated,
+            } => value_of(field).is_some_and(|actual| regex.is_match(actual) != *negated),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// Byte offset of the next character.
+    offset: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn parse_or(&mut self) -> Result<Expression, QueryError> {
+        let mut expressions = vec![self.parse_and()?];
+        while self.keyword("or") {
+            expressions.push(self.parse_and()?);
+        }
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::Or(expressions),
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, QueryError> {
+        let mut expressions = vec![self.parse_unary()?];
+        while self.keyword("and") {
+            expressions.push(self.parse_unary()?);
+        }
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::And(expressions),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, QueryError> {
+        self.skip_whitespace();
+        if self.depth == MAX_QUERY_DEPTH {
+            return Err(self.error("query nests too deeply"));
+        }
+        if self.keyword("not") {
+            self.depth += 1;
+            let expression = self.parse_unary()?;
+            self.depth -= 1;
+            return Ok(Expression::Not(Box::new(expression)));
+        }
+        if self.eat('(') {
+            self.depth += 1;
+            let expression = self.parse_or()?;
+            self.depth -= 1;
+            self.skip_whitespace();
+            if !self.eat(')') {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(expression);
+        }
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expression, QueryError> {
+        let field = self.parse_field()?;
+        self.skip_whitespace();
+        let rest = &self.text[self.offset..];
+        let Some((symbol, operator)) = OPERATORS
+            .iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))
+        else {
+            return Err(self.error(&format!("expected an operator after '{field}'")));
+        };
+        self.offset += symbol.len();
+        self.skip_whitespace();
+        match operator {
+            Operator::Compare(operator) => {
+                let value = self.parse_value(symbol)?;
+                Ok(Expression::Compare {
+                    field,
+                    operator: *operator,
+                    timestamp: parse_timestamp(&value, None),
+                    value,
+                })
+            }
+            Operator::Matches | Operator::NotMatches => Ok(Expression::Matches {
+                field,
+                regex: self.parse_regex()?,
+                negated: matches!(operator, Operator::NotMatches),
+            }),
+        }
+    }
+
+    fn parse_field(&mut self) -> Result<String, QueryError> {
+        let start = self.offset;
+        if self.eat('`') {
+            let Some(length) = self.text[self.offset..].find('`') else {
+                return Err(self.error_at(start, "unterminated field name"));
+            };
+            let field = self.text[self.offset..self.offset + length].to_string();
+            self.offset += length + 1;
+            if field.is_empty() {
+                return Err(self.error_at(start, "expected a field name"));
+            }
+            return Ok(field);
+        }
+        let field = self.take_while(is_field_character);
+        if field.is_empty() || ["and", "or"].contains(&field.to_ascii_lowercase().as_str()) {
+            return Err(self.error_at(start, "expected a field name"));
+        }
+        Ok(field.to_string())
+    }
+
+    fn parse_value(&mut self, operator: &str) -> Result<String, QueryError> {
+        if self.peek() == Some('"') {
+            return self.parse_string();
+        }
+        let value = self.take_while(|character| {
+            !character.is_whitespace() && !matches!(character, '(' | ')' | '"')
+        });
+        if value.is_empty() {
+            return Err(self.error(&format!("expected a value after '{operator}'")));
+        }
+        Ok(value.to_string())
+    }
+
+    fn parse_string(&mut self) -> Result<String, QueryError> {
+        let start = self.offset;
+        self.eat('"');
+        let mut value = String::new();
+        let mut characters = self.text[self.offset..].char_indices();
+        while let Some((index, character)) = characters.next() {
+            match character {
+                '"' => {
+                    self.offset += index + 1;
+                    return Ok(value);
+                }
+                '\\' => match characters.next() {
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                _ => value.push(character),
+            }
+        }
+        Err(self.error_at(start, "unterminated string"))
+    }
+
+    fn parse_regex(&mut self) -> Result<Regex, QueryError> {
+        let start = self.offset;
+        let (pattern, ignore_case) = match self.peek() {
+            Some('"') => (self.parse_string()?, false),
+            Some('/') => {
+                self.eat('/');
+                let mut pattern = String::new();
+                let mut characters = self.text[self.offset..].char_indices();
+                let mut closed = false;
+                while let Some((index, character)) = characters.next() {
+                    match character {
+                        '/' => {
+                            self.offset += index + 1;
+                            closed = true;
+                            break;
+                        }
+                        '\\' => match characters.next() {
+                            Some((_, '/')) => pattern.push('/'),
+                            Some((_, escaped)) => {
+                                pattern.push('\\');
+                                pattern.push(escaped);
+                            }
+                            None => break,
+                        },
+                        _ => pattern.push(character),
+                    }
+                }
+                if !closed {
+                    return Err(self.error_at(start, "unterminated regex"));
+                }
+                (pattern, self.eat('i'))
+            }
+            _ => return Err(self.error("expected a /regex/ or a quoted pattern")),
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|error| self.error_at(start, &format!("invalid regex: {error}")))
+    }
+
+    /// Consumes `word` if it comes next as a whole word, ignoring case.
+    fn keyword(&mut self, word: &str) -> bool {
+        self.skip_whitespace();
+        let rest = &self.text[self.offset..];
+        let matched = rest
+            .get(..word.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(word))
+            && !rest[word.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_field_character);
+        if matched {
+            self.offset += word.len();
+        }
+        matched
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> &str {
+        let start = self.offset;
+        let length = self.text[start..]
+            .find(|character: char| !keep(character))
+            .unwrap_or(self.text.len() - start);
+        self.offset += length;
+        &self.text[start..self.offset]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.offset += expected.len_utf8();
+        }
+        found
+    }
+
+    fn at_end(&self) -> bool {
+        self.offset == self.text.len()
+    }
+
+    fn error(&self, message: &str) -> QueryError {
+        self.error_at(self.offset, message)
+    }
+
+    fn error_at(&self, offset: usize, message: &str) -> QueryError {
+        QueryError {
+            message: message.to_string(),
+            position: self.text[..offset].chars().count(),
+        }
+    }
+}
+
+fn is_field_character(character: char) -> bool {
+    character.is_alphanumeric() || matches!(character, '_' | '.' | '-' | '@')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn matches(query: &str, pairs: &[(&str, &str)]) -> bool {
+        LogQuery::parse(query).unwrap().matches(&line(pairs), None)
+    }
+
+    fn error(query: &str) -> (String, usize) {
+        let error = LogQuery::parse(query).unwrap_err();
+        (error.message, error.position)
+    }
+
+    #[test]
+    fn predicates_combine_with_and_or_not_and_parentheses() {
+        let query =
+            r#"level >= WARN and service = "billing" and duration > 250ms and message ~ /timeout/"#;
+        let slow = [
+            ("level", "error"),
+            ("service", "Billing"),
+            ("duration", "1.2s"),
+            ("message", "upstream timeout"),
+        ];
+        assert!(matches(query, &slow));
+        let mut fast = slow;
+        fast[2] = ("duration", "90ms");
+        assert!(!matches(query, &fast));
+        assert!(!matches(query, &slow[..3]));
+
+        let fields = [("level", "info"), ("status", "503")];
+        assert!(matches("level = warn OR status >= 500", &fields));
+        assert!(!matches(
+            "level = warn or status >= 500 and status < 503",
+            &fields
+        ));
+        assert!(matches(
+            "(level = warn or status >= 500) and not status = 200",
+            &fields
+        ));
+        assert!(matches("not missing = 1", &fields));
+        assert!(matches("status !~ /^2/ and level ~ /INFO/i", &fields));
+        assert!(matches(r#"`status` ~ "50\\d""#, &fields));
+    }
+
+    #[test]
+    fn comparisons_are_typed_by_the_value() {
+        assert!(matches("status > 99", &[("status", "200")]));
+        assert!(!matches("status > 99", &[("status", "abc")]));
+        assert!(matches("user != 42", &[("user", "alice")]));
+        assert!(!matches("level >= warn", &[("level", "12")]));
+        assert!(matches("took < 1s", &[("took", "900ms")]));
+        assert!(matches(
+            "time >= 2026-10-03T10:00:00Z and time < 2026-10-04",
+            &[("time", "2026-10-03T12:30:00+02:00")]
+        ));
+        assert!(!matches("time > 2026-10-03", &[("time", "yesterday")]));
+
+        let query = LogQuery::parse("time > 2026-10-03T09:59:59").unwrap();
+        let access = line(&[("time", "03/Oct/2026:10:00:00 +0000")]);
+        assert!(query.matches(&access, Some("%d/%b/%Y:%H:%M:%S %z")));
+        assert!(!query.matches(&access, None));
+    }
+
+    #[test]
+    fn syntax_errors_point_at_the_offending_character() {
+        assert_eq!(error("  "), ("query is empty".to_string(), 2));
+        assert_eq!(
+            error("level >= "),
+            ("expected a value after '>='".to_string(), 9)
+        );
+        assert_eq!(
+            error("level warn"),
+            ("expected an operator after 'level'".to_string(), 6)
+        );
+        assert_eq!(error("(level = warn"), ("expected ')'".to_string(), 13));
+        assert_eq!(
+            error("level = warn status = 1"),
+            (
+                "expected 'and', 'or' or the end of the query".to_string(),
+                13
+            )
+        );
+        assert_eq!(
+            error(r#"msg = "é and"#),
+            ("unterminated string".to_string(), 6)
+        );
+        assert_eq!(error("and = 1"), ("expected a field name".to_string(), 0));
+        assert!(error("msg ~ /(/").0.starts_with("invalid regex"));
+        assert_eq!(
+            LogQuery::parse("é = 1 é").unwrap_err().to_string(),
+            "expected 'and', 'or' or the end of the query at column 7"
+        );
+    }
+}
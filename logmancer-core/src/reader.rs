@@ -1,34 +1,97 @@
+use crate::file_ops::read::FileReadOps;
 use crate::handler::LogFileHandler;
+use crate::log_format::{LogFormat, LogFormats};
+use crate::log_format_manager::LogFormatManager;
+use crate::models::density::DensityMap;
+use crate::models::log_file::LineFilter;
+use crate::models::log_format::{
+    DiscoveredField, FORMAT_DETECTION_SAMPLE_LINES, FieldDiscovery, FormatDetection,
+    LogFormatStatus, MAX_FIELD_DISCOVERY_LINES, MAX_LOG_COLUMNS,
+};
+use crate::models::marks::MarkStatus;
+use crate::models::quote_checkpoints::QuoteCheckpoints;
+use crate::models::rule_counts::{RuleCountStatus, RuleNavigation};
+use crate::models::search::SearchHitsPage;
+use crate::models::time::{LineTime, TimeSettings};
+use crate::models::visual_rules::{VisualColor, VisualProfileStatus};
 use crate::models::{FileInfo, PageLine, PageResult, SearchStatus, VisualRule};
-use crate::{VisualRuleEvaluator, VisualRulesManager};
+use crate::query::LogQuery;
+use crate::timestamps::{Zone, format_instant, line_instant, parse_time_zone, time_zone_name};
+use crate::{VisualLine, VisualRuleEvaluator, VisualRulesManager};
+use chrono::{DateTime, Utc};
 use log::debug;
 use std::cmp::min;
-use std::io::{self};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Bytes read from each end of a file to sample lines for format detection.
+const DETECTION_SAMPLE_BYTES: u64 = 64 * 1024;
+/// Lines searched above a line of a page, and below the line looked at before it, for the
+/// timestamp its delta is measured from.
+const PREVIOUS_TIMESTAMP_LOOKBACK: usize = 64;
 
 pub struct LogReader {
     handler: LogFileHandler,
+    path: String,
     current_view_start: usize,
     visual_rules_manager: std::sync::Arc<VisualRulesManager>,
+    /// Profile chosen for this file; `None` follows the auto-select conditions.
+    visual_profile: Option<String>,
+    log_formats: std::sync::Arc<LogFormatManager>,
+    /// Format chosen for this file; lines are not parsed without one.
+    log_format: Option<String>,
+    /// Format detected when the file was opened, chosen if it was confident enough.
+    detection: Option<FormatDetection>,
+    /// Field paths shown as columns for this file; `None` shows the header row of a
+    /// delimited format and no columns for other formats.
+    columns: Option<Vec<String>>,
+    /// Where records of a delimited format begin, so pages need not count from the start.
+    quote_checkpoints: QuoteCheckpoints,
+    /// Time zone page timestamps are shown in.
+    time_zone: Zone,
+    /// Line, counted from 1, that reference deltas are measured from.
+    time_reference: Option<usize>,
 }
 
 impl LogReader {
     pub fn new(path: String) -> io::Result<Self> {
-        let file_log_handler = LogFileHandler::new(path)?;
-        Ok(LogReader {
-            handler: file_log_handler,
-            current_view_start: 0,
-            visual_rules_manager: VisualRulesManager::in_memory(),
-        })
+        Self::with_managers(
+            path,
+            VisualRulesManager::in_memory(),
+            LogFormatManager::in_memory(),
+        )
     }
 
     pub fn with_manager(
         path: String,
         visual_rules_manager: std::sync::Arc<VisualRulesManager>,
     ) -> io::Result<Self> {
+        Self::with_managers(path, visual_rules_manager, LogFormatManager::in_memory())
+    }
+
+    pub fn with_managers(
+        path: String,
+        visual_rules_manager: std::sync::Arc<VisualRulesManager>,
+        log_formats: std::sync::Arc<LogFormatManager>,
+    ) -> io::Result<Self> {
+        let handler = LogFileHandler::new(path.clone())?;
+        let detection = detect_log_format(&path, &log_formats.formats());
         Ok(Self {
-            handler: LogFileHandler::new(path)?,
+            handler,
             current_view_start: 0,
             visual_rules_manager,
+            visual_profile: None,
+            log_formats,
+            log_format: detection
+                .as_ref()
+                .filter(|detection| detection.is_applied())
+                .map(|detection| detection.format.clone()),
+            detection,
+            path,
+            columns: None,
+            quote_checkpoints: QuoteCheckpoints::default(),
+            time_zone: Zone::default(),
+            time_reference: None,
         })
     }
 
@@ -36,11 +99,12 @@ impl LogReader {
         let managed = rules
             .into_iter()
             .map(|rule| crate::ManagedVisualRule {
-                name: None,
+                name: rule.name,
                 enabled: true,
                 matcher: rule.matcher,
                 case_sensitive: rule.case_sensitive,
                 style: rule.style,
+                scope: rule.scope,
             })
             .collect();
         let _ = self
@@ -48,6 +112,198 @@ impl LogReader {
             .apply_memory(crate::VisualRulesEnvelope::new(managed));
     }
 
+    /// Visual rule profile this file renders with.
+    pub fn visual_profile(&self) -> VisualProfileStatus {
+        let format = self.chosen_log_format();
+        self.visual_rules_manager.profiles().status(
+            self.visual_profile.as_deref(),
+            &self.path,
+            format.as_ref().map(|format| format.name()),
+        )
+    }
+
+    /// Pins `profile` for this file, or goes back to auto-selection with `None`.
+    pub fn set_visual_profile(
+        &mut self,
+        profile: Option<String>,
+    ) -> io::Result<VisualProfileStatus> {
+        if let Some(name) = &profile
+            && !self.visual_rules_manager.profiles().contains(name)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown visual rule profile '{name}'"),
+            ));
+        }
+        self.visual_profile = profile;
+        Ok(self.visual_profile())
+    }
+
+    fn visual_evaluator(&self) -> std::sync::Arc<VisualRuleEvaluator> {
+        let format = self.chosen_log_format();
+        self.visual_rules_manager.profiles().evaluator_for(
+            self.visual_profile.as_deref(),
+            &self.path,
+            format.as_ref().map(|format| format.name()),
+        )
+    }
+
+    /// Log format this file is parsed with and the fields it shows as columns.
+    pub fn log_format(&self) -> LogFormatStatus {
+        let mut status = self
+            .log_formats
+            .formats()
+            .status(self.log_format.as_deref());
+        if let Some(format) = self.active_log_format() {
+            status.columns = match &self.columns {
+                Some(columns) => columns.clone(),
+                None => format
+                    .header()
+                    .iter()
+                    .take(MAX_LOG_COLUMNS)
+                    .cloned()
+                    .collect(),
+            };
+        }
+        status
+    }
+
+    /// Parses this file with `format` from now on, or stops parsing it with `None`. Columns
+    /// chosen for another format are cleared. Files start with the format detected when they
+    /// are opened, if it parses enough of their lines; this overrides it.
+    pub fn set_log_format(&mut self, format: Option<String>) -> io::Result<LogFormatStatus> {
+        if let Some(name) = &format
+            && self.log_formats.formats().get(name).is_none()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown log format '{name}'"),
+            ));
+        }
+        let same_format = match (&format, &self.log_format) {
+            (Some(new), Some(old)) => new.eq_ignore_ascii_case(old),
+            (None, None) => true,
+            _ => false,
+        };
+        if !same_format {
+            self.columns = None;
+            self.quote_checkpoints.clear();
+        }
+        self.log_format = format;
+        Ok(self.log_format())
+    }
+
+    /// Shows `columns`, field paths such as `http.status`, in that order; an empty list goes
+    /// back to the plain text view.
+    pub fn set_columns(&mut self, columns: Vec<String>) -> io::Result<LogFormatStatus> {
+        if !columns.is_empty() && self.active_log_format().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "choose a log format before choosing columns",
+            ));
+        }
+        if columns.len() > MAX_LOG_COLUMNS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("at most {MAX_LOG_COLUMNS} columns may be shown"),
+            ));
+        }
+        let mut unique = Vec::with_capacity(columns.len());
+        for column in columns {
+            let column = column.trim().to_string();
+            if !column.is_empty() && !unique.contains(&column) {
+                unique.push(column);
+            }
+        }
+        self.columns = Some(unique);
+        Ok(self.log_format())
+    }
+
+    /// Parses up to `sample_lines` lines from the start of the file with the chosen format and
+    /// lists the fields they carry; blank lines and header rows are skipped. Without a format
+    /// nothing is parsed.
+    pub fn discover_fields(&self, sample_lines: usize) -> io::Result<FieldDiscovery> {
+        let Some(format) = self.active_log_format() else {
+            return Ok(FieldDiscovery::default());
+        };
+        let read_ops = self.handler.read_ops();
+        let limit = read_ops
+            .total_lines()?
+            .min(sample_lines.min(MAX_FIELD_DISCOVERY_LINES));
+        let mut discovery = FieldDiscovery::default();
+        for line in usize::from(format.has_header())..limit {
+            let text = read_ops.read_line(line)?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            discovery.sampled_lines += 1;
+            let Some(fields) = format.parse(&text) else {
+                continue;
+            };
+            discovery.parsed_lines += 1;
+            for (path, _) in fields {
+                match discovery.fields.iter_mut().find(|field| field.path == path) {
+                    Some(field) => field.lines += 1,
+                    None => discovery.fields.push(DiscoveredField { path, lines: 1 }),
+                }
+            }
+        }
+        Ok(discovery)
+    }
+
+    /// Runs a read-only SQL query over the file's `log` table: one row per line, or per
+    /// record of a delimited file, with `log_line`, `log_text` and a column per field of the
+    /// chosen format. Without a format only `log_line` and `log_text` are there.
+    #[cfg(feature = "sql")]
+    pub fn sql(&self, query: &str) -> io::Result<crate::models::sql::SqlResult> {
+        self.sql_table()?.query(query)
+    }
+
+    /// The `log` table [`LogReader::sql`] queries, for running a query after the reader is
+    /// released.
+    #[cfg(feature = "sql")]
+    pub fn sql_table(&self) -> io::Result<crate::sql::LogTableSource> {
+        let format = self.active_log_format();
+        let fields = match &format {
+            Some(format) if format.has_header() => format.header().to_vec(),
+            Some(_) => self
+                .discover_fields(MAX_FIELD_DISCOVERY_LINES)?
+                .fields
+                .into_iter()
+                .map(|field| field.path)
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(crate::sql::LogTableSource {
+            log_file: self.handler.log_file(),
+            format,
+            fields,
+        })
+    }
+
+    /// Format chosen for this file, before a delimited one is bound to the header row.
+    fn chosen_log_format(&self) -> Option<std::sync::Arc<LogFormat>> {
+        self.log_formats.formats().get(self.log_format.as_deref()?)
+    }
+
+    /// Format lines are parsed with, a delimited one named after the header row once the
+    /// first line is indexed. Takes the file's read lock, so callers get it before theirs.
+    fn active_log_format(&self) -> Option<std::sync::Arc<LogFormat>> {
+        let format = self.chosen_log_format()?;
+        if !format.has_header() {
+            return Some(format);
+        }
+        let read_ops = self.handler.read_ops();
+        if read_ops.total_lines().unwrap_or(0) < 2 {
+            return Some(format);
+        }
+        let bound = read_ops
+            .read_line(0)
+            .ok()
+            .and_then(|header| format.with_header(&header));
+        Some(bound.map(std::sync::Arc::new).unwrap_or(format))
+    }
+
     /// Return file_id, path and other info about the open file
     pub fn file_info(&self) -> io::Result<FileInfo> {
         let read_ops = self.handler.read_ops();
@@ -55,29 +311,88 @@ impl LogReader {
             path: read_ops.file_path(),
             total_lines: read_ops.total_lines()?,
             indexing_progress: read_ops.indexing_progress()?,
+            detected_format: self.detection.clone(),
         };
         debug!("{file_info:?}");
         Ok(file_info)
     }
 
+    /// Time zone and reference line that page timestamps are shown with.
+    pub fn time_settings(&self) -> TimeSettings {
+        let format = self.active_log_format();
+        let read_ops = self.handler.read_ops();
+        let reference = self
+            .time_reference
+            .and_then(|line| Self::instant_at(&read_ops, format.as_deref()?, line.checked_sub(1)?));
+        TimeSettings {
+            time_zone: time_zone_name(self.time_zone),
+            reference_line: self.time_reference,
+            reference_timestamp: reference.map(|instant| format_instant(instant, self.time_zone)),
+        }
+    }
+
+    /// Shows page timestamps in `time_zone`: `UTC`, an offset such as `+02:00` or an IANA zone
+    /// such as `Europe/Madrid`, which follows its daylight saving time.
+    pub fn set_time_zone(&mut self, time_zone: &str) -> io::Result<TimeSettings> {
+        self.time_zone = parse_time_zone(time_zone).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("time zone '{time_zone}' is not UTC, an offset or an IANA zone"),
+            )
+        })?;
+        Ok(self.time_settings())
+    }
+
+    /// Measures reference deltas from the timestamp of `line`, counted from 1; `None` stops
+    /// measuring them.
+    pub fn set_time_reference(&mut self, line: Option<usize>) -> io::Result<TimeSettings> {
+        if let Some(line) = line {
+            let format = self.active_log_format();
+            let read_ops = self.handler.read_ops();
+            let instant = format
+                .as_deref()
+                .and_then(|format| Self::instant_at(&read_ops, format, line.checked_sub(1)?));
+            if instant.is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("line {line} has no timestamp"),
+                ));
+            }
+        }
+        self.time_reference = line;
+        Ok(self.time_settings())
+    }
+
     /// Reads a page from the file, starting at `start_line` and reading up to `max_lines` lines.
     pub fn read_page(&mut self, start_line: usize, max_lines: usize) -> io::Result<PageResult> {
         debug!("Reading from line {start_line} to max {max_lines}");
+        let evaluator = self.visual_evaluator();
+        let format = self.active_log_format();
         let read_ops = self.handler.read_ops();
         let to_line = min(start_line + max_lines, read_ops.total_lines()?);
         let from_line = to_line.saturating_sub(max_lines);
-        let mut lines = Vec::with_capacity(max_lines);
-        let evaluator = self.visual_rules_manager.snapshot();
-        for current_line in from_line..to_line {
-            let text = read_ops.read_line(current_line)?;
-            lines.push(Self::page_line(&evaluator, current_line + 1, text));
-        }
+        let mut lines = Self::page_lines(
+            &mut self.quote_checkpoints,
+            &read_ops,
+            &evaluator,
+            format.as_deref(),
+            from_line,
+            to_line,
+        )?;
+        Self::annotate_times(
+            &read_ops,
+            format.as_deref(),
+            self.time_zone,
+            self.time_reference,
+            &mut lines,
+        );
         let page = PageResult {
             lines,
             start_line: from_line,
             total_lines: read_ops.total_lines()?,
             indexing_progress: read_ops.indexing_progress()?,
             search: read_ops.page_search_result(from_line, to_line),
+            marks: read_ops.page_mark_results(from_line, to_line),
         };
         self.current_view_start = page.start_line;
         Ok(page)
@@ -89,58 +404,122 @@ impl LogReader {
         if follow {
             self.handler.reload();
         }
+        let evaluator = self.visual_evaluator();
+        let format = self.active_log_format();
         let read_ops = self.handler.read_ops();
         let total_lines = read_ops.total_lines()?;
         let start_line = total_lines.saturating_sub(max_lines);
-        let mut lines = Vec::with_capacity(max_lines);
-        let evaluator = self.visual_rules_manager.snapshot();
-        for current_line in start_line..total_lines {
-            let text = read_ops.read_line(current_line)?;
-            lines.push(Self::page_line(&evaluator, current_line + 1, text));
-        }
+        let mut lines = Self::page_lines(
+            &mut self.quote_checkpoints,
+            &read_ops,
+            &evaluator,
+            format.as_deref(),
+            start_line,
+            total_lines,
+        )?;
+        Self::annotate_times(
+            &read_ops,
+            format.as_deref(),
+            self.time_zone,
+            self.time_reference,
+            &mut lines,
+        );
         let page = PageResult {
             lines,
             start_line,
             total_lines,
             indexing_progress: read_ops.indexing_progress()?,
             search: read_ops.page_search_result(start_line, total_lines),
+            marks: read_ops.page_mark_results(start_line, total_lines),
         };
         self.current_view_start = page.start_line;
         Ok(page)
     }
 
     pub fn filter(&mut self, regex: String) {
-        self.handler.filter(Some(regex));
+        self.handler.filter(Some(LineFilter {
+            pattern: regex,
+            field: None,
+            comparison: None,
+            query: None,
+        }));
+    }
+
+    /// Keeps the lines whose fields, parsed with the chosen format, satisfy `query`.
+    pub fn filter_query(&mut self, query: LogQuery) -> io::Result<()> {
+        let Some(format) = self.active_log_format() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "choose a log format before filtering with a query",
+            ));
+        };
+        self.handler.filter(Some(LineFilter::query(query, format)));
+        Ok(())
+    }
+
+    /// Keeps the lines whose `field`, parsed with the chosen format, matches `pattern`.
+    ///
+    /// A pattern that starts with `=`, `!=`, `<`, `<=`, `>` or `>=` compares the field with
+    /// the rest, so `>100ms` keeps slow requests; other patterns are regexes, and one that does
+    /// not compile is rejected.
+    pub fn filter_field(&mut self, field: String, pattern: String) -> io::Result<()> {
+        let Some(format) = self.active_log_format() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "choose a log format before filtering by field",
+            ));
+        };
+        let line_filter = LineFilter::field(pattern, field, format);
+        if line_filter.uses_regex() {
+            regex::Regex::new(&line_filter.pattern)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+        }
+        self.handler.filter(Some(line_filter));
+        Ok(())
     }
 
     pub fn read_filter(&mut self, start_line: usize, max_lines: usize) -> io::Result<PageResult> {
         debug!("Reading filter from line {start_line} to max {max_lines}");
+        let evaluator = self.visual_evaluator();
+        let format = self.active_log_format();
         let read_ops = self.handler.read_ops();
 
         let total_lines = read_ops.filtered_lines()?;
         let processed_lines = read_ops.processed_filter_lines()?;
         let mut matched_lines = 0;
         let mut current_line = 0;
-        let mut lines = Vec::with_capacity(max_lines);
         let mut visible_line_indexes = Vec::with_capacity(max_lines);
-        let evaluator = self.visual_rules_manager.snapshot();
 
-        while lines.len() < max_lines && current_line < processed_lines {
-            if let Some(line) = read_ops.read_filter_line(current_line)? {
+        while visible_line_indexes.len() < max_lines && current_line < processed_lines {
+            if read_ops.is_filtered_line(current_line) {
                 if matched_lines >= start_line {
                     visible_line_indexes.push(current_line);
-                    lines.push(Self::page_line(&evaluator, current_line + 1, line));
                 }
                 matched_lines += 1;
             }
             current_line += 1;
         }
+        let mut lines = Self::filtered_page_lines(
+            &mut self.quote_checkpoints,
+            &read_ops,
+            &evaluator,
+            format.as_deref(),
+            &visible_line_indexes,
+        )?;
+        Self::annotate_times(
+            &read_ops,
+            format.as_deref(),
+            self.time_zone,
+            self.time_reference,
+            &mut lines,
+        );
         let page = PageResult {
             lines,
             start_line,
             total_lines,
             indexing_progress: read_ops.filter_indexing_progress()?,
             search: read_ops.page_search_result_for_lines(&visible_line_indexes),
+            marks: read_ops.page_mark_results_for_lines(&visible_line_indexes),
         };
         self.current_view_start = page.start_line;
         Ok(page)
@@ -149,29 +528,42 @@ impl LogReader {
     pub fn tail_filter(&mut self, max_lines: usize, follow: bool) -> io::Result<PageResult> {
         debug!("Reading last {max_lines} lines to the end");
         if follow {
-            self.handler.filter(None);
+            self.handler.reload();
         }
+        let evaluator = self.visual_evaluator();
+        let format = self.active_log_format();
         let read_ops = self.handler.read_ops();
-        let mut lines = Vec::with_capacity(max_lines);
         let mut visible_line_indexes = Vec::with_capacity(max_lines);
         let mut current_line = read_ops.total_lines()?;
-        let evaluator = self.visual_rules_manager.snapshot();
 
-        while lines.len() < max_lines && current_line > 0 {
+        while visible_line_indexes.len() < max_lines && current_line > 0 {
             current_line -= 1;
-            if let Some(line) = read_ops.read_filter_line(current_line)? {
+            if read_ops.is_filtered_line(current_line) {
                 visible_line_indexes.push(current_line);
-                lines.push(Self::page_line(&evaluator, current_line + 1, line));
             }
         }
-        lines.reverse();
         visible_line_indexes.reverse();
+        let mut lines = Self::filtered_page_lines(
+            &mut self.quote_checkpoints,
+            &read_ops,
+            &evaluator,
+            format.as_deref(),
+            &visible_line_indexes,
+        )?;
+        Self::annotate_times(
+            &read_ops,
+            format.as_deref(),
+            self.time_zone,
+            self.time_reference,
+            &mut lines,
+        );
         let page = PageResult {
             lines,
             start_line: current_line,
             total_lines: read_ops.total_lines()?,
             indexing_progress: read_ops.filter_indexing_progress()?,
             search: read_ops.page_search_result_for_lines(&visible_line_indexes),
+            marks: read_ops.page_mark_results_for_lines(&visible_line_indexes),
         };
         self.current_view_start = page.start_line;
         Ok(page)
@@ -205,6 +597,39 @@ impl LogReader {
         self.search_positioned_page(max_lines)
     }
 
+    /// Buckets the file into at most `buckets` ranges with search, filter and visual rule counts.
+    pub fn density_map(&mut self, buckets: usize) -> io::Result<DensityMap> {
+        let evaluator = self.visual_evaluator();
+        let format = self.active_log_format();
+        self.handler.read_ops().density_map(
+            buckets,
+            &evaluator,
+            format.as_deref(),
+            &mut self.quote_checkpoints,
+        )
+    }
+
+    /// Returns a "find all" page of the active search hits, `None` without a search.
+    pub fn search_hits(&self, offset: usize, limit: usize) -> io::Result<Option<SearchHitsPage>> {
+        self.handler.read_ops().search_hits(offset, limit)
+    }
+
+    /// Makes the hit at `line_index`/`start` the current match and positions the page around it.
+    pub fn search_select(
+        &mut self,
+        line_index: usize,
+        start: usize,
+        max_lines: usize,
+    ) -> io::Result<PageResult> {
+        if !self.handler.search_select(line_index, start) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "search match not found",
+            ));
+        }
+        self.search_positioned_page(max_lines)
+    }
+
     fn search_positioned_page(&mut self, max_lines: usize) -> io::Result<PageResult> {
         let status = self.search_status();
         let start = status
@@ -214,20 +639,382 @@ impl LogReader {
         self.read_page(start, max_lines)
     }
 
-    fn page_line(evaluator: &VisualRuleEvaluator, number: usize, text: String) -> PageLine {
-        let style = evaluator.evaluate(&text);
+    /// Highlights every occurrence of `query` with a palette color until the mark is removed.
+    ///
+    /// When `color` is `None` the next unused color of the mark rotation is assigned.
+    pub fn add_mark(&mut self, query: String, color: Option<VisualColor>) -> io::Result<u64> {
+        self.handler.add_mark(query, color)
+    }
+
+    pub fn remove_mark(&mut self, mark_id: u64) -> bool {
+        self.handler.remove_mark(mark_id)
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.handler.clear_marks();
+    }
+
+    pub fn marks_status(&self) -> Vec<MarkStatus> {
+        self.handler.read_ops().mark_statuses()
+    }
+
+    pub fn mark_next(&mut self, mark_id: u64, max_lines: usize) -> io::Result<PageResult> {
+        if !self.handler.mark_next(mark_id) {
+            return Err(Self::unknown_mark(mark_id));
+        }
+        self.mark_positioned_page(mark_id, max_lines)
+    }
+
+    pub fn mark_previous(&mut self, mark_id: u64, max_lines: usize) -> io::Result<PageResult> {
+        if !self.handler.mark_previous(mark_id) {
+            return Err(Self::unknown_mark(mark_id));
+        }
+        self.mark_positioned_page(mark_id, max_lines)
+    }
+
+    fn mark_positioned_page(&mut self, mark_id: u64, max_lines: usize) -> io::Result<PageResult> {
+        let start = self
+            .marks_status()
+            .into_iter()
+            .find(|mark| mark.id == mark_id)
+            .and_then(|mark| mark.search.current)
+            .map(|m| m.line_index.saturating_sub(max_lines / 2))
+            .unwrap_or(self.current_view_start);
+        self.read_page(start, max_lines)
+    }
+
+    fn unknown_mark(mark_id: u64) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, format!("mark {mark_id} not found"))
+    }
+
+    /// Lines matched by each rule of the profile this file renders with, counted in the
+    /// background. Publishing new rules or switching profiles restarts the count.
+    pub fn visual_rule_counts(&mut self) -> io::Result<RuleCountStatus> {
+        self.sync_rule_counts()?;
+        self.handler
+            .read_ops()
+            .rule_count_status()
+            .ok_or_else(|| io::Error::other("visual rule counts are not running"))
+    }
+
+    /// Moves to the next line matched by rule `rule` of the active profile, starting from
+    /// the current page the first time, and positions the page around it.
+    pub fn visual_rule_next(
+        &mut self,
+        rule: usize,
+        max_lines: usize,
+    ) -> io::Result<RuleNavigation> {
+        self.sync_rule_counts()?;
+        if !self.handler.rule_next(rule, self.current_view_start) {
+            return Err(Self::unknown_rule(rule));
+        }
+        self.rule_positioned_page(rule, max_lines)
+    }
+
+    pub fn visual_rule_previous(
+        &mut self,
+        rule: usize,
+        max_lines: usize,
+    ) -> io::Result<RuleNavigation> {
+        self.sync_rule_counts()?;
+        if !self.handler.rule_previous(rule, self.current_view_start) {
+            return Err(Self::unknown_rule(rule));
+        }
+        self.rule_positioned_page(rule, max_lines)
+    }
+
+    fn sync_rule_counts(&mut self) -> io::Result<()> {
+        let revision = self.visual_rules_manager.revision();
+        let profile = self.visual_profile().active;
+        let evaluator = self.visual_evaluator();
+        let format = self.active_log_format();
+        self.handler
+            .sync_rule_counts(revision, profile, evaluator, format)
+    }
+
+    fn rule_positioned_page(
+        &mut self,
+        rule: usize,
+        max_lines: usize,
+    ) -> io::Result<RuleNavigation> {
+        let line_index = self
+            .handler
+            .read_ops()
+            .rule_count_status()
+            .and_then(|status| status.rules.get(rule)?.current_line_index);
+        let start = line_index
+            .map(|line| line.saturating_sub(max_lines / 2))
+            .unwrap_or(self.current_view_start);
+        Ok(RuleNavigation {
+            rule,
+            line_index,
+            page: self.read_page(start, max_lines)?,
+        })
+    }
+
+    fn unknown_rule(rule: usize) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("visual rule {rule} not found"),
+        )
+    }
+
+    /// Lines `from_line..to_line` of a page. A delimited record whose quoted value continues
+    /// on the lines below is joined and parsed on its first line, and the lines it continues
+    /// on are marked as continuations without fields.
+    pub(crate) fn page_lines(
+        quote_checkpoints: &mut QuoteCheckpoints,
+        read_ops: &FileReadOps,
+        evaluator: &VisualRuleEvaluator,
+        format: Option<&LogFormat>,
+        from_line: usize,
+        to_line: usize,
+    ) -> io::Result<Vec<PageLine>> {
+        let mut lines = Vec::with_capacity(to_line.saturating_sub(from_line));
+        let Some(delimited) = format.filter(|format| format.has_header()) else {
+            for current_line in from_line..to_line {
+                let text = read_ops.read_line(current_line)?;
+                lines.push(Self::page_line(evaluator, format, current_line + 1, text));
+            }
+            return Ok(lines);
+        };
+        let mut in_quotes =
+            quote_checkpoints.opens_inside_quotes(read_ops.log_file(), delimited, from_line);
+        for current_line in from_line..to_line {
+            let (line, ends_inside) =
+                Self::delimited_line(read_ops, evaluator, delimited, current_line, in_quotes)?;
+            in_quotes = ends_inside;
+            lines.push(line);
+        }
+        Ok(lines)
+    }
+
+    /// Filtered lines at `line_indexes`, in file order, parsed the way [`Self::page_lines`]
+    /// parses them, so a delimited record that opens on a matching line is joined with the
+    /// lines it continues on.
+    fn filtered_page_lines(
+        quote_checkpoints: &mut QuoteCheckpoints,
+        read_ops: &FileReadOps,
+        evaluator: &VisualRuleEvaluator,
+        format: Option<&LogFormat>,
+        line_indexes: &[usize],
+    ) -> io::Result<Vec<PageLine>> {
+        let mut lines = Vec::with_capacity(line_indexes.len());
+        let Some(delimited) = format.filter(|format| format.has_header()) else {
+            for &line_index in line_indexes {
+                let text = read_ops.read_line(line_index)?;
+                lines.push(Self::page_line(evaluator, format, line_index + 1, text));
+            }
+            return Ok(lines);
+        };
+        for &line_index in line_indexes {
+            let in_quotes =
+                quote_checkpoints.opens_inside_quotes(read_ops.log_file(), delimited, line_index);
+            let (line, _) =
+                Self::delimited_line(read_ops, evaluator, delimited, line_index, in_quotes)?;
+            lines.push(line);
+        }
+        Ok(lines)
+    }
+
+    /// Line `line_index` of a delimited file, and whether it ends inside a quoted value.
+    fn delimited_line(
+        read_ops: &FileReadOps,
+        evaluator: &VisualRuleEvaluator,
+        delimited: &LogFormat,
+        line_index: usize,
+        in_quotes: bool,
+    ) -> io::Result<(PageLine, bool)> {
+        let text = read_ops.read_line(line_index)?;
+        let (fields, ends_inside) = FileReadOps::record_fields(
+            read_ops.log_file(),
+            delimited,
+            line_index,
+            &text,
+            in_quotes,
+        );
+        let mut line = Self::styled_line(evaluator, line_index + 1, text, fields);
+        line.continuation = in_quotes;
+        Ok((line, ends_inside))
+    }
+
+    /// Sets the time of every line on a page that has a timestamp. Lines are in file order, and
+    /// a line's delta is measured from the nearest line above it with a timestamp, whether or
+    /// not that line is on the page.
+    fn annotate_times(
+        read_ops: &FileReadOps,
+        format: Option<&LogFormat>,
+        time_zone: Zone,
+        reference_line: Option<usize>,
+        lines: &mut [PageLine],
+    ) {
+        let Some(format) = format else {
+            return;
+        };
+        let timestamp_format = format.definition().timestamp_format.as_deref();
+        let source_zone = format.source_zone();
+        let reference = reference_line
+            .and_then(|line| Self::instant_at(read_ops, format, line.checked_sub(1)?));
+        // Last line looked at, and the latest timestamp at or above it. Only the lines between
+        // it and the next line on the page are read, so no line is parsed twice for a page.
+        let mut scanned: Option<(usize, Option<DateTime<Utc>>)> = None;
+        for line in lines {
+            let line_index = line.number - 1;
+            let first = line_index
+                .saturating_sub(PREVIOUS_TIMESTAMP_LOOKBACK)
+                .max(scanned.map_or(0, |(number, _)| number));
+            let previous = Self::instant_between(read_ops, format, first, line_index)
+                .or_else(|| scanned.and_then(|(_, latest)| latest));
+            let instant = line_instant(&line.fields, timestamp_format, source_zone);
+            scanned = Some((line.number, instant.or(previous)));
+            let Some(instant) = instant else {
+                continue;
+            };
+            let millis_since = |earlier: DateTime<Utc>| (instant - earlier).num_milliseconds();
+            line.time = Some(LineTime {
+                timestamp: format_instant(instant, time_zone),
+                since_previous_ms: previous.map(millis_since),
+                since_reference_ms: reference.map(millis_since),
+            });
+        }
+    }
+
+    /// Timestamp of the last line in `first..end` that has one.
+    fn instant_between(
+        read_ops: &FileReadOps,
+        format: &LogFormat,
+        first: usize,
+        end: usize,
+    ) -> Option<DateTime<Utc>> {
+        (first..end)
+            .rev()
+            .find_map(|index| Self::instant_at(read_ops, format, index))
+    }
+
+    fn instant_at(
+        read_ops: &FileReadOps,
+        format: &LogFormat,
+        line_index: usize,
+    ) -> Option<DateTime<Utc>> {
+        if format.has_header() && line_index == 0 {
+            return None;
+        }
+        let fields = format.parse(&read_ops.read_line(line_index).ok()?)?;
+        line_instant(
+            &fields,
+            format.definition().timestamp_format.as_deref(),
+            format.source_zone(),
+        )
+    }
+
+    fn page_line(
+        evaluator: &VisualRuleEvaluator,
+        format: Option<&LogFormat>,
+        number: usize,
+        text: String,
+    ) -> PageLine {
+        let fields = format
+            .and_then(|format| format.parse(&text))
+            .unwrap_or_default();
+        Self::styled_line(evaluator, number, text, fields)
+    }
+
+    fn styled_line(
+        evaluator: &VisualRuleEvaluator,
+        number: usize,
+        text: String,
+        fields: Vec<(String, String)>,
+    ) -> PageLine {
+        let line = VisualLine {
+            text: &text,
+            number: Some(number),
+            fields: &fields,
+        };
+        let style = evaluator.evaluate(line);
+        let spans = evaluator.spans(line);
         PageLine {
             number,
             text,
             style,
+            spans,
+            fields,
+            continuation: false,
+            time: None,
         }
     }
 }
 
+/// Format of the file at `path`, detected from lines at both ends without waiting for the
+/// index.
+fn detect_log_format(path: &str, formats: &LogFormats) -> Option<FormatDetection> {
+    let (head, tail) = sample_lines(path).ok()?;
+    formats.detect(path, &head, &tail)
+}
+
+/// Up to `FORMAT_DETECTION_SAMPLE_LINES` whole lines from the start of the file and as many
+/// from its end, never the same line twice.
+fn sample_lines(path: &str) -> io::Result<(Vec<String>, Vec<String>)> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut head = Vec::new();
+    (&mut file)
+        .take(DETECTION_SAMPLE_BYTES)
+        .read_to_end(&mut head)?;
+    let head_lines = whole_lines(&head, false, head.len() as u64 == size);
+    let tail_start = size
+        .saturating_sub(DETECTION_SAMPLE_BYTES)
+        .max(head.len() as u64);
+    let tail_lines = if tail_start < size {
+        let mut tail = Vec::new();
+        file.seek(SeekFrom::Start(tail_start))?;
+        file.read_to_end(&mut tail)?;
+        whole_lines(&tail, true, true)
+    } else {
+        head_lines
+            .iter()
+            .skip(FORMAT_DETECTION_SAMPLE_LINES)
+            .cloned()
+            .collect()
+    };
+    let first = head_lines
+        .into_iter()
+        .take(FORMAT_DETECTION_SAMPLE_LINES)
+        .collect();
+    let last = tail_lines
+        .iter()
+        .skip(
+            tail_lines
+                .len()
+                .saturating_sub(FORMAT_DETECTION_SAMPLE_LINES),
+        )
+        .cloned()
+        .collect();
+    Ok((first, last))
+}
+
+/// Lines of a chunk of the file, leaving out a first line that may have started before the
+/// chunk and a last line that may go on after it.
+fn whole_lines(chunk: &[u8], starts_mid_line: bool, reaches_end: bool) -> Vec<String> {
+    let text = String::from_utf8_lossy(chunk);
+    let mut lines: Vec<String> = text
+        .lines()
+        .map(|line| line.trim_end_matches('\r').to_string())
+        .collect();
+    if !reaches_end && !text.ends_with('\n') {
+        lines.pop();
+    }
+    if starts_mid_line && !lines.is_empty() {
+        lines.remove(0);
+    }
+    lines
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{LineStyleIntent, VisualColor, VisualMatcher, VisualRule};
+    use crate::{
+        LineStyleIntent, StyledSpan, VisualColor, VisualMatcher, VisualRule, VisualRuleScope,
+    };
     use std::fs::File;
     use std::io::Write;
     use std::path::PathBuf;
@@ -286,14 +1073,17 @@ mod tests {
         LineStyleIntent {
             foreground: Some(VisualColor(foreground.to_string())),
             background: Some(VisualColor(background.to_string())),
+            ..LineStyleIntent::default()
         }
     }
 
     fn visual_rule(pattern: &str, foreground: &str) -> VisualRule {
         VisualRule {
+            name: None,
             matcher: VisualMatcher::Text(pattern.to_string()),
             case_sensitive: false,
             style: style(foreground, "default"),
+            scope: VisualRuleScope::Line,
         }
     }
 
@@ -325,6 +1115,10 @@ mod tests {
                 number: 2,
                 text: "beta match".to_string(),
                 style: None,
+                spans: Vec::new(),
+                fields: Vec::new(),
+                continuation: false,
+                time: None,
             }]
         );
 
@@ -336,6 +1130,10 @@ mod tests {
                 number: 4,
                 text: "delta match".to_string(),
                 style: None,
+                spans: Vec::new(),
+                fields: Vec::new(),
+                continuation: false,
+                time: None,
             }]
         );
 
@@ -394,11 +1192,19 @@ mod tests {
                     number: 2,
                     text: "one".to_string(),
                     style: None,
+                    spans: Vec::new(),
+                    fields: Vec::new(),
+                    continuation: false,
+                    time: None,
                 },
                 PageLine {
                     number: 3,
                     text: "two".to_string(),
                     style: None,
+                    spans: Vec::new(),
+                    fields: Vec::new(),
+                    continuation: false,
+                    time: None,
                 },
             ]
         );
@@ -433,11 +1239,19 @@ mod tests {
                     number: 1,
                     text: "first".to_string(),
                     style: None,
+                    spans: Vec::new(),
+                    fields: Vec::new(),
+                    continuation: false,
+                    time: None,
                 },
                 PageLine {
                     number: 2,
                     text: "second".to_string(),
                     style: None,
+                    spans: Vec::new(),
+                    fields: Vec::new(),
+                    continuation: false,
+                    time: None,
                 },
             ]
         );
@@ -446,108 +1260,878 @@ mod tests {
     }
 
     #[test]
-    fn visual_rules_style_page_and_tail_without_changing_visible_lines() {
-        let path = temp_file_path("visual-rules-page-tail");
+    fn match_scoped_visual_rules_return_span_decorations_on_page_lines() {
+        let path = temp_file_path("visual-rules-spans");
         let mut file = File::create(&path).unwrap();
-        writeln!(file, "INFO boot").unwrap();
-        writeln!(file, "WARN cache").unwrap();
-        write!(file, "ERROR disk").unwrap();
+        writeln!(file, "login user=alice").unwrap();
+        writeln!(file, "logout").unwrap();
         drop(file);
 
-        let mut plain_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
-        wait_total_lines(&plain_reader, 3);
-        let plain_page = plain_reader.read_page(0, 3).unwrap();
-        let plain_tail = plain_reader.tail(2, false).unwrap();
-
-        let mut styled_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
-        wait_total_lines(&styled_reader, 3);
-        styled_reader.set_visual_rules(vec![visual_rule("error", "red")]);
-
-        let styled_page = styled_reader.read_page(0, 3).unwrap();
-        let styled_tail = styled_reader.tail(2, false).unwrap();
-
-        assert_eq!(line_identity(&styled_page), line_identity(&plain_page));
-        assert_eq!(styled_page.start_line, plain_page.start_line);
-        assert_eq!(styled_page.total_lines, plain_page.total_lines);
-        assert_eq!(styled_page.search, plain_page.search);
-        assert_eq!(styled_page.lines[0].style, None);
-        assert_eq!(styled_page.lines[2].style, Some(style("red", "default")));
-
-        assert_eq!(line_identity(&styled_tail), line_identity(&plain_tail));
-        assert_eq!(styled_tail.start_line, plain_tail.start_line);
-        assert_eq!(styled_tail.total_lines, plain_tail.total_lines);
-        assert_eq!(styled_tail.lines[1].style, Some(style("red", "default")));
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 3);
+        reader.set_visual_rules(vec![VisualRule {
+            matcher: VisualMatcher::Regex(r"user=(\w+)".to_string()),
+            scope: VisualRuleScope::Groups(vec![1]),
+            ..visual_rule("", "cyan")
+        }]);
+
+        let page = reader.read_page(0, 2).unwrap();
+        assert_eq!(
+            page.lines[0].style, None,
+            "span rules leave the row unstyled"
+        );
+        assert_eq!(
+            page.lines[0].spans,
+            vec![StyledSpan {
+                start: 11,
+                end: 16,
+                style: style("cyan", "default"),
+            }]
+        );
+        assert!(page.lines[1].spans.is_empty());
 
         keep_temp_file_for_background_workers(path);
     }
 
     #[test]
-    fn visual_rules_do_not_change_filtered_reads_or_tail_filter_outcome() {
-        let path = temp_file_path("visual-rules-filter-tail");
+    fn chosen_log_formats_parse_page_fields_for_rules_and_profile_auto_select() {
+        let path = temp_file_path("log-format-fields");
         let mut file = File::create(&path).unwrap();
-        writeln!(file, "INFO boot").unwrap();
-        writeln!(file, "WARN cache").unwrap();
-        writeln!(file, "ERROR disk").unwrap();
+        writeln!(
+            file,
+            "2024-01-15 10:00:00,123 - app.db - WARNING - slow query"
+        )
+        .unwrap();
+        writeln!(file, "Traceback (most recent call last):").unwrap();
         drop(file);
+        let warnings = crate::ManagedVisualRule {
+            name: None,
+            enabled: true,
+            matcher: VisualMatcher::Field(crate::FieldPredicate {
+                field: "level".to_string(),
+                operator: crate::FieldOperator::Ge,
+                value: "warn".to_string(),
+            }),
+            case_sensitive: false,
+            style: style("orange", "default"),
+            scope: VisualRuleScope::Line,
+        };
+        let mut envelope = crate::VisualRulesEnvelope::new(Vec::new());
+        envelope.profiles = vec![crate::VisualRuleProfile {
+            name: "python".to_string(),
+            rules: vec![warnings],
+            auto_select: Some(crate::ProfileAutoSelect {
+                path_glob: None,
+                format: Some("Python".to_string()),
+            }),
+        }];
+        let manager = VisualRulesManager::in_memory();
+        manager.apply_memory(envelope).unwrap();
+        let mut reader = LogReader::with_managers(
+            path.to_string_lossy().into_owned(),
+            manager,
+            LogFormatManager::in_memory(),
+        )
+        .unwrap();
+        wait_total_lines(&reader, 2);
+        assert!(reader.read_page(0, 2).unwrap().lines[0].fields.is_empty());
+        assert_eq!(reader.visual_profile().auto_selected, None);
+
+        let status = reader.set_log_format(Some("python".to_string())).unwrap();
+        assert_eq!(status.active.as_deref(), Some("python"));
+        assert_eq!(reader.visual_profile().active, "python");
+        let page = reader.read_page(0, 2).unwrap();
+        assert_eq!(
+            page.lines[0].fields[2],
+            ("level".to_string(), "WARNING".to_string())
+        );
+        assert_eq!(page.lines[0].style, Some(style("orange", "default")));
+        assert!(page.lines[1].fields.is_empty());
+        assert_eq!(page.lines[1].style, None);
+        assert_eq!(
+            reader
+                .set_log_format(Some("missing".to_string()))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
 
-        let mut plain_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
-        wait_total_lines(&plain_reader, 3);
-        plain_reader.filter("WARN|ERROR".to_string());
-        wait_filtered_lines(&mut plain_reader, 2);
-        let plain_filter = plain_reader.read_filter(0, 10).unwrap();
-        let plain_tail_filter = plain_reader.tail_filter(2, false).unwrap();
+        keep_temp_file_for_background_workers(path);
+    }
 
-        let mut styled_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
-        wait_total_lines(&styled_reader, 3);
-        styled_reader.filter("WARN|ERROR".to_string());
-        wait_filtered_lines(&mut styled_reader, 2);
-        styled_reader.set_visual_rules(vec![visual_rule("warn", "yellow")]);
-        let styled_filter = styled_reader.read_filter(0, 10).unwrap();
-        let styled_tail_filter = styled_reader.tail_filter(2, false).unwrap();
+    #[test]
+    fn json_lines_discover_dotted_fields_choose_columns_and_filter_by_field() {
+        let path = temp_file_path("json-lines");
+        let mut file = File::create(&path).unwrap();
+        writeln!(
+            file,
+            r#"{{"level":"info","http":{{"status":200}},"msg":"ok"}}"#
+        )
+        .unwrap();
+        writeln!(file, "plain startup banner").unwrap();
+        writeln!(
+            file,
+            r#"{{"level":"error","http":{{"status":503}},"msg":"error upstream"}}"#
+        )
+        .unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 3);
+        let detection = reader.file_info().unwrap().detected_format.unwrap();
+        assert_eq!(detection.format, "json");
+        assert!(detection.is_applied());
+        assert_eq!(reader.log_format().active.as_deref(), Some("json"));
 
-        assert_eq!(line_identity(&styled_filter), line_identity(&plain_filter));
-        assert_eq!(styled_filter.start_line, plain_filter.start_line);
-        assert_eq!(styled_filter.total_lines, plain_filter.total_lines);
-        assert_eq!(styled_filter.search, plain_filter.search);
+        reader.set_log_format(None).unwrap();
         assert_eq!(
-            styled_filter.lines[0].style,
-            Some(style("yellow", "default"))
+            reader.discover_fields(100).unwrap(),
+            FieldDiscovery::default()
+        );
+        assert_eq!(
+            reader
+                .filter_field("level".to_string(), "error".to_string())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
         );
-        assert_eq!(styled_filter.lines[1].style, None);
 
+        reader.set_log_format(Some("json".to_string())).unwrap();
+        let discovery = reader.discover_fields(100).unwrap();
+        assert_eq!((discovery.sampled_lines, discovery.parsed_lines), (3, 2));
         assert_eq!(
-            line_identity(&styled_tail_filter),
-            line_identity(&plain_tail_filter)
+            discovery.fields[1],
+            DiscoveredField {
+                path: "http.status".to_string(),
+                lines: 2,
+            }
         );
-        assert_eq!(styled_tail_filter.start_line, plain_tail_filter.start_line);
+        let status = reader
+            .set_columns(vec![
+                "level".to_string(),
+                " http.status ".to_string(),
+                "level".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(status.columns, ["level", "http.status"]);
+        let page = reader.read_page(0, 3).unwrap();
+        assert!(page.lines[1].fields.is_empty());
+        assert_eq!(page.lines[2].fields[1].1, "503");
+
+        reader
+            .filter_field("msg".to_string(), "error".to_string())
+            .unwrap();
+        wait_filtered_lines(&mut reader, 1);
+        let filtered = reader.read_filter(0, 10).unwrap();
+        assert_eq!(filtered.lines.len(), 1);
+        assert_eq!(filtered.lines[0].number, 3);
         assert_eq!(
-            styled_tail_filter.total_lines,
-            plain_tail_filter.total_lines
+            reader
+                .filter_field("msg".to_string(), "(unclosed".to_string())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
         );
-        assert_eq!(styled_tail_filter.search, plain_tail_filter.search);
         assert_eq!(
-            styled_tail_filter.lines[0].style,
-            Some(style("yellow", "default"))
+            reader.read_filter(0, 10).unwrap().lines[0].number,
+            3,
+            "the previous filter stays"
         );
-        assert_eq!(styled_tail_filter.lines[1].style, None);
 
+        assert!(
+            reader
+                .set_log_format(Some("go".to_string()))
+                .unwrap()
+                .columns
+                .is_empty()
+        );
         keep_temp_file_for_background_workers(path);
     }
 
     #[test]
-    fn search_status_and_page_metadata_include_multi_occurrence_spans() {
-        let path = temp_file_path("search-multi-occurrence");
+    fn logfmt_fields_filter_by_duration_and_style_with_field_rules() {
+        let path = temp_file_path("logfmt");
         let mut file = File::create(&path).unwrap();
-        writeln!(file, "foo foo").unwrap();
-        writeln!(file, "bar").unwrap();
-        writeln!(file, "foo").unwrap();
+        writeln!(file, r#"level=info msg="GET /health" duration=12ms"#).unwrap();
+        writeln!(file, r#"level=warn msg="GET /report" duration=1.5s"#).unwrap();
+        writeln!(file, "listening on :8080").unwrap();
+        writeln!(file, r#"level=info msg="GET /users" duration=250ms"#).unwrap();
         drop(file);
-
-        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
-        for _ in 0..10 {
-            if reader.file_info().unwrap().total_lines >= 3 {
-                break;
-            }
+        let slow = crate::ManagedVisualRule {
+            name: None,
+            enabled: true,
+            matcher: VisualMatcher::Field(crate::FieldPredicate {
+                field: "duration".to_string(),
+                operator: crate::FieldOperator::Gt,
+                value: "100ms".to_string(),
+            }),
+            case_sensitive: false,
+            style: style("red", "default"),
+            scope: VisualRuleScope::Line,
+        };
+        let manager = VisualRulesManager::in_memory();
+        manager
+            .apply_memory(crate::VisualRulesEnvelope::new(vec![slow]))
+            .unwrap();
+        let mut reader = LogReader::with_managers(
+            path.to_string_lossy().into_owned(),
+            manager,
+            LogFormatManager::in_memory(),
+        )
+        .unwrap();
+        wait_total_lines(&reader, 4);
+        reader.set_log_format(Some("logfmt".to_string())).unwrap();
+
+        let page = reader.read_page(0, 4).unwrap();
+        assert_eq!(
+            page.lines[0].fields[1],
+            ("msg".to_string(), "GET /health".to_string())
+        );
+        assert_eq!(page.lines[0].style, None);
+        assert_eq!(page.lines[1].style, Some(style("red", "default")));
+        assert!(page.lines[2].fields.is_empty());
+        let discovery = reader.discover_fields(100).unwrap();
+        assert_eq!((discovery.sampled_lines, discovery.parsed_lines), (4, 3));
+        assert_eq!(
+            reader
+                .set_columns(vec!["duration".to_string()])
+                .unwrap()
+                .columns,
+            ["duration"]
+        );
+
+        reader
+            .filter_field("duration".to_string(), ">100ms".to_string())
+            .unwrap();
+        wait_filtered_lines(&mut reader, 2);
+        let filtered = reader.read_filter(0, 10).unwrap();
+        let numbers: Vec<_> = filtered.lines.iter().map(|line| line.number).collect();
+        assert_eq!(numbers, [2, 4]);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn queries_filter_parsed_lines_by_typed_fields() {
+        let path = temp_file_path("query");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "level=info service=billing duration=900ms").unwrap();
+        writeln!(
+            file,
+            "level=error service=billing duration=1.5s msg=timeout"
+        )
+        .unwrap();
+        writeln!(file, "level=warn service=api duration=2s msg=timeout").unwrap();
+        writeln!(file, "level=error service=billing duration=abc").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 4);
+        let query = || {
+            LogQuery::parse(
+                r#"level >= WARN and service = "billing" and duration > 250ms and msg ~ /time/"#,
+            )
+            .unwrap()
+        };
+
+        reader.set_log_format(None).unwrap();
+        assert_eq!(
+            reader.filter_query(query()).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        reader.set_log_format(Some("logfmt".to_string())).unwrap();
+        reader.filter_query(query()).unwrap();
+        wait_filtered_lines(&mut reader, 1);
+        let filtered = reader.read_filter(0, 10).unwrap();
+        let numbers: Vec<_> = filtered.lines.iter().map(|line| line.number).collect();
+        assert_eq!(numbers, [2]);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn query_filters_keep_whole_csv_records_and_skip_the_header() {
+        let path = temp_file_path("query-filter-records").with_extension("csv");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "user,action,note").unwrap();
+        writeln!(file, r#"jane,login,"first"#).unwrap();
+        writeln!(file, r#"user = bob""#).unwrap();
+        writeln!(file, "bob,logout,plain").unwrap();
+        writeln!(file, "user,user,header-like").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 5);
+        assert_eq!(reader.log_format().active.as_deref(), Some("csv"));
+        let mut filtered = |query: &str, expected: usize| {
+            reader
+                .filter_query(LogQuery::parse(query).unwrap())
+                .unwrap();
+            wait_filtered_lines(&mut reader, expected);
+            let page = reader.read_filter(0, 10).unwrap();
+            page.lines
+                .iter()
+                .map(|line| line.number)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(filtered(r#"user = "jane""#, 2), [2, 3]);
+        assert_eq!(filtered(r#"note ~ /first\nuser = bob/"#, 2), [2, 3]);
+        assert_eq!(filtered(r#"user = "bob""#, 1), [4]);
+        assert_eq!(filtered(r#"user = "user""#, 1), [5]);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[cfg(feature = "sql")]
+    #[test]
+    fn sql_queries_group_and_sort_parsed_fields_and_link_rows_to_lines() {
+        use serde_json::json;
+
+        let path = temp_file_path("sql");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "level=info duration=900").unwrap();
+        writeln!(file, "level=error duration=1500 msg=timeout").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "level=error duration=2500").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 4);
+        reader.set_log_format(Some("logfmt".to_string())).unwrap();
+
+        let counts = reader
+            .sql("SELECT level, count(*) AS n FROM log GROUP BY level ORDER BY level")
+            .unwrap();
+        assert_eq!(counts.columns, ["level", "n"]);
+        let values: Vec<_> = counts.rows.iter().map(|row| row.values.clone()).collect();
+        assert_eq!(
+            values,
+            [
+                vec![json!("error"), json!(2)],
+                vec![json!("info"), json!(1)]
+            ]
+        );
+        assert!(counts.rows.iter().all(|row| row.line.is_none()));
+
+        let slow = reader
+            .sql("SELECT * FROM log WHERE duration > 1000 ORDER BY duration DESC LIMIT 20")
+            .unwrap();
+        assert_eq!(slow.columns[..2], ["log_line", "log_text"]);
+        let lines: Vec<_> = slow.rows.iter().map(|row| row.line).collect();
+        assert_eq!(lines, [Some(4), Some(2)]);
+        assert!(!slow.truncated);
+
+        assert_eq!(
+            reader.sql("DELETE FROM log").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        for statement in [
+            "ATTACH DATABASE 'other.db' AS other",
+            "PRAGMA journal_mode = WAL",
+        ] {
+            let error = reader.sql(statement).unwrap_err();
+            assert_eq!(error.to_string(), "only SELECT queries can run over a log");
+        }
+        assert_eq!(
+            reader.sql("SELECT nope FROM log").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[cfg(feature = "sql")]
+    #[test]
+    fn sql_reads_durations_with_units_in_milliseconds() {
+        use serde_json::json;
+
+        let path = temp_file_path("sql_durations");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "path=/a duration=12ms").unwrap();
+        writeln!(file, "path=/b duration=1.5s").unwrap();
+        writeln!(file, "path=/c duration=250us").unwrap();
+        writeln!(file, "path=/d duration=2500").unwrap();
+        writeln!(file, "path=/e duration=570ms id=007").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 5);
+        reader.set_log_format(Some("logfmt".to_string())).unwrap();
+
+        let slow = reader
+            .sql("SELECT * FROM log WHERE duration > 1000 ORDER BY duration DESC LIMIT 20")
+            .unwrap();
+        let lines: Vec<_> = slow.rows.iter().map(|row| row.line).collect();
+        assert_eq!(lines, [Some(4), Some(2)]);
+        let durations = reader
+            .sql("SELECT duration FROM log ORDER BY duration")
+            .unwrap();
+        let values: Vec<_> = durations
+            .rows
+            .iter()
+            .map(|row| row.values[0].clone())
+            .collect();
+        assert_eq!(
+            values,
+            [json!(0.25), json!(12), json!(570), json!(1500), json!(2500)]
+        );
+        let exact = reader
+            .sql("SELECT id, typeof(id) FROM log WHERE duration = 570")
+            .unwrap();
+        assert_eq!(exact.rows.len(), 1);
+        assert_eq!(exact.rows[0].values, [json!("007"), json!("text")]);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn filtered_page_deltas_carry_the_previous_timestamp_across_long_gaps() {
+        let path = temp_file_path("times-gap");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, r#"{{"ts":"2026-10-03T10:00:00Z","msg":"start"}}"#).unwrap();
+        for _ in 0..PREVIOUS_TIMESTAMP_LOOKBACK * 2 {
+            writeln!(file, r#"{{"msg":"no time"}}"#).unwrap();
+        }
+        writeln!(file, r#"{{"ts":"2026-10-03T10:00:02Z","msg":"done"}}"#).unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, PREVIOUS_TIMESTAMP_LOOKBACK * 2 + 2);
+        reader.set_log_format(Some("json".to_string())).unwrap();
+
+        reader.filter("start|done".to_string());
+        wait_filtered_lines(&mut reader, 2);
+        let filtered = reader.read_filter(0, 10).unwrap();
+        assert_eq!(
+            filtered.lines[1].time.as_ref().unwrap().since_previous_ms,
+            Some(2_000)
+        );
+        let alone = reader.read_filter(1, 1).unwrap();
+        assert_eq!(
+            alone.lines[0].time.as_ref().unwrap().since_previous_ms,
+            None,
+            "a page's first line looks a bounded number of lines up"
+        );
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn page_times_show_in_the_chosen_zone_with_deltas_to_the_previous_and_reference_lines() {
+        let path = temp_file_path("times");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, r#"{{"ts":1791021600000,"msg":"start"}}"#).unwrap();
+        writeln!(file, r#"{{"msg":"no time"}}"#).unwrap();
+        writeln!(
+            file,
+            r#"{{"ts":"2026-10-03T12:00:01.250+02:00","msg":"retry"}}"#
+        )
+        .unwrap();
+        writeln!(file, r#"{{"ts":"2026-10-03 10:02:00,000","msg":"done"}}"#).unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 4);
+        reader.set_log_format(Some("json".to_string())).unwrap();
+
+        let settings = reader.set_time_zone("+02:00").unwrap();
+        assert_eq!(settings.time_zone, "+02:00");
+        assert_eq!(
+            reader.set_time_reference(Some(2)).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        let settings = reader.set_time_reference(Some(3)).unwrap();
+        assert_eq!(
+            settings.reference_timestamp.as_deref(),
+            Some("2026-10-03 12:00:01.250")
+        );
+
+        let page = reader.read_page(0, 4).unwrap();
+        let times: Vec<_> = page.lines.iter().map(|line| line.time.clone()).collect();
+        assert_eq!(
+            times[0],
+            Some(LineTime {
+                timestamp: "2026-10-03 12:00:00.000".to_string(),
+                since_previous_ms: None,
+                since_reference_ms: Some(-1_250),
+            })
+        );
+        assert_eq!(times[1], None);
+        assert_eq!(times[2].as_ref().unwrap().since_previous_ms, Some(1_250));
+        assert_eq!(
+            times[3],
+            Some(LineTime {
+                timestamp: "2026-10-03 12:02:00.000".to_string(),
+                since_previous_ms: Some(118_750),
+                since_reference_ms: Some(118_750),
+            })
+        );
+
+        let from_below = reader.read_page(3, 1).unwrap();
+        assert_eq!(
+            from_below.lines[0].time.as_ref().unwrap().since_previous_ms,
+            Some(118_750)
+        );
+        reader.filter("start|done".to_string());
+        wait_filtered_lines(&mut reader, 2);
+        let filtered = reader.read_filter(0, 10).unwrap();
+        assert_eq!(
+            filtered.lines[1].time.as_ref().unwrap().since_previous_ms,
+            Some(118_750),
+            "deltas are measured in file order, not between filtered lines"
+        );
+        assert_eq!(
+            reader.set_time_zone("Mars/Olympus").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        let settings = reader.set_time_zone("europe/madrid").unwrap();
+        assert_eq!(settings.time_zone, "Europe/Madrid");
+        assert_eq!(
+            reader.read_page(0, 1).unwrap().lines[0]
+                .time
+                .as_ref()
+                .unwrap()
+                .timestamp,
+            "2026-10-03 12:00:00.000"
+        );
+        assert_eq!(
+            reader.set_time_reference(None).unwrap().reference_line,
+            None
+        );
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn csv_files_are_detected_and_page_records_spanning_lines_under_the_header() {
+        let path = temp_file_path("audit").with_extension("csv");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "time,user,action").unwrap();
+        writeln!(file, r#"10:00,"Doe, Jane","login"#).unwrap();
+        writeln!(file, r#"from ""vpn""""#).unwrap();
+        writeln!(file, "10:01,bob,logout").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 4);
+
+        let status = reader.log_format();
+        assert_eq!(status.active.as_deref(), Some("csv"));
+        assert_eq!(status.columns, ["time", "user", "action"]);
+        let page = reader.read_page(0, 4).unwrap();
+        assert!(page.lines[0].fields.is_empty());
+        assert_eq!(
+            page.lines[1].fields,
+            [
+                ("time".to_string(), "10:00".to_string()),
+                ("user".to_string(), "Doe, Jane".to_string()),
+                ("action".to_string(), "login\nfrom \"vpn\"".to_string()),
+            ]
+        );
+        assert!(page.lines[2].continuation && page.lines[2].fields.is_empty());
+        assert_eq!(page.lines[3].fields[1].1, "bob");
+        assert!(!page.lines[3].continuation);
+
+        let from_inside = reader.read_page(2, 2).unwrap();
+        assert!(from_inside.lines[0].continuation);
+        assert_eq!(from_inside.lines[1].fields[2].1, "logout");
+        let discovery = reader.discover_fields(100).unwrap();
+        assert_eq!(discovery.fields.len(), 3);
+        assert!(reader.set_columns(Vec::new()).unwrap().columns.is_empty());
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn filtered_pages_join_csv_records_spanning_lines() {
+        let path = temp_file_path("audit-filtered").with_extension("csv");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "time,user,action").unwrap();
+        writeln!(file, r#"10:00,jane,"login"#).unwrap();
+        writeln!(file, r#"from vpn""#).unwrap();
+        writeln!(file, "10:01,bob,logout").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 4);
+        assert_eq!(reader.log_format().active.as_deref(), Some("csv"));
+
+        reader.filter("jane|vpn|bob".to_string());
+        wait_filtered_lines(&mut reader, 3);
+        for page in [
+            reader.read_filter(0, 10).unwrap(),
+            reader.tail_filter(10, false).unwrap(),
+        ] {
+            let numbers: Vec<_> = page.lines.iter().map(|line| line.number).collect();
+            assert_eq!(numbers, [2, 3, 4]);
+            assert_eq!(page.lines[0].fields[2].1, "login\nfrom vpn");
+            assert!(!page.lines[0].continuation);
+            assert!(page.lines[1].continuation && page.lines[1].fields.is_empty());
+            assert_eq!(page.lines[2].fields[1].1, "bob");
+        }
+
+        reader.filter("vpn|bob".to_string());
+        wait_filtered_lines(&mut reader, 2);
+        let page = reader.read_filter(0, 10).unwrap();
+        assert!(page.lines[0].continuation, "a record continued from above");
+        assert_eq!(page.lines[1].fields[2].1, "logout");
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn blank_csv_lines_have_no_fields_and_never_match_field_filters() {
+        let path = temp_file_path("blank-rows").with_extension("csv");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "item,user").unwrap();
+        writeln!(file, "pen,bob").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "ink,amy").unwrap();
+        writeln!(file).unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 5);
+        assert_eq!(reader.log_format().active.as_deref(), Some("csv"));
+
+        let page = reader.read_page(0, 10).unwrap();
+        assert!(page.lines[2].fields.is_empty() && page.lines[4].fields.is_empty());
+
+        reader
+            .filter_field("item".to_string(), "!=pen".to_string())
+            .unwrap();
+        wait_filtered_lines(&mut reader, 1);
+        let numbers: Vec<_> = reader
+            .read_filter(0, 10)
+            .unwrap()
+            .lines
+            .iter()
+            .map(|line| line.number)
+            .collect();
+        assert_eq!(numbers, [4]);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn stray_quotes_in_unquoted_csv_values_do_not_join_the_rows_below() {
+        let path = temp_file_path("stray-quote").with_extension("csv");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "item,size,user").unwrap();
+        writeln!(file, r#"monitor,27" wide,alice"#).unwrap();
+        writeln!(file, "disk,2 TB,bob").unwrap();
+        writeln!(file, r#"cable,"1 m"#).unwrap();
+        writeln!(file, r#"coiled",carol"#).unwrap();
+        writeln!(file, "mouse,small,dave").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 6);
+        assert_eq!(reader.log_format().active.as_deref(), Some("csv"));
+
+        let page = reader.read_page(0, 6).unwrap();
+        let users: Vec<_> = page
+            .lines
+            .iter()
+            .map(|line| line.fields.get(2).map(|(_, user)| user.as_str()))
+            .collect();
+        assert_eq!(
+            users,
+            [
+                None,
+                Some("alice"),
+                Some("bob"),
+                Some("carol"),
+                None,
+                Some("dave")
+            ]
+        );
+        assert_eq!(page.lines[1].fields[1].1, r#"27" wide"#);
+        assert_eq!(page.lines[3].fields[1].1, "1 m\ncoiled");
+        let continuations: Vec<_> = page.lines.iter().map(|line| line.continuation).collect();
+        assert_eq!(continuations, [false, false, false, false, true, false]);
+
+        for (user, expected) in [("bob", vec![3]), ("carol", vec![4, 5]), ("dave", vec![6])] {
+            reader
+                .filter_query(LogQuery::parse(&format!(r#"user = "{user}""#)).unwrap())
+                .unwrap();
+            wait_filtered_lines(&mut reader, expected.len());
+            let numbers: Vec<_> = reader
+                .read_filter(0, 10)
+                .unwrap()
+                .lines
+                .iter()
+                .map(|line| line.number)
+                .collect();
+            assert_eq!(numbers, expected);
+        }
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn files_render_with_their_pinned_or_auto_selected_visual_profile() {
+        let path = temp_file_path("visual-profiles");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "GET /health 200").unwrap();
+        drop(file);
+        let managed = |pattern: &str, foreground: &str| crate::ManagedVisualRule {
+            name: None,
+            enabled: true,
+            matcher: VisualMatcher::Text(pattern.to_string()),
+            case_sensitive: false,
+            style: style(foreground, "default"),
+            scope: VisualRuleScope::Line,
+        };
+        let mut envelope = crate::VisualRulesEnvelope::new(vec![managed("GET", "gray")]);
+        envelope.profiles = vec![
+            crate::VisualRuleProfile {
+                name: "access".to_string(),
+                rules: vec![managed("GET", "green")],
+                auto_select: Some(crate::ProfileAutoSelect {
+                    path_glob: Some("logmancer-visual-profiles-*".to_string()),
+                    format: None,
+                }),
+            },
+            crate::VisualRuleProfile {
+                name: "quiet".to_string(),
+                rules: Vec::new(),
+                auto_select: None,
+            },
+        ];
+        let manager = VisualRulesManager::in_memory();
+        manager.apply_memory(envelope.clone()).unwrap();
+
+        let mut reader =
+            LogReader::with_manager(path.to_string_lossy().into_owned(), manager.clone()).unwrap();
+        wait_total_lines(&reader, 1);
+        assert_eq!(reader.visual_profile().active, "access");
+        assert_eq!(
+            reader.read_page(0, 1).unwrap().lines[0].style,
+            Some(style("green", "default"))
+        );
+
+        let status = reader
+            .set_visual_profile(Some("quiet".to_string()))
+            .unwrap();
+        assert_eq!(status.pinned.as_deref(), Some("quiet"));
+        assert_eq!(reader.read_page(0, 1).unwrap().lines[0].style, None);
+        assert_eq!(
+            reader
+                .set_visual_profile(Some("missing".to_string()))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        envelope.profiles.truncate(1);
+        manager.apply_memory(envelope).unwrap();
+        assert_eq!(reader.visual_profile().active, "access");
+        assert_eq!(reader.visual_profile().pinned, None);
+        reader
+            .set_visual_profile(Some(crate::DEFAULT_VISUAL_PROFILE.to_string()))
+            .unwrap();
+        assert_eq!(
+            reader.read_page(0, 1).unwrap().lines[0].style,
+            Some(style("gray", "default"))
+        );
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn visual_rules_style_page_and_tail_without_changing_visible_lines() {
+        let path = temp_file_path("visual-rules-page-tail");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "INFO boot").unwrap();
+        writeln!(file, "WARN cache").unwrap();
+        write!(file, "ERROR disk").unwrap();
+        drop(file);
+
+        let mut plain_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&plain_reader, 3);
+        let plain_page = plain_reader.read_page(0, 3).unwrap();
+        let plain_tail = plain_reader.tail(2, false).unwrap();
+
+        let mut styled_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&styled_reader, 3);
+        styled_reader.set_visual_rules(vec![visual_rule("error", "red")]);
+
+        let styled_page = styled_reader.read_page(0, 3).unwrap();
+        let styled_tail = styled_reader.tail(2, false).unwrap();
+
+        assert_eq!(line_identity(&styled_page), line_identity(&plain_page));
+        assert_eq!(styled_page.start_line, plain_page.start_line);
+        assert_eq!(styled_page.total_lines, plain_page.total_lines);
+        assert_eq!(styled_page.search, plain_page.search);
+        assert_eq!(styled_page.lines[0].style, None);
+        assert_eq!(styled_page.lines[2].style, Some(style("red", "default")));
+
+        assert_eq!(line_identity(&styled_tail), line_identity(&plain_tail));
+        assert_eq!(styled_tail.start_line, plain_tail.start_line);
+        assert_eq!(styled_tail.total_lines, plain_tail.total_lines);
+        assert_eq!(styled_tail.lines[1].style, Some(style("red", "default")));
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn visual_rules_do_not_change_filtered_reads_or_tail_filter_outcome() {
+        let path = temp_file_path("visual-rules-filter-tail");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "INFO boot").unwrap();
+        writeln!(file, "WARN cache").unwrap();
+        writeln!(file, "ERROR disk").unwrap();
+        drop(file);
+
+        let mut plain_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&plain_reader, 3);
+        plain_reader.filter("WARN|ERROR".to_string());
+        wait_filtered_lines(&mut plain_reader, 2);
+        let plain_filter = plain_reader.read_filter(0, 10).unwrap();
+        let plain_tail_filter = plain_reader.tail_filter(2, false).unwrap();
+
+        let mut styled_reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&styled_reader, 3);
+        styled_reader.filter("WARN|ERROR".to_string());
+        wait_filtered_lines(&mut styled_reader, 2);
+        styled_reader.set_visual_rules(vec![visual_rule("warn", "yellow")]);
+        let styled_filter = styled_reader.read_filter(0, 10).unwrap();
+        let styled_tail_filter = styled_reader.tail_filter(2, false).unwrap();
+
+        assert_eq!(line_identity(&styled_filter), line_identity(&plain_filter));
+        assert_eq!(styled_filter.start_line, plain_filter.start_line);
+        assert_eq!(styled_filter.total_lines, plain_filter.total_lines);
+        assert_eq!(styled_filter.search, plain_filter.search);
+        assert_eq!(
+            styled_filter.lines[0].style,
+            Some(style("yellow", "default"))
+        );
+        assert_eq!(styled_filter.lines[1].style, None);
+
+        assert_eq!(
+            line_identity(&styled_tail_filter),
+            line_identity(&plain_tail_filter)
+        );
+        assert_eq!(styled_tail_filter.start_line, plain_tail_filter.start_line);
+        assert_eq!(
+            styled_tail_filter.total_lines,
+            plain_tail_filter.total_lines
+        );
+        assert_eq!(styled_tail_filter.search, plain_tail_filter.search);
+        assert_eq!(
+            styled_tail_filter.lines[0].style,
+            Some(style("yellow", "default"))
+        );
+        assert_eq!(styled_tail_filter.lines[1].style, None);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn search_status_and_page_metadata_include_multi_occurrence_spans() {
+        let path = temp_file_path("search-multi-occurrence");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "foo foo").unwrap();
+        writeln!(file, "bar").unwrap();
+        writeln!(file, "foo").unwrap();
+        drop(file);
+
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        for _ in 0..10 {
+            if reader.file_info().unwrap().total_lines >= 3 {
+                break;
+            }
             sleep(Duration::from_millis(50));
         }
 
@@ -687,14 +2271,14 @@ mod tests {
     fn apply_search_returns_before_full_indexing_ready() {
         let path = temp_file_path("search-async-fast-return");
         let mut file = File::create(&path).unwrap();
-        for i in 0..8_000 {
+        for i in 0..300_000 {
             writeln!(file, "line {i} foo").unwrap();
         }
         drop(file);
 
         let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
-        for _ in 0..40 {
-            if reader.file_info().unwrap().total_lines >= 8_000 {
+        for _ in 0..100 {
+            if reader.file_info().unwrap().total_lines >= 300_000 {
                 break;
             }
             sleep(Duration::from_millis(20));
@@ -743,4 +2327,357 @@ mod tests {
 
         std::fs::remove_file(path).unwrap();
     }
+
+    #[test]
+    fn marks_decorate_pages_and_navigate_independently_from_search() {
+        let path = temp_file_path("marks");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "req-7 host-a").unwrap();
+        writeln!(file, "other").unwrap();
+        writeln!(file, "req-7 host-b").unwrap();
+        writeln!(file, "host-a").unwrap();
+        drop(file);
+
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 4);
+
+        let request = reader.add_mark("req-7".to_string(), None).unwrap();
+        let host = reader
+            .add_mark(
+                "host-a".to_string(),
+                Some(VisualColor("purple".to_string())),
+            )
+            .unwrap();
+        for _ in 0..40 {
+            if reader
+                .marks_status()
+                .iter()
+                .all(|mark| mark.search.is_ready)
+            {
+                break;
+            }
+            sleep(Duration::from_millis(20));
+        }
+
+        let page = reader.read_page(0, 2).unwrap();
+        assert_eq!(page.search, None);
+        assert_eq!(page.marks.len(), 2);
+        assert_eq!(page.marks[0].id, request);
+        assert_eq!(page.marks[0].color, VisualColor("yellow".to_string()));
+        assert_eq!(page.marks[0].search.total_matches, 2);
+        assert_eq!(page.marks[0].search.page_matches.len(), 1);
+        assert_eq!(page.marks[1].color, VisualColor("purple".to_string()));
+        assert_eq!(page.marks[1].search.page_matches[0].start, 6);
+
+        let next = reader.mark_next(request, 2).unwrap();
+        let current = next.marks[0].search.current.as_ref().unwrap();
+        assert_eq!(current.line_index, 2);
+        assert_eq!(next.marks[1].search.current.as_ref().unwrap().line_index, 0);
+
+        assert_eq!(
+            reader.add_mark("(".to_string(), None).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert!(reader.remove_mark(host));
+        assert_eq!(
+            reader.mark_next(host, 2).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(reader.read_page(0, 2).unwrap().marks.len(), 1);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    fn wait_rule_counts_ready(reader: &mut LogReader) -> RuleCountStatus {
+        for _ in 0..40 {
+            let status = reader.visual_rule_counts().unwrap();
+            if status.is_ready {
+                return status;
+            }
+            sleep(Duration::from_millis(20));
+        }
+        reader.visual_rule_counts().unwrap()
+    }
+
+    #[test]
+    fn rule_counts_navigate_by_rule_and_restart_when_rules_are_published() {
+        let path = temp_file_path("rule-counts");
+        let mut file = File::create(&path).unwrap();
+        for line in [
+            "ERROR disk",
+            "INFO ok",
+            "WARN slow",
+            "ERROR OutOfMemory",
+            "INFO ok",
+        ] {
+            writeln!(file, "{line}").unwrap();
+        }
+        drop(file);
+
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 5);
+        reader.set_visual_rules(vec![
+            VisualRule {
+                name: Some("Errors".to_string()),
+                ..visual_rule("error", "red")
+            },
+            visual_rule("outofmemory", "purple"),
+        ]);
+
+        let status = wait_rule_counts_ready(&mut reader);
+        assert_eq!(status.profile, crate::DEFAULT_VISUAL_PROFILE);
+        let counts = status
+            .rules
+            .iter()
+            .map(|count| (count.name.as_deref(), count.matched_lines))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [(Some("Errors"), 2), (None, 1)]);
+
+        reader.read_page(1, 2).unwrap();
+        let next = reader.visual_rule_next(1, 2).unwrap();
+        assert_eq!(next.line_index, Some(3));
+        assert_eq!(next.page.start_line, 2);
+        let previous = reader.visual_rule_previous(0, 2).unwrap();
+        assert_eq!(
+            previous.line_index,
+            Some(0),
+            "starts before the current page"
+        );
+        assert_eq!(
+            reader.visual_rule_next(2, 2).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        reader.set_visual_rules(vec![visual_rule("info", "gray")]);
+        let restarted = wait_rule_counts_ready(&mut reader);
+        assert!(restarted.generation > status.generation);
+        assert!(restarted.revision > status.revision);
+        assert_eq!(restarted.rules.len(), 1);
+        assert_eq!(restarted.rules[0].matched_lines, 2);
+        assert_eq!(restarted.rules[0].current_line_index, None);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn rule_counts_and_density_parse_fields_like_pages() {
+        let path = temp_file_path("rule-count-fields").with_extension("csv");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "user,note").unwrap();
+        writeln!(file, r#"bob,"first"#).unwrap();
+        writeln!(file, r#"bob,second""#).unwrap();
+        writeln!(file, "amy,plain").unwrap();
+        writeln!(file, "bob,done").unwrap();
+        drop(file);
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 6);
+        assert_eq!(reader.log_format().active.as_deref(), Some("csv"));
+        reader.set_visual_rules(vec![VisualRule {
+            matcher: VisualMatcher::Field(crate::FieldPredicate {
+                field: "user".to_string(),
+                operator: crate::FieldOperator::Eq,
+                value: "bob".to_string(),
+            }),
+            ..visual_rule("", "red")
+        }]);
+
+        let status = wait_rule_counts_ready(&mut reader);
+        assert_eq!(
+            status.rules[0].matched_lines, 2,
+            "the continuation line is part of a record"
+        );
+        let first = reader.visual_rule_next(0, 2).unwrap();
+        assert_eq!(first.line_index, Some(1));
+        assert!(first.page.lines.iter().any(|line| line.style.is_some()));
+        assert_eq!(reader.visual_rule_next(0, 2).unwrap().line_index, Some(4));
+        assert_eq!(
+            reader.visual_rule_previous(0, 2).unwrap().line_index,
+            Some(1)
+        );
+
+        let map = reader.density_map(3).unwrap();
+        let rule_lines: Vec<_> = map
+            .buckets
+            .iter()
+            .map(|bucket| bucket.rule_lines[0])
+            .collect();
+        assert_eq!(rule_lines, [1, 0, 1]);
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn search_hits_page_through_matches_and_select_jumps_to_hit() {
+        let path = temp_file_path("search-hits");
+        let mut file = File::create(&path).unwrap();
+        for line in ["timeout a", "ok", "timeout b timeout", "ok", "timeout c"] {
+            writeln!(file, "{line}").unwrap();
+        }
+        drop(file);
+
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 5);
+        assert_eq!(reader.search_hits(0, 10).unwrap(), None);
+
+        reader.apply_search("timeout".to_string(), 2).unwrap();
+        wait_search_ready(&reader);
+
+        let first = reader.search_hits(0, 2).unwrap().expect("hits page");
+        assert_eq!(first.total_matches, 4);
+        assert!(!first.is_indexing);
+        assert_eq!(
+            first
+                .hits
+                .iter()
+                .map(|hit| (hit.line_number, hit.search_match.start))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (3, 0)]
+        );
+        assert_eq!(first.hits[1].text, "timeout b timeout");
+
+        let second = reader.search_hits(2, 2).unwrap().expect("hits page");
+        assert_eq!(second.offset, 2);
+        assert_eq!(second.hits[0].search_match.start, 10);
+        assert_eq!(second.hits[1].line_number, 5);
+        assert!(reader.search_hits(9, 2).unwrap().unwrap().hits.is_empty());
+
+        let page = reader.search_select(2, 10, 2).unwrap();
+        let current = page.search.unwrap().current.unwrap();
+        assert_eq!(
+            (current.line_index, current.start, current.ordinal),
+            (2, 10, 2)
+        );
+        assert_eq!(reader.search_next(2).unwrap().start_line, 3);
+        assert_eq!(
+            reader.search_select(1, 0, 2).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn density_map_counts_search_filter_and_rule_lines_per_bucket() {
+        let path = temp_file_path("density-map");
+        let mut file = File::create(&path).unwrap();
+        for line in [
+            "ERROR boot timeout",
+            "INFO ok",
+            "WARN slow",
+            "INFO ok",
+            "ERROR disk timeout",
+            "INFO timeout",
+        ] {
+            writeln!(file, "{line}").unwrap();
+        }
+        drop(file);
+
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 7);
+        reader.set_visual_rules(vec![
+            visual_rule("error", "red"),
+            visual_rule("warn", "yellow"),
+        ]);
+
+        let empty = reader.density_map(3).unwrap();
+        assert!(!empty.search_active && !empty.filter_active);
+        assert_eq!(empty.rule_styles.len(), 2);
+
+        reader.filter("INFO".to_string());
+        wait_filtered_lines(&mut reader, 3);
+        reader.apply_search("timeout".to_string(), 2).unwrap();
+        wait_search_ready(&reader);
+
+        let map = reader.density_map(3).unwrap();
+        assert_eq!(map.total_lines, 7);
+        assert!(map.search_active && map.filter_active && !map.rule_lines_sampled);
+        assert!(!map.is_indexing);
+        assert_eq!(
+            map.buckets
+                .iter()
+                .map(|bucket| (
+                    bucket.start_line,
+                    bucket.search_matches,
+                    bucket.filter_matches,
+                    bucket.rule_lines.clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, 1, 1, vec![1, 0]),
+                (2, 0, 1, vec![0, 1]),
+                (4, 2, 1, vec![1, 0]),
+            ]
+        );
+
+        keep_temp_file_for_background_workers(path);
+    }
+
+    #[test]
+    fn following_a_growing_file_extends_search_marks_and_filter() {
+        let path = temp_file_path("follow-extends-sessions");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "ERROR boot").unwrap();
+        writeln!(file, "INFO ok").unwrap();
+        drop(file);
+
+        let mut reader = LogReader::new(path.to_string_lossy().into_owned()).unwrap();
+        wait_total_lines(&reader, 3);
+        reader.filter("ERROR".to_string());
+        wait_filtered_lines(&mut reader, 1);
+        reader.apply_search("ERROR".to_string(), 5).unwrap();
+        wait_search_ready(&reader);
+        let mark_id = reader.add_mark("INFO".to_string(), None).unwrap();
+        let current = reader.search_status().current.unwrap();
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(file, "ERROR disk").unwrap();
+        writeln!(file, "INFO done").unwrap();
+        drop(file);
+        reader.tail(5, true).unwrap();
+
+        for _ in 0..50 {
+            let status = reader.search_status();
+            let marks = reader.marks_status();
+            if status.is_ready
+                && status.total_matches == 2
+                && marks[0].search.is_ready
+                && marks[0].search.total_matches == 2
+            {
+                break;
+            }
+            sleep(Duration::from_millis(20));
+        }
+        let status = reader.search_status();
+        assert_eq!(status.total_matches, 2);
+        assert!(status.total_matches_final);
+        assert_eq!(status.current, Some(current), "current match stays put");
+        let marks = reader.marks_status();
+        assert_eq!((marks[0].id, marks[0].search.total_matches), (mark_id, 2));
+
+        let filtered = reader.tail_filter(5, true).unwrap();
+        assert_eq!(
+            filtered
+                .lines
+                .iter()
+                .map(|line| line.number)
+                .collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(
+            reader
+                .search_next(5)
+                .unwrap()
+                .search
+                .unwrap()
+                .current
+                .unwrap()
+                .line_index,
+            2
+        );
+
+        keep_temp_file_for_background_workers(path);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{LogReader, VisualRulesManager};
+use crate::{LogFormatManager, LogReader, VisualRulesManager};
 use dashmap::DashMap;
 use dashmap::mapref::one::RefMut;
 use std::io;
@@ -8,6 +8,7 @@ use uuid::Uuid;
 pub struct LogRegistry {
     open_files: Arc<DashMap<Uuid, LogReader>>,
     visual_rules_manager: Arc<VisualRulesManager>,
+    log_formats: Arc<LogFormatManager>,
 }
 
 impl LogRegistry {
@@ -15,20 +16,38 @@ impl LogRegistry {
         LogRegistry {
             open_files: Arc::new(DashMap::new()),
             visual_rules_manager: VisualRulesManager::in_memory(),
+            log_formats: LogFormatManager::in_memory(),
         }
     }
 
     pub fn with_manager(visual_rules_manager: Arc<VisualRulesManager>) -> Self {
+        Self::with_managers(visual_rules_manager, LogFormatManager::in_memory())
+    }
+
+    pub fn with_managers(
+        visual_rules_manager: Arc<VisualRulesManager>,
+        log_formats: Arc<LogFormatManager>,
+    ) -> Self {
         Self {
             open_files: Arc::new(DashMap::new()),
             visual_rules_manager,
+            log_formats,
         }
     }
 
+    /// Log formats shared by every open file.
+    pub fn log_formats(&self) -> &Arc<LogFormatManager> {
+        &self.log_formats
+    }
+
     /// Opens a new file and register with a UUID
     pub fn open_file(&self, path: &str) -> io::Result<String> {
         let uuid = Uuid::new_v4();
-        let reader = LogReader::with_manager(path.to_string(), self.visual_rules_manager.clone());
+        let reader = LogReader::with_managers(
+            path.to_string(),
+            self.visual_rules_manager.clone(),
+            self.log_formats.clone(),
+        );
         self.open_files.insert(uuid, reader?);
         Ok(uuid.to_string())
     }
@@ -0,0 +1,220 @@
+use aho_corasick::{AhoCorasick, MatchKind};
+use memchr::memmem;
+use regex::Regex;
+
+use crate::models::log_file::LogFile;
+use crate::models::search::SearchMatch;
+
+/// Characters that give a query regex meaning; a query without them is matched literally.
+const REGEX_META_CHARACTERS: [char; 14] = [
+    '\\', '.', '+', '*', '?', '(', ')', '|', '[', ']', '{', '}', '^', '$',
+];
+
+/// A search query compiled once per search or mark session.
+///
+/// Queries are always validated as regexes so literal scanning is only an optimization:
+/// plain text uses memchr's substring search and plain alternations (`foo|bar`) use
+/// Aho-Corasick with leftmost-first semantics, which yields the same spans the regex would.
+#[derive(Clone, Debug)]
+pub enum SearchMatcher {
+    Literal(Box<memmem::Finder<'static>>),
+    Literals(AhoCorasick),
+    Regex(Regex),
+}
+
+impl SearchMatcher {
+    pub fn new(query: &str) -> Result<Self, regex::Error> {
+        let regex = Regex::new(query)?;
+        let Some(literals) = plain_alternatives(query) else {
+            return Ok(Self::Regex(regex));
+        };
+
+        Ok(match literals.as_slice() {
+            [literal] => Self::Literal(Box::new(
+                memmem::Finder::new(literal.as_bytes()).into_owned(),
+            )),
+            _ => match AhoCorasick::builder()
+                .match_kind(MatchKind::LeftmostFirst)
+                .build(&literals)
+            {
+                Ok(automaton) => Self::Literals(automaton),
+                Err(_) => Self::Regex(regex),
+            },
+        })
+    }
+
+    /// Collects every match in `start_line..end_line`. Lines that are not valid UTF-8 are
+    /// skipped, like the page reader does.
+    pub fn find_in_lines(
+        &self,
+        log_file: &LogFile,
+        start_line: usize,
+        end_line: usize,
+    ) -> Vec<SearchMatch> {
+        if start_line >= end_line {
+            return Vec::new();
+        }
+
+        match self {
+            Self::Literal(finder) => scan_bytes(log_file, start_line, end_line, |haystack| {
+                finder
+                    .find_iter(haystack)
+                    .map(|start| (start, start + finder.needle().len()))
+                    .collect()
+            }),
+            Self::Literals(automaton) => scan_bytes(log_file, start_line, end_line, |haystack| {
+                automaton
+                    .find_iter(haystack)
+                    .map(|found| (found.start(), found.end()))
+                    .collect()
+            }),
+            Self::Regex(regex) => {
+                let mut batch = Vec::new();
+                for line_index in start_line..end_line {
+                    let line = line_bytes(log_file, line_index);
+                    if let Ok(text) = std::str::from_utf8(line) {
+                        batch.extend(regex.find_iter(text).map(|found| SearchMatch {
+                            line_index,
+                            start: found.start(),
+                            end: found.end(),
+                            ordinal: 0,
+                        }));
+                    }
+                }
+                batch
+            }
+        }
+    }
+}
+
+/// Splits `query` into its alternatives when none of them uses regex syntax.
+fn plain_alternatives(query: &str) -> Option<Vec<&str>> {
+    let literals = query.split('|').collect::<Vec<_>>();
+    let plain = literals.iter().all(|literal| {
+        !literal.is_empty()
+            && !literal.contains('\n')
+            && !literal.contains(REGEX_META_CHARACTERS.as_slice())
+    });
+    plain.then_some(literals)
+}
+
+fn line_bytes(log_file: &LogFile, line_index: usize) -> &[u8] {
+    &log_file.mmap[log_file.index[line_index]..log_file.index[line_index + 1]]
+}
+
+/// Runs `find` over the whole byte range of the lines at once and maps each hit back to
+/// its line. Literals never contain a newline, so a hit cannot span two lines.
+fn scan_bytes(
+    log_file: &LogFile,
+    start_line: usize,
+    end_line: usize,
+    find: impl Fn(&[u8]) -> Vec<(usize, usize)>,
+) -> Vec<SearchMatch> {
+    let base = log_file.index[start_line];
+    let haystack = &log_file.mmap[base..log_file.index[end_line]];
+    let line_starts = &log_file.index[start_line..=end_line];
+
+    let mut batch = Vec::new();
+    let mut checked_line = None;
+    let mut valid_line = false;
+    for (start, end) in find(haystack) {
+        let offset = line_starts.partition_point(|position| *position <= base + start) - 1;
+        let line_index = start_line + offset;
+        if checked_line != Some(line_index) {
+            checked_line = Some(line_index);
+            valid_line = std::str::from_utf8(line_bytes(log_file, line_index)).is_ok();
+        }
+        if valid_line {
+            let line_start = line_starts[offset] - base;
+            batch.push(SearchMatch {
+                line_index,
+                start: start - line_start,
+                end: end - line_start,
+                ordinal: 0,
+            });
+        }
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchMatcher;
+    use crate::file_ops::write::FileWriteOps;
+    use crate::models::log_file::LogFile;
+    use std::fs::File;
+    use std::io::Write;
+    use std::sync::{Arc, RwLock};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn indexed_log_file(prefix: &str, content: &[u8]) -> Arc<RwLock<LogFile>> {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("logmancer-{prefix}-{unique}.log"));
+        File::create(&path).unwrap().write_all(content).unwrap();
+
+        let log_file = Arc::new(RwLock::new(
+            LogFile::new(path.to_string_lossy().into_owned()).unwrap(),
+        ));
+        let mut write_ops = FileWriteOps::new(Arc::clone(&log_file));
+        while !write_ops.index_lines().unwrap() {}
+        std::fs::remove_file(path).unwrap();
+        log_file
+    }
+
+    fn spans(matcher: &SearchMatcher, log_file: &LogFile, lines: usize) -> Vec<(usize, usize)> {
+        matcher
+            .find_in_lines(log_file, 0, lines)
+            .into_iter()
+            .map(|found| (found.line_index, found.start))
+            .collect()
+    }
+
+    #[test]
+    fn picks_literal_scanners_only_for_plain_queries() {
+        assert!(matches!(
+            SearchMatcher::new("connection reset").unwrap(),
+            SearchMatcher::Literal(_)
+        ));
+        assert!(matches!(
+            SearchMatcher::new("WARN|ERROR").unwrap(),
+            SearchMatcher::Literals(_)
+        ));
+        assert!(matches!(
+            SearchMatcher::new("ERROR|").unwrap(),
+            SearchMatcher::Regex(_)
+        ));
+        assert!(matches!(
+            SearchMatcher::new("req-\\d+").unwrap(),
+            SearchMatcher::Regex(_)
+        ));
+        assert!(SearchMatcher::new("(unclosed").is_err());
+    }
+
+    #[test]
+    fn literal_scanners_report_the_same_spans_as_the_regex() {
+        let log_file = indexed_log_file(
+            "search-matcher-equivalence",
+            b"foo foo\nbar\n\xff foo invalid\nbarfoo foobar\nfoo\n",
+        );
+        let log_file = log_file.read().unwrap();
+        let lines = log_file.index.len() - 1;
+
+        for query in ["foo", "foo|bar", "foobar|foo", "o"] {
+            let regex = SearchMatcher::Regex(regex::Regex::new(query).unwrap());
+            let matcher = SearchMatcher::new(query).unwrap();
+            assert!(!matches!(matcher, SearchMatcher::Regex(_)));
+            assert_eq!(
+                spans(&matcher, &log_file, lines),
+                spans(&regex, &log_file, lines),
+                "query {query}"
+            );
+        }
+        assert_eq!(
+            spans(&SearchMatcher::new("foo").unwrap(), &log_file, lines),
+            vec![(0, 0), (0, 4), (3, 3), (3, 7), (4, 0)]
+        );
+    }
+}
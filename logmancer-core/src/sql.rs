@@ -0,0 +1,345 @@
+//! SQL over a log file: SQLite runs the query against an eponymous `log` virtual table that
+//! reads the file through its line index and parses rows with the chosen format.
+
+use std::ffi::c_int;
+use std::io;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::vtab::{
+    Context, Filters, IndexInfo, VTab, VTabConfig, VTabConnection, VTabCursor,
+    eponymous_only_module,
+};
+use rusqlite::{Connection, ErrorCode, ffi};
+
+use crate::field_value::quantity_magnitude;
+use crate::file_ops::read::FileReadOps;
+use crate::log_format::LogFormat;
+use crate::models::PageLine;
+use crate::models::log_file::LogFile;
+use crate::models::quote_checkpoints::QuoteCheckpoints;
+use crate::models::sql::{MAX_SQL_ROWS, SQL_LINE_COLUMN, SQL_TEXT_COLUMN, SqlResult, SqlRow};
+use crate::reader::LogReader;
+use crate::visual_rules::VisualRuleEvaluator;
+
+/// Longest a query may run before it is interrupted.
+const SQL_TIME_LIMIT: Duration = Duration::from_secs(10);
+/// Lines read under one hold of the file's read lock, so indexing goes on during a query.
+const SQL_CHUNK_LINES: usize = 1024;
+/// SQLite virtual machine steps between checks of the time limit.
+const SQL_PROGRESS_STEPS: c_int = 10_000;
+
+/// What the `log` table reads: the file, the format its rows are parsed with and the fields
+/// shown as columns after `log_line` and `log_text`.
+///
+/// It holds the shared file rather than the reader, so a query can run on another thread
+/// without keeping the reader borrowed.
+pub struct LogTableSource {
+    pub(crate) log_file: Arc<RwLock<LogFile>>,
+    pub(crate) format: Option<Arc<LogFormat>>,
+    pub(crate) fields: Vec<String>,
+}
+
+impl LogTableSource {
+    /// Runs a single read-only `query` over the `log` table.
+    pub fn query(self, query: &str) -> io::Result<SqlResult> {
+        query_log(self, query)
+    }
+}
+
+/// Runs a single read-only `query` over the `log` table of `source`.
+///
+/// Only reads and function calls are authorized, so statements that write, attach databases
+/// or change pragmas fail to prepare.
+fn query_log(source: LogTableSource, query: &str) -> io::Result<SqlResult> {
+    let connection = Connection::open_in_memory().map_err(io::Error::other)?;
+    connection
+        .create_module(
+            c"log",
+            eponymous_only_module::<LogTable>(),
+            Some(Arc::new(source)),
+        )
+        .map_err(io::Error::other)?;
+    connection.authorizer(Some(|context: AuthContext<'_>| match context.action {
+        AuthAction::Select
+        | AuthAction::Read { .. }
+        | AuthAction::Function { .. }
+        | AuthAction::Recursive => Authorization::Allow,
+        _ => Authorization::Deny,
+    }));
+    let started = Instant::now();
+    connection.progress_handler(
+        SQL_PROGRESS_STEPS,
+        Some(move || started.elapsed() > SQL_TIME_LIMIT),
+    );
+
+    let mut statement = connection.prepare(query).map_err(sql_error)?;
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let line_column = columns
+        .iter()
+        .position(|column| column.eq_ignore_ascii_case(SQL_LINE_COLUMN));
+    let mut result = SqlResult {
+        columns,
+        ..SqlResult::default()
+    };
+    let mut rows = statement.query([]).map_err(sql_error)?;
+    while let Some(row) = rows.next().map_err(sql_error)? {
+        if result.rows.len() == MAX_SQL_ROWS {
+            result.truncated = true;
+            break;
+        }
+        let values = (0..result.columns.len())
+            .map(|index| row.get_ref(index).map(json_value))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sql_error)?;
+        let line = line_column
+            .and_then(|index| values[index].as_u64())
+            .map(|line| line as usize);
+        result.rows.push(SqlRow { line, values });
+    }
+    Ok(result)
+}
+
+fn sql_error(error: rusqlite::Error) -> io::Error {
+    let message = match error.sqlite_error_code() {
+        Some(ErrorCode::OperationInterrupted) => {
+            format!("query ran longer than {} seconds", SQL_TIME_LIMIT.as_secs())
+        }
+        Some(ErrorCode::AuthorizationForStatementDenied) => {
+            "only SELECT queries can run over a log".to_string()
+        }
+        _ => error.to_string(),
+    };
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn json_value(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(value) => value.into(),
+        ValueRef::Real(value) => value.into(),
+        ValueRef::Text(text) | ValueRef::Blob(text) => {
+            String::from_utf8_lossy(text).into_owned().into()
+        }
+    }
+}
+
+/// Field values that read as numbers are numbers in SQL, so that `duration > 1000` compares
+/// magnitudes; durations such as `1.5s` or `250us` are read in milliseconds the way field
+/// conditions compare them, and the other values are text. A number that would not be
+/// written back the same, such as the ID `007`, stays text.
+fn sql_value(text: &str) -> Value {
+    let number_end = text
+        .find(|character: char| character.is_alphabetic() || character == 'µ')
+        .unwrap_or(text.len());
+    if !reads_back(text[..number_end].trim()) {
+        return Value::Text(text.to_string());
+    }
+    if let Ok(value) = text.parse::<i64>() {
+        return Value::Integer(value);
+    }
+    match quantity_magnitude(text) {
+        Some(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+            Value::Integer(value as i64)
+        }
+        Some(value) => Value::Real(value),
+        None => Value::Text(text.to_string()),
+    }
+}
+
+/// Whether `number` is written the way the number it reads as is written, apart from zeros
+/// ending its fraction, so that storing it as a number loses nothing.
+fn reads_back(number: &str) -> bool {
+    if let Ok(value) = number.parse::<i64>() {
+        return value.to_string() == number;
+    }
+    let Ok(value) = number.parse::<f64>() else {
+        return false;
+    };
+    let written = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+    value.to_string() == written
+}
+
+#[repr(C)]
+struct LogTable {
+    /// Base class, first so that SQLite can treat the table as one.
+    base: ffi::sqlite3_vtab,
+    source: Arc<LogTableSource>,
+    /// Field of each column after `log_line` and `log_text`.
+    fields: Vec<String>,
+}
+
+unsafe impl<'vtab> VTab<'vtab> for LogTable {
+    type Aux = Arc<LogTableSource>;
+    type Cursor = LogCursor<'vtab>;
+
+    fn connect(
+        db: &mut VTabConnection,
+        aux: Option<&Self::Aux>,
+        _args: &[&[u8]],
+    ) -> rusqlite::Result<(String, Self)> {
+        let source = aux
+            .cloned()
+            .ok_or_else(|| rusqlite::Error::ModuleError("the log table has no file".into()))?;
+        db.config(VTabConfig::Innocuous)?;
+        // SQLite column names are case-insensitive, so the first of two fields that differ
+        // only in case gets the column.
+        let mut folded = vec![SQL_LINE_COLUMN.to_string(), SQL_TEXT_COLUMN.to_string()];
+        let mut fields = Vec::new();
+        for field in &source.fields {
+            if !folded.contains(&field.to_lowercase()) {
+                folded.push(field.to_lowercase());
+                fields.push(field.clone());
+            }
+        }
+        let columns: Vec<String> = [
+            format!("{SQL_LINE_COLUMN} INTEGER"),
+            format!("{SQL_TEXT_COLUMN} TEXT"),
+        ]
+        .into_iter()
+        .chain(
+            fields
+                .iter()
+                .map(|field| format!("\"{}\"", field.replace('"', "\"\""))),
+        )
+        .collect();
+        let schema = format!("CREATE TABLE x({})", columns.join(", "));
+        let table = LogTable {
+            base: ffi::sqlite3_vtab::default(),
+            source,
+            fields,
+        };
+        Ok((schema, table))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> rusqlite::Result<()> {
+        info.set_estimated_cost(1_000_000.0);
+        Ok(())
+    }
+
+    fn open(&'vtab mut self) -> rusqlite::Result<LogCursor<'vtab>> {
+        Ok(LogCursor {
+            base: ffi::sqlite3_vtab_cursor::default(),
+            source: Arc::clone(&self.source),
+            fields: self.fields.clone(),
+            evaluator: VisualRuleEvaluator::compile(&[]),
+            quote_checkpoints: QuoteCheckpoints::default(),
+            rows: Vec::new(),
+            position: 0,
+            next_line: 0,
+            end_line: 0,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[repr(C)]
+struct LogCursor<'vtab> {
+    /// Base class, first so that SQLite can treat the cursor as one.
+    base: ffi::sqlite3_vtab_cursor,
+    source: Arc<LogTableSource>,
+    /// Field of each column after `log_line` and `log_text`.
+    fields: Vec<String>,
+    /// Rules-free evaluator, since pages are only read for their fields.
+    evaluator: VisualRuleEvaluator,
+    quote_checkpoints: QuoteCheckpoints,
+    /// Rows of the chunk being read.
+    rows: Vec<PageLine>,
+    position: usize,
+    /// First line of the next chunk.
+    next_line: usize,
+    /// Lines indexed when the scan began; lines added later are left for the next query.
+    end_line: usize,
+    phantom: PhantomData<&'vtab LogTable>,
+}
+
+impl LogCursor<'_> {
+    /// Reads chunks until one has a row or the scan reaches `end_line`. Blank lines, the
+    /// header row of a delimited file and the lines a record continues on are not rows.
+    fn fill(&mut self) -> rusqlite::Result<()> {
+        self.rows.clear();
+        self.position = 0;
+        let skip_header = self
+            .source
+            .format
+            .as_ref()
+            .is_some_and(|format| format.has_header());
+        while self.rows.is_empty() && self.next_line < self.end_line {
+            let to_line = self.end_line.min(self.next_line + SQL_CHUNK_LINES);
+            let read_ops = FileReadOps::new(self.source.log_file.read().unwrap());
+            let lines = LogReader::page_lines(
+                &mut self.quote_checkpoints,
+                &read_ops,
+                &self.evaluator,
+                self.source.format.as_deref(),
+                self.next_line,
+                to_line,
+            )
+            .map_err(|error| rusqlite::Error::ModuleError(error.to_string()))?;
+            self.rows.extend(lines.into_iter().filter(|line| {
+                let header = skip_header && line.number == 1;
+                !(header || line.continuation || line.text.trim().is_empty())
+            }));
+            self.next_line = to_line;
+        }
+        Ok(())
+    }
+}
+
+unsafe impl VTabCursor for LogCursor<'_> {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        _args: &Filters<'_>,
+    ) -> rusqlite::Result<()> {
+        self.end_line = FileReadOps::new(self.source.log_file.read().unwrap())
+            .total_lines()
+            .unwrap_or(0);
+        self.next_line = 0;
+        self.fill()
+    }
+
+    fn next(&mut self) -> rusqlite::Result<()> {
+        self.position += 1;
+        if self.position >= self.rows.len() {
+            self.fill()?;
+        }
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.position >= self.rows.len()
+    }
+
+    fn column(&self, context: &mut Context, column: c_int) -> rusqlite::Result<()> {
+        let row = &self.rows[self.position];
+        match column {
+            0 => context.set_result(&(row.number as i64)),
+            1 => context.set_result(&row.text),
+            column => {
+                let value = self
+                    .fields
+                    .get(column as usize - 2)
+                    .and_then(|field| row.fields.iter().find(|(name, _)| name == field))
+                    .map(|(_, value)| sql_value(value));
+                context.set_result(&value)
+            }
+        }
+    }
+
+    fn rowid(&self) -> rusqlite::Result<i64> {
+        Ok(self.rows[self.position].number as i64)
+    }
+}
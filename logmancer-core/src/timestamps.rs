@@ -0,0 +1,383 @@
+//! Line timestamps read into UTC instants, shown in a display time zone, and the time
+//! elapsed between them.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc};
+use chrono_tz::{TZ_VARIANTS, Tz};
+
+use crate::field_value::parse_timestamp;
+
+/// Fields a line's timestamp is read from, in order of preference, compared ignoring case.
+pub const TIMESTAMP_FIELDS: [&str; 6] =
+    ["timestamp", "@timestamp", "time", "ts", "datetime", "date"];
+
+/// Layouts with an offset tried after RFC 3339 and RFC 2822, for timestamps without a format.
+const ZONED_LAYOUTS: [&str; 3] = [
+    "%d/%b/%Y:%H:%M:%S %z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+];
+/// Fraction specifiers a decimal comma may precede in a timestamp format.
+const FRACTION_SPECIFIERS: [&str; 4] = ["%f", "%3f", "%6f", "%9f"];
+/// Specifiers that give a timestamp format its year; formats without one, like syslog's,
+/// are read in the current year.
+const YEAR_SPECIFIERS: [&str; 7] = ["%Y", "%y", "%G", "%g", "%s", "%+", "%c"];
+
+/// Time zone timestamps are shown or read in: a fixed offset, or a named IANA zone whose
+/// offset follows its daylight saving time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Zone {
+    Offset(FixedOffset),
+    Named(Tz),
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Self::Offset(Utc.fix())
+    }
+}
+
+impl Zone {
+    /// Offset from UTC in the zone at `instant`.
+    fn offset_at(self, instant: DateTime<Utc>) -> FixedOffset {
+        match self {
+            Self::Offset(offset) => offset,
+            Self::Named(zone) => zone.offset_from_utc_datetime(&instant.naive_utc()).fix(),
+        }
+    }
+
+    /// Instant of a date and time on the zone's clocks. A time repeated when the clocks go
+    /// back reads as its first occurrence, and a time skipped when they go forward reads as
+    /// the hour after it, as a clock that was not moved would show it.
+    fn instant(self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        let resolve = |local: NaiveDateTime| match self {
+            Self::Offset(offset) => offset
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|at| at.to_utc()),
+            Self::Named(zone) => zone
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|at| at.to_utc()),
+        };
+        resolve(local).or_else(|| {
+            resolve(local.checked_sub_signed(TimeDelta::hours(1))?)
+                .map(|instant| instant + TimeDelta::hours(1))
+        })
+    }
+}
+
+/// Reads the timestamp of a line from the first of [`TIMESTAMP_FIELDS`] it has; `zone` is the
+/// one its format's timestamps without an offset are written in.
+pub(crate) fn line_instant(
+    fields: &[(String, String)],
+    timestamp_format: Option<&str>,
+    zone: Zone,
+) -> Option<DateTime<Utc>> {
+    TIMESTAMP_FIELDS.iter().find_map(|name| {
+        let (_, value) = fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))?;
+        parse_instant(value, timestamp_format, zone)
+    })
+}
+
+/// Reads a timestamp into a UTC instant: with the strftime `format` first, then as epoch
+/// seconds, milliseconds, microseconds or nanoseconds, RFC 3339, RFC 2822, an access log
+/// timestamp or an ISO 8601 date and time. A decimal comma before the fraction of a second
+/// reads as a point, and timestamps without an offset are taken as written in `zone`.
+pub(crate) fn parse_instant(text: &str, format: Option<&str>, zone: Zone) -> Option<DateTime<Utc>> {
+    let text = decimal_point(text.trim());
+    if let Some(instant) = format.and_then(|format| parse_with_format(&text, format, zone)) {
+        return Some(instant);
+    }
+    parse_epoch(&text)
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(&text)
+                .ok()
+                .map(|instant| instant.to_utc())
+        })
+        .or_else(|| {
+            DateTime::parse_from_rfc2822(&text)
+                .ok()
+                .map(|instant| instant.to_utc())
+        })
+        .or_else(|| {
+            ZONED_LAYOUTS
+                .iter()
+                .find_map(|layout| DateTime::parse_from_str(&text, layout).ok())
+                .map(|instant| instant.to_utc())
+        })
+        .or_else(|| zone.instant(parse_timestamp(&text, None)?))
+}
+
+fn parse_with_format(text: &str, format: &str, zone: Zone) -> Option<DateTime<Utc>> {
+    let mut format = format.to_string();
+    for specifier in FRACTION_SPECIFIERS {
+        format = format.replace(&format!(",{specifier}"), &format!(".{specifier}"));
+    }
+    let (text, format) = if YEAR_SPECIFIERS.iter().any(|year| format.contains(year)) {
+        (text.to_string(), format)
+    } else {
+        (format!("{text} {}", current_year()), format!("{format} %Y"))
+    };
+    DateTime::parse_from_str(&text, &format)
+        .map(|instant| instant.to_utc())
+        .ok()
+        .or_else(|| zone.instant(NaiveDateTime::parse_from_str(&text, &format).ok()?))
+}
+
+fn current_year() -> i32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|elapsed| DateTime::from_timestamp(elapsed.as_secs() as i64, 0))
+        .map_or(1970, |now| now.year())
+}
+
+/// Replaces the decimal comma of `12:00:00,250` with a point.
+fn decimal_point(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut normalized = String::with_capacity(text.len());
+    for (index, character) in text.char_indices() {
+        let seconds_before = index >= 3
+            && bytes[index - 3] == b':'
+            && bytes[index - 2].is_ascii_digit()
+            && bytes[index - 1].is_ascii_digit();
+        let fraction_after = bytes.get(index + 1).is_some_and(u8::is_ascii_digit);
+        if character == ',' && seconds_before && fraction_after {
+            normalized.push('.');
+        } else {
+            normalized.push(character);
+        }
+    }
+    normalized
+}
+
+/// Reads epoch time, its unit told by the number of digits before any fraction: up to 10 for
+/// seconds, 13 for milliseconds, 16 for microseconds and 19 for nanoseconds.
+fn parse_epoch(text: &str) -> Option<DateTime<Utc>> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if !(9..=19).contains(&whole.len()) || !digits(whole) || !digits(fraction) {
+        return None;
+    }
+    let value: i64 = whole.parse().ok()?;
+    let nanos_per_unit: i64 = match whole.len() {
+        ..=10 => 1_000_000_000,
+        11..=13 => 1_000_000,
+        14..=16 => 1_000,
+        _ => 1,
+    };
+    let fraction_nanos = format!("{fraction:0<9}")
+        .get(..9)
+        .and_then(|nanos| nanos.parse::<i64>().ok())
+        .map_or(0, |nanos| nanos * nanos_per_unit / 1_000_000_000);
+    let nanos = value
+        .checked_mul(nanos_per_unit)?
+        .checked_add(fraction_nanos)?;
+    Some(DateTime::from_timestamp_nanos(nanos))
+}
+
+/// Reads a time zone: `UTC`, `Z`, an offset such as `+02:00`, `-0530` or `UTC+2`, or an IANA
+/// zone such as `Europe/Madrid`, its name compared ignoring case.
+pub(crate) fn parse_time_zone(text: &str) -> Option<Zone> {
+    let text = text.trim();
+    parse_offset(text).map(Zone::Offset).or_else(|| {
+        let named = text.parse::<Tz>().ok().or_else(|| {
+            TZ_VARIANTS
+                .into_iter()
+                .find(|zone| zone.name().eq_ignore_ascii_case(text))
+        })?;
+        Some(Zone::Named(named))
+    })
+}
+
+fn parse_offset(text: &str) -> Option<FixedOffset> {
+    let upper = text.to_ascii_uppercase();
+    let offset = upper
+        .strip_prefix("UTC")
+        .or_else(|| upper.strip_prefix("GMT"))
+        .unwrap_or(&upper);
+    if offset.is_empty() || offset == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+    let (sign, offset) = match offset.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if offset.len() > 2 => offset.split_at(offset.len() - 2),
+        None => (offset, "0"),
+    };
+    if hours.is_empty() || hours.len() > 2 || minutes.len() > 2 {
+        return None;
+    }
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Name of a time zone as [`parse_time_zone`] reads it: `UTC`, `+02:00` or `Europe/Madrid`.
+pub(crate) fn time_zone_name(zone: Zone) -> String {
+    let zone = match zone {
+        Zone::Offset(offset) => offset,
+        Zone::Named(zone) => return zone.name().to_string(),
+    };
+    let seconds = zone.local_minus_utc();
+    if seconds == 0 {
+        return "UTC".to_string();
+    }
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Instant as shown beside a line, to the millisecond, in the display time zone.
+pub(crate) fn format_instant(instant: DateTime<Utc>, zone: Zone) -> String {
+    instant
+        .with_timezone(&zone.offset_at(instant))
+        .format("%Y-%m-%d %H:%M:%S%.3f")
+        .to_string()
+}
+
+/// Time elapsed between two lines, signed and in the largest units that fit, such as
+/// `+250ms`, `+1.250s`, `+2m03s`, `-1h05m` or `+3d04h`.
+pub fn format_time_delta(millis: i64) -> String {
+    let sign = if millis < 0 { '-' } else { '+' };
+    let millis = millis.unsigned_abs();
+    let seconds = millis / 1000;
+    let (minutes, hours, days) = (seconds / 60, seconds / 3600, seconds / 86_400);
+    match millis {
+        ..1_000 => format!("{sign}{millis}ms"),
+        1_000..60_000 => format!("{sign}{seconds}.{:03}s", millis % 1000),
+        60_000..3_600_000 => format!("{sign}{minutes}m{:02}s", seconds % 60),
+        3_600_000..86_400_000 => format!("{sign}{hours}h{:02}m", minutes % 60),
+        _ => format!("{sign}{days}d{:02}h", hours % 24),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str, format: Option<&str>) -> Option<String> {
+        parse_instant(text, format, Zone::default()).map(|instant| instant.to_rfc3339())
+    }
+
+    #[test]
+    fn timestamps_of_every_layout_read_to_one_instant() {
+        let noon = Some("2026-10-03T10:00:00.250+00:00".to_string());
+        assert_eq!(utc("2026-10-03T12:00:00.250+02:00", None), noon);
+        assert_eq!(utc("2026-10-03 10:00:00,250", None), noon);
+        assert_eq!(utc("1791021600250", None), noon);
+        assert_eq!(utc("1791021600.25", None), noon);
+        assert_eq!(utc("1791021600250000", None), noon);
+        assert_eq!(
+            utc(
+                "03/Oct/2026:05:00:00.250 -0500",
+                Some("%d/%b/%Y:%H:%M:%S%.f %z")
+            ),
+            noon
+        );
+        assert_eq!(
+            utc("2026-10-03 10:00:00,250", Some("%Y-%m-%d %H:%M:%S,%3f")),
+            noon
+        );
+        assert_eq!(
+            utc("2026-10-03 10:00:00,250", Some("%Y-%m-%d %H:%M:%S%.3f")),
+            noon
+        );
+        assert_eq!(
+            utc("03/Oct/2026:12:00:00 +0200", None).as_deref(),
+            Some("2026-10-03T10:00:00+00:00")
+        );
+        assert_eq!(
+            parse_instant("Oct  3 10:00:00", Some("%b %e %H:%M:%S"), Zone::default())
+                .map(|instant| (instant.month(), instant.year() == current_year())),
+            Some((10, true))
+        );
+        assert_eq!(utc("200", None), None);
+        assert_eq!(utc("not a time", Some("%Y")), None);
+
+        let fields = vec![
+            ("level".to_string(), "info".to_string()),
+            ("Time".to_string(), "2026-10-03T10:00:00Z".to_string()),
+        ];
+        assert!(line_instant(&fields, None, Zone::default()).is_some());
+        assert!(line_instant(&fields[..1], None, Zone::default()).is_none());
+    }
+
+    #[test]
+    fn time_zones_are_offsets_or_named_zones_and_deltas_use_the_largest_units() {
+        let zone = |text| parse_time_zone(text).map(time_zone_name);
+        assert_eq!(zone("utc").as_deref(), Some("UTC"));
+        assert_eq!(zone("Z").as_deref(), Some("UTC"));
+        assert_eq!(zone("+02:00").as_deref(), Some("+02:00"));
+        assert_eq!(zone("-0530").as_deref(), Some("-05:30"));
+        assert_eq!(zone("UTC+2").as_deref(), Some("+02:00"));
+        assert_eq!(zone("Europe/Madrid").as_deref(), Some("Europe/Madrid"));
+        assert_eq!(
+            zone("america/new_york").as_deref(),
+            Some("America/New_York")
+        );
+        assert_eq!(zone("Mars/Olympus"), None);
+        assert_eq!(zone("+24:00"), None);
+
+        let instant = parse_instant("2026-10-03T23:30:00Z", None, Zone::default()).unwrap();
+        assert_eq!(
+            format_instant(instant, parse_time_zone("+02:00").unwrap()),
+            "2026-10-04 01:30:00.000"
+        );
+        let madrid = parse_time_zone("Europe/Madrid").unwrap();
+        assert_eq!(format_instant(instant, madrid), "2026-10-04 01:30:00.000");
+        let winter = parse_instant("2026-12-03T23:30:00Z", None, Zone::default()).unwrap();
+        assert_eq!(format_instant(winter, madrid), "2026-12-04 00:30:00.000");
+
+        assert_eq!(format_time_delta(0), "+0ms");
+        assert_eq!(format_time_delta(250), "+250ms");
+        assert_eq!(format_time_delta(1_250), "+1.250s");
+        assert_eq!(format_time_delta(-123_000), "-2m03s");
+        assert_eq!(format_time_delta(3_900_000), "+1h05m");
+        assert_eq!(format_time_delta(273_600_000), "+3d04h");
+    }
+
+    #[test]
+    fn timestamps_without_an_offset_read_in_the_source_zone() {
+        let madrid = parse_time_zone("Europe/Madrid").unwrap();
+        let read =
+            |text, format| parse_instant(text, format, madrid).map(|instant| instant.to_rfc3339());
+        let utc = |text: &str| Some(text.to_string());
+        assert_eq!(
+            read("2026-07-01 14:00:00", None),
+            utc("2026-07-01T12:00:00+00:00")
+        );
+        assert_eq!(
+            read("2026-12-01 14:00:00", None),
+            utc("2026-12-01T13:00:00+00:00")
+        );
+        assert_eq!(
+            read("01/Dec/2026 14:00", Some("%d/%b/%Y %H:%M")),
+            utc("2026-12-01T13:00:00+00:00")
+        );
+        assert_eq!(
+            read("2026-07-01T14:00:00+00:00", None),
+            utc("2026-07-01T14:00:00+00:00")
+        );
+        assert_eq!(read("1791021600", None), utc("2026-10-03T10:00:00+00:00"));
+        // Skipped when the clocks go forward, and repeated when they go back.
+        assert_eq!(
+            read("2026-03-29 02:30:00", None),
+            utc("2026-03-29T01:30:00+00:00")
+        );
+        assert_eq!(
+            read("2026-10-25 02:30:00", None),
+            utc("2026-10-25T00:30:00+00:00")
+        );
+    }
+}
@@ -1,15 +1,101 @@
-use regex::{Regex, RegexBuilder};
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use std::sync::Arc;
 
-use crate::models::visual_rules::{LineStyleIntent, VisualMatcher, VisualRule};
+use crate::field_value::field_matches;
+use crate::models::palette::VisualPalette;
+use crate::models::visual_rules::{
+    DEFAULT_VISUAL_PROFILE, FieldOperator, LineStyleIntent, StyledSpan, ValidatedProfile,
+    ValidationReport, VisualMatcher, VisualProfileStatus, VisualRule, VisualRuleScope,
+    compile_path_glob,
+};
 
+/// Upper bound on the spans one line may carry, so a rule like `.` cannot flood a page.
+pub const MAX_STYLED_SPANS_PER_LINE: usize = 256;
+
+/// A line as rules see it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VisualLine<'a> {
+    pub text: &'a str,
+    /// Line number counting from 1, when known; line range conditions need it.
+    pub number: Option<usize>,
+    /// Parsed fields of the line, empty when the line was not parsed.
+    pub fields: &'a [(String, String)],
+}
+
+impl<'a> VisualLine<'a> {
+    pub fn new(text: &'a str, number: usize) -> Self {
+        Self {
+            text,
+            number: Some(number),
+            fields: &[],
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&'a str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl<'a> From<&'a str> for VisualLine<'a> {
+    fn from(text: &'a str) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Scans of a line made on this thread: one per multi-pattern matcher and one per rule
+    /// tested on its own.
+    static LINE_SCANS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+fn count_scans(scans: usize) {
+    LINE_SCANS.with(|count| count.set(count.get() + scans));
+}
+
+/// Compiled rules of one profile.
+///
+/// Rules whose whole condition is one text or regex are also compiled into a single
+/// multi-pattern matcher, so a line is scanned once for all of them; compound rules are
+/// tested on their own. Priority still follows rule order: the first rule that holds wins.
 #[derive(Clone, Debug, Default)]
 pub struct VisualRuleEvaluator {
     rules: Vec<CompiledVisualRule>,
+    plain: PlainRuleSet,
+}
+
+/// Text and regex conditions of the plain rules, matched in one pass per line.
+#[derive(Clone, Debug, Default)]
+struct PlainRuleSet {
+    /// Whether each rule is decided by this set rather than by its own matcher.
+    covers: Vec<bool>,
+    regexes: Option<(RegexSet, Vec<usize>)>,
+    case_sensitive_text: Option<TextPatterns>,
+    /// Patterns are lowercased and run against the lowercased line, like single rules do.
+    case_insensitive_text: Option<TextPatterns>,
+}
+
+#[derive(Clone, Debug)]
+struct TextPatterns {
+    automaton: AhoCorasick,
+    /// Rules of each distinct pattern.
+    rules: Vec<Vec<usize>>,
 }
 
 #[derive(Clone, Debug)]
 struct CompiledVisualRule {
+    name: Option<String>,
+    case_sensitive: bool,
     matcher: CompiledVisualMatcher,
+    scope: VisualRuleScope,
     style: LineStyleIntent,
 }
 
@@ -20,6 +106,19 @@ enum CompiledVisualMatcher {
         case_sensitive: bool,
     },
     Regex(Regex),
+    Lines {
+        first: usize,
+        last: Option<usize>,
+    },
+    Field {
+        field: String,
+        operator: FieldOperator,
+        value: String,
+        case_sensitive: bool,
+    },
+    All(Vec<CompiledVisualMatcher>),
+    Any(Vec<CompiledVisualMatcher>),
+    Not(Box<CompiledVisualMatcher>),
 }
 
 impl VisualRuleEvaluator {
@@ -27,50 +126,495 @@ impl VisualRuleEvaluator {
         let rules = rules
             .iter()
             .filter_map(CompiledVisualRule::compile)
-            .collect();
+            .collect::<Vec<_>>();
+        let plain = PlainRuleSet::compile(&rules);
 
-        Self { rules }
+        Self { rules, plain }
     }
 
-    pub fn evaluate(&self, line: &str) -> Option<LineStyleIntent> {
+    /// Style of the first matching line-scoped rule.
+    pub fn evaluate<'a>(&self, line: impl Into<VisualLine<'a>>) -> Option<LineStyleIntent> {
+        self.first_match(&line.into(), |rule| rule.scope.is_line())
+            .map(|rule| self.rules[rule].style.clone())
+    }
+
+    /// Spans styled by match-scoped rules, in rule priority order so earlier rules win
+    /// where spans overlap.
+    pub fn spans<'a>(&self, line: impl Into<VisualLine<'a>>) -> Vec<StyledSpan> {
+        let line = line.into();
+        let mut spans = Vec::new();
+        for rule in self.rules.iter().filter(|rule| !rule.scope.is_line()) {
+            rule.collect_spans(&line, &mut spans);
+            if spans.len() >= MAX_STYLED_SPANS_PER_LINE {
+                spans.truncate(MAX_STYLED_SPANS_PER_LINE);
+                break;
+            }
+        }
+        spans
+    }
+
+    /// Index of the first rule that styles any part of `line`, following rule priority.
+    pub fn matching_rule<'a>(&self, line: impl Into<VisualLine<'a>>) -> Option<usize> {
+        self.first_match(&line.into(), |_| true)
+    }
+
+    /// Whether the condition of each rule holds for `line`, indexed like `matching_rule`.
+    pub fn matching_rules<'a>(&self, line: impl Into<VisualLine<'a>>) -> Vec<bool> {
+        let line = line.into();
+        let plain = self.plain.matches(line.text);
         self.rules
             .iter()
-            .find(|rule| rule.matches(line))
-            .map(|rule| rule.style.clone())
+            .enumerate()
+            .map(|(index, rule)| self.holds(index, rule, &line, &plain))
+            .collect()
+    }
+
+    /// Styles of the compiled rules, indexed like `matching_rule`.
+    pub fn styles(&self) -> Vec<LineStyleIntent> {
+        self.rules.iter().map(|rule| rule.style.clone()).collect()
+    }
+
+    /// Names of the compiled rules, indexed like `matching_rule`.
+    pub fn names(&self) -> Vec<Option<String>> {
+        self.rules.iter().map(|rule| rule.name.clone()).collect()
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether the condition of rule `rule` holds for `line`, whatever the rules before it do.
+    pub fn rule_matches<'a>(&self, rule: usize, line: impl Into<VisualLine<'a>>) -> bool {
+        self.rules
+            .get(rule)
+            .is_some_and(|rule| rule.matcher.matches(&line.into()))
+    }
+
+    fn first_match(
+        &self,
+        line: &VisualLine,
+        eligible: impl Fn(&CompiledVisualRule) -> bool,
+    ) -> Option<usize> {
+        if !self.rules.iter().any(&eligible) {
+            return None;
+        }
+        let plain = self.plain.matches(line.text);
+        self.rules
+            .iter()
+            .enumerate()
+            .position(|(index, rule)| eligible(rule) && self.holds(index, rule, line, &plain))
+    }
+
+    fn holds(
+        &self,
+        index: usize,
+        rule: &CompiledVisualRule,
+        line: &VisualLine,
+        plain: &[bool],
+    ) -> bool {
+        if self.plain.covers[index] {
+            plain[index]
+        } else {
+            #[cfg(test)]
+            count_scans(1);
+            rule.matcher.matches(line)
+        }
     }
 }
 
-impl CompiledVisualRule {
-    fn compile(rule: &VisualRule) -> Option<Self> {
-        let matcher = match &rule.matcher {
-            VisualMatcher::Text(pattern) => CompiledVisualMatcher::Text {
-                pattern: normalize_text(pattern, rule.case_sensitive),
-                case_sensitive: rule.case_sensitive,
-            },
-            VisualMatcher::Regex(pattern) => RegexBuilder::new(pattern)
-                .case_insensitive(!rule.case_sensitive)
-                .build()
-                .ok()
-                .map(CompiledVisualMatcher::Regex)?,
+impl PlainRuleSet {
+    fn compile(rules: &[CompiledVisualRule]) -> Self {
+        let mut regexes = Vec::new();
+        let mut case_sensitive_text = Vec::new();
+        let mut case_insensitive_text = Vec::new();
+        for (index, rule) in rules.iter().enumerate() {
+            match &rule.matcher {
+                CompiledVisualMatcher::Text {
+                    pattern,
+                    case_sensitive: true,
+                } => case_sensitive_text.push((pattern.as_str(), index)),
+                CompiledVisualMatcher::Text { pattern, .. } => {
+                    case_insensitive_text.push((pattern.as_str(), index))
+                }
+                // Each regex keeps its own case flag inside the shared set.
+                CompiledVisualMatcher::Regex(regex) if rule.case_sensitive => {
+                    regexes.push((format!("(?:{})", regex.as_str()), index))
+                }
+                CompiledVisualMatcher::Regex(regex) => {
+                    regexes.push((format!("(?i:{})", regex.as_str()), index))
+                }
+                _ => {}
+            }
+        }
+
+        // A set that fails to build, e.g. past the regex size limit, leaves its rules to
+        // their own matchers.
+        let regexes = (!regexes.is_empty())
+            .then(|| RegexSetBuilder::new(regexes.iter().map(|(pattern, _)| pattern)).build())
+            .and_then(Result::ok)
+            .map(|set| (set, regexes.into_iter().map(|(_, rule)| rule).collect()));
+        let mut plain = Self {
+            covers: vec![false; rules.len()],
+            regexes,
+            case_sensitive_text: TextPatterns::compile(&case_sensitive_text),
+            case_insensitive_text: TextPatterns::compile(&case_insensitive_text),
         };
+        let covered = plain
+            .regexes
+            .iter()
+            .flat_map(|(_, rules)| rules.clone())
+            .chain(
+                [&plain.case_sensitive_text, &plain.case_insensitive_text]
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|patterns| patterns.rules.concat()),
+            )
+            .collect::<Vec<_>>();
+        for rule in covered {
+            plain.covers[rule] = true;
+        }
+        plain
+    }
+
+    /// Whether each plain rule holds for `text`; entries of other rules stay false.
+    fn matches(&self, text: &str) -> Vec<bool> {
+        #[cfg(test)]
+        count_scans(
+            usize::from(self.regexes.is_some())
+                + usize::from(self.case_sensitive_text.is_some())
+                + usize::from(self.case_insensitive_text.is_some()),
+        );
+        let mut matched = vec![false; self.covers.len()];
+        if let Some((set, rules)) = &self.regexes {
+            for pattern in set.matches(text).iter() {
+                matched[rules[pattern]] = true;
+            }
+        }
+        if let Some(patterns) = &self.case_sensitive_text {
+            patterns.mark(text, &mut matched);
+        }
+        if let Some(patterns) = &self.case_insensitive_text {
+            patterns.mark(&text.to_lowercase(), &mut matched);
+        }
+        matched
+    }
+}
+
+impl TextPatterns {
+    fn compile(patterns: &[(&str, usize)]) -> Option<Self> {
+        let mut distinct: Vec<&str> = Vec::new();
+        let mut rules: Vec<Vec<usize>> = Vec::new();
+        for (pattern, rule) in patterns {
+            match distinct.iter().position(|known| known == pattern) {
+                Some(slot) => rules[slot].push(*rule),
+                None => {
+                    distinct.push(pattern);
+                    rules.push(vec![*rule]);
+                }
+            }
+        }
+        if distinct.is_empty() {
+            return None;
+        }
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::Standard)
+            .build(&distinct)
+            .ok()?;
+        Some(Self { automaton, rules })
+    }
+
+    /// Marks the rules of every pattern found in `haystack`.
+    fn mark(&self, haystack: &str, matched: &mut [bool]) {
+        for found in self.automaton.find_overlapping_iter(haystack) {
+            for rule in &self.rules[found.pattern().as_usize()] {
+                matched[*rule] = true;
+            }
+        }
+    }
+}
+
+/// Evaluators of the default rules and of every profile, with their auto-select conditions
+/// and the palette their color tokens resolve through.
+#[derive(Debug, Default)]
+pub struct VisualRuleProfiles {
+    default: Arc<VisualRuleEvaluator>,
+    profiles: Vec<CompiledProfile>,
+    palette: VisualPalette,
+}
+
+#[derive(Debug)]
+struct CompiledProfile {
+    name: String,
+    path_glob: Option<Regex>,
+    match_file_name: bool,
+    format: Option<String>,
+    evaluator: Arc<VisualRuleEvaluator>,
+}
+
+impl VisualRuleProfiles {
+    pub fn compile(report: &ValidationReport) -> Self {
+        Self {
+            default: Arc::new(VisualRuleEvaluator::compile(&report.evaluator_rules)),
+            profiles: report
+                .profiles
+                .iter()
+                .map(CompiledProfile::compile)
+                .collect(),
+            palette: VisualPalette::new(report.palette.clone()),
+        }
+    }
+
+    pub fn palette(&self) -> &VisualPalette {
+        &self.palette
+    }
+
+    pub fn default_evaluator(&self) -> Arc<VisualRuleEvaluator> {
+        self.default.clone()
+    }
 
+    /// Selectable profile names, starting with the default one.
+    pub fn names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_VISUAL_PROFILE.to_string())
+            .chain(self.profiles.iter().map(|profile| profile.name.clone()))
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        name == DEFAULT_VISUAL_PROFILE || self.find(name).is_some()
+    }
+
+    /// First profile, in envelope order, whose auto-select condition matches the file.
+    pub fn auto_select(&self, path: &str, format: Option<&str>) -> Option<&str> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.auto_selects(path, format))
+            .map(|profile| profile.name.as_str())
+    }
+
+    /// Status of a file that pinned `pinned`; a pinned profile that no longer exists is ignored.
+    pub fn status(
+        &self,
+        pinned: Option<&str>,
+        path: &str,
+        format: Option<&str>,
+    ) -> VisualProfileStatus {
+        let pinned = pinned.filter(|name| self.contains(name));
+        let auto_selected = self.auto_select(path, format);
+        VisualProfileStatus {
+            active: pinned
+                .or(auto_selected)
+                .unwrap_or(DEFAULT_VISUAL_PROFILE)
+                .to_string(),
+            pinned: pinned.map(str::to_string),
+            auto_selected: auto_selected.map(str::to_string),
+            profiles: self.names(),
+        }
+    }
+
+    /// Evaluator of the profile a file renders with, following the same choice as `status`.
+    pub fn evaluator_for(
+        &self,
+        pinned: Option<&str>,
+        path: &str,
+        format: Option<&str>,
+    ) -> Arc<VisualRuleEvaluator> {
+        let profile = match pinned.filter(|name| self.contains(name)) {
+            Some(name) => self.find(name),
+            None => self
+                .profiles
+                .iter()
+                .find(|profile| profile.auto_selects(path, format)),
+        };
+        profile
+            .map(|profile| profile.evaluator.clone())
+            .unwrap_or_else(|| self.default.clone())
+    }
+
+    fn find(&self, name: &str) -> Option<&CompiledProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+}
+
+impl CompiledProfile {
+    fn compile(profile: &ValidatedProfile) -> Self {
+        let auto_select = profile.auto_select.clone().unwrap_or_default();
+        Self {
+            name: profile.name.clone(),
+            match_file_name: auto_select
+                .path_glob
+                .as_ref()
+                .is_some_and(|glob| !glob.contains('/')),
+            path_glob: auto_select
+                .path_glob
+                .as_deref()
+                .and_then(|glob| compile_path_glob(glob).ok()),
+            format: auto_select.format,
+            evaluator: Arc::new(VisualRuleEvaluator::compile(&profile.evaluator_rules)),
+        }
+    }
+
+    fn auto_selects(&self, path: &str, format: Option<&str>) -> bool {
+        if self.path_glob.is_none() && self.format.is_none() {
+            return false;
+        }
+        let path = path.replace('\\', "/");
+        let target = if self.match_file_name {
+            path.rsplit('/').next().unwrap_or(&path)
+        } else {
+            &path
+        };
+        self.path_glob
+            .as_ref()
+            .is_none_or(|glob| glob.is_match(target))
+            && self.format.as_ref().is_none_or(|expected| {
+                format.is_some_and(|format| format.eq_ignore_ascii_case(expected))
+            })
+    }
+}
+
+impl CompiledVisualRule {
+    fn compile(rule: &VisualRule) -> Option<Self> {
         Some(Self {
-            matcher,
+            name: rule.name.clone(),
+            case_sensitive: rule.case_sensitive,
+            matcher: CompiledVisualMatcher::compile(
+                &rule.matcher,
+                rule.case_sensitive,
+                rule.scope.is_line(),
+            )?,
+            scope: rule.scope.clone(),
             style: rule.style.clone(),
         })
     }
 
-    fn matches(&self, line: &str) -> bool {
-        match &self.matcher {
-            CompiledVisualMatcher::Text {
+    fn collect_spans(&self, line: &VisualLine, spans: &mut Vec<StyledSpan>) {
+        match &self.scope {
+            VisualRuleScope::Line => {}
+            VisualRuleScope::Match => self.matcher.collect_matches(line, &self.style, spans),
+            VisualRuleScope::Groups(groups) => {
+                let CompiledVisualMatcher::Regex(regex) = &self.matcher else {
+                    return;
+                };
+                for captures in regex
+                    .captures_iter(line.text)
+                    .take(MAX_STYLED_SPANS_PER_LINE)
+                {
+                    for found in groups.iter().filter_map(|group| captures.get(*group)) {
+                        push_span(found, &self.style, spans);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl CompiledVisualMatcher {
+    /// Compiles a condition tree; `line_scope` keeps text conditions as substring tests,
+    /// which cannot report the byte offsets spans need.
+    fn compile(matcher: &VisualMatcher, case_sensitive: bool, line_scope: bool) -> Option<Self> {
+        let compile_all = |children: &[VisualMatcher]| {
+            children
+                .iter()
+                .map(|child| Self::compile(child, case_sensitive, line_scope))
+                .collect::<Option<Vec<_>>>()
+        };
+        Some(match matcher {
+            VisualMatcher::Text(pattern) if line_scope => Self::Text {
+                pattern: normalize_text(pattern, case_sensitive),
+                case_sensitive,
+            },
+            // Spans need byte offsets into the original line, which lowercasing may shift.
+            VisualMatcher::Text(pattern) => compile_regex(&regex::escape(pattern), case_sensitive)?,
+            VisualMatcher::Regex(pattern) => compile_regex(pattern, case_sensitive)?,
+            VisualMatcher::Lines { first, last } => Self::Lines {
+                first: *first,
+                last: *last,
+            },
+            VisualMatcher::Field(predicate) => Self::Field {
+                field: predicate.field.trim().to_string(),
+                operator: predicate.operator,
+                value: predicate.value.clone(),
+                case_sensitive,
+            },
+            VisualMatcher::All(children) => Self::All(compile_all(children)?),
+            VisualMatcher::Any(children) => Self::Any(compile_all(children)?),
+            VisualMatcher::Not(child) => {
+                Self::Not(Box::new(Self::compile(child, case_sensitive, line_scope)?))
+            }
+        })
+    }
+
+    fn matches(&self, line: &VisualLine) -> bool {
+        match self {
+            Self::Text {
                 pattern,
                 case_sensitive,
-            } => normalize_text(line, *case_sensitive).contains(pattern),
-            CompiledVisualMatcher::Regex(regex) => regex.is_match(line),
+            } => normalize_text(line.text, *case_sensitive).contains(pattern),
+            Self::Regex(regex) => regex.is_match(line.text),
+            Self::Lines { first, last } => line
+                .number
+                .is_some_and(|number| number >= *first && last.is_none_or(|last| number <= last)),
+            Self::Field {
+                field,
+                operator,
+                value,
+                case_sensitive,
+            } => line
+                .field(field)
+                .is_some_and(|actual| field_matches(*operator, actual, value, *case_sensitive)),
+            Self::All(children) => children.iter().all(|child| child.matches(line)),
+            Self::Any(children) => children.iter().any(|child| child.matches(line)),
+            Self::Not(child) => !child.matches(line),
+        }
+    }
+
+    /// Spans of the text and regex conditions that make the line match; negated conditions
+    /// and branches that do not hold contribute none.
+    fn collect_matches(
+        &self,
+        line: &VisualLine,
+        style: &LineStyleIntent,
+        spans: &mut Vec<StyledSpan>,
+    ) {
+        match self {
+            Self::Regex(regex) => {
+                for found in regex.find_iter(line.text).take(MAX_STYLED_SPANS_PER_LINE) {
+                    push_span(found, style, spans);
+                }
+            }
+            Self::All(children) if self.matches(line) => {
+                for child in children {
+                    child.collect_matches(line, style, spans);
+                }
+            }
+            Self::Any(children) => {
+                for child in children.iter().filter(|child| child.matches(line)) {
+                    child.collect_matches(line, style, spans);
+                }
+            }
+            _ => {}
         }
     }
 }
 
+fn push_span(found: regex::Match, style: &LineStyleIntent, spans: &mut Vec<StyledSpan>) {
+    if !found.is_empty() {
+        spans.push(StyledSpan {
+            start: found.start(),
+            end: found.end(),
+            style: style.clone(),
+        });
+    }
+}
+
+fn compile_regex(pattern: &str, case_sensitive: bool) -> Option<CompiledVisualMatcher> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .ok()
+        .map(CompiledVisualMatcher::Regex)
+}
+
 fn normalize_text(value: &str, case_sensitive: bool) -> String {
     if case_sensitive {
         value.to_string()
@@ -82,28 +626,33 @@ fn normalize_text(value: &str, case_sensitive: bool) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{LineStyleIntent, VisualColor, VisualMatcher, VisualRule};
+    use crate::{LineStyleIntent, VisualColor, VisualMatcher, VisualRule, VisualRuleScope};
 
     fn style(foreground: &str, background: &str) -> LineStyleIntent {
         LineStyleIntent {
             foreground: Some(VisualColor(foreground.to_string())),
             background: Some(VisualColor(background.to_string())),
+            ..LineStyleIntent::default()
         }
     }
 
     fn text_rule(pattern: &str, case_sensitive: bool, foreground: &str) -> VisualRule {
         VisualRule {
+            name: None,
             matcher: VisualMatcher::Text(pattern.to_string()),
             case_sensitive,
             style: style(foreground, "default"),
+            scope: VisualRuleScope::Line,
         }
     }
 
     fn regex_rule(pattern: &str, case_sensitive: bool, foreground: &str) -> VisualRule {
         VisualRule {
+            name: None,
             matcher: VisualMatcher::Regex(pattern.to_string()),
             case_sensitive,
             style: style(foreground, "default"),
+            scope: VisualRuleScope::Line,
         }
     }
 
@@ -160,4 +709,350 @@ mod tests {
         );
         assert_eq!(evaluator.evaluate("thread healthy"), None);
     }
+
+    #[test]
+    fn match_scoped_rules_style_spans_and_capture_groups_only() {
+        let evaluator = VisualRuleEvaluator::compile(&[
+            VisualRule {
+                scope: VisualRuleScope::Groups(vec![1]),
+                ..regex_rule(r"user=(\w+)", true, "cyan")
+            },
+            VisualRule {
+                scope: VisualRuleScope::Match,
+                ..text_rule("user", false, "red")
+            },
+            text_rule("login", false, "green"),
+        ]);
+        let line = "login User=alice user=bob";
+
+        assert_eq!(evaluator.evaluate(line), Some(style("green", "default")));
+        assert_eq!(
+            evaluator
+                .spans(line)
+                .iter()
+                .map(|span| (
+                    span.start,
+                    span.end,
+                    span.style.foreground.clone().unwrap().0
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (22, 25, "cyan".to_string()),
+                (6, 10, "red".to_string()),
+                (17, 21, "red".to_string()),
+            ]
+        );
+        assert_eq!(
+            evaluator.evaluate("User=alice"),
+            None,
+            "span rules do not style the row"
+        );
+        assert_eq!(evaluator.matching_rule("User=alice"), Some(1));
+    }
+
+    #[test]
+    fn compound_conditions_combine_text_line_ranges_and_fields() {
+        let text = |pattern: &str| VisualMatcher::Text(pattern.to_string());
+        let unretried_errors = VisualRule {
+            matcher: VisualMatcher::All(vec![
+                text("error"),
+                VisualMatcher::Not(Box::new(text("retry"))),
+            ]),
+            ..text_rule("", false, "red")
+        };
+        let slow_warnings = VisualRule {
+            matcher: VisualMatcher::All(vec![
+                VisualMatcher::Field(crate::FieldPredicate {
+                    field: "level".to_string(),
+                    operator: crate::FieldOperator::Ge,
+                    value: "WARN".to_string(),
+                }),
+                VisualMatcher::Field(crate::FieldPredicate {
+                    field: "duration".to_string(),
+                    operator: crate::FieldOperator::Gt,
+                    value: "500".to_string(),
+                }),
+            ]),
+            ..text_rule("", false, "orange")
+        };
+        let header = VisualRule {
+            matcher: VisualMatcher::Lines {
+                first: 1,
+                last: Some(2),
+            },
+            ..text_rule("", false, "gray")
+        };
+        let evaluator = VisualRuleEvaluator::compile(&[unretried_errors, slow_warnings, header]);
+
+        assert_eq!(
+            evaluator.evaluate(VisualLine::new("ERROR disk full", 10)),
+            Some(style("red", "default"))
+        );
+        assert_eq!(
+            evaluator.evaluate(VisualLine::new("ERROR disk full, retry 2", 10)),
+            None
+        );
+        let fields = [
+            ("level".to_string(), "error".to_string()),
+            ("duration".to_string(), "750".to_string()),
+        ];
+        let parsed = VisualLine {
+            fields: &fields,
+            ..VisualLine::new("request done", 10)
+        };
+        assert_eq!(evaluator.evaluate(parsed), Some(style("orange", "default")));
+        assert_eq!(
+            evaluator.evaluate(VisualLine::new("request done", 10)),
+            None,
+            "field conditions need parsed fields"
+        );
+        assert_eq!(
+            evaluator.evaluate(VisualLine::new("request done", 2)),
+            Some(style("gray", "default"))
+        );
+        assert_eq!(
+            evaluator.evaluate("request done"),
+            None,
+            "line ranges need a line number"
+        );
+    }
+
+    #[test]
+    fn match_scoped_compound_rules_span_only_conditions_that_hold() {
+        let evaluator = VisualRuleEvaluator::compile(&[VisualRule {
+            matcher: VisualMatcher::All(vec![
+                VisualMatcher::Any(vec![
+                    VisualMatcher::Text("timeout".to_string()),
+                    VisualMatcher::Regex("refused".to_string()),
+                ]),
+                VisualMatcher::Regex(r"id=\d+".to_string()),
+                VisualMatcher::Not(Box::new(VisualMatcher::Text("ignored".to_string()))),
+            ]),
+            scope: VisualRuleScope::Match,
+            ..text_rule("", false, "red")
+        }]);
+        let ranges = |line: &str| {
+            evaluator
+                .spans(line)
+                .iter()
+                .map(|span| (span.start, span.end))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ranges("Timeout for id=42"), vec![(0, 7), (12, 17)]);
+        assert_eq!(ranges("timeout for id=42, ignored"), Vec::new());
+        assert_eq!(ranges("refused"), Vec::new());
+    }
+
+    #[test]
+    fn profiles_auto_select_by_file_name_glob_path_glob_and_format() {
+        let profile =
+            |name: &str, path_glob: Option<&str>, format: Option<&str>| ValidatedProfile {
+                name: name.to_string(),
+                auto_select: Some(crate::ProfileAutoSelect {
+                    path_glob: path_glob.map(str::to_string),
+                    format: format.map(str::to_string),
+                }),
+                evaluator_rules: vec![text_rule(name, false, "red")],
+            };
+        let profiles = VisualRuleProfiles::compile(&ValidationReport {
+            evaluator_rules: vec![text_rule("default", false, "gray")],
+            profiles: vec![
+                profile("nginx", Some("{access,error}.log*"), None),
+                profile("kubernetes", Some("/var/log/pods/**/*.log"), None),
+                profile("jvm", None, Some("log4j")),
+            ],
+            palette: Vec::new(),
+            diagnostics: Vec::new(),
+        });
+
+        assert_eq!(
+            profiles.auto_select("/srv/nginx/access.log.1", None),
+            Some("nginx")
+        );
+        assert_eq!(
+            profiles.auto_select("C:\\logs\\error.log", None),
+            Some("nginx")
+        );
+        assert_eq!(
+            profiles.auto_select("/var/log/pods/ns_api/app/0.log", None),
+            Some("kubernetes")
+        );
+        assert_eq!(profiles.auto_select("/var/log/pods/0.txt", None), None);
+        assert_eq!(
+            profiles.auto_select("/opt/app.log", Some("Log4j")),
+            Some("jvm")
+        );
+        assert_eq!(profiles.auto_select("/opt/app.log", None), None);
+
+        let status = profiles.status(Some("jvm"), "/srv/access.log", None);
+        assert_eq!(status.active, "jvm");
+        assert_eq!(status.auto_selected.as_deref(), Some("nginx"));
+        assert_eq!(
+            status.profiles,
+            vec!["Default", "nginx", "kubernetes", "jvm"]
+        );
+        assert_eq!(
+            profiles
+                .status(Some("removed"), "/opt/app.log", None)
+                .active,
+            "Default"
+        );
+        assert!(
+            profiles
+                .evaluator_for(None, "/srv/access.log", None)
+                .evaluate("nginx")
+                .is_some()
+        );
+        assert!(
+            profiles
+                .evaluator_for(Some("Default"), "/srv/access.log", None)
+                .evaluate("default")
+                .is_some()
+        );
+    }
+
+    fn sequential_match(evaluator: &VisualRuleEvaluator, line: &str) -> Option<usize> {
+        (0..evaluator.rule_count()).find(|rule| evaluator.rule_matches(*rule, line))
+    }
+
+    #[test]
+    fn single_pass_matching_agrees_with_testing_rules_one_by_one() {
+        let evaluator = VisualRuleEvaluator::compile(&[
+            VisualRule {
+                matcher: VisualMatcher::Not(Box::new(VisualMatcher::Text("ok".to_string()))),
+                ..text_rule("", false, "gray")
+            },
+            text_rule("Échec", false, "red"),
+            text_rule("timeout", true, "orange"),
+            text_rule("timeout", true, "blue"),
+            regex_rule(r"^warn\b", false, "yellow"),
+            regex_rule(r"(?i)id=\d+", true, "cyan"),
+            VisualRule {
+                scope: VisualRuleScope::Match,
+                ..text_rule("user", false, "purple")
+            },
+            regex_rule("[", true, "broken"),
+        ]);
+        let lines = [
+            "ok ÉCHEC de connexion",
+            "ok request timeout",
+            "ok WARN disk id=7",
+            "ok Timeout for USER",
+            "ok ID=9",
+            "nothing",
+        ];
+
+        for line in lines {
+            let one_by_one = (0..evaluator.rule_count())
+                .map(|rule| evaluator.rule_matches(rule, line))
+                .collect::<Vec<_>>();
+            assert_eq!(evaluator.matching_rules(line), one_by_one, "{line}");
+            assert_eq!(
+                evaluator.matching_rule(line),
+                sequential_match(&evaluator, line),
+                "{line}"
+            );
+        }
+        assert_eq!(evaluator.matching_rule("ok request timeout"), Some(2));
+        assert_eq!(
+            evaluator.evaluate("ok Timeout for USER"),
+            None,
+            "the match-scoped rule does not style the row"
+        );
+        assert_eq!(evaluator.evaluate("failed"), Some(style("gray", "default")));
+    }
+
+    fn fifty_rules() -> VisualRuleEvaluator {
+        let rules = (0..50)
+            .map(|index| match index % 2 {
+                0 => text_rule(&format!("component-{index} failed"), false, "red"),
+                _ => regex_rule(&format!(r"code={index}\d{{3}}\b"), false, "blue"),
+            })
+            .collect::<Vec<_>>();
+        VisualRuleEvaluator::compile(&rules)
+    }
+
+    fn request_lines(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|index| {
+                format!(
+                    "2024-05-01T10:00:{:02} INFO Worker-{index} handled request {index} code={} in 12ms component-{} FAILED",
+                    index % 60,
+                    index % 97,
+                    index % 120
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_pass_evaluation_of_fifty_rules_matches_testing_them_in_sequence() {
+        let evaluator = fifty_rules();
+        let lines = request_lines(200);
+
+        let mut matched = 0;
+        for line in &lines {
+            let single_pass = evaluator.matching_rule(line.as_str());
+            assert_eq!(single_pass, sequential_match(&evaluator, line), "{line}");
+            matched += usize::from(single_pass.is_some());
+        }
+        assert!(matched > 0);
+    }
+
+    #[test]
+    fn plain_rules_scan_a_line_once_per_matcher_whatever_their_number() {
+        let lines = request_lines(100);
+        let scans = |evaluator: &VisualRuleEvaluator| {
+            LINE_SCANS.with(|count| count.set(0));
+            for line in &lines {
+                evaluator.matching_rule(line.as_str());
+            }
+            LINE_SCANS.with(std::cell::Cell::get)
+        };
+        for rule_count in [1, 10, 50] {
+            let rules = (0..rule_count)
+                .map(|index| text_rule(&format!("component-{index} failed"), true, "red"))
+                .collect::<Vec<_>>();
+            let evaluator = VisualRuleEvaluator::compile(&rules);
+            assert_eq!(scans(&evaluator), lines.len(), "{rule_count} text rules");
+        }
+        assert_eq!(
+            scans(&fifty_rules()),
+            2 * lines.len(),
+            "one automaton and one regex set"
+        );
+
+        let mut rule_by_rule = fifty_rules();
+        rule_by_rule.plain = PlainRuleSet::default();
+        rule_by_rule.plain.covers = vec![false; rule_by_rule.rule_count()];
+        assert!(scans(&rule_by_rule) > 10 * lines.len());
+    }
+
+    #[test]
+    #[ignore = "timing comparison; run with --release --ignored on an idle machine"]
+    fn single_pass_evaluation_of_fifty_rules_beats_testing_them_in_sequence() {
+        let evaluator = fifty_rules();
+        let lines = request_lines(2_000);
+
+        let time = |matcher: &dyn Fn(&str) -> Option<usize>| {
+            (0..3)
+                .map(|_| {
+                    let started = std::time::Instant::now();
+                    let matched = lines.iter().filter_map(|line| matcher(line)).count();
+                    (started.elapsed(), matched)
+                })
+                .min()
+                .unwrap()
+        };
+        let (single_pass, single_pass_matches) = time(&|line: &str| evaluator.matching_rule(line));
+        let (sequential, sequential_matches) =
+            time(&|line: &str| sequential_match(&evaluator, line));
+
+        assert_eq!(single_pass_matches, sequential_matches);
+        assert!(
+            single_pass * 2 < sequential,
+            "single pass took {single_pass:?}, rule by rule took {sequential:?}"
+        );
+    }
 }
@@ -0,0 +1,296 @@
+//! klogg highlighter sets and glogg filter sets, stored by Qt as INI files.
+//!
+//! klogg writes `[HighlighterSetCollection]` with `sets\N\highlighters\M\...` keys (older
+//! releases wrote a single `[HighlighterSet]`), glogg writes `[FilterSet]` with
+//! `filters\M\...` keys. Every set becomes one rule list; the current set is the default.
+
+use super::{
+    ColorRole, Diagnostics, LabeledRule, RuleSet, import_color, matcher_regex, palette_hex,
+    regex_matcher, rule_label,
+};
+use crate::models::palette::VisualPalette;
+use crate::models::visual_rules::{
+    LineStyleIntent, ManagedVisualRule, ValidationError, VisualMatcher, VisualRuleScope,
+};
+use std::collections::BTreeMap;
+
+const HIGHLIGHTER_KEYS: [&str; 2] = ["highlighters", "filters"];
+
+/// Settings of one highlighter, keyed by field name such as `regexp` or `fore_colour`.
+type Fields = BTreeMap<String, String>;
+
+pub(super) fn import(
+    content: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<RuleSet>, ValidationError> {
+    let mut current_set = None;
+    let mut set_fields: BTreeMap<usize, Fields> = BTreeMap::new();
+    let mut highlighters: BTreeMap<(usize, usize), Fields> = BTreeMap::new();
+    let mut recognized = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            recognized |= matches!(
+                line,
+                "[HighlighterSetCollection]" | "[HighlighterSet]" | "[FilterSet]"
+            );
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = unescape_value(value.trim());
+        if key == "current_set" {
+            current_set = Some(value);
+            continue;
+        }
+        let segments = key.split('\\').collect::<Vec<_>>();
+        let (set, rest) = match segments.as_slice() {
+            ["sets", set, rest @ ..] => match set.parse::<usize>() {
+                Ok(set) => (set, rest),
+                Err(_) => continue,
+            },
+            rest => (0, rest),
+        };
+        match rest {
+            [collection, index, field] if HIGHLIGHTER_KEYS.contains(collection) => {
+                if let Ok(index) = index.parse::<usize>() {
+                    highlighters
+                        .entry((set, index))
+                        .or_default()
+                        .insert(field.to_string(), value);
+                }
+            }
+            [field] if set > 0 => {
+                set_fields
+                    .entry(set)
+                    .or_default()
+                    .insert(field.to_string(), value);
+            }
+            _ => {}
+        }
+    }
+    if !recognized {
+        return Err(ValidationError {
+            message: "not a klogg or glogg highlighter file".to_string(),
+        });
+    }
+
+    let mut set_ids = highlighters.keys().map(|(set, _)| *set).collect::<Vec<_>>();
+    set_ids.extend(set_fields.keys().copied());
+    set_ids.sort_unstable();
+    set_ids.dedup();
+    let current = set_ids
+        .iter()
+        .copied()
+        .find(|set| {
+            current_set.is_some()
+                && set_fields.get(set).and_then(|fields| fields.get("id")) == current_set.as_ref()
+        })
+        .or(set_ids.first().copied());
+    set_ids.sort_by_key(|set| Some(*set) != current);
+
+    Ok(set_ids
+        .into_iter()
+        .map(|set| {
+            let name = set_fields
+                .get(&set)
+                .and_then(|fields| fields.get("name"))
+                .cloned()
+                .unwrap_or_else(|| format!("Set {set}"));
+            let prefix = if Some(set) == current {
+                String::new()
+            } else {
+                format!("set '{name}' ")
+            };
+            let rules = highlighters
+                .range((set, 0)..=(set, usize::MAX))
+                .enumerate()
+                .filter_map(|(position, (_, fields))| {
+                    import_highlighter(fields, &prefix, position + 1, diagnostics)
+                })
+                .collect();
+            RuleSet {
+                name,
+                format: None,
+                rules,
+            }
+        })
+        .collect())
+}
+
+fn import_highlighter(
+    fields: &Fields,
+    prefix: &str,
+    position: usize,
+    diagnostics: &mut Diagnostics,
+) -> Option<LabeledRule> {
+    let label = rule_label(prefix, None, position);
+    let Some(pattern) = fields.get("regexp").filter(|pattern| !pattern.is_empty()) else {
+        diagnostics.warning(format!("{label} skipped: no regexp"));
+        return None;
+    };
+    let flag = |name: &str| fields.get(name).map(|value| value == "true");
+    let case_sensitive = !flag("ignore_case").unwrap_or(false);
+    let plain_text = flag("plain_text") == Some(true) || flag("use_regex") == Some(false);
+    let (matcher, case_sensitive) = if plain_text {
+        (VisualMatcher::Text(pattern.clone()), case_sensitive)
+    } else {
+        regex_matcher(pattern, case_sensitive)
+    };
+    let label = rule_label(prefix, Some(pattern), position);
+    if flag("variate_colors") == Some(true) {
+        diagnostics.warning(format!(
+            "{label}: color variation is not supported; every match uses the base colors"
+        ));
+    }
+    let scope = if flag("match_only").or(flag("highlight_only_match")) == Some(true) {
+        VisualRuleScope::Match
+    } else {
+        VisualRuleScope::Line
+    };
+    let mut color = |key: &str, role: ColorRole| {
+        let value = fields.get(key)?;
+        if value.starts_with("@Variant(") {
+            diagnostics.warning(format!(
+                "{label}: {} color stored as a Qt variant was dropped",
+                role.name()
+            ));
+            return None;
+        }
+        import_color(value, role, &label, diagnostics)
+    };
+    let foreground = color("fore_colour", ColorRole::Foreground);
+    let background = color("back_colour", ColorRole::Background);
+    Some(LabeledRule {
+        rule: ManagedVisualRule {
+            name: Some(pattern.chars().take(80).collect()),
+            enabled: true,
+            matcher,
+            case_sensitive,
+            style: LineStyleIntent {
+                foreground,
+                background,
+                ..LineStyleIntent::default()
+            },
+            scope,
+        },
+        label,
+    })
+}
+
+pub(super) fn export(
+    sets: &[RuleSet],
+    palette: &VisualPalette,
+    diagnostics: &mut Diagnostics,
+) -> String {
+    let mut lines = vec![
+        "[HighlighterSetCollection]".to_string(),
+        "current_set=logmancer-1".to_string(),
+    ];
+    for (set_index, set) in sets.iter().enumerate() {
+        let set_key = format!("sets\\{}", set_index + 1);
+        lines.push(format!("{set_key}\\id=logmancer-{}", set_index + 1));
+        lines.push(format!("{set_key}\\name={}", escape_value(&set.name)));
+        for (index, LabeledRule { label, rule }) in set.rules.iter().enumerate() {
+            let key = format!("{set_key}\\highlighters\\{}", index + 1);
+            let match_only = match &rule.scope {
+                VisualRuleScope::Line => false,
+                VisualRuleScope::Match => true,
+                VisualRuleScope::Groups(_) => {
+                    diagnostics.warning(format!(
+                        "{label}: capture-group scope exported as the whole match"
+                    ));
+                    true
+                }
+            };
+            if !rule.style.attributes.is_empty() {
+                diagnostics.warning(format!("{label}: text attributes were not exported"));
+            }
+            if rule.style.gutter.is_some() {
+                diagnostics.warning(format!("{label}: gutter marker was not exported"));
+            }
+            let pattern = matcher_regex(&rule.matcher);
+            let ignore_case = !rule.case_sensitive;
+            let foreground = palette_hex(
+                palette,
+                rule.style.foreground.as_ref(),
+                ColorRole::Foreground,
+            );
+            let background = palette_hex(
+                palette,
+                rule.style.background.as_ref(),
+                ColorRole::Background,
+            );
+            lines.push(format!(
+                "{key}\\back_colour={}",
+                background.as_deref().unwrap_or("#ffffff")
+            ));
+            lines.push(format!(
+                "{key}\\fore_colour={}",
+                foreground.as_deref().unwrap_or("#000000")
+            ));
+            lines.push(format!("{key}\\ignore_case={ignore_case}"));
+            lines.push(format!("{key}\\match_only={match_only}"));
+            lines.push(format!("{key}\\regexp={}", escape_value(&pattern)));
+            lines.push(format!("{key}\\variate_colors=false"));
+        }
+        lines.push(format!("{set_key}\\highlighters\\size={}", set.rules.len()));
+    }
+    lines.push(format!("sets\\size={}", sets.len()));
+    lines.push("version=1".to_string());
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Reverses Qt's INI value escaping: optional surrounding quotes and backslash escapes.
+fn unescape_value(value: &str) -> String {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            unescaped.push(character);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn escape_value(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    let needs_quotes =
+        value.trim() != value || value.is_empty() || value.contains([';', ',', '=', '"', '#']);
+    if needs_quotes {
+        format!("\"{escaped}\"")
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_value, unescape_value};
+
+    #[test]
+    fn ini_values_round_trip_backslashes_quotes_and_separators() {
+        for value in [r"\s5\d\d\s", r#"user="bob""#, "a,b; c", " padded "] {
+            assert_eq!(unescape_value(&escape_value(value)), value);
+        }
+        assert_eq!(escape_value(r"\d+"), r"\\d+");
+    }
+}
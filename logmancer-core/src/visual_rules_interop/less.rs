@@ -0,0 +1,386 @@
+//! `less` colors, read from the `LESS` variable of a lesskey source file or a shell profile.
+//!
+//! `less` has no highlighter list: it colors the current search (`-DS`) and, within it, each
+//! parenthesized subpattern (`-D1` to `-D5`). A `+/pattern` start-up search therefore acts as
+//! a set of rules. Exports put up to five rules into one `+/(a)|(b)` search, one subpattern
+//! color per rule. Options are split at whitespace, so imported patterns cannot contain
+//! spaces.
+
+use super::{ColorRole, Diagnostics, LabeledRule, RuleSet, import_color, rule_regex};
+use crate::models::visual_rules::{
+    LineStyleIntent, ManagedVisualRule, TextAttributes, ValidationError, VisualColor,
+    VisualMatcher, VisualRuleScope,
+};
+
+const MAX_SUBPATTERN_COLORS: usize = 5;
+
+/// Color and attributes of one `-D` option.
+#[derive(Default)]
+struct LessColor {
+    foreground: Option<String>,
+    background: Option<String>,
+    attributes: TextAttributes,
+}
+
+pub(super) fn import(
+    content: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<RuleSet>, ValidationError> {
+    let Some(options) = content.lines().rev().find_map(less_variable) else {
+        return Err(ValidationError {
+            message: "no LESS variable found".to_string(),
+        });
+    };
+
+    let mut pattern = None;
+    let mut ignore_case = false;
+    let mut smart_case = false;
+    let mut search_color = None;
+    let mut subpattern_colors: Vec<(usize, LessColor)> = Vec::new();
+    // `$` ends string options such as `-D`, but is part of regexes in `+/` searches.
+    let tokens = options.split_whitespace().flat_map(|token| {
+        if token.starts_with('+') {
+            vec![token]
+        } else {
+            token.split('$').collect()
+        }
+    });
+    for token in tokens {
+        if let Some(search) = token
+            .strip_prefix("++/")
+            .or_else(|| token.strip_prefix("+/"))
+        {
+            pattern = Some(search.to_string());
+            continue;
+        }
+        let color_option = if let Some(spec) = token.strip_prefix("--color=") {
+            Some(spec)
+        } else if let Some(flags) = token
+            .strip_prefix('-')
+            .filter(|flags| !flags.starts_with('-'))
+        {
+            // Single-letter options may be bundled, as in `-iRDSyk`.
+            let (flags, spec) = match flags.find('D') {
+                Some(index) => (&flags[..index], Some(&flags[index + 1..])),
+                None => (flags, None),
+            };
+            ignore_case |= flags.contains('I');
+            smart_case |= flags.contains('i');
+            spec
+        } else {
+            None
+        };
+        let Some(spec) = color_option else {
+            continue;
+        };
+        let mut chars = spec.chars();
+        let Some(selector) = chars.next() else {
+            continue;
+        };
+        let color = parse_color(chars.as_str(), selector, diagnostics);
+        match selector {
+            'S' => search_color = Some(color),
+            '1'..='5' => subpattern_colors.push((selector as usize - '0' as usize, color)),
+            other => diagnostics.warning(format!(
+                "less color -D{other} has no visual rule equivalent"
+            )),
+        }
+    }
+
+    let Some(pattern) = pattern.filter(|pattern| !pattern.is_empty()) else {
+        if search_color.is_some() || !subpattern_colors.is_empty() {
+            diagnostics.warning(
+                "less search colors apply to interactive searches; add a +/pattern to import them as rules",
+            );
+        }
+        return Ok(vec![RuleSet {
+            name: String::new(),
+            format: None,
+            rules: Vec::new(),
+        }]);
+    };
+    // With -i, less ignores case only for patterns without uppercase letters.
+    let case_sensitive = !(ignore_case || smart_case && !pattern.chars().any(char::is_uppercase));
+
+    subpattern_colors.sort_by_key(|(group, _)| *group);
+    let mut rules = subpattern_colors
+        .into_iter()
+        .map(|(group, color)| {
+            search_rule(
+                &pattern,
+                case_sensitive,
+                VisualRuleScope::Groups(vec![group]),
+                color,
+                format!("subpattern {group}"),
+                diagnostics,
+            )
+        })
+        .collect::<Vec<_>>();
+    // Subpattern colors paint over the search color, so their rules come first.
+    if let Some(color) = search_color {
+        rules.push(search_rule(
+            &pattern,
+            case_sensitive,
+            VisualRuleScope::Match,
+            color,
+            "search".to_string(),
+            diagnostics,
+        ));
+    } else if rules.is_empty() {
+        diagnostics.warning(
+            "the +/ search has no -DS or -D1..5 color; less shows it in standout, which has no palette equivalent",
+        );
+    }
+    Ok(vec![RuleSet {
+        name: String::new(),
+        format: None,
+        rules,
+    }])
+}
+
+/// Value of a `LESS=...`, `export LESS="..."` or lesskey `LESS = ...` line.
+fn less_variable(line: &str) -> Option<String> {
+    let line = line.trim();
+    let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+    let value = line
+        .strip_prefix("LESS")?
+        .trim_start()
+        .strip_prefix('=')?
+        .trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value);
+    Some(value.to_string())
+}
+
+fn search_rule(
+    pattern: &str,
+    case_sensitive: bool,
+    scope: VisualRuleScope,
+    color: LessColor,
+    name: String,
+    diagnostics: &mut Diagnostics,
+) -> LabeledRule {
+    let label = format!("rule '{name}'");
+    let mut import = |color: Option<String>, role: ColorRole| {
+        color.and_then(|color| import_color(&color, role, &label, diagnostics))
+    };
+    let foreground = import(color.foreground, ColorRole::Foreground);
+    let background = import(color.background, ColorRole::Background);
+    LabeledRule {
+        rule: ManagedVisualRule {
+            name: Some(name),
+            enabled: true,
+            matcher: VisualMatcher::Regex(pattern.to_string()),
+            case_sensitive,
+            style: LineStyleIntent {
+                foreground,
+                background,
+                attributes: color.attributes,
+                gutter: None,
+            },
+            scope,
+        },
+        label,
+    }
+}
+
+/// Parses a `-D` color: one or two 4-bit color letters or `fg.bg` 8-bit numbers, followed by
+/// attribute characters.
+fn parse_color(spec: &str, selector: char, diagnostics: &mut Diagnostics) -> LessColor {
+    let spec = spec.strip_prefix('+').unwrap_or(spec);
+    let mut color = LessColor::default();
+    let numeric_len = spec
+        .find(|character: char| !character.is_ascii_digit() && character != '.')
+        .unwrap_or(spec.len());
+    let attributes = if numeric_len > 0 {
+        let (foreground, background) = spec[..numeric_len]
+            .split_once('.')
+            .unwrap_or((&spec[..numeric_len], ""));
+        color.foreground = foreground.parse().ok().map(ansi_256_color);
+        color.background = background.parse().ok().map(ansi_256_color);
+        &spec[numeric_len..]
+    } else {
+        let letters = spec
+            .chars()
+            .take(2)
+            .take_while(|character| "bcgkmrwyBCGKMRWY-".contains(*character))
+            .collect::<Vec<_>>();
+        color.foreground = letters.first().and_then(|letter| letter_color(*letter));
+        color.background = letters.get(1).and_then(|letter| letter_color(*letter));
+        &spec[letters.len()..]
+    };
+    for attribute in attributes.chars() {
+        match attribute {
+            'd' | '*' => color.attributes.bold = true,
+            'u' | '_' => color.attributes.underline = true,
+            's' | '~' | 'l' | '&' => diagnostics.warning(format!(
+                "less color -D{selector}: standout and blink are not supported"
+            )),
+            _ => {}
+        }
+    }
+    color
+}
+
+fn letter_color(letter: char) -> Option<String> {
+    Some(
+        match letter.to_ascii_lowercase() {
+            'b' => "blue",
+            'c' => "cyan",
+            'g' => "green",
+            'm' => "magenta",
+            'r' => "red",
+            'y' => "yellow",
+            'k' if letter.is_ascii_uppercase() => "gray",
+            'k' => "black",
+            'w' => "white",
+            _ => return None,
+        }
+        .to_string(),
+    )
+}
+
+/// Hex form of an xterm 256-color index.
+fn ansi_256_color(index: u8) -> String {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    let (red, green, blue) = match index {
+        0..=15 => BASIC[usize::from(index)],
+        16..=231 => {
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            let index = index - 16;
+            (level(index / 36), level(index / 6 % 6), level(index % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    };
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
+
+pub(super) fn export(sets: &[RuleSet], diagnostics: &mut Diagnostics) -> String {
+    if sets.iter().skip(1).any(|set| !set.rules.is_empty()) {
+        diagnostics.warning("less has no profiles; only the default rules were exported");
+    }
+    let mut alternatives = Vec::new();
+    let mut colors = Vec::new();
+    let rules = sets
+        .first()
+        .map(|set| set.rules.as_slice())
+        .unwrap_or_default();
+    for LabeledRule { label, rule } in rules {
+        if alternatives.len() == MAX_SUBPATTERN_COLORS {
+            diagnostics.warning(format!(
+                "{label}: less colors at most five subpatterns; rule was not exported"
+            ));
+            continue;
+        }
+        let pattern = rule_regex(rule);
+        if pattern.contains(char::is_whitespace) {
+            diagnostics.warning(format!(
+                "{label}: patterns with spaces cannot be passed in the LESS variable; rule was not exported"
+            ));
+            continue;
+        }
+        if regex::Regex::new(&pattern).is_ok_and(|regex| regex.captures_len() > 1) {
+            diagnostics.warning(format!(
+                "{label}: patterns with capture groups would shift less subpattern colors; rule was not exported"
+            ));
+            continue;
+        }
+        if !rule.case_sensitive {
+            diagnostics.warning(format!(
+                "{label}: case-insensitive match written as (?i), which less needs PCRE2 for"
+            ));
+        }
+        let pattern = match &rule.scope {
+            VisualRuleScope::Line => format!(".*(?:{pattern}).*"),
+            VisualRuleScope::Match => pattern,
+            VisualRuleScope::Groups(_) => {
+                diagnostics.warning(format!(
+                    "{label}: capture-group scope exported as the whole match"
+                ));
+                pattern
+            }
+        };
+        let attributes = rule.style.attributes;
+        if attributes.italic || attributes.strikethrough || attributes.dim {
+            diagnostics.warning(format!(
+                "{label}: less supports bold and underline only; other text attributes were not exported"
+            ));
+        }
+        if rule.style.gutter.is_some() {
+            diagnostics.warning(format!("{label}: gutter marker was not exported"));
+        }
+        let mut spec = format!(
+            "{}{}",
+            color_letter(rule.style.foreground.as_ref(), label, diagnostics),
+            color_letter(rule.style.background.as_ref(), label, diagnostics)
+        );
+        if spec.ends_with('-') {
+            spec.pop();
+        }
+        if attributes.bold {
+            spec.push('d');
+        }
+        if attributes.underline {
+            spec.push('u');
+        }
+        alternatives.push(format!("({pattern})"));
+        colors.push(format!("-D{}{spec}", alternatives.len()));
+    }
+
+    let mut options = vec!["--use-color".to_string()];
+    options.extend(colors);
+    if !alternatives.is_empty() {
+        options.push(format!("+/{}", alternatives.join("|")));
+    }
+    format!("#env\nLESS = {}\n", options.join(" "))
+}
+
+fn color_letter(color: Option<&VisualColor>, label: &str, diagnostics: &mut Diagnostics) -> char {
+    match color.map(|color| color.0.as_str()) {
+        Some("red") => 'r',
+        Some("orange") => {
+            diagnostics.warning(format!("{label}: orange exported as yellow"));
+            'y'
+        }
+        Some("yellow") => 'y',
+        Some("green") => 'g',
+        Some("cyan") => 'c',
+        Some("blue") => 'b',
+        Some("purple") => 'm',
+        Some("gray") => 'K',
+        Some("default") | None => '-',
+        Some(token) => {
+            diagnostics.warning(format!(
+                "{label}: custom color '{token}' exported as the default color"
+            ));
+            '-'
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! lnav `highlights`, from format files (`{"<format>": {"highlights": ...}}`) and from theme
+//! definitions in configuration files (`ui.theme-defs.<theme>.highlights`).
+//!
+//! lnav only colors the matched text, so imported rules are match-scoped; exported line rules
+//! wrap their pattern in `^.*(?:...).*$`, which imports back as a line rule.
+
+use super::{
+    ColorRole, Diagnostics, LabeledRule, RuleSet, import_color, palette_hex, regex_matcher,
+    rule_label, rule_regex,
+};
+use crate::models::palette::VisualPalette;
+use crate::models::visual_rules::{
+    LineStyleIntent, ManagedVisualRule, TextAttributes, ValidationError, VisualRuleScope,
+};
+use serde_json::{Map, Value, json};
+
+const FORMAT_SCHEMA: &str = "https://lnav.org/schemas/format-v1.schema.json";
+const LINE_PREFIX: &str = "^.*(?:";
+const LINE_SUFFIX: &str = ").*$";
+
+pub(super) fn import(
+    content: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<RuleSet>, ValidationError> {
+    let document: Value = serde_json::from_str(content).map_err(|error| ValidationError {
+        message: format!("invalid lnav JSON: {error}"),
+    })?;
+    let Some(document) = document.as_object() else {
+        return Err(ValidationError {
+            message: "lnav files must contain a JSON object".to_string(),
+        });
+    };
+
+    let mut sources = Vec::new();
+    for (key, value) in document {
+        if key == "$schema" {
+            continue;
+        }
+        if key == "ui" {
+            let themes = value.get("theme-defs").and_then(Value::as_object);
+            for (theme, definition) in themes.into_iter().flatten() {
+                if let Some(highlights) = definition.get("highlights").and_then(Value::as_object) {
+                    sources.push((format!("{theme} theme"), None, highlights));
+                }
+            }
+            continue;
+        }
+        if let Some(highlights) = value.get("highlights").and_then(Value::as_object) {
+            let title = value
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or(key)
+                .to_string();
+            if value.get("file-pattern").is_some() {
+                diagnostics.warning(format!(
+                    "format '{key}': file-pattern is not converted; the profile is selected by format"
+                ));
+            }
+            sources.push((title, Some(key.clone()), highlights));
+        }
+    }
+
+    // A single source needs no profile: its highlights become the default rules.
+    let single = sources.len() == 1;
+    let mut sets = Vec::new();
+    if !single {
+        sets.push(RuleSet {
+            name: String::new(),
+            format: None,
+            rules: Vec::new(),
+        });
+    }
+    for (name, format, highlights) in sources {
+        let prefix = if single {
+            String::new()
+        } else {
+            format!("profile '{name}' ")
+        };
+        let rules = highlights
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (key, highlight))| {
+                import_highlight(
+                    key,
+                    highlight,
+                    &rule_label(&prefix, Some(key), index + 1),
+                    diagnostics,
+                )
+            })
+            .collect();
+        sets.push(RuleSet {
+            name,
+            format: if single { None } else { format },
+            rules,
+        });
+    }
+    Ok(sets)
+}
+
+fn import_highlight(
+    name: &str,
+    highlight: &Value,
+    label: &str,
+    diagnostics: &mut Diagnostics,
+) -> Option<LabeledRule> {
+    let Some(pattern) = highlight.get("pattern").and_then(Value::as_str) else {
+        diagnostics.warning(format!("{label} skipped: no pattern"));
+        return None;
+    };
+    // Theme highlights nest their style; format highlights carry it inline.
+    let style = highlight.get("style").unwrap_or(highlight);
+    let flag = |key: &str| style.get(key).and_then(Value::as_bool).unwrap_or(false);
+    let mut color = |key: &str, role: ColorRole| {
+        let value = style.get(key).and_then(Value::as_str)?;
+        import_color(value, role, label, diagnostics)
+    };
+    let foreground = color("color", ColorRole::Foreground);
+    let background = color("background-color", ColorRole::Background);
+    if flag("blink") {
+        diagnostics.warning(format!("{label}: blink is not supported"));
+    }
+    let (pattern, scope) = match pattern
+        .strip_prefix(LINE_PREFIX)
+        .and_then(|inner| inner.strip_suffix(LINE_SUFFIX))
+    {
+        Some(inner) => (inner, VisualRuleScope::Line),
+        None => (pattern, VisualRuleScope::Match),
+    };
+    let (matcher, case_sensitive) = regex_matcher(pattern, true);
+    Some(LabeledRule {
+        rule: ManagedVisualRule {
+            name: Some(name.chars().take(80).collect()),
+            enabled: true,
+            matcher,
+            case_sensitive,
+            style: LineStyleIntent {
+                foreground,
+                background,
+                attributes: TextAttributes {
+                    bold: flag("bold"),
+                    italic: flag("italic"),
+                    underline: flag("underline"),
+                    strikethrough: flag("strike"),
+                    dim: false,
+                },
+                gutter: None,
+            },
+            scope,
+        },
+        label: label.to_string(),
+    })
+}
+
+pub(super) fn export(
+    sets: &[RuleSet],
+    palette: &VisualPalette,
+    diagnostics: &mut Diagnostics,
+) -> String {
+    let mut document = Map::new();
+    document.insert("$schema".to_string(), json!(FORMAT_SCHEMA));
+    for set in sets.iter().filter(|set| !set.rules.is_empty()) {
+        let format = match &set.format {
+            Some(format) => format.clone(),
+            None => {
+                let format = generated_format_name(&set.name, &document);
+                diagnostics.warning(format!(
+                    "lnav applies highlights per log format: rules of '{}' were exported under \
+                     '{format}'; rename it to the format they should extend",
+                    set.name
+                ));
+                format
+            }
+        };
+        let mut highlights = Map::new();
+        for LabeledRule { label, rule } in &set.rules {
+            let mut highlight = Map::new();
+            let pattern = rule_regex(rule);
+            let pattern = match &rule.scope {
+                VisualRuleScope::Line => format!("{LINE_PREFIX}{pattern}{LINE_SUFFIX}"),
+                VisualRuleScope::Match => pattern,
+                VisualRuleScope::Groups(_) => {
+                    diagnostics.warning(format!(
+                        "{label}: capture-group scope exported as the whole match"
+                    ));
+                    pattern
+                }
+            };
+            highlight.insert("pattern".to_string(), json!(pattern));
+            if let Some(color) = palette_hex(
+                palette,
+                rule.style.foreground.as_ref(),
+                ColorRole::Foreground,
+            ) {
+                highlight.insert("color".to_string(), json!(color));
+            }
+            if let Some(color) = palette_hex(
+                palette,
+                rule.style.background.as_ref(),
+                ColorRole::Background,
+            ) {
+                highlight.insert("background-color".to_string(), json!(color));
+            }
+            let attributes = rule.style.attributes;
+            if attributes.underline {
+                highlight.insert("underline".to_string(), json!(true));
+            }
+            if attributes.bold || attributes.italic || attributes.strikethrough || attributes.dim {
+                diagnostics.warning(format!(
+                    "{label}: lnav format highlights only support underline; other text attributes were not exported"
+                ));
+            }
+            if rule.style.gutter.is_some() {
+                diagnostics.warning(format!("{label}: gutter marker was not exported"));
+            }
+            let key = unique_key(&highlight_key(rule.name.as_deref()), &highlights);
+            highlights.insert(key, Value::Object(highlight));
+        }
+        let entry = document
+            .entry(format)
+            .or_insert_with(|| json!({ "highlights": {} }));
+        if let Some(existing) = entry.get_mut("highlights").and_then(Value::as_object_mut) {
+            for (key, highlight) in highlights {
+                let key = unique_key(&key, existing);
+                existing.insert(key, highlight);
+            }
+        }
+    }
+    let mut content = serde_json::to_string_pretty(&Value::Object(document)).unwrap_or_default();
+    content.push('\n');
+    content
+}
+
+fn generated_format_name(profile: &str, document: &Map<String, Value>) -> String {
+    let slug = slug(profile);
+    let base = if slug == "default" {
+        "logmancer_log".to_string()
+    } else {
+        format!("logmancer_{slug}_log")
+    };
+    unique_key(&base, document)
+}
+
+fn highlight_key(name: Option<&str>) -> String {
+    let slug = name.map(slug).unwrap_or_default();
+    if slug.is_empty() {
+        "rule".to_string()
+    } else {
+        slug
+    }
+}
+
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn unique_key(base: &str, taken: &Map<String, Value>) -> String {
+    let mut key = base.to_string();
+    let mut suffix = 2;
+    while taken.contains_key(&key) {
+        key = format!("{base}_{suffix}");
+        suffix += 1;
+    }
+    key
+}
@@ -0,0 +1,522 @@
+//! Converters between `VisualRulesEnvelope` and the highlighter files of other log viewers.
+//!
+//! Imports never fail on a single unrepresentable construct: it is dropped or approximated
+//! and reported as a `ValidationDiagnostic`, and the returned envelope always passes
+//! `VisualRulesEnvelope::validate_for_save`.
+
+mod klogg;
+mod less;
+mod lnav;
+
+use crate::models::palette::{VisualColorRole, VisualPalette, VisualTheme};
+use crate::models::visual_rules::{
+    DEFAULT_VISUAL_PROFILE, MAX_ENABLED_VISUAL_RULES, MAX_STORED_VISUAL_RULES,
+    MAX_VISUAL_RULE_PROFILES, ManagedVisualRule, ProfileAutoSelect, ValidationDiagnostic,
+    ValidationError, ValidationSeverity, VisualColor, VisualMatcher, VisualRuleProfile,
+    VisualRulesEnvelope, validate_rule,
+};
+use serde::{Deserialize, Serialize};
+
+/// Largest file accepted for import.
+pub const MAX_VISUAL_RULES_IMPORT_SIZE: usize = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum VisualRulesFormat {
+    /// klogg/glogg highlighter and filter sets (`highlighters.conf`, `filters.conf`).
+    Klogg,
+    /// lnav format files and theme definitions with `highlights`.
+    Lnav,
+    /// `less` colors from the `LESS` variable: `-D` options and a `+/pattern` search.
+    Less,
+}
+
+impl VisualRulesFormat {
+    /// File name suggested for exports.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Klogg => "highlighters.conf",
+            Self::Lnav => "logmancer-highlights.json",
+            Self::Less => "lesskey",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisualRulesImport {
+    pub envelope: VisualRulesEnvelope,
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisualRulesExport {
+    pub content: String,
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+/// Converts another viewer's highlighter file into an envelope that is ready to save.
+pub fn import_visual_rules(
+    format: VisualRulesFormat,
+    content: &str,
+) -> Result<VisualRulesImport, ValidationError> {
+    if content.len() > MAX_VISUAL_RULES_IMPORT_SIZE {
+        return Err(ValidationError {
+            message: "import exceeds 1 MiB".to_string(),
+        });
+    }
+    let mut diagnostics = Diagnostics::default();
+    let sets = match format {
+        VisualRulesFormat::Klogg => klogg::import(content, &mut diagnostics)?,
+        VisualRulesFormat::Lnav => lnav::import(content, &mut diagnostics)?,
+        VisualRulesFormat::Less => less::import(content, &mut diagnostics)?,
+    };
+    if sets.iter().all(|set| set.rules.is_empty()) {
+        diagnostics.warning("no highlighters found");
+    }
+    let envelope = envelope_from_sets(sets, &mut diagnostics);
+    Ok(VisualRulesImport {
+        envelope,
+        diagnostics: diagnostics.0,
+    })
+}
+
+/// Writes `envelope` in another viewer's format; disabled rules and styles the target cannot
+/// express are left out and reported.
+pub fn export_visual_rules(
+    format: VisualRulesFormat,
+    envelope: &VisualRulesEnvelope,
+) -> Result<VisualRulesExport, ValidationError> {
+    let report = envelope.validate_for_load()?;
+    let palette = VisualPalette::new(report.palette);
+    let mut diagnostics = Diagnostics::default();
+    let mut sets = vec![RuleSet {
+        name: DEFAULT_VISUAL_PROFILE.to_string(),
+        format: None,
+        rules: enabled_rules(&envelope.rules, "", &mut diagnostics),
+    }];
+    for profile in &envelope.profiles {
+        let prefix = format!("profile '{}' ", profile.name);
+        sets.push(RuleSet {
+            name: profile.name.clone(),
+            format: profile
+                .auto_select
+                .as_ref()
+                .and_then(|auto_select| auto_select.format.clone()),
+            rules: enabled_rules(&profile.rules, &prefix, &mut diagnostics),
+        });
+    }
+    let content = match format {
+        VisualRulesFormat::Klogg => klogg::export(&sets, &palette, &mut diagnostics),
+        VisualRulesFormat::Lnav => lnav::export(&sets, &palette, &mut diagnostics),
+        VisualRulesFormat::Less => less::export(&sets, &mut diagnostics),
+    };
+    Ok(VisualRulesExport {
+        content,
+        diagnostics: diagnostics.0,
+    })
+}
+
+/// Rules of one highlighter set; the first set of an import becomes the default rules.
+struct RuleSet {
+    name: String,
+    /// Log format the set applies to, used as the profile auto-select format.
+    format: Option<String>,
+    rules: Vec<LabeledRule>,
+}
+
+/// Rule with the label diagnostics use for it, such as `rule 'Errors'` or `profile 'x' rule 3`.
+struct LabeledRule {
+    label: String,
+    rule: ManagedVisualRule,
+}
+
+#[derive(Default)]
+struct Diagnostics(Vec<ValidationDiagnostic>);
+
+impl Diagnostics {
+    fn warning(&mut self, message: impl Into<String>) {
+        self.0.push(ValidationDiagnostic {
+            severity: ValidationSeverity::Warning,
+            message: message.into(),
+        });
+    }
+
+    fn error(&mut self, message: impl Into<String>) {
+        self.0.push(ValidationDiagnostic {
+            severity: ValidationSeverity::Error,
+            message: message.into(),
+        });
+    }
+}
+
+fn rule_label(prefix: &str, name: Option<&str>, position: usize) -> String {
+    match name {
+        Some(name) => format!("{prefix}rule '{name}'"),
+        None => format!("{prefix}rule {position}"),
+    }
+}
+
+fn enabled_rules(
+    rules: &[ManagedVisualRule],
+    prefix: &str,
+    diagnostics: &mut Diagnostics,
+) -> Vec<LabeledRule> {
+    rules
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| {
+            let label = rule_label(prefix, rule.name.as_deref(), index + 1);
+            if !rule.enabled {
+                diagnostics.warning(format!("{label}: disabled rules are not exported"));
+                None
+            } else if rule.matcher.is_compound() {
+                diagnostics.warning(format!(
+                    "{label}: compound conditions have no equivalent; rule was not exported"
+                ));
+                None
+            } else {
+                Some(LabeledRule {
+                    label,
+                    rule: rule.clone(),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Drops rules core cannot evaluate and trims the sets to the envelope limits.
+fn envelope_from_sets(sets: Vec<RuleSet>, diagnostics: &mut Diagnostics) -> VisualRulesEnvelope {
+    let mut stored = 0;
+    let mut lists = Vec::new();
+    for (index, set) in sets.into_iter().enumerate() {
+        if index > MAX_VISUAL_RULE_PROFILES {
+            diagnostics.error(format!(
+                "highlighter set '{}' skipped: at most 16 profiles may be stored",
+                set.name
+            ));
+            continue;
+        }
+        let mut rules = Vec::new();
+        let mut enabled = 0;
+        for LabeledRule { label, mut rule } in set.rules {
+            if let Err(message) = validate_rule(&rule, &VisualPalette::default()) {
+                diagnostics.error(format!("{label} skipped: {message}"));
+                continue;
+            }
+            if stored == MAX_STORED_VISUAL_RULES {
+                diagnostics.error(format!("{label} skipped: at most 100 rules may be stored"));
+                continue;
+            }
+            if enabled == MAX_ENABLED_VISUAL_RULES {
+                rule.enabled = false;
+                diagnostics.warning(format!("{label} disabled: at most 50 rules may be enabled"));
+            } else {
+                enabled += 1;
+            }
+            stored += 1;
+            rules.push(rule);
+        }
+        lists.push((set.name, set.format, rules));
+    }
+
+    let mut lists = lists.into_iter();
+    let rules = lists.next().map(|(_, _, rules)| rules).unwrap_or_default();
+    let mut profiles: Vec<VisualRuleProfile> = Vec::new();
+    for (name, format, rules) in lists {
+        let name = unique_profile_name(&name, &profiles);
+        profiles.push(VisualRuleProfile {
+            name,
+            rules,
+            auto_select: format.map(|format| ProfileAutoSelect {
+                path_glob: None,
+                format: Some(format),
+            }),
+        });
+    }
+    let mut envelope = VisualRulesEnvelope::new(rules);
+    envelope.profiles = profiles;
+    envelope
+}
+
+fn unique_profile_name(name: &str, profiles: &[VisualRuleProfile]) -> String {
+    let base = name.trim().chars().take(70).collect::<String>();
+    let base = if base.is_empty() || base.eq_ignore_ascii_case(DEFAULT_VISUAL_PROFILE) {
+        format!("Imported {}", profiles.len() + 1)
+    } else {
+        base
+    };
+    let taken = |candidate: &str| {
+        profiles
+            .iter()
+            .any(|profile| profile.name.eq_ignore_ascii_case(candidate))
+    };
+    let mut candidate = base.clone();
+    let mut suffix = 2;
+    while taken(&candidate) {
+        candidate = format!("{base} ({suffix})");
+        suffix += 1;
+    }
+    candidate
+}
+
+/// Regex source of a text or regex matcher; exports never see compound conditions.
+fn matcher_regex(matcher: &VisualMatcher) -> String {
+    match matcher {
+        VisualMatcher::Text(text) => regex::escape(text),
+        VisualMatcher::Regex(pattern) => pattern.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Regex source matching what `rule` matches, with `(?i)` for case-insensitive rules.
+fn rule_regex(rule: &ManagedVisualRule) -> String {
+    let pattern = matcher_regex(&rule.matcher);
+    if rule.case_sensitive {
+        pattern
+    } else {
+        format!("(?i){pattern}")
+    }
+}
+
+/// Splits a leading `(?i)` off an imported pattern into the rule's case sensitivity.
+fn regex_matcher(pattern: &str, case_sensitive: bool) -> (VisualMatcher, bool) {
+    match pattern.strip_prefix("(?i)") {
+        Some(rest) if !rest.is_empty() => (VisualMatcher::Regex(rest.to_string()), false),
+        _ => (VisualMatcher::Regex(pattern.to_string()), case_sensitive),
+    }
+}
+
+/// Representative colors of the palette tokens, matching the web viewer.
+const PALETTE_COLORS: [(&str, &str, &str); 8] = [
+    ("red", "#b91c1c", "#fee2e2"),
+    ("orange", "#c2410c", "#ffedd5"),
+    ("yellow", "#a16207", "#fef9c3"),
+    ("green", "#15803d", "#dcfce7"),
+    ("cyan", "#0e7490", "#cffafe"),
+    ("blue", "#1d4ed8", "#dbeafe"),
+    ("purple", "#7e22ce", "#f3e8ff"),
+    ("gray", "#4b5563", "#f3f4f6"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ColorRole {
+    Foreground,
+    Background,
+}
+
+impl ColorRole {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Foreground => "foreground",
+            Self::Background => "background",
+        }
+    }
+}
+
+/// Light-theme color of a token, custom palette colors included.
+fn palette_hex(
+    palette: &VisualPalette,
+    color: Option<&VisualColor>,
+    role: ColorRole,
+) -> Option<String> {
+    let role = match role {
+        ColorRole::Foreground => VisualColorRole::Foreground,
+        ColorRole::Background => VisualColorRole::Background,
+    };
+    palette
+        .resolve(color?, role, VisualTheme::Light)
+        .map(|rgb| rgb.to_hex())
+}
+
+fn parse_hex_color(text: &str) -> Option<(u8, u8, u8)> {
+    let digits = text.strip_prefix('#')?;
+    if !digits
+        .chars()
+        .all(|character| character.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    let channel = |start: usize, len: usize| {
+        let value = u8::from_str_radix(&digits[start..start + len], 16).ok()?;
+        Some(if len == 1 { value * 17 } else { value })
+    };
+    match digits.len() {
+        3 => Some((channel(0, 1)?, channel(1, 1)?, channel(2, 1)?)),
+        6 => Some((channel(0, 2)?, channel(2, 2)?, channel(4, 2)?)),
+        // Qt writes `#aarrggbb` for colors with an alpha channel.
+        8 => Some((channel(2, 2)?, channel(4, 2)?, channel(6, 2)?)),
+        _ => None,
+    }
+}
+
+/// Maps a hex or named color onto the palette; `None` is the UI default color.
+///
+/// Colors that are not exactly a palette color are approximated by hue and reported, except
+/// black text and white backgrounds, which other viewers use to mean "unchanged".
+fn import_color(
+    text: &str,
+    role: ColorRole,
+    label: &str,
+    diagnostics: &mut Diagnostics,
+) -> Option<VisualColor> {
+    let text = text.trim();
+    let lowercase = text.to_ascii_lowercase();
+    if lowercase.is_empty() || lowercase == "default" {
+        return None;
+    }
+    if let Some(token) = named_palette_color(&lowercase) {
+        return Some(VisualColor(token.to_string()));
+    }
+    let Some(rgb) = parse_hex_color(&lowercase).or_else(|| named_rgb(&lowercase)) else {
+        diagnostics.warning(format!(
+            "{label}: {} color '{text}' is not supported and was dropped",
+            role.name()
+        ));
+        return None;
+    };
+    let exact = PALETTE_COLORS.iter().find(|(_, foreground, background)| {
+        let hex = match role {
+            ColorRole::Foreground => foreground,
+            ColorRole::Background => background,
+        };
+        parse_hex_color(hex) == Some(rgb)
+    });
+    if let Some((token, _, _)) = exact {
+        return Some(VisualColor(token.to_string()));
+    }
+    if matches!(
+        (role, rgb),
+        (ColorRole::Foreground, (0, 0, 0)) | (ColorRole::Background, (255, 255, 255))
+    ) {
+        return None;
+    }
+    let approximation = nearest_palette_color(rgb, role);
+    diagnostics.warning(format!(
+        "{label}: {} color '{text}' approximated as {}",
+        role.name(),
+        approximation.unwrap_or("default")
+    ));
+    approximation.map(|token| VisualColor(token.to_string()))
+}
+
+fn named_palette_color(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "red" => "red",
+        "orange" => "orange",
+        "yellow" => "yellow",
+        "green" => "green",
+        "cyan" => "cyan",
+        "blue" => "blue",
+        "purple" | "magenta" => "purple",
+        "gray" | "grey" => "gray",
+        _ => return None,
+    })
+}
+
+fn named_rgb(name: &str) -> Option<(u8, u8, u8)> {
+    Some(match name {
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "navy" => (0, 0, 128),
+        "teal" => (0, 128, 128),
+        "silver" => (192, 192, 192),
+        "lime" => (0, 255, 0),
+        "aqua" => (0, 255, 255),
+        "fuchsia" => (255, 0, 255),
+        _ => return None,
+    })
+}
+
+/// Palette token with the closest hue; low-saturation colors become gray, or the default
+/// color for dark text and light backgrounds.
+fn nearest_palette_color(
+    (red, green, blue): (u8, u8, u8),
+    role: ColorRole,
+) -> Option<&'static str> {
+    let [red, green, blue] = [red, green, blue].map(|channel| f32::from(channel) / 255.0);
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (max + min) / 2.0;
+    let chroma = max - min;
+    if chroma < 0.12 {
+        return match role {
+            ColorRole::Foreground if lightness < 0.2 => None,
+            ColorRole::Background if lightness > 0.9 => None,
+            _ => Some("gray"),
+        };
+    }
+    let hue = if max == red {
+        60.0 * ((green - blue) / chroma).rem_euclid(6.0)
+    } else if max == green {
+        60.0 * ((blue - red) / chroma + 2.0)
+    } else {
+        60.0 * ((red - green) / chroma + 4.0)
+    };
+    Some(match hue {
+        hue if !(15.0..330.0).contains(&hue) => "red",
+        hue if hue < 40.0 => "orange",
+        hue if hue < 70.0 => "yellow",
+        hue if hue < 165.0 => "green",
+        hue if hue < 200.0 => "cyan",
+        hue if hue < 255.0 => "blue",
+        _ => "purple",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_map_onto_the_palette_and_report_approximations() {
+        let mut diagnostics = Diagnostics::default();
+        let import = |text: &str, role: ColorRole, diagnostics: &mut Diagnostics| {
+            import_color(text, role, "rule 'Errors'", diagnostics).map(|color| color.0)
+        };
+
+        assert_eq!(
+            import("#b91c1c", ColorRole::Foreground, &mut diagnostics).as_deref(),
+            Some("red")
+        );
+        assert_eq!(
+            import("Magenta", ColorRole::Foreground, &mut diagnostics).as_deref(),
+            Some("purple")
+        );
+        assert_eq!(
+            import("#000000", ColorRole::Foreground, &mut diagnostics),
+            None
+        );
+        assert_eq!(
+            import("#ffffffff", ColorRole::Background, &mut diagnostics),
+            None
+        );
+        assert!(diagnostics.0.is_empty());
+
+        assert_eq!(
+            import("#ff8800", ColorRole::Foreground, &mut diagnostics).as_deref(),
+            Some("orange")
+        );
+        assert_eq!(
+            import("#00ff00", ColorRole::Background, &mut diagnostics).as_deref(),
+            Some("green")
+        );
+        assert_eq!(
+            import("$error", ColorRole::Foreground, &mut diagnostics),
+            None
+        );
+        assert_eq!(
+            diagnostics
+                .0
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "rule 'Errors': foreground color '#ff8800' approximated as orange",
+                "rule 'Errors': background color '#00ff00' approximated as green",
+                "rule 'Errors': foreground color '$error' is not supported and was dropped",
+            ]
+        );
+    }
+}
@@ -1,8 +1,15 @@
-use crate::models::visual_rules::{ValidationDiagnostic, VisualRulesEnvelope};
-use crate::visual_rules::VisualRuleEvaluator;
+use crate::models::visual_rules::{ValidationDiagnostic, ValidationReport, VisualRulesEnvelope};
+use crate::visual_rules::{VisualRuleEvaluator, VisualRuleProfiles};
+#[cfg(feature = "native-persistence")]
+use crate::visual_rules_migration::{VisualRulesMigration, migrate_visual_rules};
 #[cfg(feature = "native-persistence")]
 use crate::visual_rules_store::{StoreCommit, VisualRulesStore};
+#[cfg(feature = "native-persistence")]
+use log::debug;
 use std::sync::{Arc, Mutex, RwLock};
+#[cfg(feature = "native-persistence")]
+use std::time::Duration;
+use tokio::sync::watch;
 
 #[cfg(feature = "native-persistence")]
 const PERSISTED_SIZE_ERROR: &str = "visual rules configuration exceeds 256 KiB";
@@ -60,8 +67,10 @@ struct ManagerState {
 }
 
 pub struct VisualRulesManager {
-    evaluator: RwLock<Arc<VisualRuleEvaluator>>,
+    profiles: RwLock<Arc<VisualRuleProfiles>>,
     state: Mutex<ManagerState>,
+    /// Sends the revision whenever it advances.
+    revision_changed: watch::Sender<u64>,
     #[cfg(feature = "native-persistence")]
     store: Option<Arc<dyn VisualRulesStore>>,
 }
@@ -69,13 +78,14 @@ pub struct VisualRulesManager {
 impl VisualRulesManager {
     pub fn in_memory() -> Arc<Self> {
         Arc::new(Self {
-            evaluator: RwLock::new(Arc::new(VisualRuleEvaluator::default())),
+            profiles: RwLock::new(Arc::new(VisualRuleProfiles::default())),
             state: Mutex::new(ManagerState {
                 revision: 0,
                 envelope: VisualRulesEnvelope::new(Vec::new()),
                 source: None,
                 diagnostics: Vec::new(),
             }),
+            revision_changed: watch::Sender::new(0),
             #[cfg(feature = "native-persistence")]
             store: None,
         })
@@ -93,13 +103,29 @@ impl VisualRulesManager {
         })
     }
 
+    /// Evaluator of the default profile.
     pub fn snapshot(&self) -> Arc<VisualRuleEvaluator> {
-        self.evaluator
+        self.profiles().default_evaluator()
+    }
+
+    pub fn profiles(&self) -> Arc<VisualRuleProfiles> {
+        self.profiles
             .read()
             .expect("visual rules evaluator lock")
             .clone()
     }
 
+    /// Revision of the published rules, bumped whenever the evaluators are recompiled.
+    pub fn revision(&self) -> u64 {
+        self.state.lock().expect("visual rules state lock").revision
+    }
+
+    /// Receiver of every revision the manager publishes, so clients can await saves and reloads
+    /// without refetching the rules or holding a thread.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.revision_changed.subscribe()
+    }
+
     pub fn state(&self) -> VisualRulesState {
         let state = self.state.lock().expect("visual rules state lock");
         VisualRulesState {
@@ -117,13 +143,7 @@ impl VisualRulesManager {
             .validate_for_save()
             .map_err(|error| VisualRulesError::Validation(error.message))?;
         let mut state = self.state.lock().expect("visual rules state lock");
-        self.publish(
-            &mut state,
-            envelope,
-            report.evaluator_rules,
-            report.diagnostics,
-            None,
-        );
+        self.publish(&mut state, envelope, report, None);
         Ok(SaveResult {
             revision: state.revision,
             outcome: SaveOutcome::Committed,
@@ -133,65 +153,136 @@ impl VisualRulesManager {
     #[cfg(feature = "native-persistence")]
     pub fn load(&self) -> Result<VisualRulesState, VisualRulesError> {
         let mut state = self.state.lock().expect("visual rules state lock");
-        let source = self
-            .store
+        let source = self.read_source()?;
+        Ok(self.load_source(&mut state, source))
+    }
+
+    /// Reloads the store when it no longer holds the source the published rules came from, as
+    /// after a hand edit of the rules file; returns `None` when it is unchanged. The manager
+    /// records what its own saves write, so they never count as a change.
+    ///
+    /// The store is read without holding the state, and a read that a save overtook is
+    /// dropped. A missing store keeps the rules read from it, since an atomic replace can
+    /// briefly remove the file; `load` still resets them.
+    #[cfg(feature = "native-persistence")]
+    pub fn reload_if_changed(&self) -> Result<Option<VisualRulesState>, VisualRulesError> {
+        let revision = self.revision();
+        let source = self.read_source()?;
+        let mut state = self.state.lock().expect("visual rules state lock");
+        if state.revision != revision
+            || source == state.source
+            || (source.is_none() && state.source.is_some())
+        {
+            return Ok(None);
+        }
+        Ok(Some(self.load_source(&mut state, source)))
+    }
+
+    /// Checks the store for outside changes every `interval` on a background thread that
+    /// stops once the manager is dropped. The store is only read again once its fingerprint
+    /// changes, and the published rules stay when a read fails.
+    #[cfg(feature = "native-persistence")]
+    pub fn watch(self: &Arc<Self>, interval: Duration) {
+        let manager = Arc::downgrade(self);
+        std::thread::spawn(move || {
+            let mut checked = None;
+            loop {
+                std::thread::sleep(interval);
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                let fingerprint = manager.store.as_ref().expect("native store").fingerprint();
+                if fingerprint.is_some() && fingerprint == checked {
+                    continue;
+                }
+                match manager.reload_if_changed() {
+                    Ok(_) => checked = fingerprint,
+                    Err(error) => debug!("Could not check visual rules for changes: {error}"),
+                }
+            }
+        });
+    }
+
+    #[cfg(feature = "native-persistence")]
+    fn read_source(&self) -> Result<Option<Vec<u8>>, VisualRulesError> {
+        self.store
             .as_ref()
             .expect("native store")
             .read()
-            .map_err(io_error)?;
+            .map_err(io_error)
+    }
+
+    #[cfg(feature = "native-persistence")]
+    fn load_source(
+        &self,
+        state: &mut ManagerState,
+        mut source: Option<Vec<u8>>,
+    ) -> VisualRulesState {
         if source
             .as_ref()
             .is_some_and(|bytes| bytes.len() > VisualRulesEnvelope::MAX_PERSISTED_SIZE)
         {
-            state.revision += 1;
+            self.advance(state);
             state.source = source;
             state.diagnostics = vec![ValidationDiagnostic {
                 severity: crate::models::ValidationSeverity::Warning,
                 message: PERSISTED_SIZE_ERROR.to_string(),
             }];
-            return Ok(VisualRulesState {
+            return VisualRulesState {
                 revision: state.revision,
                 envelope: state.envelope.clone(),
                 diagnostics: state.diagnostics.clone(),
-            });
+            };
         }
         let (envelope, report) = match source.as_deref() {
             None | Some([]) => (VisualRulesEnvelope::new(Vec::new()), None),
-            Some(bytes) => match serde_json::from_slice::<VisualRulesEnvelope>(bytes)
-                .map_err(|error| error.to_string())
-                .and_then(|envelope| {
-                    envelope
-                        .validate_for_load()
-                        .map_err(|error| error.message)
-                        .map(|report| (envelope, report))
-                }) {
-                Ok((envelope, report)) => (envelope, Some(report)),
+            Some(bytes) => match migrate_visual_rules(bytes).and_then(|migration| {
+                migration
+                    .envelope
+                    .validate_for_load()
+                    .map_err(|error| error.message)
+                    .map(|report| (migration, report))
+            }) {
+                Ok((migration, mut report)) => {
+                    let mut diagnostics = migration.diagnostics.clone();
+                    if migration.is_upgrade() {
+                        let (commit, message) = self.commit_migration(&migration, &source);
+                        if let Some(bytes) = commit {
+                            source = Some(bytes);
+                        }
+                        diagnostics.insert(
+                            0,
+                            ValidationDiagnostic {
+                                severity: crate::models::ValidationSeverity::Warning,
+                                message,
+                            },
+                        );
+                    }
+                    diagnostics.append(&mut report.diagnostics);
+                    report.diagnostics = diagnostics;
+                    (migration.envelope, Some(report))
+                }
                 Err(message) => {
-                    state.revision += 1;
+                    self.advance(state);
                     state.source = source;
                     state.diagnostics = vec![ValidationDiagnostic {
                         severity: crate::models::ValidationSeverity::Warning,
                         message,
                     }];
-                    return Ok(VisualRulesState {
+                    return VisualRulesState {
                         revision: state.revision,
                         envelope: state.envelope.clone(),
                         diagnostics: state.diagnostics.clone(),
-                    });
+                    };
                 }
             },
         };
-        let evaluator_rules = report
-            .as_ref()
-            .map(|report| report.evaluator_rules.clone())
-            .unwrap_or_default();
-        let diagnostics = report.map(|report| report.diagnostics).unwrap_or_default();
-        self.publish(&mut state, envelope, evaluator_rules, diagnostics, source);
-        Ok(VisualRulesState {
+        self.publish(state, envelope, report.unwrap_or_default(), source);
+        VisualRulesState {
             revision: state.revision,
             envelope: state.envelope.clone(),
             diagnostics: state.diagnostics.clone(),
-        })
+        }
     }
 
     #[cfg(feature = "native-persistence")]
@@ -216,9 +307,11 @@ impl VisualRulesManager {
     fn persist(
         &self,
         base_revision: u64,
-        envelope: VisualRulesEnvelope,
+        mut envelope: VisualRulesEnvelope,
         replace: bool,
     ) -> Result<SaveResult, VisualRulesError> {
+        // Every older schema is a subset of the current one, so saves always write the latest.
+        envelope.schema_version = VisualRulesEnvelope::SCHEMA_VERSION;
         let report = envelope
             .validate_for_save()
             .map_err(|error| VisualRulesError::Validation(error.message))?;
@@ -243,13 +336,7 @@ impl VisualRulesManager {
                     io_error(error)
                 }
             })?;
-        self.publish(
-            &mut state,
-            envelope,
-            report.evaluator_rules,
-            report.diagnostics,
-            Some(bytes),
-        );
+        self.publish(&mut state, envelope, report, Some(bytes));
         Ok(SaveResult {
             revision: state.revision,
             outcome: match commit {
@@ -261,20 +348,56 @@ impl VisualRulesManager {
         })
     }
 
+    /// Writes an upgraded envelope over the source it was migrated from; the store backs the
+    /// original file up first. Returns the written bytes and the diagnostic reporting the
+    /// upgrade, which keeps the migration in memory when the write fails.
+    #[cfg(feature = "native-persistence")]
+    fn commit_migration(
+        &self,
+        migration: &VisualRulesMigration,
+        source: &Option<Vec<u8>>,
+    ) -> (Option<Vec<u8>>, String) {
+        let upgrade = format!(
+            "migrated from schemaVersion {} to {}",
+            migration.from_version, migration.envelope.schema_version
+        );
+        let written = serde_json::to_vec(&migration.envelope)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| {
+                self.store
+                    .as_ref()
+                    .expect("native store")
+                    .compare_and_commit(source.as_deref(), &bytes, true)
+                    .map(|_| bytes)
+                    .map_err(|error| error.to_string())
+            });
+        match written {
+            Ok(bytes) => (
+                Some(bytes),
+                format!("{upgrade}; the original file was kept as a backup"),
+            ),
+            Err(error) => (None, format!("{upgrade} in memory only: {error}")),
+        }
+    }
+
     fn publish(
         &self,
         state: &mut ManagerState,
         envelope: VisualRulesEnvelope,
-        rules: Vec<crate::models::VisualRule>,
-        diagnostics: Vec<ValidationDiagnostic>,
+        report: ValidationReport,
         source: Option<Vec<u8>>,
     ) {
-        *self.evaluator.write().expect("visual rules evaluator lock") =
-            Arc::new(VisualRuleEvaluator::compile(&rules));
+        *self.profiles.write().expect("visual rules evaluator lock") =
+            Arc::new(VisualRuleProfiles::compile(&report));
         state.envelope = envelope;
-        state.diagnostics = diagnostics;
+        state.diagnostics = report.diagnostics;
         state.source = source;
+        self.advance(state);
+    }
+
+    fn advance(&self, state: &mut ManagerState) {
         state.revision += 1;
+        self.revision_changed.send_replace(state.revision);
     }
 }
 
@@ -287,7 +410,8 @@ fn io_error(error: std::io::Error) -> VisualRulesError {
 mod tests {
     use super::*;
     use crate::{
-        LineStyleIntent, ManagedVisualRule, VisualColor, VisualMatcher, VisualRulesEnvelope,
+        LineStyleIntent, ManagedVisualRule, VisualColor, VisualMatcher, VisualRuleScope,
+        VisualRulesEnvelope,
     };
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::mpsc::{self, Receiver, Sender};
@@ -353,7 +477,9 @@ mod tests {
             style: LineStyleIntent {
                 foreground: Some(VisualColor("red".to_string())),
                 background: Some(VisualColor("default".to_string())),
+                ..LineStyleIntent::default()
             },
+            scope: VisualRuleScope::Line,
         }])
     }
 
@@ -416,4 +542,51 @@ mod tests {
         assert!(manager.snapshot().evaluate("WARN").is_some());
         assert_eq!(manager.snapshot().evaluate("ERROR"), None);
     }
+
+    #[test]
+    fn reload_reads_without_holding_state_and_drops_reads_a_save_overtook() {
+        let (read_captured_tx, read_captured_rx) = mpsc::channel();
+        let (release_read_tx, release_read_rx) = mpsc::channel();
+        let (commit_finished_tx, _commit_finished_rx) = mpsc::channel();
+        let store = Arc::new(PausingReadStore {
+            bytes: Mutex::new(Some(
+                serde_json::to_vec(&envelope("ERROR")).expect("serialize initial envelope"),
+            )),
+            pause_next_read: AtomicBool::new(false),
+            read_captured: read_captured_tx,
+            release_read: Mutex::new(release_read_rx),
+            commit_finished: commit_finished_tx,
+        });
+        let manager = VisualRulesManager::with_store(store.clone());
+        let initial = manager.load().expect("initial load");
+        let mut revisions = manager.subscribe();
+        *store.bytes.lock().expect("store lock") =
+            Some(serde_json::to_vec(&envelope("INFO")).expect("serialize hand edit"));
+
+        store.pause_next_read.store(true, Ordering::SeqCst);
+        let reloading_manager = manager.clone();
+        let reload_thread = std::thread::spawn(move || reloading_manager.reload_if_changed());
+        read_captured_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("reload did not read the store");
+        assert!(manager.state.try_lock().is_ok());
+
+        *store.bytes.lock().expect("store lock") =
+            Some(serde_json::to_vec(&envelope("ERROR")).expect("serialize initial envelope"));
+        let saved = manager
+            .replace(initial.revision, envelope("WARN"))
+            .expect("save while the reload reads");
+        assert!(revisions.has_changed().expect("manager alive"));
+        assert_eq!(*revisions.borrow_and_update(), saved.revision);
+        release_read_tx.send(()).expect("release captured read");
+
+        let reloaded = reload_thread
+            .join()
+            .expect("reload thread")
+            .expect("reload");
+        assert!(reloaded.is_none());
+        assert_eq!(manager.revision(), saved.revision);
+        assert!(manager.snapshot().evaluate("WARN").is_some());
+        assert_eq!(manager.snapshot().evaluate("INFO"), None);
+    }
 }
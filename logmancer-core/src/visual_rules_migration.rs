@@ -0,0 +1,252 @@
+//! Upgrades of persisted visual rules envelopes written with an older `schemaVersion`.
+//!
+//! Migrations work on the decoded JSON so a step can rename or reshape fields the current
+//! models no longer accept. Each step upgrades by one version; older files run every step
+//! from their version up to [`VisualRulesEnvelope::SCHEMA_VERSION`].
+
+use serde_json::{Map, Value};
+
+use crate::models::visual_rules::{ValidationDiagnostic, ValidationSeverity, VisualRulesEnvelope};
+
+type MigrationFn =
+    fn(&mut Map<String, Value>, &mut Vec<ValidationDiagnostic>) -> Result<(), String>;
+
+/// Upgrade of an envelope from schema version `from` to `from + 1`.
+struct MigrationStep {
+    from: u32,
+    apply: MigrationFn,
+}
+
+const MIGRATIONS: &[MigrationStep] = &[
+    MigrationStep {
+        from: 1,
+        apply: migrate_1_to_2,
+    },
+    MigrationStep {
+        from: 2,
+        apply: migrate_2_to_3,
+    },
+    MigrationStep {
+        from: 3,
+        apply: migrate_3_to_4,
+    },
+    MigrationStep {
+        from: 4,
+        apply: migrate_4_to_5,
+    },
+];
+
+/// Envelope decoded from persisted bytes, upgraded to the current schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisualRulesMigration {
+    /// Version the bytes were written with.
+    pub from_version: u32,
+    pub envelope: VisualRulesEnvelope,
+    /// Entries the upgrade had to drop.
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+impl VisualRulesMigration {
+    pub fn is_upgrade(&self) -> bool {
+        self.from_version != self.envelope.schema_version
+    }
+}
+
+/// Decodes a persisted envelope, running the migrations from its version to the current one.
+///
+/// Unsupported versions decode unchanged and are left to validation to reject.
+pub fn migrate_visual_rules(bytes: &[u8]) -> Result<VisualRulesMigration, String> {
+    let mut value: Value = serde_json::from_slice(bytes).map_err(|error| error.to_string())?;
+    let from_version = value
+        .get("schemaVersion")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok());
+    let mut diagnostics = Vec::new();
+    if let Some(version) = from_version
+        && (1..VisualRulesEnvelope::SCHEMA_VERSION).contains(&version)
+        && let Value::Object(envelope) = &mut value
+    {
+        for step in MIGRATIONS.iter().filter(|step| step.from >= version) {
+            (step.apply)(envelope, &mut diagnostics)?;
+            envelope.insert("schemaVersion".to_string(), Value::from(step.from + 1));
+        }
+    }
+    let envelope: VisualRulesEnvelope =
+        serde_json::from_value(value).map_err(|error| error.to_string())?;
+    Ok(VisualRulesMigration {
+        from_version: from_version.unwrap_or(envelope.schema_version),
+        envelope,
+        diagnostics,
+    })
+}
+
+/// Version 1 had no text attributes or gutter markers; its readers ignored them.
+fn migrate_1_to_2(
+    envelope: &mut Map<String, Value>,
+    diagnostics: &mut Vec<ValidationDiagnostic>,
+) -> Result<(), String> {
+    for (index, rule) in rules_mut(envelope).enumerate() {
+        let Some(Value::Object(style)) = rule.get_mut("style") else {
+            continue;
+        };
+        let attributes = style.remove("attributes").is_some();
+        let gutter = style.remove("gutter").is_some();
+        if attributes || gutter {
+            diagnostics.push(dropped(format!(
+                "rule {}: text attributes and gutter markers require schemaVersion 2",
+                index + 1
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Version 3 introduced profiles, so older files cannot hold any.
+fn migrate_2_to_3(
+    envelope: &mut Map<String, Value>,
+    _diagnostics: &mut Vec<ValidationDiagnostic>,
+) -> Result<(), String> {
+    if envelope
+        .get("profiles")
+        .and_then(Value::as_array)
+        .is_some_and(|profiles| !profiles.is_empty())
+    {
+        return Err("profiles require schemaVersion 3".to_string());
+    }
+    Ok(())
+}
+
+/// Version 3 readers rejected compound matchers, so rules carrying one are dropped.
+fn migrate_3_to_4(
+    envelope: &mut Map<String, Value>,
+    diagnostics: &mut Vec<ValidationDiagnostic>,
+) -> Result<(), String> {
+    drop_compound_rules(envelope, "", diagnostics);
+    if let Some(Value::Array(profiles)) = envelope.get_mut("profiles") {
+        for profile in profiles.iter_mut().filter_map(Value::as_object_mut) {
+            let prefix = format!(
+                "profile '{}' ",
+                profile.get("name").and_then(Value::as_str).unwrap_or("")
+            );
+            drop_compound_rules(profile, &prefix, diagnostics);
+        }
+    }
+    Ok(())
+}
+
+/// Version 5 introduced the palette; older readers ignored the key.
+fn migrate_4_to_5(
+    envelope: &mut Map<String, Value>,
+    diagnostics: &mut Vec<ValidationDiagnostic>,
+) -> Result<(), String> {
+    if envelope
+        .remove("palette")
+        .is_some_and(|palette| palette.as_array().is_none_or(|colors| !colors.is_empty()))
+    {
+        diagnostics.push(dropped(
+            "palette colors require schemaVersion 5".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn drop_compound_rules(
+    owner: &mut Map<String, Value>,
+    prefix: &str,
+    diagnostics: &mut Vec<ValidationDiagnostic>,
+) {
+    let Some(Value::Array(rules)) = owner.get_mut("rules") else {
+        return;
+    };
+    let mut index = 0;
+    rules.retain(|rule| {
+        index += 1;
+        let compound = rule
+            .get("matcher")
+            .and_then(Value::as_object)
+            .is_some_and(|matcher| {
+                matcher
+                    .keys()
+                    .any(|variant| variant != "Text" && variant != "Regex")
+            });
+        if compound {
+            diagnostics.push(dropped(format!(
+                "{prefix}rule {index}: compound conditions require schemaVersion 4"
+            )));
+        }
+        !compound
+    });
+}
+
+fn rules_mut(envelope: &mut Map<String, Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    envelope
+        .get_mut("rules")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+fn dropped(message: String) -> ValidationDiagnostic {
+    ValidationDiagnostic {
+        severity: ValidationSeverity::Warning,
+        message: format!("{message}; dropped while migrating"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::migrate_visual_rules;
+    use crate::models::visual_rules::{VisualMatcher, VisualRulesEnvelope};
+
+    #[test]
+    fn current_envelopes_decode_unchanged_and_future_ones_are_left_to_validation() {
+        let current = br#"{"schemaVersion":5,"rules":[{"name":null,"enabled":true,"matcher":{"Not":{"Text":"ok"}},"caseSensitive":false,"style":{"foreground":"red","background":null}}]}"#;
+        let migration = migrate_visual_rules(current).unwrap();
+        assert!(!migration.is_upgrade());
+        assert!(migration.diagnostics.is_empty());
+        assert!(matches!(
+            migration.envelope.rules[0].matcher,
+            VisualMatcher::Not(_)
+        ));
+
+        let future = migrate_visual_rules(br#"{"schemaVersion":9,"rules":[]}"#).unwrap();
+        assert_eq!(future.envelope.schema_version, 9);
+        assert!(!future.is_upgrade());
+    }
+
+    #[test]
+    fn steps_run_in_order_from_the_stored_version() {
+        let migration = migrate_visual_rules(
+            br#"{"schemaVersion":2,"rules":[{"name":"a","enabled":true,"matcher":{"Text":"a"},"caseSensitive":false,"style":{"foreground":null,"background":null,"attributes":{"bold":true}}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(migration.from_version, 2);
+        assert_eq!(
+            migration.envelope.schema_version,
+            VisualRulesEnvelope::SCHEMA_VERSION
+        );
+        assert!(migration.envelope.rules[0].style.attributes.bold);
+        assert!(migration.diagnostics.is_empty());
+
+        assert_eq!(
+            migrate_visual_rules(
+                br#"{"schemaVersion":2,"rules":[],"profiles":[{"name":"p","rules":[]}]}"#
+            )
+            .unwrap_err(),
+            "profiles require schemaVersion 3"
+        );
+    }
+
+    #[test]
+    fn palettes_in_files_older_than_version_5_are_dropped() {
+        let migration =
+            migrate_visual_rules(br#"{"schemaVersion":4,"rules":[],"palette":[{"name":"teal"}]}"#)
+                .unwrap();
+        assert!(migration.envelope.palette.is_empty());
+        assert_eq!(
+            migration.diagnostics[0].message,
+            "palette colors require schemaVersion 5; dropped while migrating"
+        );
+    }
+}
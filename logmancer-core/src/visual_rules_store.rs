@@ -28,6 +28,11 @@ impl StoreCommit {
 
 pub trait VisualRulesStore: Send + Sync {
     fn read(&self) -> io::Result<Option<Vec<u8>>>;
+    /// Modification time and length of the stored source, so watchers can skip reading a
+    /// store that has not changed; `None` when unknown or missing.
+    fn fingerprint(&self) -> Option<(SystemTime, u64)> {
+        None
+    }
     fn save_new(&self, bytes: &[u8]) -> io::Result<StoreCommit>;
     fn replace(&self, bytes: &[u8]) -> io::Result<StoreCommit>;
     fn compare_and_commit(
@@ -98,6 +103,11 @@ impl VisualRulesStore for NativeVisualRulesStore {
         Ok(Some(bytes))
     }
 
+    fn fingerprint(&self) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(&self.path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
     fn save_new(&self, bytes: &[u8]) -> io::Result<StoreCommit> {
         self.replacer.save_new(&self.path, bytes)
     }
@@ -1,3 +1,8 @@
+use crate::file_ops::read::FileReadOps;
+use crate::file_ops::write::{FileWriteOps, SEARCH_BATCH_MAX_LINES};
+use crate::models::log_file::LogFile;
+use crate::models::search::SearchMatch;
+use crate::search_matcher::SearchMatcher;
 use std::time::Duration;
 
 pub fn wait(millis: u64) {
@@ -7,3 +12,166 @@ pub fn wait(millis: u64) {
         std::thread::sleep(wait_duration);
     }
 }
+
+/// Upper bound for the threads one scan may use; the file is memory mapped, so more
+/// threads mostly contend on I/O.
+const SEARCH_MAX_THREADS: usize = 8;
+
+/// Consecutive batches scanned by each thread of a wave before the results are merged.
+const SEARCH_BATCHES_PER_THREAD: usize = 8;
+
+fn search_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+        .clamp(1, SEARCH_MAX_THREADS)
+}
+
+/// Scans `start..end` in waves of disjoint ranges, one per thread, handing each wave to
+/// `merge` in file order until it reports a stale session. Waves advance from `start`, so
+/// callers keep the origin-first circular order by scanning `origin..end` before `0..origin`.
+/// The first wave is a single batch and later waves double up to one chunk per thread, so
+/// matches near `start` are published before the wider scan starts.
+pub fn scan_search_range<F>(
+    write_ops: &mut FileWriteOps,
+    matcher: &SearchMatcher,
+    start: usize,
+    end: usize,
+    finalize_last_batch: bool,
+    merge: &mut F,
+) where
+    F: FnMut(&mut FileWriteOps, Vec<SearchMatch>, bool) -> bool,
+{
+    let chunk_lines = SEARCH_BATCH_MAX_LINES * SEARCH_BATCHES_PER_THREAD;
+    let max_wave_lines = chunk_lines * search_threads();
+    let mut wave_lines = SEARCH_BATCH_MAX_LINES;
+    let mut cursor = start;
+    while cursor < end {
+        let wave_end = usize::min(cursor + wave_lines, end);
+        let batch = {
+            let log_file = write_ops.log_file();
+            let file_lock = log_file.read().unwrap();
+            compute_search_wave(&file_lock, matcher, cursor, wave_end, chunk_lines)
+        };
+        let mark_ready = finalize_last_batch && wave_end == end;
+        if !merge(write_ops, batch, mark_ready) {
+            break;
+        }
+        cursor = wave_end;
+        wave_lines = usize::min(wave_lines * 2, max_wave_lines);
+    }
+}
+
+/// Splits `start..end` into `chunk_lines` ranges scanned concurrently and concatenates the
+/// results in range order, so the wave is sorted like a single-threaded scan.
+fn compute_search_wave(
+    log_file: &LogFile,
+    matcher: &SearchMatcher,
+    start: usize,
+    end: usize,
+    chunk_lines: usize,
+) -> Vec<SearchMatch> {
+    if end - start <= chunk_lines {
+        return FileReadOps::compute_search_batch(log_file, matcher, start, end);
+    }
+
+    std::thread::scope(|scope| {
+        let workers = (start..end)
+            .step_by(chunk_lines)
+            .map(|chunk_start| {
+                let chunk_end = usize::min(chunk_start + chunk_lines, end);
+                scope.spawn(move || {
+                    FileReadOps::compute_search_batch(log_file, matcher, chunk_start, chunk_end)
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("search scan thread panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_search_wave, scan_search_range};
+    use crate::file_ops::read::FileReadOps;
+    use crate::file_ops::write::FileWriteOps;
+    use crate::models::log_file::LogFile;
+    use crate::search_matcher::SearchMatcher;
+    use std::fs::File;
+    use std::io::Write;
+    use std::sync::{Arc, RwLock};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn indexed_write_ops(prefix: &str, lines: usize) -> FileWriteOps {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("logmancer-{prefix}-{unique}.log"));
+        let mut file = File::create(&path).unwrap();
+        for line in 0..lines {
+            if line % 7 == 0 {
+                writeln!(file, "line {line} timeout timeout").unwrap();
+            } else {
+                writeln!(file, "line {line} ok").unwrap();
+            }
+        }
+        drop(file);
+
+        let log_file = RwLock::new(LogFile::new(path.to_string_lossy().into_owned()).unwrap());
+        let mut write_ops = FileWriteOps::new(Arc::new(log_file));
+        while !write_ops.index_lines().unwrap() {}
+        std::fs::remove_file(path).unwrap();
+        write_ops
+    }
+
+    #[test]
+    fn parallel_wave_matches_single_threaded_scan() {
+        let write_ops = indexed_write_ops("search-wave", 1000);
+        let log_file = write_ops.log_file();
+        let log_file = log_file.read().unwrap();
+        let matcher = SearchMatcher::new("timeout").unwrap();
+
+        let sequential = FileReadOps::compute_search_batch(&log_file, &matcher, 3, 997);
+        let parallel = compute_search_wave(&log_file, &matcher, 3, 997, 64);
+
+        assert_eq!(parallel, sequential);
+        assert_eq!(
+            parallel.len(),
+            2 * (3..997).filter(|line| line % 7 == 0).count()
+        );
+    }
+
+    #[test]
+    fn scan_merges_waves_in_range_order_and_finalizes_last() {
+        let mut write_ops = indexed_write_ops("search-wave-order", 60_000);
+        let matcher = SearchMatcher::new("timeout").unwrap();
+        let mut merged = Vec::new();
+        let mut merge = |_: &mut FileWriteOps, batch: Vec<_>, ready: bool| {
+            merged.push((batch, ready));
+            true
+        };
+
+        scan_search_range(&mut write_ops, &matcher, 45_000, 60_000, false, &mut merge);
+        scan_search_range(&mut write_ops, &matcher, 0, 45_000, true, &mut merge);
+
+        let lines = merged
+            .iter()
+            .flat_map(|(batch, _)| batch.iter().map(|found| found.line_index))
+            .collect::<Vec<_>>();
+        let origin_first = (45_000..60_000)
+            .chain(0..45_000)
+            .filter(|line| line % 7 == 0)
+            .flat_map(|line| [line, line])
+            .collect::<Vec<_>>();
+        assert_eq!(lines, origin_first);
+        assert_eq!(
+            merged.iter().filter(|(_, ready)| *ready).count(),
+            1,
+            "only the last wave marks the session ready"
+        );
+        assert!(merged.last().unwrap().1);
+    }
+}
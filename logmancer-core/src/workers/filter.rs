@@ -1,16 +1,20 @@
 use crate::file_ops::write::FileWriteOps;
+use crate::models::log_file::LineFilter;
 use crate::workers::common::wait;
 use crossbeam_channel::{Receiver, select};
 use std::time::Duration;
 
-pub fn spawn_filter_worker(mut write_ops: FileWriteOps, filter_receiver: Receiver<Option<String>>) {
+pub fn spawn_filter_worker(
+    mut write_ops: FileWriteOps,
+    filter_receiver: Receiver<Option<LineFilter>>,
+) {
     std::thread::spawn(move || {
         loop {
             select! {
                 recv(filter_receiver) -> msg => {
                     match msg {
-                        Ok(pattern) => {
-                            write_ops.filter(pattern).unwrap();
+                        Ok(line_filter) => {
+                            write_ops.filter(line_filter).unwrap();
                             loop {
                                 match write_ops.index_filter() {
                                     Ok(end_reached) => {
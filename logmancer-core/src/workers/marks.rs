@@ -0,0 +1,59 @@
+use crate::file_ops::write::FileWriteOps;
+use crate::models::search::SearchScan;
+use crate::workers::common::scan_search_range;
+use crossbeam_channel::{Receiver, select};
+use std::time::Duration;
+
+pub enum MarkCommand {
+    Start {
+        scan: SearchScan,
+    },
+    /// Scans lines indexed since each mark became ready.
+    Extend,
+}
+
+pub fn spawn_mark_worker(mut write_ops: FileWriteOps, mark_receiver: Receiver<MarkCommand>) {
+    std::thread::spawn(move || {
+        loop {
+            select! {
+                recv(mark_receiver) -> msg => {
+                    match msg {
+                        Ok(MarkCommand::Start { scan }) => {
+                            scan_mark(&mut write_ops, &scan);
+                            // Lines indexed during the first scan are picked up right away.
+                            extend_marks(&mut write_ops);
+                        }
+                        Ok(MarkCommand::Extend) => extend_marks(&mut write_ops),
+                        Err(_) => break,
+                    }
+                }
+                default(Duration::from_secs(5)) => {}
+            }
+        }
+    });
+}
+
+fn scan_mark(write_ops: &mut FileWriteOps, scan: &SearchScan) {
+    if scan.start_line >= scan.end_line {
+        write_ops.merge_mark_batch(scan.id, Vec::new(), true);
+        return;
+    }
+    let mut merge = |ops: &mut FileWriteOps, batch, mark_ready| {
+        ops.merge_mark_batch(scan.id, batch, mark_ready)
+    };
+    scan_search_range(
+        write_ops,
+        &scan.matcher,
+        scan.start_line,
+        scan.end_line,
+        true,
+        &mut merge,
+    );
+}
+
+/// Scans newly indexed lines into every ready mark.
+fn extend_marks(write_ops: &mut FileWriteOps) {
+    for scan in write_ops.extend_marks() {
+        scan_mark(write_ops, &scan);
+    }
+}
@@ -1,8 +1,12 @@
 mod common;
 mod filter;
+mod marks;
 mod reload;
+mod rule_counts;
 mod search;
 
 pub use filter::spawn_filter_worker;
+pub use marks::{MarkCommand, spawn_mark_worker};
 pub use reload::spawn_reload_worker;
+pub use rule_counts::{RuleCountCommand, spawn_rule_count_worker};
 pub use search::{SearchCommand, spawn_search_worker};
@@ -1,12 +1,19 @@
 use crate::file_ops::write::FileWriteOps;
+use crate::models::log_file::LineFilter;
 use crate::workers::common::wait;
+use crate::workers::{MarkCommand, RuleCountCommand, SearchCommand};
 use crossbeam_channel::{Receiver, Sender, select};
 use std::time::Duration;
 
+/// Indexes appended lines on reload and lets the filter, search, mark and rule count workers
+/// extend their sessions over them.
 pub fn spawn_reload_worker(
     mut write_ops: FileWriteOps,
     reload_receiver: Receiver<()>,
-    filter_sender: Sender<Option<String>>,
+    filter_sender: Sender<Option<LineFilter>>,
+    search_sender: Sender<SearchCommand>,
+    mark_sender: Sender<MarkCommand>,
+    rule_count_sender: Sender<RuleCountCommand>,
 ) {
     std::thread::spawn(move || {
         loop {
@@ -28,6 +35,9 @@ pub fn spawn_reload_worker(
                                     }
                                 }
                             }
+                            search_sender.send(SearchCommand::Extend).unwrap();
+                            mark_sender.send(MarkCommand::Extend).unwrap();
+                            rule_count_sender.send(RuleCountCommand::Extend).unwrap();
                         }
                         Err(error) => {
                             panic!("Error reloading file: {error}")
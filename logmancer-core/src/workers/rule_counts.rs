@@ -0,0 +1,83 @@
+use crate::file_ops::read::FileReadOps;
+use crate::file_ops::write::{FileWriteOps, SEARCH_BATCH_MAX_LINES};
+use crate::models::quote_checkpoints::QuoteCheckpoints;
+use crate::models::rule_counts::RuleCountScan;
+use crossbeam_channel::{Receiver, select};
+use std::time::Duration;
+
+pub enum RuleCountCommand {
+    Start {
+        scan: RuleCountScan,
+    },
+    /// Scans lines indexed since the session became ready.
+    Extend,
+}
+
+pub fn spawn_rule_count_worker(
+    mut write_ops: FileWriteOps,
+    rule_count_receiver: Receiver<RuleCountCommand>,
+) {
+    std::thread::spawn(move || {
+        // Where delimited records begin, kept while a session extends over a growing file.
+        let mut quote_checkpoints = QuoteCheckpoints::default();
+        loop {
+            select! {
+                recv(rule_count_receiver) -> msg => {
+                    match msg {
+                        Ok(RuleCountCommand::Start { scan }) => {
+                            quote_checkpoints.clear();
+                            scan_rule_counts(&mut write_ops, &scan, &mut quote_checkpoints);
+                            // Lines indexed during the first scan are picked up right away.
+                            extend_rule_counts(&mut write_ops, &mut quote_checkpoints);
+                        }
+                        Ok(RuleCountCommand::Extend) => {
+                            extend_rule_counts(&mut write_ops, &mut quote_checkpoints)
+                        }
+                        Err(_) => break,
+                    }
+                }
+                default(Duration::from_secs(5)) => {}
+            }
+        }
+    });
+}
+
+/// Scans the range batch by batch, stopping as soon as a newer generation replaced the
+/// session, such as after the visual rules were published again.
+fn scan_rule_counts(
+    write_ops: &mut FileWriteOps,
+    scan: &RuleCountScan,
+    quote_checkpoints: &mut QuoteCheckpoints,
+) {
+    if scan.start_line >= scan.end_line {
+        write_ops.merge_rule_count_batch(scan.generation, Vec::new(), true);
+        return;
+    }
+    let mut cursor = scan.start_line;
+    while cursor < scan.end_line {
+        let batch_end = usize::min(cursor + SEARCH_BATCH_MAX_LINES, scan.end_line);
+        let batch = {
+            let log_file = write_ops.log_file();
+            let file_lock = log_file.read().unwrap();
+            FileReadOps::compute_rule_count_batch(
+                &file_lock,
+                &scan.evaluator,
+                scan.format.as_deref(),
+                quote_checkpoints,
+                cursor,
+                batch_end,
+            )
+        };
+        let mark_ready = batch_end == scan.end_line;
+        if !write_ops.merge_rule_count_batch(scan.generation, batch, mark_ready) {
+            break;
+        }
+        cursor = batch_end;
+    }
+}
+
+fn extend_rule_counts(write_ops: &mut FileWriteOps, quote_checkpoints: &mut QuoteCheckpoints) {
+    if let Some(scan) = write_ops.extend_rule_counts() {
+        scan_rule_counts(write_ops, &scan, quote_checkpoints);
+    }
+}
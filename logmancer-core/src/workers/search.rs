@@ -1,16 +1,16 @@
-use crate::file_ops::read::FileReadOps;
 use crate::file_ops::write::FileWriteOps;
-use crate::workers::common::wait;
+use crate::models::search::SearchScan;
+use crate::workers::common::scan_search_range;
 use crossbeam_channel::{Receiver, select};
 use std::time::Duration;
 
 pub enum SearchCommand {
     Start {
-        generation: u64,
-        query: String,
+        scan: SearchScan,
         origin_line: usize,
-        indexed_lines: usize,
     },
+    /// Scans lines indexed since the active session became ready.
+    Extend,
 }
 
 pub fn spawn_search_worker(mut write_ops: FileWriteOps, search_receiver: Receiver<SearchCommand>) {
@@ -19,29 +19,35 @@ pub fn spawn_search_worker(mut write_ops: FileWriteOps, search_receiver: Receive
             select! {
                 recv(search_receiver) -> msg => {
                     match msg {
-                        Ok(SearchCommand::Start { generation, query, origin_line, indexed_lines }) => {
-                            if indexed_lines <= 1 {
+                        Ok(SearchCommand::Start { scan, origin_line }) => {
+                            let generation = scan.id;
+                            let total_content_lines = scan.end_line;
+                            if total_content_lines == 0 {
                                 write_ops.merge_search_batch(generation, Vec::new(), true);
                                 continue;
                             }
+                            let origin = origin_line.min(total_content_lines - 1);
 
-                            let total_content_lines = indexed_lines - 1;
-                            let origin = origin_line.min(total_content_lines.saturating_sub(1));
-
-                            process_range(
+                            let mut merge = |ops: &mut FileWriteOps, batch, mark_ready| {
+                                ops.merge_search_batch(generation, batch, mark_ready)
+                            };
+                            scan_search_range(
                                 &mut write_ops,
-                                generation,
-                                &query,
+                                &scan.matcher,
                                 origin,
                                 total_content_lines,
                                 false,
+                                &mut merge,
                             );
                             if origin > 0 {
-                                process_range(&mut write_ops, generation, &query, 0, origin, true);
+                                scan_search_range(&mut write_ops, &scan.matcher, 0, origin, true, &mut merge);
                             } else {
                                 write_ops.merge_search_batch(generation, Vec::new(), true);
                             }
+                            // Lines indexed during the first scan are picked up right away.
+                            extend_search(&mut write_ops);
                         }
+                        Ok(SearchCommand::Extend) => extend_search(&mut write_ops),
                         Err(_) => break,
                     }
                 }
@@ -51,31 +57,19 @@ pub fn spawn_search_worker(mut write_ops: FileWriteOps, search_receiver: Receive
     });
 }
 
-fn process_range(
-    write_ops: &mut FileWriteOps,
-    generation: u64,
-    query: &str,
-    start: usize,
-    end: usize,
-    finalize_last_batch: bool,
-) {
-    let mut cursor = start;
-    while cursor < end {
-        let batch_end = usize::min(cursor + crate::file_ops::write::SEARCH_BATCH_MAX_LINES, end);
-        let batch = {
-            let log_file = write_ops.log_file();
-            let file_lock = log_file.read().unwrap();
-            match FileReadOps::compute_search_batch(&file_lock, query, cursor, batch_end) {
-                Ok(batch) => batch,
-                Err(error) => panic!("Error indexing search batch: {error}"),
-            }
+/// Scans newly indexed lines into the ready session until it has caught up with the index.
+fn extend_search(write_ops: &mut FileWriteOps) {
+    while let Some(scan) = write_ops.extend_search() {
+        let mut merge = |ops: &mut FileWriteOps, batch, mark_ready| {
+            ops.merge_search_batch(scan.id, batch, mark_ready)
         };
-        let mark_ready = finalize_last_batch && batch_end == end;
-        let merged = write_ops.merge_search_batch(generation, batch, mark_ready);
-        if !merged {
-            break;
-        }
-        cursor = batch_end;
-        wait(1);
+        scan_search_range(
+            write_ops,
+            &scan.matcher,
+            scan.start_line,
+            scan.end_line,
+            true,
+            &mut merge,
+        );
     }
 }
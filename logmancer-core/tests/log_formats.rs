@@ -0,0 +1,122 @@
+#![cfg(feature = "native-persistence")]
+
+use logmancer_core::{
+    LogFormatDefinition, LogFormatKind, LogFormatManager, LogFormatsEnvelope,
+    NativeVisualRulesStore, SaveOutcome, VisualRulesError,
+};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_config_path(name: &str) -> std::path::PathBuf {
+    let suffix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock")
+        .as_nanos();
+    std::env::temp_dir().join(format!("logmancer-{name}-{suffix}.json"))
+}
+
+fn native_manager(path: &std::path::Path) -> Arc<LogFormatManager> {
+    LogFormatManager::with_store(Arc::new(NativeVisualRulesStore::new(path.to_path_buf())))
+}
+
+fn access_format(pattern: &str) -> LogFormatDefinition {
+    LogFormatDefinition {
+        name: "access".to_string(),
+        kind: LogFormatKind::Regex,
+        pattern: pattern.to_string(),
+        timestamp_format: None,
+        time_zone: None,
+    }
+}
+
+#[test]
+fn custom_formats_persist_next_to_the_builtins_with_revision_and_source_checks() {
+    let path = temp_config_path("log-formats");
+    let manager = native_manager(&path);
+    let loaded = manager.load().expect("missing config loads safely");
+    assert!(loaded.envelope.formats.is_empty());
+    assert_eq!(
+        manager.formats().names(),
+        [
+            "log4j",
+            "python",
+            "go",
+            "syslog",
+            "access-log",
+            "json",
+            "logfmt",
+            "csv",
+            "tsv"
+        ]
+    );
+
+    let invalid = manager.save(
+        loaded.revision,
+        LogFormatsEnvelope::new(vec![access_format("(.*)")]),
+    );
+    assert_eq!(
+        invalid.expect_err("no named captures"),
+        VisualRulesError::Validation("log format 'access': pattern has no named captures".into())
+    );
+    let saved = manager
+        .save(
+            loaded.revision,
+            LogFormatsEnvelope::new(vec![access_format(r"^(?P<client>\S+) (?P<message>.*)$")]),
+        )
+        .expect("first save");
+    assert_eq!(saved.outcome, SaveOutcome::Committed);
+    assert_eq!(
+        manager
+            .save(loaded.revision, LogFormatsEnvelope::new(Vec::new()))
+            .expect_err("stale revision"),
+        VisualRulesError::RevisionConflict
+    );
+
+    let reopened = native_manager(&path);
+    reopened.load().expect("reload");
+    assert_eq!(
+        reopened
+            .formats()
+            .get("access")
+            .and_then(|format| format.parse("10.0.0.1 GET /"))
+            .map(|fields| fields[0].clone()),
+        Some(("client".to_string(), "10.0.0.1".to_string()))
+    );
+
+    std::fs::write(
+        &path,
+        r#"{"schemaVersion":1,"formats":[{"name":"broken","pattern":"("}]}"#,
+    )
+    .expect("hand edit");
+    let conflict = manager.save(saved.revision, LogFormatsEnvelope::new(Vec::new()));
+    assert!(conflict.expect_err("changed source").is_source_conflict());
+    let recovered = manager.load().expect("invalid entries are dropped");
+    assert_eq!(
+        manager.formats().names(),
+        [
+            "log4j",
+            "python",
+            "go",
+            "syslog",
+            "access-log",
+            "json",
+            "logfmt",
+            "csv",
+            "tsv"
+        ]
+    );
+    assert!(
+        recovered.diagnostics[0]
+            .message
+            .starts_with("log format 'broken': invalid regex")
+    );
+
+    std::fs::write(&path, "not-json").expect("corrupt config");
+    let corrupt = manager
+        .load()
+        .expect("malformed config keeps the last good formats");
+    assert_eq!(corrupt.revision, recovered.revision + 1);
+    assert!(corrupt.diagnostics[0].message.contains("expected"));
+
+    std::fs::remove_file(path).expect("remove config");
+}
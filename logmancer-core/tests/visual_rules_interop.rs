@@ -0,0 +1,371 @@
+use logmancer_core::{
+    GutterIcon, GutterMarker, LineStyleIntent, ManagedVisualRule, PaletteColor, PaletteVariant,
+    TextAttributes, ValidationSeverity, VisualColor, VisualMatcher, VisualRuleProfile,
+    VisualRuleScope, VisualRulesEnvelope, VisualRulesFormat, export_visual_rules,
+    import_visual_rules,
+};
+
+fn rule(
+    name: &str,
+    matcher: VisualMatcher,
+    scope: VisualRuleScope,
+    color: &str,
+) -> ManagedVisualRule {
+    ManagedVisualRule {
+        name: Some(name.to_string()),
+        enabled: true,
+        matcher,
+        case_sensitive: true,
+        scope,
+        style: LineStyleIntent {
+            foreground: Some(VisualColor(color.to_string())),
+            ..LineStyleIntent::default()
+        },
+    }
+}
+
+fn messages(diagnostics: &[logmancer_core::ValidationDiagnostic]) -> Vec<&str> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect()
+}
+
+#[test]
+fn klogg_highlighter_sets_import_as_default_rules_and_profiles() {
+    let content = r#"[HighlighterSetCollection]
+current_set={b2}
+sets\1\id={a1}
+sets\1\name=Access
+sets\1\highlighters\1\regexp="\\s5\\d\\d\\s"
+sets\1\highlighters\1\ignore_case=false
+sets\1\highlighters\1\match_only=true
+sets\1\highlighters\1\fore_colour=#b91c1c
+sets\1\highlighters\1\back_colour=#ffffff
+sets\1\highlighters\size=1
+sets\2\id={b2}
+sets\2\name=Application
+sets\2\highlighters\1\regexp=error
+sets\2\highlighters\1\ignore_case=true
+sets\2\highlighters\1\match_only=false
+sets\2\highlighters\1\variate_colors=true
+sets\2\highlighters\1\fore_colour=#000000
+sets\2\highlighters\1\back_colour=#ff8080
+sets\2\highlighters\2\regexp=(unclosed
+sets\2\highlighters\size=2
+sets\size=2
+version=1
+"#;
+
+    let import = import_visual_rules(VisualRulesFormat::Klogg, content).unwrap();
+
+    assert!(import.envelope.validate_for_save().is_ok());
+    let default = &import.envelope.rules;
+    assert_eq!(default.len(), 1);
+    assert_eq!(
+        default[0].matcher,
+        VisualMatcher::Regex("error".to_string())
+    );
+    assert!(!default[0].case_sensitive);
+    assert_eq!(default[0].scope, VisualRuleScope::Line);
+    assert_eq!(default[0].style.foreground, None);
+    assert_eq!(
+        default[0].style.background,
+        Some(VisualColor("red".to_string()))
+    );
+    let profile = &import.envelope.profiles[0];
+    assert_eq!(profile.name, "Access");
+    assert_eq!(
+        profile.rules[0].matcher,
+        VisualMatcher::Regex(r"\s5\d\d\s".to_string())
+    );
+    assert_eq!(profile.rules[0].scope, VisualRuleScope::Match);
+    assert_eq!(
+        messages(&import.diagnostics),
+        vec![
+            "rule 'error': color variation is not supported; every match uses the base colors",
+            "rule 'error': background color '#ff8080' approximated as red",
+            "rule '(unclosed' skipped: invalid regex",
+        ]
+    );
+    assert_eq!(import.diagnostics[2].severity, ValidationSeverity::Error);
+}
+
+#[test]
+fn glogg_filter_sets_import_as_line_rules() {
+    let content = "[FilterSet]\nfilters\\1\\regexp=WARN\nfilters\\1\\ignore_case=false\nfilters\\1\\fore_colour=#a16207\nfilters\\1\\back_colour=#ffffff\nfilters\\size=1\nversion=2\n";
+
+    let import = import_visual_rules(VisualRulesFormat::Klogg, content).unwrap();
+
+    assert_eq!(import.envelope.rules.len(), 1);
+    assert_eq!(import.envelope.rules[0].scope, VisualRuleScope::Line);
+    assert_eq!(
+        import.envelope.rules[0].style.foreground,
+        Some(VisualColor("yellow".to_string()))
+    );
+    assert!(import.diagnostics.is_empty());
+}
+
+#[test]
+fn lnav_format_and_theme_highlights_import_per_source() {
+    let content = r##"{
+        "$schema": "https://lnav.org/schemas/format-v1.schema.json",
+        "myapp_log": {
+            "title": "My App",
+            "file-pattern": "myapp\\.log",
+            "highlights": {
+                "request": { "pattern": "req-\\d+", "color": "Cyan", "underline": true },
+                "slow": { "pattern": "slow", "color": "$warning", "blink": true }
+            }
+        },
+        "ui": {
+            "theme-defs": {
+                "night": {
+                    "highlights": {
+                        "failure": { "pattern": "^.*(?:(?i)failed).*$", "style": { "background-color": "#fee2e2", "bold": true } }
+                    }
+                }
+            }
+        }
+    }"##;
+
+    let import = import_visual_rules(VisualRulesFormat::Lnav, content).unwrap();
+
+    assert!(import.envelope.validate_for_save().is_ok());
+    assert!(import.envelope.rules.is_empty());
+    let [format, theme] = import.envelope.profiles.as_slice() else {
+        panic!("expected two profiles");
+    };
+    assert_eq!(format.name, "My App");
+    assert_eq!(
+        format
+            .auto_select
+            .as_ref()
+            .and_then(|auto| auto.format.as_deref()),
+        Some("myapp_log")
+    );
+    assert_eq!(format.rules[0].scope, VisualRuleScope::Match);
+    assert!(format.rules[0].style.attributes.underline);
+    assert_eq!(format.rules[1].style.foreground, None);
+    assert_eq!(theme.name, "night theme");
+    assert_eq!(theme.auto_select, None);
+    assert_eq!(
+        theme.rules[0].matcher,
+        VisualMatcher::Regex("failed".to_string())
+    );
+    assert!(!theme.rules[0].case_sensitive);
+    assert_eq!(theme.rules[0].scope, VisualRuleScope::Line);
+    assert!(theme.rules[0].style.attributes.bold);
+    assert_eq!(
+        messages(&import.diagnostics),
+        vec![
+            "format 'myapp_log': file-pattern is not converted; the profile is selected by format",
+            "profile 'My App' rule 'slow': foreground color '$warning' is not supported and was dropped",
+            "profile 'My App' rule 'slow': blink is not supported",
+        ]
+    );
+}
+
+#[test]
+fn less_search_colors_import_as_match_and_capture_group_rules() {
+    let content =
+        "#command\nq quit\n#env\nLESS = -iR --use-color -DSyk -D1r -D2gd -DNb +/(ERROR)|(warn)\n";
+
+    let import = import_visual_rules(VisualRulesFormat::Less, content).unwrap();
+
+    assert!(import.envelope.validate_for_save().is_ok());
+    let rules = &import.envelope.rules;
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0].scope, VisualRuleScope::Groups(vec![1]));
+    assert_eq!(
+        rules[0].style.foreground,
+        Some(VisualColor("red".to_string()))
+    );
+    assert_eq!(rules[1].scope, VisualRuleScope::Groups(vec![2]));
+    assert!(rules[1].style.attributes.bold);
+    assert_eq!(rules[2].scope, VisualRuleScope::Match);
+    assert_eq!(
+        rules[2].style.foreground,
+        Some(VisualColor("yellow".to_string()))
+    );
+    assert!(rules.iter().all(|rule| rule.case_sensitive));
+    assert_eq!(
+        messages(&import.diagnostics),
+        vec![
+            "less color -DN has no visual rule equivalent",
+            "rule 'search': background color 'black' approximated as gray",
+        ]
+    );
+}
+
+#[test]
+fn exports_report_unsupported_styles_and_round_trip_through_import() {
+    let mut errors = rule(
+        "Errors",
+        VisualMatcher::Text("ERROR".to_string()),
+        VisualRuleScope::Line,
+        "red",
+    );
+    errors.style.attributes = TextAttributes {
+        italic: true,
+        ..TextAttributes::default()
+    };
+    errors.style.gutter = Some(GutterMarker {
+        icon: GutterIcon::Error,
+        color: None,
+    });
+    let mut disabled = rule(
+        "Disabled",
+        VisualMatcher::Text("DEBUG".to_string()),
+        VisualRuleScope::Line,
+        "gray",
+    );
+    disabled.enabled = false;
+    let mut users = rule(
+        "Users",
+        VisualMatcher::Regex(r"user=\w+".to_string()),
+        VisualRuleScope::Match,
+        "blue",
+    );
+    users.case_sensitive = false;
+    let mut envelope = VisualRulesEnvelope::new(vec![errors, disabled, users]);
+    envelope.profiles.push(VisualRuleProfile {
+        name: "Access logs".to_string(),
+        rules: vec![rule(
+            "Server errors",
+            VisualMatcher::Regex(r"\s5\d\d\s".to_string()),
+            VisualRuleScope::Match,
+            "orange",
+        )],
+        auto_select: None,
+    });
+
+    let klogg = export_visual_rules(VisualRulesFormat::Klogg, &envelope).unwrap();
+    assert!(
+        klogg
+            .content
+            .contains(r"sets\1\highlighters\1\regexp=ERROR")
+    );
+    assert!(
+        messages(&klogg.diagnostics).contains(&"rule 'Disabled': disabled rules are not exported")
+    );
+    assert!(
+        messages(&klogg.diagnostics).contains(&"rule 'Errors': gutter marker was not exported")
+    );
+    let reimported = import_visual_rules(VisualRulesFormat::Klogg, &klogg.content).unwrap();
+    assert_eq!(reimported.envelope.rules.len(), 2);
+    assert!(!reimported.envelope.rules[1].case_sensitive);
+    assert_eq!(reimported.envelope.rules[1].scope, VisualRuleScope::Match);
+    assert_eq!(reimported.envelope.profiles[0].name, "Access logs");
+    assert_eq!(
+        reimported.envelope.profiles[0].rules[0].style.foreground,
+        Some(VisualColor("orange".to_string()))
+    );
+    assert!(reimported.diagnostics.is_empty());
+
+    let lnav = export_visual_rules(VisualRulesFormat::Lnav, &envelope).unwrap();
+    assert!(messages(&lnav.diagnostics).contains(
+        &"lnav applies highlights per log format: rules of 'Default' were exported under 'logmancer_log'; rename it to the format they should extend"
+    ));
+    let reimported = import_visual_rules(VisualRulesFormat::Lnav, &lnav.content).unwrap();
+    let default = &reimported.envelope.profiles[0];
+    assert_eq!(default.rules[0].scope, VisualRuleScope::Line);
+    assert_eq!(
+        default.rules[0].matcher,
+        VisualMatcher::Regex("ERROR".to_string())
+    );
+    assert!(!default.rules[1].case_sensitive);
+    assert_eq!(
+        reimported.envelope.profiles[1].rules[0].name.as_deref(),
+        Some("server_errors")
+    );
+
+    let less = export_visual_rules(VisualRulesFormat::Less, &envelope).unwrap();
+    assert_eq!(
+        less.content,
+        "#env\nLESS = --use-color -D1r -D2b +/(.*(?:ERROR).*)|((?i)user=\\w+)\n"
+    );
+    assert!(
+        messages(&less.diagnostics)
+            .contains(&"less has no profiles; only the default rules were exported")
+    );
+    let reimported = import_visual_rules(VisualRulesFormat::Less, &less.content).unwrap();
+    assert_eq!(reimported.envelope.rules.len(), 2);
+    assert_eq!(
+        reimported.envelope.rules[1].style.foreground,
+        Some(VisualColor("blue".to_string()))
+    );
+}
+
+#[test]
+fn malformed_imports_are_rejected_without_an_envelope() {
+    assert!(import_visual_rules(VisualRulesFormat::Lnav, "{").is_err());
+    assert!(import_visual_rules(VisualRulesFormat::Klogg, "[General]\nfoo=bar\n").is_err());
+    assert!(import_visual_rules(VisualRulesFormat::Less, "#command\nq quit\n").is_err());
+    let oversized = " ".repeat(logmancer_core::MAX_VISUAL_RULES_IMPORT_SIZE + 1);
+    assert!(import_visual_rules(VisualRulesFormat::Lnav, &oversized).is_err());
+}
+
+#[test]
+fn compound_condition_rules_are_left_out_of_exports() {
+    let envelope = VisualRulesEnvelope::new(vec![
+        rule(
+            "Unretried",
+            VisualMatcher::All(vec![
+                VisualMatcher::Text("ERROR".to_string()),
+                VisualMatcher::Not(Box::new(VisualMatcher::Text("retry".to_string()))),
+            ]),
+            VisualRuleScope::Line,
+            "red",
+        ),
+        rule(
+            "Warnings",
+            VisualMatcher::Text("WARN".to_string()),
+            VisualRuleScope::Line,
+            "yellow",
+        ),
+    ]);
+
+    let export = export_visual_rules(VisualRulesFormat::Klogg, &envelope).unwrap();
+
+    assert!(
+        export
+            .content
+            .contains(r"sets\1\highlighters\1\regexp=WARN")
+    );
+    assert!(export.content.contains(r"sets\1\highlighters\size=1"));
+    assert_eq!(
+        messages(&export.diagnostics),
+        vec!["rule 'Unretried': compound conditions have no equivalent; rule was not exported"]
+    );
+}
+
+#[test]
+fn custom_palette_colors_export_as_their_light_variant() {
+    let mut envelope = VisualRulesEnvelope::new(vec![rule(
+        "Audit",
+        VisualMatcher::Text("AUDIT".to_string()),
+        VisualRuleScope::Match,
+        "teal",
+    )]);
+    envelope.palette = vec![PaletteColor {
+        name: "teal".to_string(),
+        light: PaletteVariant {
+            foreground: "#0f766e".to_string(),
+            background: "#ccfbf1".to_string(),
+        },
+        dark: PaletteVariant {
+            foreground: "#2dd4bf".to_string(),
+            background: "#134e4a".to_string(),
+        },
+    }];
+
+    let lnav = export_visual_rules(VisualRulesFormat::Lnav, &envelope).unwrap();
+    assert!(lnav.content.contains(r##""color": "#0f766e""##));
+
+    let less = export_visual_rules(VisualRulesFormat::Less, &envelope).unwrap();
+    assert_eq!(
+        messages(&less.diagnostics),
+        vec!["rule 'Audit': custom color 'teal' exported as the default color"]
+    );
+}
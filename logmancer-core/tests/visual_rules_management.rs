@@ -1,9 +1,11 @@
 #![cfg(feature = "native-persistence")]
 
 use logmancer_core::{
-    AtomicFileReplacer, LineStyleIntent, LogRegistry, ManagedVisualRule, NativeVisualRulesStore,
-    SaveOutcome, StoreCommit, ValidationSeverity, VisualColor, VisualMatcher, VisualRulesEnvelope,
-    VisualRulesError, VisualRulesManager, VisualRulesStore,
+    AtomicFileReplacer, FieldOperator, FieldPredicate, GutterIcon, GutterMarker, LineStyleIntent,
+    LogRegistry, MAX_VISUAL_CONDITION_DEPTH, ManagedVisualRule, NativeVisualRulesStore,
+    PaletteColor, PaletteVariant, ProfileAutoSelect, SaveOutcome, StoreCommit, TextAttributes,
+    ValidationSeverity, VisualColor, VisualMatcher, VisualRuleProfile, VisualRuleScope,
+    VisualRulesEnvelope, VisualRulesError, VisualRulesManager, VisualRulesStore,
 };
 use std::fs::File;
 use std::io::{BufRead, Write};
@@ -16,6 +18,7 @@ fn style(foreground: Option<&str>, background: Option<&str>) -> LineStyleIntent
     LineStyleIntent {
         foreground: foreground.map(|value| VisualColor(value.to_string())),
         background: background.map(|value| VisualColor(value.to_string())),
+        ..LineStyleIntent::default()
     }
 }
 
@@ -30,6 +33,7 @@ fn rule(pattern: &str) -> ManagedVisualRule {
         matcher: VisualMatcher::Text(pattern.to_string()),
         case_sensitive: false,
         style: style(Some("red"), Some("default")),
+        scope: VisualRuleScope::Line,
     }
 }
 
@@ -58,6 +62,7 @@ fn envelope_with_serialized_size(target: usize) -> VisualRulesEnvelope {
         matcher: VisualMatcher::Text(String::new()),
         case_sensitive: false,
         style: style(Some("red"), Some("default")),
+        scope: VisualRuleScope::Line,
     };
     let mut envelope = VisualRulesEnvelope::new(vec![empty_rule; 100]);
     let baseline = serde_json::to_vec(&envelope)
@@ -112,6 +117,385 @@ fn envelope_validation_enforces_limits_palette_and_enabled_conversion() {
     assert!(report.evaluator_rules.is_empty());
 }
 
+#[test]
+fn envelope_validation_checks_rule_scopes_and_keeps_line_scope_implicit() {
+    let mut groups = rule("user=(\\w+)");
+    groups.matcher = VisualMatcher::Regex("user=(\\w+)".to_string());
+    groups.scope = VisualRuleScope::Groups(vec![1]);
+    let mut text_groups = rule("user");
+    text_groups.scope = VisualRuleScope::Groups(vec![1]);
+    let mut missing_group = groups.clone();
+    missing_group.scope = VisualRuleScope::Groups(vec![2]);
+    let mut spans = rule("user");
+    spans.scope = VisualRuleScope::Match;
+
+    let report = VisualRulesEnvelope::new(vec![groups, text_groups, missing_group, spans])
+        .validate_for_load()
+        .expect("scope failures are recoverable");
+    assert_eq!(
+        report
+            .evaluator_rules
+            .iter()
+            .map(|rule| rule.scope.clone())
+            .collect::<Vec<_>>(),
+        vec![VisualRuleScope::Groups(vec![1]), VisualRuleScope::Match]
+    );
+    assert_eq!(
+        report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "rule 2: capture groups require a regex matcher",
+            "rule 3: capture group 2 does not exist"
+        ]
+    );
+
+    let line_rule = serde_json::to_string(&rule("ERROR")).expect("serialize line rule");
+    assert!(!line_rule.contains("scope"));
+    let decoded: ManagedVisualRule = serde_json::from_str(&line_rule).expect("decode line rule");
+    assert_eq!(decoded.scope, VisualRuleScope::Line);
+}
+
+#[test]
+fn envelope_validation_checks_text_attributes_and_gutter_markers() {
+    let mut flagged = rule("ERROR");
+    flagged.style.attributes = TextAttributes {
+        bold: true,
+        underline: true,
+        ..TextAttributes::default()
+    };
+    flagged.style.gutter = Some(GutterMarker {
+        icon: GutterIcon::Flag,
+        color: Some(VisualColor("orange".to_string())),
+    });
+    let mut bold_dim = rule("WARN");
+    bold_dim.style.attributes = TextAttributes {
+        bold: true,
+        dim: true,
+        ..TextAttributes::default()
+    };
+    let mut span_gutter = rule("user");
+    span_gutter.scope = VisualRuleScope::Match;
+    span_gutter.style.gutter = Some(GutterMarker {
+        icon: GutterIcon::Dot,
+        color: None,
+    });
+    let mut bad_gutter_color = flagged.clone();
+    bad_gutter_color.style.gutter = Some(GutterMarker {
+        icon: GutterIcon::Dot,
+        color: Some(VisualColor("#ff0000".to_string())),
+    });
+
+    let report = VisualRulesEnvelope::new(vec![
+        flagged.clone(),
+        bold_dim,
+        span_gutter,
+        bad_gutter_color,
+    ])
+    .validate_for_load()
+    .expect("style failures are recoverable");
+    assert_eq!(report.evaluator_rules.len(), 1);
+    assert_eq!(report.evaluator_rules[0].style, flagged.style);
+    assert_eq!(
+        report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "rule 2: bold and dim cannot be combined",
+            "rule 3: gutter markers require line scope",
+            "rule 4: unsupported palette token '#ff0000'"
+        ]
+    );
+
+    let mut version_1 = VisualRulesEnvelope::new(vec![flagged.clone(), rule("INFO")]);
+    version_1.schema_version = 1;
+    let report = version_1
+        .validate_for_load()
+        .expect("schema 1 envelopes still load");
+    assert_eq!(report.evaluator_rules.len(), 1);
+    assert_eq!(
+        report.diagnostics[0].message,
+        "rule 1: text attributes and gutter markers require schemaVersion 2"
+    );
+
+    let json = serde_json::to_string(&flagged).expect("serialize styled rule");
+    assert!(json.contains(r#""attributes":{"bold":true,"underline":true}"#));
+    assert!(json.contains(r#""gutter":{"icon":"flag","color":"orange"}"#));
+    assert!(
+        !serde_json::to_string(&rule("ERROR"))
+            .unwrap()
+            .contains("attributes")
+    );
+    let decoded: ManagedVisualRule = serde_json::from_str(&json).expect("decode styled rule");
+    assert_eq!(decoded, flagged);
+}
+
+fn profile(
+    name: &str,
+    rules: Vec<ManagedVisualRule>,
+    path_glob: Option<&str>,
+) -> VisualRuleProfile {
+    VisualRuleProfile {
+        name: name.to_string(),
+        rules,
+        auto_select: path_glob.map(|glob| ProfileAutoSelect {
+            path_glob: Some(glob.to_string()),
+            format: None,
+        }),
+    }
+}
+
+fn profile_validation_error(profiles: Vec<VisualRuleProfile>) -> String {
+    let mut envelope = VisualRulesEnvelope::new(vec![rule("ERROR")]);
+    envelope.profiles = profiles;
+    envelope.validate_for_load().unwrap_err().message
+}
+
+#[test]
+fn envelope_validation_checks_profiles_and_reports_their_rules_by_name() {
+    let mut bad_regex = rule("(");
+    bad_regex.matcher = VisualMatcher::Regex("(".to_string());
+    let mut envelope = VisualRulesEnvelope::new(vec![rule("ERROR")]);
+    envelope.profiles = vec![
+        profile("nginx", vec![rule("GET"), bad_regex], Some("access.log*")),
+        profile("jvm", vec![rule("Exception")], None),
+    ];
+
+    let report = envelope.validate_for_load().expect("profiles load");
+    assert_eq!(report.evaluator_rules.len(), 1);
+    assert_eq!(
+        report
+            .profiles
+            .iter()
+            .map(|profile| (profile.name.as_str(), profile.evaluator_rules.len()))
+            .collect::<Vec<_>>(),
+        vec![("nginx", 1), ("jvm", 1)]
+    );
+    assert_eq!(
+        report.diagnostics[0].message,
+        "profile 'nginx' rule 2: invalid regex"
+    );
+    let json = serde_json::to_string(&envelope).expect("serialize profiles");
+    assert!(json.contains(r#""autoSelect":{"pathGlob":"access.log*"}"#));
+    assert!(
+        !serde_json::to_string(&VisualRulesEnvelope::new(Vec::new()))
+            .unwrap()
+            .contains("profiles")
+    );
+
+    assert_eq!(
+        profile_validation_error(vec![profile("default", Vec::new(), None)]),
+        "profile 'default' uses the reserved default profile name"
+    );
+    assert_eq!(
+        profile_validation_error(vec![
+            profile("nginx", Vec::new(), None),
+            profile("NGINX", Vec::new(), None)
+        ]),
+        "profile 'NGINX' is defined more than once"
+    );
+    assert_eq!(
+        profile_validation_error(vec![profile("nginx", Vec::new(), Some("{access"))]),
+        "profile 'nginx' invalid path glob"
+    );
+    assert_eq!(
+        profile_validation_error(vec![VisualRuleProfile {
+            auto_select: Some(ProfileAutoSelect::default()),
+            ..profile("nginx", Vec::new(), None)
+        }]),
+        "profile 'nginx' auto-select needs a path glob or a format"
+    );
+    assert_eq!(
+        profile_validation_error(vec![profile("many", vec![rule("x"); 100], None)]),
+        "at most 100 rules may be stored"
+    );
+    assert_eq!(
+        profile_validation_error(vec![profile("many", vec![rule("x"); 51], None)]),
+        "profile 'many' at most 50 rules may be enabled"
+    );
+
+    let mut version_2 = envelope.clone();
+    version_2.schema_version = 2;
+    assert_eq!(
+        version_2.validate_for_load().unwrap_err().message,
+        "profiles require schemaVersion 3"
+    );
+}
+
+#[test]
+fn envelope_validation_checks_compound_conditions() {
+    let text = |pattern: &str| VisualMatcher::Text(pattern.to_string());
+    let with_matcher = |matcher: VisualMatcher| ManagedVisualRule {
+        matcher,
+        ..rule("unused")
+    };
+    let unretried = with_matcher(VisualMatcher::All(vec![
+        text("ERROR"),
+        VisualMatcher::Not(Box::new(text("retry"))),
+        VisualMatcher::Lines {
+            first: 10,
+            last: None,
+        },
+        VisualMatcher::Field(FieldPredicate {
+            field: "level".to_string(),
+            operator: FieldOperator::Ge,
+            value: "WARN".to_string(),
+        }),
+    ]));
+    let mut nested = text("x");
+    for _ in 0..=MAX_VISUAL_CONDITION_DEPTH {
+        nested = VisualMatcher::Not(Box::new(nested));
+    }
+    let report = VisualRulesEnvelope::new(vec![
+        unretried.clone(),
+        with_matcher(VisualMatcher::Any(Vec::new())),
+        with_matcher(VisualMatcher::Lines {
+            first: 5,
+            last: Some(4),
+        }),
+        with_matcher(VisualMatcher::Not(Box::new(VisualMatcher::Regex(
+            "(".to_string(),
+        )))),
+        with_matcher(VisualMatcher::Field(FieldPredicate {
+            field: " ".to_string(),
+            operator: FieldOperator::Eq,
+            value: String::new(),
+        })),
+        with_matcher(nested),
+        with_matcher(VisualMatcher::Any(vec![text("x"); 32])),
+        ManagedVisualRule {
+            scope: VisualRuleScope::Groups(vec![1]),
+            ..unretried.clone()
+        },
+    ])
+    .validate_for_load()
+    .expect("condition failures are recoverable");
+    assert_eq!(report.evaluator_rules.len(), 1);
+    assert_eq!(report.evaluator_rules[0].matcher, unretried.matcher);
+    assert_eq!(
+        report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "rule 2: condition group is empty",
+            "rule 3: line range ends before it starts",
+            "rule 4: invalid regex",
+            "rule 5: field condition needs a field name",
+            "rule 6: conditions are nested more than 8 levels deep",
+            "rule 7: conditions exceed 32 entries",
+            "rule 8: capture groups require a regex matcher",
+        ]
+    );
+
+    let mut version_3 = VisualRulesEnvelope::new(vec![unretried.clone()]);
+    version_3.schema_version = 3;
+    assert_eq!(
+        version_3.validate_for_load().unwrap().diagnostics[0].message,
+        "rule 1: compound conditions require schemaVersion 4"
+    );
+
+    let json = serde_json::to_string(&unretried.matcher).expect("serialize conditions");
+    assert_eq!(
+        json,
+        r#"{"All":[{"Text":"ERROR"},{"Not":{"Text":"retry"}},{"Lines":{"first":10}},{"Field":{"field":"level","operator":"ge","value":"WARN"}}]}"#
+    );
+}
+
+#[test]
+fn envelope_validation_checks_palette_colors_and_the_tokens_rules_use() {
+    let color = |name: &str, light_foreground: &str| PaletteColor {
+        name: name.to_string(),
+        light: PaletteVariant {
+            foreground: light_foreground.to_string(),
+            background: "#ccfbf1".to_string(),
+        },
+        dark: PaletteVariant {
+            foreground: "#2dd4bf".to_string(),
+            background: "#134e4a".to_string(),
+        },
+    };
+    let tinted = |token: &str| ManagedVisualRule {
+        style: style(Some(token), None),
+        ..rule(token)
+    };
+    let mut envelope = VisualRulesEnvelope::new(vec![tinted("teal"), tinted("pale")]);
+    envelope.palette = vec![color("teal", "#0f766e"), color("pale", "#fde68a")];
+
+    assert_eq!(
+        envelope.validate_for_save().unwrap_err().message,
+        "palette color 'pale': light foreground contrast 1.2:1 is below 4.5:1"
+    );
+    let report = envelope
+        .validate_for_load()
+        .expect("palette failures are recoverable");
+    assert_eq!(report.palette, [color("teal", "#0f766e")]);
+    assert_eq!(report.evaluator_rules.len(), 1);
+    assert_eq!(
+        report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "palette color 'pale': light foreground contrast 1.2:1 is below 4.5:1",
+            "rule 2: unsupported palette token 'pale'",
+        ]
+    );
+
+    envelope.schema_version = 4;
+    assert_eq!(
+        envelope.validate_for_load().unwrap_err().message,
+        "palette requires schemaVersion 5"
+    );
+}
+
+#[test]
+fn saves_upgrade_older_schema_envelopes_to_the_current_version() {
+    let path = temp_config_path("visual-rules-schema-upgrade");
+    std::fs::write(&path, r#"{"schemaVersion":1,"rules":[]}"#).expect("write schema 1");
+    let manager = native_manager(&path);
+    let loaded = manager.load().expect("schema 1 loads");
+    assert_eq!(
+        loaded.envelope.schema_version,
+        VisualRulesEnvelope::SCHEMA_VERSION
+    );
+
+    let mut envelope = loaded.envelope.clone();
+    envelope.rules.push(rule("ERROR"));
+    let saved = manager
+        .replace(loaded.revision, envelope)
+        .expect("replace upgrades schema");
+
+    let written: VisualRulesEnvelope =
+        serde_json::from_slice(&std::fs::read(&path).expect("read config")).expect("decode");
+    assert_eq!(written.schema_version, VisualRulesEnvelope::SCHEMA_VERSION);
+    assert_eq!(manager.state().revision, saved.revision);
+    assert_eq!(manager.state().envelope, written);
+    let backup_prefix = format!(
+        "{}.",
+        path.file_stem()
+            .and_then(|name| name.to_str())
+            .expect("config file stem")
+    );
+    for entry in std::fs::read_dir(path.parent().expect("parent")).expect("read config directory") {
+        let backup = entry.expect("directory entry").path();
+        if backup
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&backup_prefix) && name.ends_with(".bak"))
+        {
+            std::fs::remove_file(backup).expect("remove backup");
+        }
+    }
+    std::fs::remove_file(path).expect("remove config");
+}
+
 #[test]
 fn envelope_validation_rejects_limits_and_recovers_valid_siblings_in_order() {
     let mut bad = rule(&"x".repeat(513));
@@ -546,6 +930,76 @@ fn envelope_load_failure_retains_the_last_good_snapshot_and_reports_a_diagnostic
     std::fs::remove_file(path).expect("remove config");
 }
 
+/// Writes a whole file at once, so the watcher never reads half of an edit.
+fn hand_edit(path: &std::path::Path, bytes: &[u8]) {
+    let staged = path.with_extension("edit");
+    std::fs::write(&staged, bytes).expect("stage hand edit");
+    std::fs::rename(staged, path).expect("hand edit");
+}
+
+/// Polls until the revision moves past `revision` or `timeout` elapses, like a client
+/// following the manager's revisions.
+fn wait_for_change(manager: &VisualRulesManager, revision: u64, timeout: Duration) -> u64 {
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        let current = manager.revision();
+        if current != revision || std::time::Instant::now() >= deadline {
+            return current;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn watched_store_reloads_hand_edits_but_not_its_own_saves() {
+    let path = temp_config_path("visual-rules-watch");
+    let manager = native_manager(&path);
+    let initial = manager.load().expect("initial load");
+    let saved = manager
+        .save(
+            initial.revision,
+            VisualRulesEnvelope::new(vec![rule("ERROR")]),
+        )
+        .expect("first save");
+    assert!(manager.reload_if_changed().expect("check").is_none());
+
+    manager.watch(Duration::from_millis(10));
+    let edited = serde_json::to_vec(&VisualRulesEnvelope::new(vec![rule("WARN")])).expect("JSON");
+    hand_edit(&path, &edited);
+    let revision = wait_for_change(&manager, saved.revision, Duration::from_secs(5));
+    assert_eq!(revision, saved.revision + 1);
+    assert_eq!(
+        manager.snapshot().evaluate("WARN"),
+        Some(style(Some("red"), Some("default")))
+    );
+    assert_eq!(manager.snapshot().evaluate("ERROR"), None);
+
+    let moved = path.with_extension("moved");
+    std::fs::rename(&path, &moved).expect("remove config during a replace");
+    assert!(manager.reload_if_changed().expect("check").is_none());
+    assert_eq!(
+        wait_for_change(&manager, revision, Duration::from_millis(100)),
+        revision
+    );
+    assert!(manager.snapshot().evaluate("WARN").is_some());
+    std::fs::rename(&moved, &path).expect("restore config");
+
+    hand_edit(&path, b"not-json");
+    let broken = wait_for_change(&manager, revision, Duration::from_secs(5));
+    assert!(manager.state().diagnostics[0].message.contains("expected"));
+    assert!(manager.snapshot().evaluate("WARN").is_some());
+
+    let repaired = manager
+        .replace(broken, VisualRulesEnvelope::new(vec![rule("INFO")]))
+        .expect("replace the broken edit");
+    assert_eq!(
+        wait_for_change(&manager, repaired.revision, Duration::from_millis(100)),
+        repaired.revision
+    );
+
+    std::fs::remove_file(path).expect("remove config");
+}
+
 #[test]
 fn save_rejects_more_than_fifty_enabled_rules_without_mutation() {
     let path = temp_config_path("visual-rules-enabled-limit");
@@ -579,7 +1033,7 @@ fn unsupported_schema_load_preserves_last_good_state_and_allows_repair() {
             VisualRulesEnvelope::new(vec![rule("ERROR")]),
         )
         .expect("save last-known-good config");
-    std::fs::write(&path, r#"{"schemaVersion":2,"rules":[]}"#).expect("write future schema");
+    std::fs::write(&path, r#"{"schemaVersion":6,"rules":[]}"#).expect("write future schema");
 
     let recovered = manager.load().expect("unsupported schema recovers");
     assert_eq!(recovered.revision, saved.revision + 1);
@@ -0,0 +1,210 @@
+#![cfg(feature = "native-persistence")]
+
+use logmancer_core::{
+    DEFAULT_VISUAL_PROFILE, NativeVisualRulesStore, ValidationSeverity, VisualColor,
+    VisualColorRole, VisualMatcher, VisualRulesEnvelope, VisualRulesManager, VisualRulesState,
+    VisualTheme,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const V1: &str = include_str!("fixtures/visual-rules/v1.json");
+const V2: &str = include_str!("fixtures/visual-rules/v2.json");
+const V3: &str = include_str!("fixtures/visual-rules/v3.json");
+const V4: &str = include_str!("fixtures/visual-rules/v4.json");
+const V5: &str = include_str!("fixtures/visual-rules/v5.json");
+
+struct Fixture {
+    directory: PathBuf,
+    path: PathBuf,
+    manager: Arc<VisualRulesManager>,
+}
+
+impl Fixture {
+    fn load(name: &str, contents: &str) -> (Self, VisualRulesState) {
+        let suffix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock")
+            .as_nanos();
+        let directory = std::env::temp_dir().join(format!("logmancer-migration-{name}-{suffix}"));
+        std::fs::create_dir_all(&directory).expect("create fixture directory");
+        let path = directory.join("visual-rules.json");
+        std::fs::write(&path, contents).expect("write fixture");
+        let manager =
+            VisualRulesManager::with_store(Arc::new(NativeVisualRulesStore::new(path.clone())));
+        let state = manager.load().expect("fixture loads");
+        (
+            Self {
+                directory,
+                path,
+                manager,
+            },
+            state,
+        )
+    }
+
+    fn written(&self) -> VisualRulesEnvelope {
+        serde_json::from_slice(&std::fs::read(&self.path).expect("read config")).expect("decode")
+    }
+
+    fn backups(&self) -> Vec<String> {
+        backups(&self.directory)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+fn backups(directory: &Path) -> Vec<String> {
+    std::fs::read_dir(directory)
+        .expect("read fixture directory")
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "bak"))
+        .map(|path| std::fs::read_to_string(path).expect("read backup"))
+        .collect()
+}
+
+fn messages(state: &VisualRulesState) -> Vec<&str> {
+    state
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect()
+}
+
+#[test]
+fn schema_1_fixture_drops_styles_it_could_not_hold_and_backs_the_file_up() {
+    let (fixture, state) = Fixture::load("v1", V1);
+
+    assert_eq!(
+        state.envelope.schema_version,
+        VisualRulesEnvelope::SCHEMA_VERSION
+    );
+    assert_eq!(state.envelope.rules.len(), 2);
+    assert!(state.envelope.rules[1].style.attributes.is_empty());
+    assert_eq!(
+        messages(&state),
+        [
+            "migrated from schemaVersion 1 to 5; the original file was kept as a backup",
+            "rule 2: text attributes and gutter markers require schemaVersion 2; dropped while migrating",
+        ]
+    );
+    assert!(
+        state
+            .diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == ValidationSeverity::Warning)
+    );
+    assert_eq!(fixture.written(), state.envelope);
+    assert_eq!(fixture.backups(), [V1]);
+    assert!(fixture.manager.snapshot().evaluate("WARN disk").is_some());
+}
+
+#[test]
+fn schema_2_fixture_keeps_attributes_gutters_and_scopes() {
+    let (fixture, state) = Fixture::load("v2", V2);
+
+    assert_eq!(
+        messages(&state),
+        ["migrated from schemaVersion 2 to 5; the original file was kept as a backup"]
+    );
+    let errors = &state.envelope.rules[0].style;
+    assert!(errors.attributes.bold);
+    assert!(errors.gutter.is_some());
+    assert_eq!(fixture.manager.snapshot().spans("GET req-42 done").len(), 1);
+    assert_eq!(fixture.backups(), [V2]);
+}
+
+#[test]
+fn schema_3_fixture_drops_compound_rules_from_profiles() {
+    let (fixture, state) = Fixture::load("v3", V3);
+
+    assert_eq!(
+        messages(&state),
+        [
+            "migrated from schemaVersion 3 to 5; the original file was kept as a backup",
+            "profile 'Access logs' rule 2: compound conditions require schemaVersion 4; dropped while migrating",
+        ]
+    );
+    let profile = &state.envelope.profiles[0];
+    assert_eq!(profile.rules.len(), 1);
+    assert_eq!(profile.rules[0].name.as_deref(), Some("Server errors"));
+    assert_eq!(
+        fixture
+            .manager
+            .profiles()
+            .auto_select("/var/log/access.log", None),
+        Some("Access logs")
+    );
+    assert_eq!(fixture.written(), state.envelope);
+    assert_eq!(fixture.backups(), [V3]);
+}
+
+#[test]
+fn schema_4_fixture_upgrades_without_dropping_anything() {
+    let (fixture, state) = Fixture::load("v4", V4);
+
+    assert_eq!(
+        messages(&state),
+        ["migrated from schemaVersion 4 to 5; the original file was kept as a backup"]
+    );
+    assert!(matches!(
+        state.envelope.rules[0].matcher,
+        VisualMatcher::All(_)
+    ));
+    assert_eq!(state.envelope.profiles[0].rules.len(), 1);
+    assert!(state.envelope.palette.is_empty());
+    assert_eq!(fixture.backups(), [V4]);
+}
+
+#[test]
+fn current_fixture_loads_without_migrating_or_backing_up() {
+    let (fixture, state) = Fixture::load("v5", V5);
+
+    assert!(state.diagnostics.is_empty());
+    assert_eq!(state.envelope.palette[0].name, "teal");
+    assert_eq!(std::fs::read_to_string(&fixture.path).unwrap(), V5);
+    assert!(fixture.backups().is_empty());
+    let profiles = fixture.manager.profiles();
+    assert_eq!(profiles.names()[0], DEFAULT_VISUAL_PROFILE);
+    assert!(
+        profiles
+            .default_evaluator()
+            .evaluate("AUDIT login")
+            .is_some()
+    );
+    assert_eq!(
+        profiles
+            .palette()
+            .resolve(
+                &VisualColor("teal".to_string()),
+                VisualColorRole::Foreground,
+                VisualTheme::Dark
+            )
+            .map(|color| color.to_hex())
+            .as_deref(),
+        Some("#2dd4bf")
+    );
+}
+
+#[test]
+fn migrated_files_reload_as_current_and_replace_the_rewritten_source() {
+    let (fixture, state) = Fixture::load("reload", V1);
+    let reloaded = fixture.manager.load().expect("reload");
+    assert!(reloaded.diagnostics.is_empty());
+    assert_eq!(reloaded.envelope, state.envelope);
+    assert_eq!(fixture.backups().len(), 1);
+
+    let mut envelope = reloaded.envelope.clone();
+    envelope.rules.pop();
+    fixture
+        .manager
+        .replace(reloaded.revision, envelope.clone())
+        .expect("replace after migration");
+    assert_eq!(fixture.written(), envelope);
+}
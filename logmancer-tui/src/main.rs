@@ -1,21 +1,55 @@
 #[macro_use]
 mod print_utils;
+mod terminal_colors;
 
 use crate::print_utils::{HighlightKind, split_highlighted_segments};
+use crate::terminal_colors::{ColorDepth, TerminalPalette, palette_color, theme_from_env};
 use crossterm::{
     cursor,
     event::{self, Event, KeyCode},
     execute,
-    style::{Attribute, Color, Print, PrintStyledContent, Stylize},
+    style::{Attribute, Color, Print, PrintStyledContent, StyledContent, Stylize},
     terminal,
 };
-use log::{LevelFilter, debug, error};
-use logmancer_core::{LogReader, PageSearchResult, SearchDisplayStatus};
+use log::{LevelFilter, debug, error, warn};
+use logmancer_core::{
+    FormatDetection, GutterIcon, LineStyleIntent, LineTime, LogFormatManager, LogFormatStatus,
+    LogReader, NativeVisualRulesStore, PageLine, PageMarkResult, PageResult, PageSearchResult,
+    RuleCountStatus, SearchDisplayStatus, StyledSpan, TimeSettings, VisualColorRole,
+    VisualRulesManager, format_time_delta,
+};
 use std::env;
 use std::fs::OpenOptions;
 use std::io::{Write, stdout};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::{process, time};
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Search,
+    Mark,
+    Zone,
+}
+
+/// Time delta shown before the text of each line, cycled with `d`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DeltaColumn {
+    Off,
+    SincePrevious,
+    SinceReference,
+}
+
+impl DeltaColumn {
+    fn next(self) -> Self {
+        match self {
+            DeltaColumn::Off => DeltaColumn::SincePrevious,
+            DeltaColumn::SincePrevious => DeltaColumn::SinceReference,
+            DeltaColumn::SinceReference => DeltaColumn::Off,
+        }
+    }
+}
+
 fn main() -> std::io::Result<()> {
     setup_logging().expect("Failed to initialize logging");
 
@@ -34,13 +68,25 @@ fn main() -> std::io::Result<()> {
         process::exit(1);
     }));
 
-    let mut reader = match LogReader::new(filepath.to_string()) {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Error opening file: {e}");
-            process::exit(1);
-        }
+    let manager = visual_rules_manager();
+    let colors = TerminalPalette {
+        palette: manager.profiles().palette().clone(),
+        depth: ColorDepth::from_env(),
+        theme: theme_from_env(),
     };
+    let mut reader =
+        match LogReader::with_managers(filepath.to_string(), manager, log_format_manager()) {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Error opening file: {e}");
+                process::exit(1);
+            }
+        };
+    let detection = reader
+        .file_info()
+        .ok()
+        .and_then(|info| info.detected_format);
+    let mut active_format = reader.log_format().active;
 
     let mut page_size: usize = 20;
     let mut page_first_line: usize = 0;
@@ -49,8 +95,13 @@ fn main() -> std::io::Result<()> {
     let mut last_dimensions = (0, 0);
     let mut follow_mode = false;
     let mut end_reached = false;
-    let mut search_input_mode = false;
+    let mut input_prompt: Option<PromptKind> = None;
     let mut search_prompt = String::new();
+    let mut selected_mark: usize = 0;
+    let mut selected_rule: usize = 0;
+    let mut last_rule_counts = None;
+    let mut show_timestamps = false;
+    let mut delta_column = DeltaColumn::Off;
     let mut ui_dirty = true;
 
     loop {
@@ -84,8 +135,27 @@ fn main() -> std::io::Result<()> {
 
         end_reached = page_first_line + page_size >= page_result.total_lines;
         let indexing_progress = page_result.indexing_progress * 100.0;
+        let search_indexing = page_result
+            .search
+            .as_ref()
+            .is_some_and(|search| search.is_indexing)
+            || page_result.marks.iter().any(|mark| mark.search.is_indexing);
+        let rule_counts = match reader.visual_rule_counts() {
+            Ok(rule_counts) => Some(rule_counts),
+            Err(e) => {
+                error!("Error counting visual rules: {e}");
+                None
+            }
+        };
+        let rule_counting = rule_counts
+            .as_ref()
+            .is_some_and(|rule_counts| !rule_counts.is_ready);
 
-        if last_page_result.as_ref() != Some(&page_result) || dimensions_changed || ui_dirty {
+        if last_page_result.as_ref() != Some(&page_result)
+            || last_rule_counts != rule_counts
+            || dimensions_changed
+            || ui_dirty
+        {
             let indexed = if indexing_progress < 100.0 {
                 format!(" ({indexing_progress:.2}% indexed)")
             } else {
@@ -95,16 +165,23 @@ fn main() -> std::io::Result<()> {
             // Header
             print_row!(
                 0,
-                "File: {} | Follow Mode: {} | Total Lines: {}{} | Search: {}",
+                "File: {} | Follow Mode: {} | Total Lines: {}{}{}{} | Search: {}{}{}",
                 &args[1],
                 if follow_mode { "ON" } else { "OFF" },
                 page_result.total_lines,
                 indexed,
+                format_log_format_status(active_format.as_deref(), detection.as_ref()),
+                format_time_status(&reader.time_settings(), show_timestamps, delta_column),
                 page_result
                     .search
                     .as_ref()
                     .map(|search| format_search_status(&search.display_status()))
-                    .unwrap_or_else(|| "OFF".to_string())
+                    .unwrap_or_else(|| "OFF".to_string()),
+                format_marks_status(&page_result.marks, selected_mark),
+                rule_counts
+                    .as_ref()
+                    .map(|rule_counts| format_rule_counts_status(rule_counts, selected_rule))
+                    .unwrap_or_default()
             );
             print_row!(1, "{}", "-".repeat(columns as usize));
 
@@ -118,30 +195,36 @@ fn main() -> std::io::Result<()> {
             for (i, line) in page_result.lines.iter().enumerate() {
                 render_line_row(
                     i + 2,
-                    line.number,
-                    line.text.trim_end(),
+                    line,
+                    &format_time_prefix(line.time.as_ref(), show_timestamps, delta_column),
                     left_offset,
                     columns as usize,
-                    page_result.search.as_ref(),
+                    &page_result,
+                    &colors,
                 )?;
             }
 
             print_row!(
                 rows as usize - 1,
                 "{}",
-                if search_input_mode {
-                    format!("/{}", search_prompt)
-                } else {
-                    "".to_string()
+                match input_prompt {
+                    Some(PromptKind::Search) => format!("/{}", search_prompt),
+                    Some(PromptKind::Mark) => format!("mark: {}", search_prompt),
+                    Some(PromptKind::Zone) => format!("zone: {}", search_prompt),
+                    None => "".to_string(),
                 }
             );
 
             last_page_result = Some(page_result);
+            last_rule_counts = rule_counts;
             ui_dirty = false;
         }
         stdout().flush()?;
 
-        let polling = (end_reached && follow_mode) || indexing_progress < 100.0;
+        let polling = (end_reached && follow_mode)
+            || indexing_progress < 100.0
+            || search_indexing
+            || rule_counting;
         let event = if polling {
             if event::poll(time::Duration::from_millis(1000))? {
                 Some(event::read()?)
@@ -152,10 +235,32 @@ fn main() -> std::io::Result<()> {
             Some(event::read()?)
         };
         if let Some(Event::Key(key_event)) = event {
-            if search_input_mode {
+            if let Some(prompt_kind) = input_prompt {
                 match key_event.code {
+                    KeyCode::Enter if prompt_kind == PromptKind::Mark => {
+                        input_prompt = None;
+                        ui_dirty = true;
+                        let mark_query = search_prompt.trim().to_string();
+                        if !mark_query.is_empty() {
+                            match reader.add_mark(mark_query, None) {
+                                Ok(_) => {
+                                    selected_mark = reader.marks_status().len().saturating_sub(1);
+                                    last_page_result = None;
+                                }
+                                Err(e) => error!("Error adding mark: {e}"),
+                            }
+                        }
+                    }
+                    KeyCode::Enter if prompt_kind == PromptKind::Zone => {
+                        input_prompt = None;
+                        ui_dirty = true;
+                        match reader.set_time_zone(&search_prompt) {
+                            Ok(_) => last_page_result = None,
+                            Err(e) => error!("Error setting time zone: {e}"),
+                        }
+                    }
                     KeyCode::Enter => {
-                        search_input_mode = false;
+                        input_prompt = None;
                         let search_query = search_prompt.trim().to_string();
                         ui_dirty = true;
 
@@ -171,7 +276,7 @@ fn main() -> std::io::Result<()> {
                         }
                     }
                     KeyCode::Esc => {
-                        search_input_mode = false;
+                        input_prompt = None;
                         search_prompt.clear();
                         ui_dirty = true;
                     }
@@ -190,10 +295,65 @@ fn main() -> std::io::Result<()> {
             match key_event.code {
                 KeyCode::Char('q') => break,
                 KeyCode::Char('/') => {
-                    search_input_mode = true;
+                    input_prompt = Some(PromptKind::Search);
+                    search_prompt.clear();
+                    ui_dirty = true;
+                }
+                KeyCode::Char('m') => {
+                    input_prompt = Some(PromptKind::Mark);
                     search_prompt.clear();
                     ui_dirty = true;
                 }
+                KeyCode::Tab => {
+                    let marks = reader.marks_status().len();
+                    if marks > 0 {
+                        selected_mark = (selected_mark + 1) % marks;
+                        ui_dirty = true;
+                    }
+                }
+                KeyCode::Char(']') | KeyCode::Char('[') => {
+                    let Some(mark) = reader.marks_status().into_iter().nth(selected_mark) else {
+                        continue;
+                    };
+                    let page = if key_event.code == KeyCode::Char(']') {
+                        reader.mark_next(mark.id, page_size)
+                    } else {
+                        reader.mark_previous(mark.id, page_size)
+                    };
+                    if let Ok(page) = page {
+                        page_first_line = page.start_line;
+                        end_reached = page_first_line + page_size >= page.total_lines;
+                        last_page_result = None;
+                    }
+                }
+                KeyCode::Char('r') => {
+                    let rules = last_rule_counts
+                        .as_ref()
+                        .map_or(0, |rule_counts| rule_counts.rules.len());
+                    if rules > 0 {
+                        selected_rule = (selected_rule + 1) % rules;
+                        ui_dirty = true;
+                    }
+                }
+                KeyCode::Char('}') | KeyCode::Char('{') => {
+                    let navigation = if key_event.code == KeyCode::Char('}') {
+                        reader.visual_rule_next(selected_rule, page_size)
+                    } else {
+                        reader.visual_rule_previous(selected_rule, page_size)
+                    };
+                    if let Ok(navigation) = navigation {
+                        page_first_line = navigation.page.start_line;
+                        end_reached = page_first_line + page_size >= navigation.page.total_lines;
+                        last_page_result = None;
+                    }
+                }
+                KeyCode::Char('M') => {
+                    if let Some(mark) = reader.marks_status().into_iter().nth(selected_mark) {
+                        reader.remove_mark(mark.id);
+                        selected_mark = selected_mark.saturating_sub(1);
+                        last_page_result = None;
+                    }
+                }
                 KeyCode::Char('n') => {
                     if let Ok(page) = reader.search_next(page_size) {
                         page_first_line = page.start_line;
@@ -208,6 +368,42 @@ fn main() -> std::io::Result<()> {
                         last_page_result = None;
                     }
                 }
+                KeyCode::Char('t') => {
+                    show_timestamps = !show_timestamps;
+                    ui_dirty = true;
+                }
+                KeyCode::Char('d') => {
+                    delta_column = delta_column.next();
+                    ui_dirty = true;
+                }
+                KeyCode::Char('R') => {
+                    let settings = reader.time_settings();
+                    let top_line = last_page_result.as_ref().and_then(|page| {
+                        page.lines
+                            .iter()
+                            .find(|line| line.time.is_some())
+                            .map(|line| line.number)
+                    });
+                    let reference = top_line.filter(|line| settings.reference_line != Some(*line));
+                    match reader.set_time_reference(reference) {
+                        Ok(_) => last_page_result = None,
+                        Err(e) => error!("Error setting the reference line: {e}"),
+                    }
+                }
+                KeyCode::Char('z') => {
+                    input_prompt = Some(PromptKind::Zone);
+                    search_prompt = reader.time_settings().time_zone;
+                    ui_dirty = true;
+                }
+                KeyCode::Char('l') => {
+                    match reader.set_log_format(next_log_format(&reader.log_format())) {
+                        Ok(status) => {
+                            active_format = status.active;
+                            last_page_result = None;
+                        }
+                        Err(e) => error!("Error switching log format: {e}"),
+                    }
+                }
                 KeyCode::Char('f') | KeyCode::Char('F') => follow_mode = !follow_mode,
                 KeyCode::Char('g') => {
                     end_reached = false;
@@ -242,33 +438,81 @@ fn main() -> std::io::Result<()> {
 
 fn render_line_row(
     row: usize,
-    line_number: usize,
-    line_text: &str,
+    line: &PageLine,
+    time_prefix: &str,
     left_offset: usize,
     columns: usize,
-    search: Option<&PageSearchResult>,
+    page: &PageResult,
+    colors: &TerminalPalette,
 ) -> std::io::Result<()> {
+    let (search, marks) = (page.search.as_ref(), page.marks.as_slice());
+    let line_number = line.number;
+    let line_text = line.text.trim_end();
     let is_current_line = search
         .and_then(|state| state.current.as_ref())
         .is_some_and(|current| current.line_index + 1 == line_number);
     let current_marker = if is_current_line { " <" } else { "" };
-    let content_width = columns.saturating_sub(left_offset + 2 + current_marker.len());
+    let content_width = columns
+        .saturating_sub(left_offset + 2 + time_prefix.chars().count() + current_marker.len());
     let visible_text = trunc_str(line_text, content_width);
-    let spans = search
-        .map(|state| collect_line_spans(state, line_number, visible_text.len()))
-        .unwrap_or_default();
+    let spans = collect_line_spans(&line.spans, search, marks, line_number, visible_text.len());
     let segments = split_highlighted_segments(visible_text, &spans);
 
     execute!(
         stdout(),
         cursor::MoveTo(0, row as u16),
         terminal::Clear(terminal::ClearType::UntilNewLine),
-        Print(format!("{line_number:<left_offset$}| "))
+        Print(format!(
+            "{line_number:<width$}",
+            width = left_offset.saturating_sub(1)
+        ))
     )?;
+    match line
+        .style
+        .as_ref()
+        .and_then(|style| gutter_marker(style, colors))
+    {
+        Some(marker) => execute!(stdout(), PrintStyledContent(marker))?,
+        None => execute!(stdout(), Print(" "))?,
+    }
+    execute!(stdout(), Print("| "))?;
+    if !time_prefix.is_empty() {
+        execute!(
+            stdout(),
+            PrintStyledContent(time_prefix.with(Color::DarkGrey))
+        )?;
+    }
 
     for segment in segments {
         match segment.kind {
-            HighlightKind::Plain => execute!(stdout(), Print(segment.text))?,
+            HighlightKind::Plain => match line.style.as_ref() {
+                Some(style) => execute!(
+                    stdout(),
+                    PrintStyledContent(styled_text(segment.text, style, colors))
+                )?,
+                None => execute!(stdout(), Print(segment.text))?,
+            },
+            HighlightKind::Rule(index) => match line.spans.get(index) {
+                Some(span) => execute!(
+                    stdout(),
+                    PrintStyledContent(styled_text(segment.text, &span.style, colors))
+                )?,
+                None => execute!(stdout(), Print(segment.text))?,
+            },
+            HighlightKind::Mark(slot) => execute!(
+                stdout(),
+                PrintStyledContent(segment.text.black().on(mark_color(marks, slot)))
+            )?,
+            HighlightKind::CurrentMark(slot) => execute!(
+                stdout(),
+                PrintStyledContent(
+                    segment
+                        .text
+                        .black()
+                        .on(mark_color(marks, slot))
+                        .attribute(Attribute::Underlined)
+                )
+            )?,
             HighlightKind::Match => execute!(
                 stdout(),
                 PrintStyledContent(segment.text.black().on(Color::Yellow))
@@ -299,24 +543,224 @@ fn render_line_row(
 }
 
 fn collect_line_spans(
-    search: &PageSearchResult,
+    rule_spans: &[StyledSpan],
+    search: Option<&PageSearchResult>,
+    marks: &[PageMarkResult],
     line_number: usize,
     visible_len: usize,
-) -> Vec<(usize, usize, bool)> {
-    search
-        .page_matches
+) -> Vec<(usize, usize, HighlightKind)> {
+    let rule_spans = rule_spans.iter().enumerate().map(|(index, span)| {
+        (
+            span.start.min(visible_len),
+            span.end.min(visible_len),
+            HighlightKind::Rule(index),
+        )
+    });
+    let mark_spans = marks.iter().enumerate().flat_map(|(slot, mark)| {
+        mark.search.page_matches.iter().map(move |mark_match| {
+            let kind = if mark.search.current.as_ref() == Some(mark_match) {
+                HighlightKind::CurrentMark(slot)
+            } else {
+                HighlightKind::Mark(slot)
+            };
+            (mark_match, kind)
+        })
+    });
+    let search_spans = search.into_iter().flat_map(|search| {
+        search.page_matches.iter().map(move |search_match| {
+            let kind = if search.current.as_ref() == Some(search_match) {
+                HighlightKind::CurrentMatch
+            } else {
+                HighlightKind::Match
+            };
+            (search_match, kind)
+        })
+    });
+
+    rule_spans
+        .chain(
+            mark_spans
+                .chain(search_spans)
+                .filter(|(found, _)| found.line_index + 1 == line_number)
+                .map(|(found, kind)| {
+                    (
+                        found.start.min(visible_len),
+                        found.end.min(visible_len),
+                        kind,
+                    )
+                }),
+        )
+        .collect()
+}
+
+/// Maps a mark palette token to the terminal background used for its occurrences.
+fn mark_color(marks: &[PageMarkResult], slot: usize) -> Color {
+    marks
+        .get(slot)
+        .and_then(|mark| palette_color(&mark.color))
+        .unwrap_or(Color::Yellow)
+}
+
+/// Applies the colors and text attributes of a visual-rule style.
+fn styled_text<'a>(
+    text: &'a str,
+    style: &LineStyleIntent,
+    colors: &TerminalPalette,
+) -> StyledContent<&'a str> {
+    let mut content = text.stylize();
+    if let Some(color) = style
+        .foreground
+        .as_ref()
+        .and_then(|token| colors.rule_color(token, VisualColorRole::Foreground))
+    {
+        content = content.with(color);
+    }
+    if let Some(color) = style
+        .background
+        .as_ref()
+        .and_then(|token| colors.rule_color(token, VisualColorRole::Background))
+    {
+        content = content.on(color);
+    }
+    for attribute in text_attributes(style) {
+        content = content.attribute(attribute);
+    }
+    content
+}
+
+fn text_attributes(style: &LineStyleIntent) -> Vec<Attribute> {
+    let attributes = style.attributes;
+    [
+        (attributes.bold, Attribute::Bold),
+        (attributes.italic, Attribute::Italic),
+        (attributes.underline, Attribute::Underlined),
+        (attributes.strikethrough, Attribute::CrossedOut),
+        (attributes.dim, Attribute::Dim),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, attribute)| enabled.then_some(attribute))
+    .collect()
+}
+
+/// Gutter glyph of a styled line, colored like the marker or the rule foreground.
+fn gutter_marker(
+    style: &LineStyleIntent,
+    colors: &TerminalPalette,
+) -> Option<StyledContent<&'static str>> {
+    let marker = style.gutter.as_ref()?;
+    let glyph = match marker.icon {
+        GutterIcon::Dot => "●",
+        GutterIcon::Flag => "⚑",
+        GutterIcon::Star => "★",
+        GutterIcon::Warning => "▲",
+        GutterIcon::Error => "✖",
+    };
+    let color = marker
+        .color
+        .as_ref()
+        .or(style.foreground.as_ref())
+        .and_then(|token| colors.rule_color(token, VisualColorRole::Foreground));
+    Some(match color {
+        Some(color) => glyph.with(color),
+        None => glyph.stylize(),
+    })
+}
+
+fn format_marks_status(marks: &[PageMarkResult], selected_mark: usize) -> String {
+    marks
         .iter()
-        .filter(|search_match| search_match.line_index + 1 == line_number)
-        .map(|search_match| {
-            (
-                search_match.start.min(visible_len),
-                search_match.end.min(visible_len),
-                search.current.as_ref() == Some(search_match),
+        .enumerate()
+        .map(|(slot, mark)| {
+            format!(
+                " | {}{}",
+                if slot == selected_mark { "*" } else { "" },
+                format_search_status(&mark.search.display_status())
             )
         })
         .collect()
 }
 
+fn format_rule_counts_status(status: &RuleCountStatus, selected_rule: usize) -> String {
+    let Some(count) = status.rules.get(selected_rule) else {
+        return String::new();
+    };
+    let name = count
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("Rule {}", selected_rule + 1));
+    let mut text = match count.current_line_index {
+        Some(line_index) => format!(
+            " | {name} {} lines, at {}",
+            count.matched_lines,
+            line_index + 1
+        ),
+        None => format!(" | {name} {} lines", count.matched_lines),
+    };
+    if !status.is_ready {
+        text.push_str(" counting...");
+    }
+    text
+}
+
+/// Header segment naming the format lines are parsed with, and the detection confidence
+/// when it was chosen on open.
+fn format_log_format_status(active: Option<&str>, detection: Option<&FormatDetection>) -> String {
+    match (active, detection) {
+        (None, _) => " | Format: plain".to_string(),
+        (Some(active), Some(detection)) if detection.format == active => format!(
+            " | Format: {active} (auto {:.0}%)",
+            detection.confidence * 100.0
+        ),
+        (Some(active), _) => format!(" | Format: {active}"),
+    }
+}
+
+/// Header segment naming the display time zone and reference line while a time column shows.
+fn format_time_status(
+    settings: &TimeSettings,
+    show_timestamps: bool,
+    delta_column: DeltaColumn,
+) -> String {
+    if !show_timestamps && delta_column == DeltaColumn::Off {
+        return String::new();
+    }
+    match settings.reference_line {
+        Some(line) => format!(" | Zone: {} | Ref: {line}", settings.time_zone),
+        None => format!(" | Zone: {}", settings.time_zone),
+    }
+}
+
+/// Time columns drawn before the text of a line, padded so that they align down the page and
+/// left blank where the line has no timestamp.
+fn format_time_prefix(
+    time: Option<&LineTime>,
+    show_timestamps: bool,
+    delta_column: DeltaColumn,
+) -> String {
+    let mut prefix = String::new();
+    if show_timestamps {
+        let timestamp = time.map_or("", |time| time.timestamp.as_str());
+        prefix.push_str(&format!("{timestamp:<23} "));
+    }
+    let delta = match delta_column {
+        DeltaColumn::Off => return prefix,
+        DeltaColumn::SincePrevious => time.and_then(|time| time.since_previous_ms),
+        DeltaColumn::SinceReference => time.and_then(|time| time.since_reference_ms),
+    };
+    let delta = delta.map(format_time_delta).unwrap_or_default();
+    prefix.push_str(&format!("{delta:>9} "));
+    prefix
+}
+
+/// Format after the active one, cycling from plain text through every format and back.
+fn next_log_format(status: &LogFormatStatus) -> Option<String> {
+    let next = match &status.active {
+        None => 0,
+        Some(active) => status.formats.iter().position(|name| name == active)? + 1,
+    };
+    status.formats.get(next).cloned()
+}
+
 fn format_search_status(status: &SearchDisplayStatus) -> String {
     let mut text = if status.total_matches == 0 {
         if status.is_indexing {
@@ -356,6 +800,45 @@ fn trunc_str(s: &str, max_len: usize) -> &str {
     }
 }
 
+/// `$LOGMANCER_CONFIG_DIR`, or `config` under the working directory.
+fn config_dir() -> PathBuf {
+    env::var_os("LOGMANCER_CONFIG_DIR")
+        .map(PathBuf::from)
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .unwrap_or_else(|| PathBuf::from("config"))
+}
+
+/// Rules shared with the web viewer, read from `visual-rules.json` in the config directory.
+fn visual_rules_manager() -> Arc<VisualRulesManager> {
+    let path = config_dir().join("visual-rules.json");
+    let manager =
+        VisualRulesManager::with_store(Arc::new(NativeVisualRulesStore::new(path.clone())));
+    match manager.load() {
+        Ok(state) => {
+            for diagnostic in state.diagnostics {
+                warn!("{}: {}", path.display(), diagnostic.message);
+            }
+        }
+        Err(e) => warn!("Could not load visual rules from {}: {e}", path.display()),
+    }
+    manager
+}
+
+/// Formats shared with the web viewer, read from `log-formats.json` in the config directory.
+fn log_format_manager() -> Arc<LogFormatManager> {
+    let path = config_dir().join("log-formats.json");
+    let manager = LogFormatManager::with_store(Arc::new(NativeVisualRulesStore::new(path.clone())));
+    match manager.load() {
+        Ok(state) => {
+            for diagnostic in state.diagnostics {
+                warn!("{}: {}", path.display(), diagnostic.message);
+            }
+        }
+        Err(e) => warn!("Could not load log formats from {}: {e}", path.display()),
+    }
+    manager
+}
+
 fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
     let file = OpenOptions::new()
         .create(true)
@@ -374,8 +857,20 @@ fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg(test)]
 mod tests {
-    use super::{collect_line_spans, format_search_status, trunc_str};
-    use logmancer_core::{PageSearchResult, SearchDisplayStatus, SearchMatch};
+    use super::{
+        DeltaColumn, collect_line_spans, format_log_format_status, format_marks_status,
+        format_rule_counts_status, format_search_status, format_time_prefix, format_time_status,
+        gutter_marker, next_log_format, palette_color, text_attributes, trunc_str,
+    };
+    use crate::print_utils::HighlightKind;
+    use crate::terminal_colors::{ColorDepth, TerminalPalette};
+    use crossterm::style::{Attribute, Color, Stylize};
+    use logmancer_core::{
+        FormatDetection, GutterIcon, GutterMarker, LineStyleIntent, LineTime, LogFormatStatus,
+        PageMarkResult, PageSearchResult, RuleCount, RuleCountStatus, SearchDisplayStatus,
+        SearchMatch, StyledSpan, TextAttributes, TimeSettings, VisualColor, VisualPalette,
+        VisualTheme,
+    };
 
     #[test]
     fn trunc_str_returns_empty_when_width_is_zero() {
@@ -409,8 +904,173 @@ mod tests {
         };
 
         assert_eq!(
-            collect_line_spans(&search, 5, 8),
-            vec![(0, 3, false), (6, 8, true)]
+            collect_line_spans(&[], Some(&search), &[], 5, 8),
+            vec![
+                (0, 3, HighlightKind::Match),
+                (6, 8, HighlightKind::CurrentMatch)
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_line_spans_includes_marks_by_page_slot() {
+        let mark_match = SearchMatch {
+            line_index: 1,
+            start: 2,
+            end: 5,
+            ordinal: 0,
+        };
+        let marks = vec![PageMarkResult {
+            id: 3,
+            color: VisualColor("cyan".to_string()),
+            search: PageSearchResult {
+                query: "req".to_string(),
+                total_matches: 4,
+                total_matches_final: true,
+                is_indexing: false,
+                first: Some(mark_match.clone()),
+                current: None,
+                page_matches: vec![mark_match],
+            },
+        }];
+
+        assert_eq!(
+            collect_line_spans(&[], None, &marks, 2, 80),
+            vec![(2, 5, HighlightKind::Mark(0))]
+        );
+        assert_eq!(collect_line_spans(&[], None, &marks, 3, 80), Vec::new());
+        assert_eq!(format_marks_status(&marks, 0), " | *req 4 matches");
+    }
+
+    #[test]
+    fn collect_line_spans_puts_rule_spans_first_and_clamps_them() {
+        let spans = vec![StyledSpan {
+            start: 4,
+            end: 30,
+            style: LineStyleIntent {
+                foreground: Some(VisualColor("cyan".to_string())),
+                background: None,
+                ..LineStyleIntent::default()
+            },
+        }];
+
+        assert_eq!(
+            collect_line_spans(&spans, None, &[], 1, 10),
+            vec![(4, 10, HighlightKind::Rule(0))]
+        );
+        assert_eq!(
+            palette_color(&VisualColor("cyan".to_string())),
+            Some(Color::Cyan)
+        );
+        assert_eq!(palette_color(&VisualColor("default".to_string())), None);
+    }
+
+    #[test]
+    fn rule_styles_map_to_terminal_attributes_and_gutter_glyphs() {
+        let style = LineStyleIntent {
+            foreground: Some(VisualColor("red".to_string())),
+            background: None,
+            attributes: TextAttributes {
+                italic: true,
+                strikethrough: true,
+                ..TextAttributes::default()
+            },
+            gutter: Some(GutterMarker {
+                icon: GutterIcon::Dot,
+                color: None,
+            }),
+        };
+
+        assert_eq!(
+            text_attributes(&style),
+            vec![Attribute::Italic, Attribute::CrossedOut]
+        );
+        let colors = |depth| TerminalPalette {
+            palette: VisualPalette::default(),
+            depth,
+            theme: VisualTheme::Dark,
+        };
+        assert_eq!(
+            gutter_marker(&style, &colors(ColorDepth::Ansi16)),
+            Some("●".with(Color::Red))
+        );
+        assert_eq!(
+            gutter_marker(&style, &colors(ColorDepth::Ansi256)),
+            Some("●".with(Color::AnsiValue(203)))
+        );
+        assert_eq!(
+            gutter_marker(&LineStyleIntent::default(), &colors(ColorDepth::TrueColor)),
+            None
+        );
+    }
+
+    #[test]
+    fn log_formats_cycle_from_plain_text_through_every_format() {
+        let status = |active: Option<&str>| LogFormatStatus {
+            active: active.map(str::to_string),
+            formats: vec!["json".to_string(), "csv".to_string()],
+            columns: Vec::new(),
+        };
+        assert_eq!(next_log_format(&status(None)).as_deref(), Some("json"));
+        assert_eq!(
+            next_log_format(&status(Some("json"))).as_deref(),
+            Some("csv")
+        );
+        assert_eq!(next_log_format(&status(Some("csv"))), None);
+        assert_eq!(next_log_format(&status(Some("gone"))), None);
+    }
+
+    #[test]
+    fn time_prefixes_align_timestamps_and_the_chosen_delta() {
+        let time = LineTime {
+            timestamp: "2026-10-03 12:00:01.250".to_string(),
+            since_previous_ms: Some(1_250),
+            since_reference_ms: None,
+        };
+        assert_eq!(format_time_prefix(Some(&time), false, DeltaColumn::Off), "");
+        assert_eq!(
+            format_time_prefix(Some(&time), true, DeltaColumn::SincePrevious),
+            "2026-10-03 12:00:01.250   +1.250s "
+        );
+        assert_eq!(
+            format_time_prefix(Some(&time), false, DeltaColumn::SinceReference),
+            " ".repeat(10)
+        );
+        assert_eq!(
+            format_time_prefix(None, true, DeltaColumn::Off),
+            " ".repeat(24)
+        );
+        assert_eq!(DeltaColumn::SinceReference.next(), DeltaColumn::Off);
+
+        let settings = TimeSettings {
+            time_zone: "+02:00".to_string(),
+            reference_line: Some(12),
+            reference_timestamp: None,
+        };
+        assert_eq!(format_time_status(&settings, false, DeltaColumn::Off), "");
+        assert_eq!(
+            format_time_status(&settings, true, DeltaColumn::Off),
+            " | Zone: +02:00 | Ref: 12"
+        );
+    }
+
+    #[test]
+    fn log_format_status_shows_the_detection_confidence_while_it_applies() {
+        let detection = FormatDetection {
+            format: "json".to_string(),
+            confidence: 0.916,
+        };
+        assert_eq!(
+            format_log_format_status(Some("json"), Some(&detection)),
+            " | Format: json (auto 92%)"
+        );
+        assert_eq!(
+            format_log_format_status(Some("csv"), Some(&detection)),
+            " | Format: csv"
+        );
+        assert_eq!(
+            format_log_format_status(None, Some(&detection)),
+            " | Format: plain"
         );
     }
 
@@ -441,4 +1101,43 @@ mod tests {
             "error no matches yet searching..."
         );
     }
+
+    #[test]
+    fn format_rule_counts_status_shows_selected_rule_count_and_position() {
+        let status = RuleCountStatus {
+            generation: 1,
+            revision: 2,
+            profile: "Default".to_string(),
+            is_ready: false,
+            rules: vec![
+                RuleCount {
+                    name: Some("errors".to_string()),
+                    style: LineStyleIntent::default(),
+                    matched_lines: 12,
+                    current_line_index: Some(40),
+                },
+                RuleCount {
+                    name: None,
+                    style: LineStyleIntent::default(),
+                    matched_lines: 3,
+                    current_line_index: None,
+                },
+            ],
+        };
+        assert_eq!(
+            format_rule_counts_status(&status, 0),
+            " | errors 12 lines, at 41 counting..."
+        );
+        assert_eq!(
+            format_rule_counts_status(
+                &RuleCountStatus {
+                    is_ready: true,
+                    ..status.clone()
+                },
+                1
+            ),
+            " | Rule 2 3 lines"
+        );
+        assert_eq!(format_rule_counts_status(&status, 2), "");
+    }
 }
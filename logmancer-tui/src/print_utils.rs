@@ -14,20 +14,46 @@ macro_rules! print_row {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum HighlightKind {
     Plain,
+    /// Span of a match-scoped visual rule, at this position in `PageLine::spans`.
+    Rule(usize),
+    /// Occurrence of the mark at this position in `PageResult::marks`.
+    Mark(usize),
+    CurrentMark(usize),
     Match,
     CurrentMatch,
 }
 
+impl HighlightKind {
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Plain => 0,
+            Self::Rule(_) => 2,
+            Self::Mark(_) => 4,
+            Self::CurrentMark(_) => 6,
+            Self::Match => 10,
+            Self::CurrentMatch => 40,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct HighlightSegment<'a> {
     pub text: &'a str,
     pub kind: HighlightKind,
 }
 
+/// Splits `line` at every span boundary; overlapping spans resolve to the highest precedence kind.
 pub fn split_highlighted_segments<'a>(
     line: &'a str,
-    spans: &[(usize, usize, bool)],
+    spans: &[(usize, usize, HighlightKind)],
 ) -> Vec<HighlightSegment<'a>> {
+    let spans = spans
+        .iter()
+        .map(|(start, end, kind)| ((*start).min(line.len()), (*end).min(line.len()), *kind))
+        .filter(|(start, end, _)| {
+            start < end && line.is_char_boundary(*start) && line.is_char_boundary(*end)
+        })
+        .collect::<Vec<_>>();
     if spans.is_empty() {
         return vec![HighlightSegment {
             text: line,
@@ -35,39 +61,40 @@ pub fn split_highlighted_segments<'a>(
         }];
     }
 
-    let mut segments = Vec::new();
-    let mut cursor = 0usize;
-
-    for (start, end, is_current) in spans {
-        let start = (*start).min(line.len());
-        let end = (*end).min(line.len());
-
-        if cursor < start {
-            segments.push(HighlightSegment {
-                text: &line[cursor..start],
-                kind: HighlightKind::Plain,
-            });
-        }
+    let mut split_points = vec![0, line.len()];
+    for (start, end, _) in &spans {
+        split_points.push(*start);
+        split_points.push(*end);
+    }
+    split_points.sort_unstable();
+    split_points.dedup();
 
-        if start < end {
-            segments.push(HighlightSegment {
-                text: &line[start..end],
-                kind: if *is_current {
-                    HighlightKind::CurrentMatch
+    let mut segments: Vec<HighlightSegment<'a>> = Vec::new();
+    let mut segment_start = 0;
+    for window in split_points.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        let kind = spans
+            .iter()
+            .filter(|(span_start, span_end, _)| *span_start <= start && end <= *span_end)
+            .map(|(_, _, kind)| *kind)
+            .fold(HighlightKind::Plain, |selected, kind| {
+                if kind.precedence() > selected.precedence() {
+                    kind
                 } else {
-                    HighlightKind::Match
-                },
+                    selected
+                }
             });
-        }
-
-        cursor = end;
-    }
 
-    if cursor < line.len() {
-        segments.push(HighlightSegment {
-            text: &line[cursor..],
-            kind: HighlightKind::Plain,
-        });
+        match segments.last_mut() {
+            Some(last) if last.kind == kind => last.text = &line[segment_start..end],
+            _ => {
+                segment_start = start;
+                segments.push(HighlightSegment {
+                    text: &line[start..end],
+                    kind,
+                });
+            }
+        }
     }
 
     segments
@@ -79,7 +106,13 @@ mod tests {
 
     #[test]
     fn split_highlighted_segments_marks_current_and_secondary_matches() {
-        let segments = split_highlighted_segments("foo bar baz", &[(0, 3, false), (8, 11, true)]);
+        let segments = split_highlighted_segments(
+            "foo bar baz",
+            &[
+                (0, 3, HighlightKind::Match),
+                (8, 11, HighlightKind::CurrentMatch),
+            ],
+        );
 
         assert_eq!(
             segments,
@@ -102,7 +135,7 @@ mod tests {
 
     #[test]
     fn split_highlighted_segments_clamps_spans_to_visible_prefix() {
-        let segments = split_highlighted_segments("foobar", &[(3, 12, false)]);
+        let segments = split_highlighted_segments("foobar", &[(3, 12, HighlightKind::Match)]);
 
         assert_eq!(
             segments,
@@ -118,4 +151,64 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn split_highlighted_segments_draws_search_over_overlapping_marks() {
+        let segments = split_highlighted_segments(
+            "req-7 failed",
+            &[
+                (0, 12, HighlightKind::Mark(0)),
+                (0, 5, HighlightKind::CurrentMark(1)),
+                (6, 12, HighlightKind::Match),
+            ],
+        );
+
+        assert_eq!(
+            segments,
+            vec![
+                HighlightSegment {
+                    text: "req-7",
+                    kind: HighlightKind::CurrentMark(1),
+                },
+                HighlightSegment {
+                    text: " ",
+                    kind: HighlightKind::Mark(0),
+                },
+                HighlightSegment {
+                    text: "failed",
+                    kind: HighlightKind::Match,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn split_highlighted_segments_keeps_rule_priority_below_marks() {
+        let segments = split_highlighted_segments(
+            "user=bob ok",
+            &[
+                (0, 8, HighlightKind::Rule(0)),
+                (5, 11, HighlightKind::Rule(1)),
+                (5, 8, HighlightKind::Mark(0)),
+            ],
+        );
+
+        assert_eq!(
+            segments,
+            vec![
+                HighlightSegment {
+                    text: "user=",
+                    kind: HighlightKind::Rule(0),
+                },
+                HighlightSegment {
+                    text: "bob",
+                    kind: HighlightKind::Mark(0),
+                },
+                HighlightSegment {
+                    text: " ok",
+                    kind: HighlightKind::Rule(1),
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,240 @@
+//! Terminal colors for visual-rule palette tokens, at the color depth the terminal supports.
+
+use crossterm::style::Color;
+use logmancer_core::{Rgb, VisualColor, VisualColorRole, VisualPalette, VisualTheme};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    pub fn from_env() -> Self {
+        Self::detect(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    /// Truecolor when `COLORTERM` advertises it, 256 colors for `*-256color` terminals and
+    /// the 16 ANSI colors otherwise.
+    pub fn detect(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if colorterm.is_some_and(|value| value == "truecolor" || value == "24bit") {
+            Self::TrueColor
+        } else if term.is_some_and(|value| value.ends_with("256color")) {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// Light terminals when `LOGMANCER_THEME` is `light`, dark ones otherwise.
+pub fn theme_from_env() -> VisualTheme {
+    match std::env::var("LOGMANCER_THEME").ok().as_deref() {
+        Some("light") => VisualTheme::Light,
+        _ => VisualTheme::Dark,
+    }
+}
+
+/// Palette of the loaded rules, resolved for one terminal.
+pub struct TerminalPalette {
+    pub palette: VisualPalette,
+    pub depth: ColorDepth,
+    pub theme: VisualTheme,
+}
+
+impl TerminalPalette {
+    /// Maps a rule color to the terminal; `default` and unknown tokens have none.
+    ///
+    /// Built-in tokens keep their ANSI color at 16 colors; beyond that, and for custom colors,
+    /// the palette's variant for the terminal theme is used as is or approximated.
+    pub fn rule_color(&self, token: &VisualColor, role: VisualColorRole) -> Option<Color> {
+        if self.depth == ColorDepth::Ansi16
+            && let Some(color) = palette_color(token)
+        {
+            return Some(color);
+        }
+        let Rgb(r, g, b) = self.palette.resolve(token, role, self.theme)?;
+        Some(match self.depth {
+            ColorDepth::TrueColor => Color::Rgb { r, g, b },
+            ColorDepth::Ansi256 => Color::AnsiValue(ansi256(r, g, b)),
+            ColorDepth::Ansi16 => ansi16(r, g, b),
+        })
+    }
+}
+
+/// Maps a palette token to its ANSI 16 color.
+pub fn palette_color(token: &VisualColor) -> Option<Color> {
+    match token.0.as_str() {
+        "red" => Some(Color::Red),
+        "orange" => Some(Color::DarkYellow),
+        "yellow" => Some(Color::Yellow),
+        "green" => Some(Color::Green),
+        "cyan" => Some(Color::Cyan),
+        "blue" => Some(Color::Blue),
+        "purple" => Some(Color::Magenta),
+        "gray" => Some(Color::Grey),
+        _ => None,
+    }
+}
+
+fn distance((r, g, b): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    [(r, r2), (g, g2), (b, b2)]
+        .into_iter()
+        .map(|(left, right)| u32::from(left.abs_diff(right)).pow(2))
+        .sum()
+}
+
+/// Nearest of the 16 ANSI colors, taking their usual xterm values.
+fn ansi16(r: u8, g: u8, b: u8) -> Color {
+    const ANSI: [(Color, (u8, u8, u8)); 16] = [
+        (Color::Black, (0, 0, 0)),
+        (Color::DarkRed, (205, 0, 0)),
+        (Color::DarkGreen, (0, 205, 0)),
+        (Color::DarkYellow, (205, 205, 0)),
+        (Color::DarkBlue, (0, 0, 238)),
+        (Color::DarkMagenta, (205, 0, 205)),
+        (Color::DarkCyan, (0, 205, 205)),
+        (Color::Grey, (229, 229, 229)),
+        (Color::DarkGrey, (127, 127, 127)),
+        (Color::Red, (255, 0, 0)),
+        (Color::Green, (0, 255, 0)),
+        (Color::Yellow, (255, 255, 0)),
+        (Color::Blue, (92, 92, 255)),
+        (Color::Magenta, (255, 0, 255)),
+        (Color::Cyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+    ANSI.into_iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(color, _)| color)
+        .unwrap_or(Color::Reset)
+}
+
+/// Nearest xterm 256-color index, from the 6×6×6 cube or the grayscale ramp.
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |value: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&index| LEVELS[index].abs_diff(value))
+            .unwrap_or(0)
+    };
+    let distance = |other| distance((r, g, b), other);
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let average = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_value = 8 + gray_step * 10;
+    if distance((gray_value, gray_value, gray_value)) < distance(cube) {
+        232 + gray_step
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorDepth, TerminalPalette, ansi16, ansi256};
+    use crossterm::style::Color;
+    use logmancer_core::{
+        PaletteColor, PaletteVariant, VisualColor, VisualColorRole, VisualPalette, VisualTheme,
+    };
+
+    fn terminal(depth: ColorDepth, theme: VisualTheme) -> TerminalPalette {
+        TerminalPalette {
+            palette: VisualPalette::new(vec![PaletteColor {
+                name: "teal".to_string(),
+                light: PaletteVariant {
+                    foreground: "#0f766e".to_string(),
+                    background: "#ccfbf1".to_string(),
+                },
+                dark: PaletteVariant {
+                    foreground: "#2dd4bf".to_string(),
+                    background: "#134e4a".to_string(),
+                },
+            }]),
+            depth,
+            theme,
+        }
+    }
+
+    #[test]
+    fn depth_follows_colorterm_then_term() {
+        assert_eq!(
+            ColorDepth::detect(Some("truecolor"), Some("xterm-256color")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::detect(None, Some("xterm-256color")),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(ColorDepth::detect(None, Some("xterm")), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::detect(None, None), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn rule_colors_map_per_depth_and_role() {
+        let red = VisualColor("red".to_string());
+        let dark = |depth| terminal(depth, VisualTheme::Dark);
+        assert_eq!(
+            dark(ColorDepth::Ansi16).rule_color(&red, VisualColorRole::Background),
+            Some(Color::Red)
+        );
+        assert_eq!(
+            dark(ColorDepth::TrueColor).rule_color(&red, VisualColorRole::Foreground),
+            Some(Color::Rgb {
+                r: 0xf8,
+                g: 0x71,
+                b: 0x71
+            })
+        );
+        assert_eq!(
+            dark(ColorDepth::Ansi256).rule_color(&red, VisualColorRole::Background),
+            Some(Color::AnsiValue(88))
+        );
+        let default = VisualColor("default".to_string());
+        assert_eq!(
+            dark(ColorDepth::TrueColor).rule_color(&default, VisualColorRole::Foreground),
+            None
+        );
+    }
+
+    #[test]
+    fn custom_colors_use_the_theme_variant_at_every_depth() {
+        let teal = VisualColor("teal".to_string());
+        assert_eq!(
+            terminal(ColorDepth::TrueColor, VisualTheme::Light)
+                .rule_color(&teal, VisualColorRole::Foreground),
+            Some(Color::Rgb {
+                r: 0x0f,
+                g: 0x76,
+                b: 0x6e
+            })
+        );
+        assert_eq!(
+            terminal(ColorDepth::Ansi16, VisualTheme::Dark)
+                .rule_color(&teal, VisualColorRole::Foreground),
+            Some(Color::DarkCyan)
+        );
+        assert_eq!(
+            terminal(ColorDepth::Ansi16, VisualTheme::Dark).rule_color(
+                &VisualColor("unknown".to_string()),
+                VisualColorRole::Foreground
+            ),
+            None
+        );
+        assert_eq!(ansi16(250, 250, 250), Color::White);
+    }
+
+    #[test]
+    fn ansi256_picks_the_cube_or_the_grayscale_ramp() {
+        assert_eq!(ansi256(0, 0, 0), 16);
+        assert_eq!(ansi256(255, 255, 255), 231);
+        assert_eq!(ansi256(255, 0, 0), 196);
+        assert_eq!(ansi256(0x37, 0x41, 0x51), 238);
+    }
+}
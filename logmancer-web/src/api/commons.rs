@@ -1,4 +1,7 @@
-use logmancer_core::VisualRulesEnvelope;
+use logmancer_core::{
+    LogFormatDefinition, LogFormatsEnvelope, ValidationDiagnostic, VisualColor,
+    VisualRulesEnvelope, VisualRulesFormat,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -17,6 +20,15 @@ pub struct ApiError {
     pub message: String,
 }
 
+/// A filter query that does not parse.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QueryErrorResponse {
+    pub code: String,
+    pub message: String,
+    /// Characters of the query before the error.
+    pub position: usize,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct VisualRulesSaveRequest {
@@ -32,6 +44,102 @@ pub struct VisualRulesResponse {
     pub diagnostics: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VisualRulesChangesRequest {
+    /// Revision the client already shows; the request waits for a different one. Absent, it
+    /// answers with the current revision at once.
+    pub after: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VisualRulesChangesResponse {
+    pub revision: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualRulesImportRequest {
+    pub format: VisualRulesFormat,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualRulesImportResponse {
+    /// Converted rules; nothing is saved until the client saves them.
+    pub envelope: VisualRulesEnvelope,
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualRulesExportRequest {
+    pub format: VisualRulesFormat,
+    /// Rules to export, such as an unsaved draft; the saved rules when absent.
+    pub envelope: Option<VisualRulesEnvelope>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualRulesExportResponse {
+    pub file_name: String,
+    pub content: String,
+    pub diagnostics: Vec<ValidationDiagnostic>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VisualProfileRequest {
+    pub file_id: String,
+    /// Profile to pin for the file; `None` returns it to auto-selection.
+    pub profile: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFormatsSaveRequest {
+    pub base_revision: u64,
+    pub envelope: LogFormatsEnvelope,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFormatsResponse {
+    pub revision: u64,
+    /// Custom formats only; the built-in ones are listed separately and never saved.
+    pub envelope: LogFormatsEnvelope,
+    pub builtins: Vec<LogFormatDefinition>,
+    pub diagnostics: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogFormatRequest {
+    pub file_id: String,
+    /// Format to parse the file with; `None` stops parsing it.
+    pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogColumnsRequest {
+    pub file_id: String,
+    /// Field paths to show as columns; empty for the plain text view.
+    pub columns: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogFieldsRequest {
+    pub file_id: String,
+    /// Lines sampled from the start of the file; the core maximum when absent.
+    pub sample_lines: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VisualRuleNavigateRequest {
+    pub file_id: String,
+    /// Rule index within the active profile, as listed by the rule counts.
+    pub rule: usize,
+    pub max_lines: usize,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ServerBrowserStatusResponse {
     pub enabled: bool,
@@ -88,6 +196,36 @@ pub struct TailRequest {
 pub struct ApplyFilterRequest {
     pub file_id: String,
     pub filter: String,
+    /// Field the filter is tested against, parsed with the file's log format; the whole line
+    /// when absent.
+    #[serde(default)]
+    pub field: Option<String>,
+    /// Whether `filter` is a field query such as `level >= WARN and duration > 250ms`
+    /// rather than a regex; `field` is then ignored.
+    #[serde(default)]
+    pub query: bool,
+}
+
+/// A SQL query over the `log` table of an open file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SqlRequest {
+    pub file_id: String,
+    pub query: String,
+}
+
+/// Time zone page timestamps of an open file are shown in.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TimeZoneRequest {
+    pub file_id: String,
+    pub time_zone: String,
+}
+
+/// Line, counted from 1, that reference time deltas are measured from; `None` clears it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TimeReferenceRequest {
+    pub file_id: String,
+    #[serde(default)]
+    pub line: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -114,3 +252,49 @@ pub struct SearchNavigateRequest {
 pub struct SearchStatusRequest {
     pub file_id: String,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchHitsRequest {
+    pub file_id: String,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DensityMapRequest {
+    pub file_id: String,
+    pub buckets: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchSelectRequest {
+    pub file_id: String,
+    pub line_index: usize,
+    pub start: usize,
+    pub max_lines: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddMarkRequest {
+    pub file_id: String,
+    pub query: String,
+    pub color: Option<VisualColor>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddMarkResponse {
+    pub mark_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MarkRequest {
+    pub file_id: String,
+    pub mark_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MarkNavigateRequest {
+    pub file_id: String,
+    pub mark_id: u64,
+    pub max_lines: usize,
+}
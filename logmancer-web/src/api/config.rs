@@ -1,13 +1,28 @@
+use crate::api::density_map::density_map;
 use crate::api::file_info::file_info;
 use crate::api::filter::{apply_filter, read_filter_page};
+use crate::api::log_formats::{
+    get_log_format, get_log_formats, log_fields, replace_log_formats, save_log_formats,
+    set_log_columns, set_log_format,
+};
+use crate::api::marks::{
+    add_mark, clear_marks, mark_next, mark_previous, marks_status, remove_mark,
+};
 use crate::api::read_page::{read_page, tail};
-use crate::api::search::{apply_search, clear_search, search_next, search_previous, search_status};
+use crate::api::search::{
+    apply_search, clear_search, search_hits, search_next, search_previous, search_select,
+    search_status,
+};
 use crate::api::server_browser::{
     server_browser_list, server_browser_open, server_browser_status, ServerFileRoot,
 };
+use crate::api::sql::run_sql;
+use crate::api::time_settings::{get_time_settings, set_time_reference, set_time_zone};
 use crate::api::upload_file::upload_file;
 use crate::api::visual_rules::{
-    get_visual_rules, replace_visual_rules, retry_visual_rules, save_visual_rules,
+    export_visual_rules_file, get_visual_profile, get_visual_rules, import_visual_rules_file,
+    replace_visual_rules, retry_visual_rules, save_visual_rules, set_visual_profile,
+    visual_rule_counts, visual_rule_next, visual_rule_previous, visual_rules_changes,
 };
 use axum::extract::DefaultBodyLimit;
 use axum::routing::{get, post};
@@ -45,10 +60,38 @@ pub fn api_routes_with_registry_and_manager<T>(
         .route("/search-status", get(search_status))
         .route("/search-next", get(search_next))
         .route("/search-previous", get(search_previous))
+        .route("/search-hits", get(search_hits))
+        .route("/search-select", get(search_select))
+        .route("/add-mark", post(add_mark))
+        .route("/remove-mark", get(remove_mark))
+        .route("/clear-marks", get(clear_marks))
+        .route("/marks-status", get(marks_status))
+        .route("/mark-next", get(mark_next))
+        .route("/mark-previous", get(mark_previous))
+        .route("/density-map", get(density_map))
         .route("/visual-rules", get(get_visual_rules))
         .route("/visual-rules/save", post(save_visual_rules))
         .route("/visual-rules/retry", post(retry_visual_rules))
+        .route("/visual-rules/changes", get(visual_rules_changes))
         .route("/visual-rules/replace", post(replace_visual_rules))
+        .route("/visual-rules/import", post(import_visual_rules_file))
+        .route("/visual-rules/export", post(export_visual_rules_file))
+        .route("/visual-profile", get(get_visual_profile))
+        .route("/visual-profile/set", post(set_visual_profile))
+        .route("/visual-rule-counts", get(visual_rule_counts))
+        .route("/visual-rule-next", get(visual_rule_next))
+        .route("/visual-rule-previous", get(visual_rule_previous))
+        .route("/log-formats", get(get_log_formats))
+        .route("/log-formats/save", post(save_log_formats))
+        .route("/log-formats/replace", post(replace_log_formats))
+        .route("/log-format", get(get_log_format))
+        .route("/log-format/set", post(set_log_format))
+        .route("/log-format/columns", post(set_log_columns))
+        .route("/log-fields", get(log_fields))
+        .route("/sql", post(run_sql))
+        .route("/time-settings", get(get_time_settings))
+        .route("/time-settings/zone", post(set_time_zone))
+        .route("/time-settings/reference", post(set_time_reference))
         .layer(DefaultBodyLimit::max(LOG_UPLOAD_BODY_LIMIT_BYTES))
         .with_state(AppState {
             registry,
@@ -68,9 +111,14 @@ pub fn api_routes<T>() -> Router<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::commons::{LogFormatsResponse, VisualRulesChangesResponse};
     use axum::body::Body;
     use axum::http::{Method, Request, StatusCode};
-    use logmancer_core::{NativeVisualRulesStore, VisualRulesEnvelope, VisualRulesManager};
+    use logmancer_core::{
+        LogFormatManager, LogFormatStatus, ManagedVisualRule, NativeVisualRulesStore,
+        ProfileAutoSelect, RuleCountStatus, RuleNavigation, VisualMatcher, VisualProfileStatus,
+        VisualRuleProfile, VisualRulesEnvelope, VisualRulesManager,
+    };
     use std::sync::Arc;
     use tower::ServiceExt;
 
@@ -181,13 +229,815 @@ mod tests {
         assert_eq!(response.status(), StatusCode::CONFLICT);
     }
 
+    #[tokio::test]
+    async fn visual_rules_changes_wait_until_the_revision_moves_on() {
+        let manager = VisualRulesManager::in_memory();
+        let router: Router =
+            api_routes_with_registry_and_manager(Arc::new(LogRegistry::new()), manager.clone());
+        let changes = |query: &str| {
+            Request::builder()
+                .uri(format!("/visual-rules/changes{query}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let revision = |body: axum::body::Bytes| {
+            serde_json::from_slice::<VisualRulesChangesResponse>(&body)
+                .unwrap()
+                .revision
+        };
+
+        let current = router.clone().oneshot(changes("")).await.unwrap();
+        let body = axum::body::to_bytes(current.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(revision(body), 0);
+
+        let publisher = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            manager
+                .apply_memory(VisualRulesEnvelope::new(Vec::new()))
+                .unwrap();
+        });
+        let changed = router.oneshot(changes("?after=0")).await.unwrap();
+        assert_eq!(changed.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(changed.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(revision(body), 1);
+        publisher.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn visual_profile_routes_report_and_pin_profiles_per_open_file() {
+        let directory = tempfile::tempdir().unwrap().keep();
+        let log_path = directory.join("access.log");
+        std::fs::write(&log_path, "GET /\n").unwrap();
+        let manager = VisualRulesManager::in_memory();
+        let mut envelope = VisualRulesEnvelope::new(Vec::new());
+        envelope.profiles = vec![VisualRuleProfile {
+            name: "nginx".to_string(),
+            rules: Vec::new(),
+            auto_select: Some(ProfileAutoSelect {
+                path_glob: Some("access.log".to_string()),
+                format: None,
+            }),
+        }];
+        manager.apply_memory(envelope).unwrap();
+        let registry = Arc::new(LogRegistry::with_manager(manager.clone()));
+        let file_id = registry.open_file(log_path.to_str().unwrap()).unwrap();
+        let router: Router = api_routes_with_registry_and_manager(registry, manager);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/visual-profile?file_id={file_id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: VisualProfileStatus = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status.active, "nginx");
+        assert_eq!(status.auto_selected.as_deref(), Some("nginx"));
+
+        let pin = |profile: serde_json::Value| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/visual-profile/set")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "file_id": file_id, "profile": profile }).to_string(),
+                ))
+                .unwrap()
+        };
+        let unknown = router
+            .clone()
+            .oneshot(pin(serde_json::json!("jvm")))
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+
+        let pinned = router
+            .oneshot(pin(serde_json::json!("Default")))
+            .await
+            .unwrap();
+        assert_eq!(pinned.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(pinned.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: VisualProfileStatus = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status.active, "Default");
+        assert_eq!(status.pinned.as_deref(), Some("Default"));
+    }
+
+    #[tokio::test]
+    async fn visual_rule_count_routes_count_lines_and_navigate_by_rule() {
+        let directory = tempfile::tempdir().unwrap().keep();
+        let log_path = directory.join("app.log");
+        std::fs::write(&log_path, "ERROR disk\nINFO ok\nERROR OutOfMemory\n").unwrap();
+        let manager = VisualRulesManager::in_memory();
+        manager
+            .apply_memory(VisualRulesEnvelope::new(vec![ManagedVisualRule {
+                name: Some("Errors".to_string()),
+                enabled: true,
+                matcher: VisualMatcher::Text("ERROR".to_string()),
+                case_sensitive: true,
+                style: Default::default(),
+                scope: Default::default(),
+            }]))
+            .unwrap();
+        let registry = Arc::new(LogRegistry::with_manager(manager.clone()));
+        let file_id = registry.open_file(log_path.to_str().unwrap()).unwrap();
+        let router: Router = api_routes_with_registry_and_manager(registry, manager);
+        let get = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let mut status = None;
+        for _ in 0..50 {
+            let response = router
+                .clone()
+                .oneshot(get(format!("/visual-rule-counts?file_id={file_id}")))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let current: RuleCountStatus = serde_json::from_slice(&body).unwrap();
+            if current.is_ready && current.rules[0].matched_lines == 2 {
+                status = Some(current);
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        let status = status.expect("rule counts become ready");
+        assert_eq!(status.rules[0].name.as_deref(), Some("Errors"));
+
+        let response = router
+            .clone()
+            .oneshot(get(format!(
+                "/visual-rule-previous?file_id={file_id}&rule=0&max_lines=2"
+            )))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let navigation: RuleNavigation = serde_json::from_slice(&body).unwrap();
+        assert_eq!(navigation.line_index, Some(2));
+        assert_eq!(navigation.page.start_line, 1);
+
+        let unknown_rule = router
+            .clone()
+            .oneshot(get(format!(
+                "/visual-rule-next?file_id={file_id}&rule=4&max_lines=2"
+            )))
+            .await
+            .unwrap();
+        assert_eq!(unknown_rule.status(), StatusCode::NOT_FOUND);
+        let unknown_file = router
+            .oneshot(get("/visual-rule-counts?file_id=missing".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(unknown_file.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn visual_rules_import_and_export_convert_without_saving() {
+        let manager = VisualRulesManager::in_memory();
+        let router: Router =
+            api_routes_with_registry_and_manager(Arc::new(LogRegistry::new()), manager.clone());
+        let post = |uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(post(
+                "/visual-rules/import",
+                serde_json::json!({
+                    "format": "klogg",
+                    "content": "[FilterSet]\nfilters\\1\\regexp=ERROR\nfilters\\1\\fore_colour=#ff0000\nfilters\\size=1\n",
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let import: crate::api::commons::VisualRulesImportResponse =
+            serde_json::from_slice(&body).unwrap();
+        assert_eq!(import.envelope.rules.len(), 1);
+        assert_eq!(import.diagnostics.len(), 1);
+        assert!(manager.state().envelope.rules.is_empty());
+
+        let malformed = router
+            .clone()
+            .oneshot(post(
+                "/visual-rules/import",
+                serde_json::json!({ "format": "lnav", "content": "{" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(malformed.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = router
+            .oneshot(post(
+                "/visual-rules/export",
+                serde_json::json!({ "format": "less", "envelope": import.envelope }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let export: crate::api::commons::VisualRulesExportResponse =
+            serde_json::from_slice(&body).unwrap();
+        assert_eq!(export.file_name, "lesskey");
+        assert_eq!(
+            export.content,
+            "#env\nLESS = --use-color -D1r +/(.*(?:ERROR).*)\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn log_format_routes_save_custom_formats_and_parse_pages_of_the_chosen_file() {
+        let directory = tempfile::tempdir().unwrap().keep();
+        let log_path = directory.join("access.log");
+        std::fs::write(&log_path, "10.0.0.1 404 GET /missing\n").unwrap();
+        let formats = LogFormatManager::with_store(Arc::new(NativeVisualRulesStore::new(
+            directory.join("log-formats.json"),
+        )));
+        formats.load().unwrap();
+        let manager = VisualRulesManager::in_memory();
+        let registry = Arc::new(LogRegistry::with_managers(manager.clone(), formats));
+        let file_id = registry.open_file(log_path.to_str().unwrap()).unwrap();
+        let router: Router = api_routes_with_registry_and_manager(registry, manager);
+        let post = |uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let access = |pattern: &str| {
+            serde_json::json!({
+                "baseRevision": 1,
+                "envelope": {
+                    "schemaVersion": 1,
+                    "formats": [{ "name": "access", "pattern": pattern }],
+                },
+            })
+        };
+
+        let invalid = router
+            .clone()
+            .oneshot(post("/log-formats/save", access("(.*)")))
+            .await
+            .unwrap();
+        assert_eq!(invalid.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let saved = router
+            .clone()
+            .oneshot(post(
+                "/log-formats/save",
+                access(r"^(?P<client>\S+) (?P<status>\d{3}) (?P<message>.*)$"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(saved.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(saved.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let saved: LogFormatsResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(saved.revision, 2);
+        assert_eq!(saved.builtins.len(), 9);
+        let stale = router
+            .clone()
+            .oneshot(post("/log-formats/save", access("(?P<message>.*)")))
+            .await
+            .unwrap();
+        assert_eq!(stale.status(), StatusCode::CONFLICT);
+
+        let unknown = router
+            .clone()
+            .oneshot(post(
+                "/log-format/set",
+                serde_json::json!({ "file_id": file_id, "format": "nginx" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+        let chosen = router
+            .clone()
+            .oneshot(post(
+                "/log-format/set",
+                serde_json::json!({ "file_id": file_id, "format": "access" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(chosen.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(chosen.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: LogFormatStatus = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status.active.as_deref(), Some("access"));
+        assert_eq!(
+            status.formats,
+            [
+                "log4j",
+                "python",
+                "go",
+                "syslog",
+                "access-log",
+                "json",
+                "logfmt",
+                "csv",
+                "tsv",
+                "access"
+            ]
+        );
+
+        let page = router
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/read-page?file_id={file_id}&start_line=0&max_lines=1"
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(page.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            page["lines"][0]["fields"],
+            serde_json::json!([
+                ["client", "10.0.0.1"],
+                ["status", "404"],
+                ["message", "GET /missing"]
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn json_lines_routes_discover_fields_choose_columns_and_filter_by_field() {
+        let directory = tempfile::tempdir().unwrap().keep();
+        let log_path = directory.join("events.jsonl");
+        std::fs::write(
+            &log_path,
+            concat!(
+                r#"{"level":"info","http":{"status":200},"msg":"error budget ok"}"#,
+                "\n",
+                "not json\n",
+                r#"{"level":"error","http":{"status":503},"msg":"upstream"}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        let registry = Arc::new(LogRegistry::new());
+        let file_id = registry.open_file(log_path.to_str().unwrap()).unwrap();
+        let router: Router = api_routes_with_registry(registry);
+        let post = |uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let get = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let plain = router
+            .clone()
+            .oneshot(post(
+                "/log-format/set",
+                serde_json::json!({ "file_id": file_id, "format": null }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(json(plain).await["active"], serde_json::Value::Null);
+        let columns_without_format = router
+            .clone()
+            .oneshot(post(
+                "/log-format/columns",
+                serde_json::json!({ "file_id": file_id, "columns": ["level"] }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(columns_without_format.status(), StatusCode::BAD_REQUEST);
+        let field_filter_without_format = router
+            .clone()
+            .oneshot(post(
+                "/apply-filter",
+                serde_json::json!({ "file_id": file_id, "filter": "error", "field": "level" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            field_filter_without_format.status(),
+            StatusCode::BAD_REQUEST
+        );
+
+        router
+            .clone()
+            .oneshot(post(
+                "/log-format/set",
+                serde_json::json!({ "file_id": file_id, "format": "json" }),
+            ))
+            .await
+            .unwrap();
+        let mut discovery = serde_json::Value::Null;
+        for _ in 0..50 {
+            discovery = json(
+                router
+                    .clone()
+                    .oneshot(get(format!("/log-fields?file_id={file_id}")))
+                    .await
+                    .unwrap(),
+            )
+            .await;
+            if discovery["sampled_lines"] == 3 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(discovery["sampled_lines"], 3);
+        assert_eq!(discovery["parsed_lines"], 2);
+        assert_eq!(
+            discovery["fields"][1],
+            serde_json::json!({ "path": "http.status", "lines": 2 })
+        );
+        let status = json(
+            router
+                .clone()
+                .oneshot(post(
+                    "/log-format/columns",
+                    serde_json::json!({ "file_id": file_id, "columns": ["http.status", "msg"] }),
+                ))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status["columns"], serde_json::json!(["http.status", "msg"]));
+
+        let applied = router
+            .clone()
+            .oneshot(post(
+                "/apply-filter",
+                serde_json::json!({ "file_id": file_id, "filter": "error", "field": "level" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(applied.status(), StatusCode::OK);
+        let mut numbers = Vec::new();
+        for _ in 0..50 {
+            let page = json(
+                router
+                    .clone()
+                    .oneshot(get(format!(
+                        "/read-filter-page?file_id={file_id}&start_line=0&max_lines=10"
+                    )))
+                    .await
+                    .unwrap(),
+            )
+            .await;
+            numbers = page["lines"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|line| line["number"].as_u64().unwrap())
+                .collect();
+            if page["indexing_progress"] == 1.0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(numbers, [3]);
+
+        let invalid = router
+            .clone()
+            .oneshot(post(
+                "/apply-filter",
+                serde_json::json!({ "file_id": file_id, "filter": "level = error and", "query": true }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        let invalid = json(invalid).await;
+        assert_eq!(invalid["code"], "query_error");
+        assert_eq!(invalid["position"], 17);
+        assert_eq!(invalid["message"], "expected a field name at column 18");
+        let queried = router
+            .clone()
+            .oneshot(post(
+                "/apply-filter",
+                serde_json::json!({
+                    "file_id": file_id,
+                    "filter": "http.status < 500 or msg ~ /^up/",
+                    "query": true,
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(queried.status(), StatusCode::OK);
+        for _ in 0..50 {
+            let page = json(
+                router
+                    .clone()
+                    .oneshot(get(format!(
+                        "/read-filter-page?file_id={file_id}&start_line=0&max_lines=10"
+                    )))
+                    .await
+                    .unwrap(),
+            )
+            .await;
+            numbers = page["lines"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|line| line["number"].as_u64().unwrap())
+                .collect();
+            if numbers.len() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(numbers, [1, 3]);
+    }
+
+    #[tokio::test]
+    async fn csv_files_open_as_tables_with_header_columns_and_joined_records() {
+        let directory = tempfile::tempdir().unwrap().keep();
+        let log_path = directory.join("audit.csv");
+        std::fs::write(
+            &log_path,
+            "time,user,note\n10:00,alice,\"two\nlines\"\n10:01,bob,ok\n",
+        )
+        .unwrap();
+        let registry = Arc::new(LogRegistry::new());
+        let file_id = registry.open_file(log_path.to_str().unwrap()).unwrap();
+        let router: Router = api_routes_with_registry(registry);
+        let get = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let mut page = serde_json::Value::Null;
+        for _ in 0..50 {
+            page = json(
+                router
+                    .clone()
+                    .oneshot(get(format!(
+                        "/read-page?file_id={file_id}&start_line=0&max_lines=4"
+                    )))
+                    .await
+                    .unwrap(),
+            )
+            .await;
+            if page["lines"]
+                .as_array()
+                .is_some_and(|lines| lines.len() == 4)
+            {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(
+            page["lines"][1]["fields"],
+            serde_json::json!([["time", "10:00"], ["user", "alice"], ["note", "two\nlines"]])
+        );
+        assert_eq!(page["lines"][2]["continuation"], true);
+        assert_eq!(page["lines"][3].get("continuation"), None);
+        let info = json(
+            router
+                .clone()
+                .oneshot(get(format!("/file_info?file_id={file_id}")))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(
+            info["detected_format"],
+            serde_json::json!({"format": "csv", "confidence": 1.0})
+        );
+        let status = json(
+            router
+                .oneshot(get(format!("/log-format?file_id={file_id}")))
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status["active"], "csv");
+        assert_eq!(
+            status["columns"],
+            serde_json::json!(["time", "user", "note"])
+        );
+    }
+
     #[test]
     fn development_visual_rules_example_uses_the_current_envelope_schema() {
         let envelope: VisualRulesEnvelope =
             serde_json::from_str(include_str!("../../../examples/visual-rules.dev.json")).unwrap();
 
         let report = envelope.validate_for_save().unwrap();
-        assert_eq!(envelope.schema_version, 1);
+        assert_eq!(envelope.schema_version, VisualRulesEnvelope::SCHEMA_VERSION);
         assert_eq!(report.evaluator_rules.len(), 2);
+        assert_eq!(report.profiles.len(), 1);
+        assert_eq!(report.palette.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sql_route_returns_result_sets_linked_to_lines_and_rejects_writes() {
+        let directory = tempfile::tempdir().unwrap().keep();
+        let log_path = directory.join("requests.csv");
+        std::fs::write(&log_path, "path,duration\n/a,120\n/b,1800\n/a,2500\n").unwrap();
+        let registry = Arc::new(LogRegistry::new());
+        let file_id = registry.open_file(log_path.to_str().unwrap()).unwrap();
+        let router: Router = api_routes_with_registry(registry);
+        let sql = |query: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/sql")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "file_id": file_id, "query": query }).to_string(),
+                ))
+                .unwrap()
+        };
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let query = "SELECT log_line, path FROM log WHERE duration > 1000 ORDER BY duration DESC";
+        let mut result = serde_json::Value::Null;
+        for _ in 0..50 {
+            let response = router.clone().oneshot(sql(query)).await.unwrap();
+            // The header names the columns once indexing reaches it.
+            let ok = response.status() == StatusCode::OK;
+            result = json(response).await;
+            if ok
+                && result["rows"]
+                    .as_array()
+                    .is_some_and(|rows| rows.len() == 2)
+            {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(result["columns"], serde_json::json!(["log_line", "path"]));
+        assert_eq!(
+            result["rows"],
+            serde_json::json!([
+                { "line": 4, "values": [4, "/a"] },
+                { "line": 3, "values": [3, "/b"] }
+            ])
+        );
+        assert_eq!(result["truncated"], false);
+
+        let rejected = router
+            .clone()
+            .oneshot(sql("ATTACH DATABASE 'copy.db' AS copy"))
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+        let rejected = json(rejected).await;
+        assert_eq!(rejected["code"], "sql_error");
+        assert_eq!(
+            rejected["message"],
+            "only SELECT queries can run over a log"
+        );
+    }
+
+    #[tokio::test]
+    async fn time_settings_routes_set_the_zone_and_reference_line_of_page_timestamps() {
+        let directory = tempfile::tempdir().unwrap().keep();
+        let log_path = directory.join("times.jsonl");
+        std::fs::write(
+            &log_path,
+            concat!(
+                r#"{"ts":"2026-10-03T10:00:00Z","msg":"start"}"#,
+                "\n",
+                r#"{"ts":"2026-10-03T10:00:01.500Z","msg":"done"}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        let registry = Arc::new(LogRegistry::new());
+        let file_id = registry.open_file(log_path.to_str().unwrap()).unwrap();
+        let router: Router = api_routes_with_registry(registry);
+        let post = |uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let get = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        router
+            .clone()
+            .oneshot(post(
+                "/log-format/set",
+                serde_json::json!({ "file_id": file_id, "format": "json" }),
+            ))
+            .await
+            .unwrap();
+        let zone = router
+            .clone()
+            .oneshot(post(
+                "/time-settings/zone",
+                serde_json::json!({ "file_id": file_id, "time_zone": "+02:00" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(json(zone).await["time_zone"], "+02:00");
+        let invalid_zone = router
+            .clone()
+            .oneshot(post(
+                "/time-settings/zone",
+                serde_json::json!({ "file_id": file_id, "time_zone": "Mars/Olympus" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(invalid_zone.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json(invalid_zone).await["code"], "time_settings_error");
+
+        let mut reference = serde_json::Value::Null;
+        for _ in 0..50 {
+            let response = router
+                .clone()
+                .oneshot(post(
+                    "/time-settings/reference",
+                    serde_json::json!({ "file_id": file_id, "line": 1 }),
+                ))
+                .await
+                .unwrap();
+            let ok = response.status() == StatusCode::OK;
+            reference = json(response).await;
+            if ok {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(reference["reference_line"], 1);
+        assert_eq!(reference["reference_timestamp"], "2026-10-03 12:00:00.000");
+        let settings = router
+            .clone()
+            .oneshot(get(format!("/time-settings?file_id={file_id}")))
+            .await
+            .unwrap();
+        assert_eq!(json(settings).await, reference);
+
+        let page = router
+            .clone()
+            .oneshot(get(format!(
+                "/read-page?file_id={file_id}&start_line=0&max_lines=2"
+            )))
+            .await
+            .unwrap();
+        let page = json(page).await;
+        assert_eq!(
+            page["lines"][1]["time"],
+            serde_json::json!({
+                "timestamp": "2026-10-03 12:00:01.500",
+                "since_previous_ms": 1500,
+                "since_reference_ms": 1500
+            })
+        );
     }
 }
@@ -0,0 +1,23 @@
+use crate::api::commons::DensityMapRequest;
+use crate::api::config::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+
+pub async fn density_map(
+    State(app_state): State<AppState>,
+    query: Query<DensityMapRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => match reader.density_map(query.buckets) {
+            Ok(density_map) => (StatusCode::OK, Json(density_map)).into_response(),
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(format!("Error building density map: {e}")),
+            )
+                .into_response(),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
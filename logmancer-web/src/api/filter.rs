@@ -1,9 +1,10 @@
-use crate::api::commons::{ApplyFilterRequest, ReadFilterRequest};
+use crate::api::commons::{ApiError, ApplyFilterRequest, QueryErrorResponse, ReadFilterRequest};
 use crate::api::config::AppState;
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::Json;
+use logmancer_core::LogQuery;
 use tracing::debug;
 
 pub async fn apply_filter(
@@ -15,12 +16,43 @@ pub async fn apply_filter(
         payload.file_id, payload.filter
     );
 
-    match app_state.registry.get_reader(&payload.file_id) {
-        Some(mut reader) => {
-            reader.filter(payload.filter);
-            (StatusCode::OK, Json("Filter applied")).into_response()
+    let Some(mut reader) = app_state.registry.get_reader(&payload.file_id) else {
+        return (StatusCode::NOT_FOUND, Json("File not opened")).into_response();
+    };
+    let applied = if payload.query {
+        match LogQuery::parse(&payload.filter) {
+            Ok(query) => reader.filter_query(query),
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(QueryErrorResponse {
+                        code: "query_error".to_string(),
+                        message: e.to_string(),
+                        position: e.position,
+                    }),
+                )
+                    .into_response()
+            }
         }
-        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    } else {
+        match payload.field {
+            Some(field) => reader.filter_field(field, payload.filter),
+            None => {
+                reader.filter(payload.filter);
+                Ok(())
+            }
+        }
+    };
+    match applied {
+        Ok(()) => (StatusCode::OK, Json("Filter applied")).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                code: "filter_error".to_string(),
+                message: e.to_string(),
+            }),
+        )
+            .into_response(),
     }
 }
 
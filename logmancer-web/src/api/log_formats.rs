@@ -0,0 +1,166 @@
+use crate::api::commons::{
+    ApiError, FileInfoRequest, LogColumnsRequest, LogFieldsRequest, LogFormatRequest,
+    LogFormatsResponse, LogFormatsSaveRequest,
+};
+use crate::api::config::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use logmancer_core::{
+    builtin_log_formats, LogFormatsEnvelope, LogFormatsState, SaveOutcome, SaveResult,
+    VisualRulesError, MAX_FIELD_DISCOVERY_LINES,
+};
+
+pub async fn get_log_formats(State(app_state): State<AppState>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        Json(log_formats_response(
+            app_state.registry.log_formats().state(),
+        )),
+    )
+}
+
+pub async fn save_log_formats(
+    State(app_state): State<AppState>,
+    Json(request): Json<LogFormatsSaveRequest>,
+) -> Response {
+    let envelope = request.envelope.clone();
+    match app_state
+        .registry
+        .log_formats()
+        .save(request.base_revision, request.envelope)
+    {
+        Ok(result) => (StatusCode::OK, Json(log_formats_success(result, envelope))).into_response(),
+        Err(error) => log_formats_error(error),
+    }
+}
+
+pub async fn replace_log_formats(
+    State(app_state): State<AppState>,
+    Json(request): Json<LogFormatsSaveRequest>,
+) -> Response {
+    let envelope = request.envelope.clone();
+    match app_state
+        .registry
+        .log_formats()
+        .replace(request.base_revision, request.envelope)
+    {
+        Ok(result) => (StatusCode::OK, Json(log_formats_success(result, envelope))).into_response(),
+        Err(error) => log_formats_error(error),
+    }
+}
+
+pub async fn get_log_format(
+    State(app_state): State<AppState>,
+    query: Query<FileInfoRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(reader) => (StatusCode::OK, Json(reader.log_format())).into_response(),
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn set_log_format(
+    State(app_state): State<AppState>,
+    Json(payload): Json<LogFormatRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&payload.file_id) {
+        Some(mut reader) => match reader.set_log_format(payload.format) {
+            Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    code: "log_format_error".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+                .into_response(),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn set_log_columns(
+    State(app_state): State<AppState>,
+    Json(payload): Json<LogColumnsRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&payload.file_id) {
+        Some(mut reader) => match reader.set_columns(payload.columns) {
+            Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    code: "log_format_error".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+                .into_response(),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn log_fields(
+    State(app_state): State<AppState>,
+    query: Query<LogFieldsRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(reader) => {
+            match reader.discover_fields(query.sample_lines.unwrap_or(MAX_FIELD_DISCOVERY_LINES)) {
+                Ok(discovery) => (StatusCode::OK, Json(discovery)).into_response(),
+                Err(e) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(format!("Error reading file: {e}")),
+                )
+                    .into_response(),
+            }
+        }
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+fn log_formats_response(state: LogFormatsState) -> LogFormatsResponse {
+    LogFormatsResponse {
+        revision: state.revision,
+        envelope: state.envelope,
+        builtins: builtin_log_formats(),
+        diagnostics: state
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect(),
+    }
+}
+
+fn log_formats_success(result: SaveResult, envelope: LogFormatsEnvelope) -> LogFormatsResponse {
+    LogFormatsResponse {
+        revision: result.revision,
+        envelope,
+        builtins: builtin_log_formats(),
+        diagnostics: match result.outcome {
+            SaveOutcome::Committed => Vec::new(),
+            SaveOutcome::CommittedWithWarning(message) => vec![message],
+        },
+    }
+}
+
+fn log_formats_error(error: VisualRulesError) -> Response {
+    let status = match error {
+        VisualRulesError::Validation(_) | VisualRulesError::Decode(_) => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        VisualRulesError::RevisionConflict | VisualRulesError::SourceConflict => {
+            StatusCode::CONFLICT
+        }
+        VisualRulesError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        Json(ApiError {
+            code: "log_formats_error".to_string(),
+            message: error.to_string(),
+        }),
+    )
+        .into_response()
+}
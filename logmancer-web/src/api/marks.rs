@@ -0,0 +1,101 @@
+use crate::api::commons::{
+    AddMarkRequest, AddMarkResponse, MarkNavigateRequest, MarkRequest, SearchStatusRequest,
+};
+use crate::api::config::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use std::io;
+
+pub async fn add_mark(
+    State(app_state): State<AppState>,
+    Json(payload): Json<AddMarkRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&payload.file_id) {
+        Some(mut reader) => match reader.add_mark(payload.query, payload.color) {
+            Ok(mark_id) => (StatusCode::OK, Json(AddMarkResponse { mark_id })).into_response(),
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(format!("Error adding mark: {e}")),
+            )
+                .into_response(),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn remove_mark(
+    State(app_state): State<AppState>,
+    query: Query<MarkRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => {
+            if reader.remove_mark(query.mark_id) {
+                (StatusCode::OK, Json("Mark removed")).into_response()
+            } else {
+                (StatusCode::NOT_FOUND, Json("Mark not found")).into_response()
+            }
+        }
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn clear_marks(
+    State(app_state): State<AppState>,
+    query: Query<SearchStatusRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => {
+            reader.clear_marks();
+            (StatusCode::OK, Json("Marks cleared")).into_response()
+        }
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn marks_status(
+    State(app_state): State<AppState>,
+    query: Query<SearchStatusRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(reader) => (StatusCode::OK, Json(reader.marks_status())).into_response(),
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn mark_next(
+    State(app_state): State<AppState>,
+    query: Query<MarkNavigateRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => navigation_response(reader.mark_next(query.mark_id, query.max_lines)),
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn mark_previous(
+    State(app_state): State<AppState>,
+    query: Query<MarkNavigateRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => {
+            navigation_response(reader.mark_previous(query.mark_id, query.max_lines))
+        }
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+fn navigation_response(result: io::Result<logmancer_core::PageResult>) -> axum::response::Response {
+    match result {
+        Ok(page_result) => (StatusCode::OK, Json(page_result)).into_response(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            (StatusCode::NOT_FOUND, Json("Mark not found")).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(format!("Error navigating mark: {e}")),
+        )
+            .into_response(),
+    }
+}
@@ -21,5 +21,20 @@ pub mod server_browser;
 #[cfg(feature = "ssr")]
 pub mod search;
 
+#[cfg(feature = "ssr")]
+pub mod marks;
+
+#[cfg(feature = "ssr")]
+pub mod density_map;
+
 #[cfg(feature = "ssr")]
 pub mod visual_rules;
+
+#[cfg(feature = "ssr")]
+pub mod log_formats;
+
+#[cfg(feature = "ssr")]
+pub mod sql;
+
+#[cfg(feature = "ssr")]
+pub mod time_settings;
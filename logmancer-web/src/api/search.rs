@@ -1,4 +1,7 @@
-use crate::api::commons::{ApplySearchRequest, SearchNavigateRequest, SearchStatusRequest};
+use crate::api::commons::{
+    ApplySearchRequest, SearchHitsRequest, SearchNavigateRequest, SearchSelectRequest,
+    SearchStatusRequest,
+};
 use crate::api::config::AppState;
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
@@ -78,3 +81,39 @@ pub async fn search_previous(
         None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
     }
 }
+
+pub async fn search_hits(
+    State(app_state): State<AppState>,
+    query: Query<SearchHitsRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(reader) => match reader.search_hits(query.offset, query.limit) {
+            Ok(hits) => (StatusCode::OK, Json(hits)).into_response(),
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(format!("Error reading search hits: {e}")),
+            )
+                .into_response(),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn search_select(
+    State(app_state): State<AppState>,
+    query: Query<SearchSelectRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => {
+            match reader.search_select(query.line_index, query.start, query.max_lines) {
+                Ok(page_result) => (StatusCode::OK, Json(page_result)).into_response(),
+                Err(e) => (
+                    StatusCode::NOT_FOUND,
+                    Json(format!("Error selecting search match: {e}")),
+                )
+                    .into_response(),
+            }
+        }
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
@@ -0,0 +1,40 @@
+use crate::api::commons::{ApiError, SqlRequest};
+use crate::api::config::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use tracing::debug;
+
+pub async fn run_sql(
+    State(app_state): State<AppState>,
+    Json(payload): Json<SqlRequest>,
+) -> impl IntoResponse {
+    debug!(
+        "run_sql: file_id={}, query={}",
+        payload.file_id, payload.query
+    );
+
+    // The reader is released before the query runs, so paging the file is not held up by it.
+    let table = match app_state.registry.get_reader(&payload.file_id) {
+        Some(reader) => reader.sql_table(),
+        None => return (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    };
+    let result = match table {
+        Ok(table) => tokio::task::spawn_blocking(move || table.query(&payload.query))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e))),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                code: "sql_error".to_string(),
+                message: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
@@ -0,0 +1,63 @@
+use crate::api::commons::{ApiError, FileInfoRequest, TimeReferenceRequest, TimeZoneRequest};
+use crate::api::config::AppState;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use logmancer_core::TimeSettings;
+use std::io;
+use tracing::debug;
+
+pub async fn get_time_settings(
+    State(app_state): State<AppState>,
+    query: Query<FileInfoRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(reader) => (StatusCode::OK, Json(reader.time_settings())).into_response(),
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn set_time_zone(
+    State(app_state): State<AppState>,
+    Json(payload): Json<TimeZoneRequest>,
+) -> impl IntoResponse {
+    debug!(
+        "set_time_zone: file_id={}, time_zone={}",
+        payload.file_id, payload.time_zone
+    );
+
+    match app_state.registry.get_reader(&payload.file_id) {
+        Some(mut reader) => time_settings_response(reader.set_time_zone(&payload.time_zone)),
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn set_time_reference(
+    State(app_state): State<AppState>,
+    Json(payload): Json<TimeReferenceRequest>,
+) -> impl IntoResponse {
+    debug!(
+        "set_time_reference: file_id={}, line={:?}",
+        payload.file_id, payload.line
+    );
+
+    match app_state.registry.get_reader(&payload.file_id) {
+        Some(mut reader) => time_settings_response(reader.set_time_reference(payload.line)),
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+fn time_settings_response(result: io::Result<TimeSettings>) -> Response {
+    match result {
+        Ok(settings) => (StatusCode::OK, Json(settings)).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError {
+                code: "time_settings_error".to_string(),
+                message: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
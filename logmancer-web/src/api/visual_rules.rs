@@ -1,10 +1,22 @@
-use crate::api::commons::{ApiError, VisualRulesResponse, VisualRulesSaveRequest};
+use crate::api::commons::{
+    ApiError, FileInfoRequest, VisualProfileRequest, VisualRuleNavigateRequest,
+    VisualRulesChangesRequest, VisualRulesChangesResponse, VisualRulesExportRequest,
+    VisualRulesExportResponse, VisualRulesImportRequest, VisualRulesImportResponse,
+    VisualRulesResponse, VisualRulesSaveRequest,
+};
 use crate::api::config::AppState;
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use logmancer_core::{SaveOutcome, SaveResult, VisualRulesEnvelope, VisualRulesError};
+use logmancer_core::{
+    export_visual_rules, import_visual_rules, SaveOutcome, SaveResult, ValidationError,
+    VisualRulesEnvelope, VisualRulesError,
+};
+use std::time::Duration;
+
+/// How long a change request waits before answering with the unchanged revision.
+const VISUAL_RULES_CHANGE_WAIT: Duration = Duration::from_secs(25);
 
 pub async fn get_visual_rules(State(app_state): State<AppState>) -> impl IntoResponse {
     let state = app_state.visual_rules_manager.state();
@@ -22,6 +34,26 @@ pub async fn get_visual_rules(State(app_state): State<AppState>) -> impl IntoRes
     )
 }
 
+/// Long poll answered once the rules move past the client's revision, whether through a save,
+/// a retry or a hand edit of the rules file picked up by the manager's watcher.
+pub async fn visual_rules_changes(
+    State(app_state): State<AppState>,
+    Query(request): Query<VisualRulesChangesRequest>,
+) -> impl IntoResponse {
+    let manager = &app_state.visual_rules_manager;
+    if let Some(after) = request.after {
+        let mut revisions = manager.subscribe();
+        let changed = revisions.wait_for(|revision| *revision != after);
+        // An unchanged revision after the wait is the answer, so the timeout is not an error.
+        let _ = tokio::time::timeout(VISUAL_RULES_CHANGE_WAIT, changed).await;
+    }
+    let revision = manager.revision();
+    (
+        StatusCode::OK,
+        Json(VisualRulesChangesResponse { revision }),
+    )
+}
+
 pub async fn save_visual_rules(
     State(app_state): State<AppState>,
     Json(request): Json<VisualRulesSaveRequest>,
@@ -73,6 +105,137 @@ pub async fn replace_visual_rules(
     }
 }
 
+pub async fn import_visual_rules_file(Json(request): Json<VisualRulesImportRequest>) -> Response {
+    match import_visual_rules(request.format, &request.content) {
+        Ok(import) => (
+            StatusCode::OK,
+            Json(VisualRulesImportResponse {
+                envelope: import.envelope,
+                diagnostics: import.diagnostics,
+            }),
+        )
+            .into_response(),
+        Err(error) => conversion_error("visual_rules_import_error", error),
+    }
+}
+
+pub async fn export_visual_rules_file(
+    State(app_state): State<AppState>,
+    Json(request): Json<VisualRulesExportRequest>,
+) -> Response {
+    let envelope = request
+        .envelope
+        .unwrap_or_else(|| app_state.visual_rules_manager.state().envelope);
+    match export_visual_rules(request.format, &envelope) {
+        Ok(export) => (
+            StatusCode::OK,
+            Json(VisualRulesExportResponse {
+                file_name: request.format.file_name().to_string(),
+                content: export.content,
+                diagnostics: export.diagnostics,
+            }),
+        )
+            .into_response(),
+        Err(error) => conversion_error("visual_rules_export_error", error),
+    }
+}
+
+fn conversion_error(code: &str, error: ValidationError) -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(ApiError {
+            code: code.to_string(),
+            message: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+pub async fn get_visual_profile(
+    State(app_state): State<AppState>,
+    query: Query<FileInfoRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(reader) => (StatusCode::OK, Json(reader.visual_profile())).into_response(),
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn set_visual_profile(
+    State(app_state): State<AppState>,
+    Json(payload): Json<VisualProfileRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&payload.file_id) {
+        Some(mut reader) => match reader.set_visual_profile(payload.profile) {
+            Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError {
+                    code: "visual_profile_error".to_string(),
+                    message: e.to_string(),
+                }),
+            )
+                .into_response(),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn visual_rule_counts(
+    State(app_state): State<AppState>,
+    query: Query<FileInfoRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => match reader.visual_rule_counts() {
+            Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+            Err(e) => rule_navigation_error(e),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn visual_rule_next(
+    State(app_state): State<AppState>,
+    query: Query<VisualRuleNavigateRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => match reader.visual_rule_next(query.rule, query.max_lines) {
+            Ok(navigation) => (StatusCode::OK, Json(navigation)).into_response(),
+            Err(e) => rule_navigation_error(e),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+pub async fn visual_rule_previous(
+    State(app_state): State<AppState>,
+    query: Query<VisualRuleNavigateRequest>,
+) -> impl IntoResponse {
+    match app_state.registry.get_reader(&query.file_id) {
+        Some(mut reader) => match reader.visual_rule_previous(query.rule, query.max_lines) {
+            Ok(navigation) => (StatusCode::OK, Json(navigation)).into_response(),
+            Err(e) => rule_navigation_error(e),
+        },
+        None => (StatusCode::NOT_FOUND, Json("File not opened")).into_response(),
+    }
+}
+
+fn rule_navigation_error(error: std::io::Error) -> Response {
+    let status = if error.kind() == std::io::ErrorKind::NotFound {
+        StatusCode::NOT_FOUND
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    (
+        status,
+        Json(ApiError {
+            code: "visual_rule_counts_error".to_string(),
+            message: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
 fn visual_rules_success(result: SaveResult, envelope: VisualRulesEnvelope) -> VisualRulesResponse {
     VisualRulesResponse {
         revision: result.revision,
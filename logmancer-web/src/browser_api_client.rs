@@ -1,15 +1,26 @@
 use crate::api::commons::{
-    ApiError, ApplyFilterRequest, ApplySearchRequest, OpenServerFileResponse, ReadFilterRequest,
-    ReadPageRequest, SearchNavigateRequest, SearchStatusRequest, ServerBrowserListRequest,
-    ServerBrowserListResponse, ServerBrowserOpenRequest, ServerBrowserStatusResponse, TailRequest,
+    AddMarkRequest, AddMarkResponse, ApiError, ApplyFilterRequest, ApplySearchRequest,
+    DensityMapRequest, FileInfoRequest, MarkNavigateRequest, MarkRequest, OpenServerFileResponse,
+    ReadFilterRequest, ReadPageRequest, SearchHitsRequest, SearchNavigateRequest,
+    SearchSelectRequest, SearchStatusRequest, ServerBrowserListRequest, ServerBrowserListResponse,
+    ServerBrowserOpenRequest, ServerBrowserStatusResponse, TailRequest, VisualRuleNavigateRequest,
 };
 #[cfg(target_arch = "wasm32")]
-use crate::api::commons::{VisualRulesResponse, VisualRulesSaveRequest};
+use crate::api::commons::{
+    LogColumnsRequest, LogFieldsRequest, LogFormatRequest, SqlRequest, TimeReferenceRequest,
+    TimeZoneRequest, VisualProfileRequest, VisualRulesChangesRequest, VisualRulesChangesResponse,
+    VisualRulesExportRequest, VisualRulesExportResponse, VisualRulesImportRequest,
+    VisualRulesImportResponse, VisualRulesResponse, VisualRulesSaveRequest,
+};
 use leptos::prelude::{window, ServerFnError};
 use leptos::wasm_bindgen::{JsCast, JsValue};
+use logmancer_core::{
+    DensityMap, PageResult, RuleCountStatus, RuleNavigation, SearchHitsPage, VisualColor,
+};
 #[cfg(target_arch = "wasm32")]
-use logmancer_core::FileInfo;
-use logmancer_core::PageResult;
+use logmancer_core::{
+    FieldDiscovery, FileInfo, LogFormatStatus, SqlResult, TimeSettings, VisualProfileStatus,
+};
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{FormData, RequestInit, Response};
 
@@ -73,6 +84,31 @@ pub async fn retry_visual_rules() -> Result<VisualRulesResponse, String> {
     request_visual_rules("/api/visual-rules/retry", reqwest::Method::POST).await
 }
 
+/// Revision of the visual rules once it differs from `after`, or the current one when absent.
+/// The server holds the request open until then or until its wait elapses.
+#[cfg(target_arch = "wasm32")]
+pub async fn wait_for_visual_rules_change(after: Option<u64>) -> Result<u64, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .get(format!("{base}/api/visual-rules/changes"))
+        .query(&VisualRulesChangesRequest { after })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    if response.status().is_success() {
+        response
+            .json::<VisualRulesChangesResponse>()
+            .await
+            .map(|changes| changes.revision)
+            .map_err(|_| "Could not parse the visual rules revision.".to_string())
+    } else {
+        Err(parse_api_error_message(response, "Could not follow visual rules changes.").await)
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 async fn request_visual_rules(
     path: &str,
@@ -129,14 +165,310 @@ pub async fn save_visual_rules(
     }
 }
 
-pub async fn apply_filter(file_id: String, filter: String) -> Result<String, ServerFnError> {
-    let base = window().location().origin().unwrap();
-    let url = format!("{base}/api/apply-filter");
-    let request = reqwest::Client::new()
-        .post(url)
-        .json(&ApplyFilterRequest { file_id, filter });
-    let result = request.send().await?.json::<String>().await?;
-    Ok(result)
+#[cfg(target_arch = "wasm32")]
+pub async fn import_visual_rules_file(
+    format: logmancer_core::VisualRulesFormat,
+    content: String,
+) -> Result<VisualRulesImportResponse, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/visual-rules/import"))
+        .json(&VisualRulesImportRequest { format, content })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|_| "Could not parse imported visual rules.".to_string())
+    } else {
+        Err(parse_api_error_message(response, "Could not import visual rules.").await)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn export_visual_rules_file(
+    format: logmancer_core::VisualRulesFormat,
+    envelope: logmancer_core::VisualRulesEnvelope,
+) -> Result<VisualRulesExportResponse, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/visual-rules/export"))
+        .json(&VisualRulesExportRequest {
+            format,
+            envelope: Some(envelope),
+        })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|_| "Could not parse exported visual rules.".to_string())
+    } else {
+        Err(parse_api_error_message(response, "Could not export visual rules.").await)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn fetch_visual_profile(file_id: String) -> Result<VisualProfileStatus, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .get(format!("{base}/api/visual-profile"))
+        .query(&FileInfoRequest { file_id })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    visual_profile_response(response, "Could not load the visual rule profile.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn set_visual_profile(
+    file_id: String,
+    profile: Option<String>,
+) -> Result<VisualProfileStatus, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/visual-profile/set"))
+        .json(&VisualProfileRequest { file_id, profile })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    visual_profile_response(response, "Could not switch the visual rule profile.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn fetch_log_format(file_id: String) -> Result<LogFormatStatus, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .get(format!("{base}/api/log-format"))
+        .query(&FileInfoRequest { file_id })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    log_format_response(response, "Could not load the log format.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn set_log_format(
+    file_id: String,
+    format: Option<String>,
+) -> Result<LogFormatStatus, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/log-format/set"))
+        .json(&LogFormatRequest { file_id, format })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    log_format_response(response, "Could not switch the log format.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn set_log_columns(
+    file_id: String,
+    columns: Vec<String>,
+) -> Result<LogFormatStatus, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/log-format/columns"))
+        .json(&LogColumnsRequest { file_id, columns })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    log_format_response(response, "Could not change the columns.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn log_format_response(
+    response: reqwest::Response,
+    fallback: &str,
+) -> Result<LogFormatStatus, String> {
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|_| "Could not parse the log format.".to_string())
+    } else {
+        Err(parse_api_error_message(response, fallback).await)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn fetch_log_fields(file_id: String) -> Result<FieldDiscovery, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .get(format!("{base}/api/log-fields"))
+        .query(&LogFieldsRequest {
+            file_id,
+            sample_lines: None,
+        })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|_| "Could not parse the log fields.".to_string())
+    } else {
+        Err(parse_api_error_message(response, "Could not discover the log fields.").await)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn run_sql(file_id: String, query: String) -> Result<SqlResult, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/sql"))
+        .json(&SqlRequest { file_id, query })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|_| "Could not parse the query result.".to_string())
+    } else {
+        Err(parse_api_error_message(response, "Could not run the query.").await)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn fetch_time_settings(file_id: String) -> Result<TimeSettings, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .get(format!("{base}/api/time-settings"))
+        .query(&FileInfoRequest { file_id })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    time_settings_response(response, "Could not load the time settings.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn set_time_zone(file_id: String, time_zone: String) -> Result<TimeSettings, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/time-settings/zone"))
+        .json(&TimeZoneRequest { file_id, time_zone })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    time_settings_response(response, "Could not change the time zone.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn set_time_reference(
+    file_id: String,
+    line: Option<usize>,
+) -> Result<TimeSettings, String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/time-settings/reference"))
+        .json(&TimeReferenceRequest { file_id, line })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+    time_settings_response(response, "Could not set the reference line.").await
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn time_settings_response(
+    response: reqwest::Response,
+    fallback: &str,
+) -> Result<TimeSettings, String> {
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|_| "Could not parse the time settings.".to_string())
+    } else {
+        Err(parse_api_error_message(response, fallback).await)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn visual_profile_response(
+    response: reqwest::Response,
+    fallback: &str,
+) -> Result<VisualProfileStatus, String> {
+    if response.status().is_success() {
+        response
+            .json()
+            .await
+            .map_err(|_| "Could not parse the visual rule profile.".to_string())
+    } else {
+        Err(parse_api_error_message(response, fallback).await)
+    }
+}
+
+pub async fn apply_filter(
+    file_id: String,
+    filter: String,
+    field: Option<String>,
+    query: bool,
+) -> Result<(), String> {
+    let base = window()
+        .location()
+        .origin()
+        .map_err(|_| "Could not detect application origin.".to_string())?;
+    let response = reqwest::Client::new()
+        .post(format!("{base}/api/apply-filter"))
+        .json(&ApplyFilterRequest {
+            file_id,
+            filter,
+            field,
+            query,
+        })
+        .send()
+        .await
+        .map_err(|_| "Could not connect to the server.".to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(parse_api_error_message(response, "Could not apply the filter.").await)
+    }
 }
 
 pub async fn apply_search(
@@ -188,6 +520,177 @@ pub async fn search_previous(
     Ok(result)
 }
 
+pub async fn fetch_search_hits(
+    file_id: String,
+    offset: usize,
+    limit: usize,
+) -> Result<Option<SearchHitsPage>, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/search-hits");
+    let request = reqwest::Client::new().get(url).query(&SearchHitsRequest {
+        file_id,
+        offset,
+        limit,
+    });
+    let result = request
+        .send()
+        .await?
+        .json::<Option<SearchHitsPage>>()
+        .await?;
+    Ok(result)
+}
+
+pub async fn fetch_density_map(
+    file_id: String,
+    buckets: usize,
+) -> Result<DensityMap, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/density-map");
+    let request = reqwest::Client::new()
+        .get(url)
+        .query(&DensityMapRequest { file_id, buckets });
+    let result = request.send().await?.json::<DensityMap>().await?;
+    Ok(result)
+}
+
+pub async fn search_select(
+    file_id: String,
+    line_index: usize,
+    start: usize,
+    max_lines: usize,
+) -> Result<PageResult, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/search-select");
+    let request = reqwest::Client::new().get(url).query(&SearchSelectRequest {
+        file_id,
+        line_index,
+        start,
+        max_lines,
+    });
+    let result = request.send().await?.json::<PageResult>().await?;
+    Ok(result)
+}
+
+pub async fn add_mark(
+    file_id: String,
+    query: String,
+    color: Option<VisualColor>,
+) -> Result<u64, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/add-mark");
+    let response = reqwest::Client::new()
+        .post(url)
+        .json(&AddMarkRequest {
+            file_id,
+            query,
+            color,
+        })
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(ServerFnError::new(response.json::<String>().await?));
+    }
+    Ok(response.json::<AddMarkResponse>().await?.mark_id)
+}
+
+pub async fn remove_mark(file_id: String, mark_id: u64) -> Result<String, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/remove-mark");
+    let request = reqwest::Client::new()
+        .get(url)
+        .query(&MarkRequest { file_id, mark_id });
+    let result = request.send().await?.json::<String>().await?;
+    Ok(result)
+}
+
+pub async fn clear_marks(file_id: String) -> Result<String, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/clear-marks");
+    let request = reqwest::Client::new()
+        .get(url)
+        .query(&SearchStatusRequest { file_id });
+    let result = request.send().await?.json::<String>().await?;
+    Ok(result)
+}
+
+pub async fn mark_next(
+    file_id: String,
+    mark_id: u64,
+    max_lines: usize,
+) -> Result<PageResult, ServerFnError> {
+    navigate_mark("mark-next", file_id, mark_id, max_lines).await
+}
+
+pub async fn mark_previous(
+    file_id: String,
+    mark_id: u64,
+    max_lines: usize,
+) -> Result<PageResult, ServerFnError> {
+    navigate_mark("mark-previous", file_id, mark_id, max_lines).await
+}
+
+async fn navigate_mark(
+    route: &str,
+    file_id: String,
+    mark_id: u64,
+    max_lines: usize,
+) -> Result<PageResult, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/{route}");
+    let request = reqwest::Client::new().get(url).query(&MarkNavigateRequest {
+        file_id,
+        mark_id,
+        max_lines,
+    });
+    let result = request.send().await?.json::<PageResult>().await?;
+    Ok(result)
+}
+
+pub async fn fetch_visual_rule_counts(file_id: String) -> Result<RuleCountStatus, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/visual-rule-counts");
+    let request = reqwest::Client::new()
+        .get(url)
+        .query(&FileInfoRequest { file_id });
+    let result = request.send().await?.json::<RuleCountStatus>().await?;
+    Ok(result)
+}
+
+pub async fn visual_rule_next(
+    file_id: String,
+    rule: usize,
+    max_lines: usize,
+) -> Result<RuleNavigation, ServerFnError> {
+    navigate_visual_rule("visual-rule-next", file_id, rule, max_lines).await
+}
+
+pub async fn visual_rule_previous(
+    file_id: String,
+    rule: usize,
+    max_lines: usize,
+) -> Result<RuleNavigation, ServerFnError> {
+    navigate_visual_rule("visual-rule-previous", file_id, rule, max_lines).await
+}
+
+async fn navigate_visual_rule(
+    route: &str,
+    file_id: String,
+    rule: usize,
+    max_lines: usize,
+) -> Result<RuleNavigation, ServerFnError> {
+    let base = window().location().origin().unwrap();
+    let url = format!("{base}/api/{route}");
+    let request = reqwest::Client::new()
+        .get(url)
+        .query(&VisualRuleNavigateRequest {
+            file_id,
+            rule,
+            max_lines,
+        });
+    let result = request.send().await?.json::<RuleNavigation>().await?;
+    Ok(result)
+}
+
 pub async fn fetch_filter_page(
     file_id: String,
     start_line: usize,
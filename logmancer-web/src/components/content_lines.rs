@@ -1,21 +1,27 @@
 use crate::components::context::{
-    ActivePaneContext, LogContentFocusContext, LogFileContext, LogViewContext, SearchUiContext,
-    SelectionSource,
+    ActivePaneContext, ColumnSort, LogContentFocusContext, LogFieldsContext, LogFileContext,
+    LogViewContext, SearchUiContext, SelectionSource, TimeColumns, TimeColumnsContext,
 };
 use crate::components::diagnostics::{scroll_trace, scroll_trace_enabled};
 use crate::components::layout::{
     SCROLL_LINE_JUMP, WHEEL_SCROLL_MAX_LINE_JUMP, WHEEL_SCROLL_PIXELS_PER_LINE_STEP,
 };
 use crate::components::line_decorations::{
-    search_decorations_by_line, split_line_segments, DecorationKind, LineDecoration,
+    extend_with_mark_decorations, rule_span_decorations, search_decorations_by_line,
+    split_line_segments, DecorationKind, LineDecoration,
 };
 use crate::components::search_status::format_page_search_status;
+use crate::components::visual_palette::visual_color_css;
 use leptos::context::use_context;
 use leptos::ev::{KeyboardEvent, WheelEvent};
 use leptos::logging::log;
 use leptos::prelude::*;
 use leptos::{component, html, view, IntoView};
-use logmancer_core::{LineStyleIntent, PageResult, VisualColor};
+use logmancer_core::{
+    compare_field_values, format_time_delta, GutterIcon, LineStyleIntent, LineTime, PageLine,
+    PageResult, StyledSpan, TextAttributes, VisualColor, VisualColorRole,
+};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -145,6 +151,9 @@ fn wheel_lines_to_jump(delta: f64, is_precise_scroll: bool) -> i32 {
 
 fn search_segment_class(kind: DecorationKind) -> &'static str {
     match kind {
+        DecorationKind::RuleSpan(_) => "visual-rule-span",
+        DecorationKind::Mark(_) => "highlight-mark",
+        DecorationKind::MarkCurrent(_) => "highlight-mark highlight-mark-current",
         DecorationKind::SearchMatch => "search-match",
         DecorationKind::SearchCurrent => "search-match search-match-current",
     }
@@ -160,64 +169,240 @@ fn line_decorations_for_row(
         .unwrap_or_default()
 }
 
-#[derive(Clone, Copy)]
-enum VisualColorRole {
-    Foreground,
-    Background,
+fn mark_segment_style(kind: DecorationKind, mark_colors: &[VisualColor]) -> Option<String> {
+    let (DecorationKind::Mark(slot) | DecorationKind::MarkCurrent(slot)) = kind else {
+        return None;
+    };
+    mark_colors
+        .get(slot)
+        .and_then(|token| visual_color_css(token, VisualColorRole::Background))
+        .map(|color| format!("--highlight-mark-background: {color}"))
 }
 
-fn visual_color_css(token: &VisualColor, role: VisualColorRole) -> Option<&'static str> {
-    match (token.0.as_str(), role) {
-        ("red", VisualColorRole::Foreground) => Some("#b91c1c"),
-        ("red", VisualColorRole::Background) => Some("#fee2e2"),
-        ("orange", VisualColorRole::Foreground) => Some("#c2410c"),
-        ("orange", VisualColorRole::Background) => Some("#ffedd5"),
-        ("yellow", VisualColorRole::Foreground) => Some("#a16207"),
-        ("yellow", VisualColorRole::Background) => Some("#fef9c3"),
-        ("green", VisualColorRole::Foreground) => Some("#15803d"),
-        ("green", VisualColorRole::Background) => Some("#dcfce7"),
-        ("cyan", VisualColorRole::Foreground) => Some("#0e7490"),
-        ("cyan", VisualColorRole::Background) => Some("#cffafe"),
-        ("blue", VisualColorRole::Foreground) => Some("#1d4ed8"),
-        ("blue", VisualColorRole::Background) => Some("#dbeafe"),
-        ("purple", VisualColorRole::Foreground) => Some("#7e22ce"),
-        ("purple", VisualColorRole::Background) => Some("#f3e8ff"),
-        ("gray", VisualColorRole::Foreground) => Some("#4b5563"),
-        ("gray", VisualColorRole::Background) => Some("#f3f4f6"),
-        ("default", _) | (_, _) => None,
-    }
+fn line_style_css_variables(style: Option<&LineStyleIntent>) -> Option<String> {
+    style_css_variables(style?, "--log-line")
 }
 
-fn line_style_css_variables(style: Option<&LineStyleIntent>) -> Option<String> {
-    let style = style?;
-    let mut declarations = Vec::with_capacity(2);
+fn rule_span_css_variables(kind: DecorationKind, rule_spans: &[StyledSpan]) -> Option<String> {
+    let DecorationKind::RuleSpan(index) = kind else {
+        return None;
+    };
+    style_css_variables(&rule_spans.get(index)?.style, "--visual-span")
+}
+
+fn style_css_variables(style: &LineStyleIntent, prefix: &str) -> Option<String> {
+    let mut declarations = text_attribute_css(style.attributes)
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
 
     if let Some(color) = style
         .foreground
         .as_ref()
         .and_then(|token| visual_color_css(token, VisualColorRole::Foreground))
     {
-        declarations.push(format!("--log-line-foreground: {color}"));
+        declarations.push(format!("{prefix}-foreground: {color}"));
     }
     if let Some(color) = style
         .background
         .as_ref()
         .and_then(|token| visual_color_css(token, VisualColorRole::Background))
     {
-        declarations.push(format!("--log-line-background: {color}"));
+        declarations.push(format!("{prefix}-background: {color}"));
     }
 
     (!declarations.is_empty()).then(|| declarations.join("; "))
 }
 
+fn text_attribute_css(attributes: TextAttributes) -> Vec<&'static str> {
+    let mut declarations = Vec::new();
+    if attributes.bold {
+        declarations.push("font-weight: 700");
+    }
+    if attributes.italic {
+        declarations.push("font-style: italic");
+    }
+    match (attributes.underline, attributes.strikethrough) {
+        (true, true) => declarations.push("text-decoration-line: underline line-through"),
+        (true, false) => declarations.push("text-decoration-line: underline"),
+        (false, true) => declarations.push("text-decoration-line: line-through"),
+        (false, false) => {}
+    }
+    if attributes.dim {
+        declarations.push("opacity: 0.6");
+    }
+    declarations
+}
+
+fn gutter_glyph(icon: GutterIcon) -> &'static str {
+    match icon {
+        GutterIcon::Dot => "●",
+        GutterIcon::Flag => "⚑",
+        GutterIcon::Star => "★",
+        GutterIcon::Warning => "▲",
+        GutterIcon::Error => "✖",
+    }
+}
+
+/// Glyph and inline color of the gutter marker of a styled line, if its rule sets one.
+fn gutter_marker(style: Option<&LineStyleIntent>) -> Option<(&'static str, Option<String>)> {
+    let style = style?;
+    let marker = style.gutter.as_ref()?;
+    let color = marker
+        .color
+        .as_ref()
+        .or(style.foreground.as_ref())
+        .and_then(|token| visual_color_css(token, VisualColorRole::Foreground))
+        .map(|color| format!("color: {color}"));
+    Some((gutter_glyph(marker.icon), color))
+}
+
+/// Values of `columns` for a line in the table view, blank where the line lacks a field;
+/// `None` for lines the format could not parse, which keep their plain text.
+fn column_cells(fields: &[(String, String)], columns: &[String]) -> Option<Vec<String>> {
+    if fields.is_empty() {
+        return None;
+    }
+    Some(
+        columns
+            .iter()
+            .map(|column| {
+                fields
+                    .iter()
+                    .find(|(name, _)| name == column)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default()
+            })
+            .collect(),
+    )
+}
+
+const TIMESTAMP_CELL_CLASS: &str = "log-time log-time--timestamp";
+const DELTA_CELL_CLASS: &str = "log-time";
+
+/// Classes and headers of the shown time columns, in the order their cells are drawn.
+fn time_headers(shown: TimeColumns) -> Vec<(&'static str, &'static str)> {
+    [
+        (shown.timestamp, TIMESTAMP_CELL_CLASS, "Time"),
+        (shown.since_previous, DELTA_CELL_CLASS, "Δ previous"),
+        (shown.since_reference, DELTA_CELL_CLASS, "Δ reference"),
+    ]
+    .into_iter()
+    .filter_map(|(shown, class, header)| shown.then_some((class, header)))
+    .collect()
+}
+
+/// Classes and text of the shown time columns for a line, blank where the line has no
+/// timestamp.
+fn time_cells(time: Option<&LineTime>, shown: TimeColumns) -> Vec<(&'static str, String)> {
+    let delta = |millis: Option<i64>| millis.map(format_time_delta).unwrap_or_default();
+    [
+        (
+            shown.timestamp,
+            TIMESTAMP_CELL_CLASS,
+            time.map(|time| time.timestamp.clone()).unwrap_or_default(),
+        ),
+        (
+            shown.since_previous,
+            DELTA_CELL_CLASS,
+            delta(time.and_then(|time| time.since_previous_ms)),
+        ),
+        (
+            shown.since_reference,
+            DELTA_CELL_CLASS,
+            delta(time.and_then(|time| time.since_reference_ms)),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(shown, class, cell)| shown.then_some((class, cell)))
+    .collect()
+}
+
+/// Sort after clicking the header of `column`: ascending, then descending, then page order.
+fn next_sort(current: Option<&ColumnSort>, column: &str) -> Option<ColumnSort> {
+    match current {
+        Some(sort) if sort.column == column && sort.descending => None,
+        Some(sort) if sort.column == column => Some(ColumnSort {
+            column: column.to_string(),
+            descending: true,
+        }),
+        _ => Some(ColumnSort {
+            column: column.to_string(),
+            descending: false,
+        }),
+    }
+}
+
+fn sort_value<'a>(record: &'a [PageLine], column: &str) -> Option<&'a str> {
+    record
+        .first()?
+        .fields
+        .iter()
+        .find(|(name, _)| name == column)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Visible rows ordered by a shown column the way field conditions compare values. Records
+/// keep the continuation lines below them, and rows without the field stay last in page order.
+fn sorted_rows(
+    lines: Vec<PageLine>,
+    columns: &[String],
+    sort: Option<&ColumnSort>,
+) -> Vec<PageLine> {
+    let Some(sort) = sort.filter(|sort| columns.contains(&sort.column)) else {
+        return lines;
+    };
+    let mut records: Vec<Vec<PageLine>> = Vec::new();
+    for line in lines {
+        match records.last_mut() {
+            Some(record) if line.continuation => record.push(line),
+            _ => records.push(vec![line]),
+        }
+    }
+    records.sort_by(|left, right| {
+        match (
+            sort_value(left, &sort.column),
+            sort_value(right, &sort.column),
+        ) {
+            (Some(left), Some(right)) => {
+                let ordering = compare_field_values(left, right, false);
+                if sort.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    });
+    records.into_iter().flatten().collect()
+}
+
 #[component]
-fn DecoratedLineText(line_text: String, decorations: Vec<LineDecoration>) -> impl IntoView {
+fn DecoratedLineText(
+    line_text: String,
+    decorations: Vec<LineDecoration>,
+    rule_spans: Vec<StyledSpan>,
+    mark_colors: Vec<VisualColor>,
+) -> impl IntoView {
     let segments = split_line_segments(&line_text, &decorations);
 
     view! {
         {segments.into_iter().map(|segment| {
-            if let Some(kind) = segment.kind {
-                view! { <mark class=search_segment_class(kind)>{segment.text.to_string()}</mark> }.into_any()
+            if let Some(kind @ DecorationKind::RuleSpan(_)) = segment.kind {
+                view! {
+                    <span class=search_segment_class(kind) style=rule_span_css_variables(kind, &rule_spans)>
+                        {segment.text.to_string()}
+                    </span>
+                }.into_any()
+            } else if let Some(kind) = segment.kind {
+                view! {
+                    <mark class=search_segment_class(kind) style=mark_segment_style(kind, &mark_colors)>
+                        {segment.text.to_string()}
+                    </mark>
+                }.into_any()
             } else {
                 view! { <span>{segment.text.to_string()}</span> }.into_any()
             }
@@ -230,7 +415,15 @@ fn LogLineRow(
     line_number: usize,
     line_text: String,
     line_style: Option<LineStyleIntent>,
+    rule_spans: Vec<StyledSpan>,
     decorations: Vec<LineDecoration>,
+    mark_colors: Vec<VisualColor>,
+    /// Column values in the table view; the line text otherwise.
+    cells: Option<Vec<String>>,
+    /// Cells of the shown time columns, drawn before the text or column values.
+    times: Vec<(&'static str, String)>,
+    /// Line inside a record that starts above it, shown as text under the record's row.
+    continuation: bool,
     selected_line: ReadSignal<Option<usize>>,
     select_line: Callback<usize>,
 ) -> impl IntoView {
@@ -241,10 +434,30 @@ fn LogLineRow(
         <div
             class:selected=move || selected_line.get() == Some(line_number)
             class:visual-rule-line=has_visual_style
+            class:log-continuation=continuation
             style=visual_style
             on:click=move |_| select_line.run(line_number)
         >
-            <DecoratedLineText line_text=line_text decorations=decorations />
+            {times
+                .into_iter()
+                .map(|(class, time)| view! { <span class=class>{time}</span> })
+                .collect_view()}
+            {match cells {
+                Some(cells) => cells
+                    .into_iter()
+                    .map(|cell| view! { <span class="log-cell" title=cell.clone()>{cell.clone()}</span> })
+                    .collect_view()
+                    .into_any(),
+                None => view! {
+                    <DecoratedLineText
+                        line_text=line_text
+                        decorations=decorations
+                        rule_spans=rule_spans
+                        mark_colors=mark_colors
+                    />
+                }
+                .into_any(),
+            }}
         </div>
     }
 }
@@ -273,6 +486,10 @@ pub fn ContentLines(context: LogViewContext) -> impl IntoView {
     } = context;
 
     let ActivePaneContext { active_pane, .. } = use_context().expect("ActivePaneContext not found");
+    let columns = use_context::<LogFieldsContext>().map(|context| context.columns);
+    let time_columns = use_context::<TimeColumnsContext>().map(|context| context.columns);
+    let column_sort =
+        use_context::<LogFieldsContext>().map(|context| (context.sort, context.set_sort));
     let LogContentFocusContext { focus_request, .. } =
         use_context().expect("LogContentFocusContext not found");
     let SearchUiContext {
@@ -592,19 +809,71 @@ pub fn ContentLines(context: LogViewContext) -> impl IntoView {
                     }
                     let search = page_result.search;
                     let lines = page_result.lines;
-                    let decorations_by_line = search
+                    let mut decorations_by_line = search
                         .as_ref()
                         .map(search_decorations_by_line)
                         .unwrap_or_default();
+                    extend_with_mark_decorations(&mut decorations_by_line, &page_result.marks);
+                    let mark_colors = page_result
+                        .marks
+                        .iter()
+                        .map(|mark| mark.color.clone())
+                        .collect::<Vec<_>>();
                     let select_line_callback = Callback::new(select_line);
+                    // Column changes refresh the page, so the page is drawn with the columns
+                    // chosen when it was fetched.
+                    let columns = columns.map(|columns| columns.get_untracked()).unwrap_or_default();
+                    let sort = column_sort.and_then(|(sort, _)| sort.get_untracked());
+                    let lines = sorted_rows(lines, &columns, sort.as_ref());
+                    let shown_times = time_columns
+                        .map(|time_columns| time_columns.get_untracked())
+                        .unwrap_or_default();
+                    let has_header = !columns.is_empty() || shown_times.any();
+                    let table_header = has_header.then(|| view! {
+                        <div class="log-table-header">
+                            {time_headers(shown_times)
+                                .into_iter()
+                                .map(|(class, header)| view! { <span class=class>{header}</span> })
+                                .collect_view()}
+                            {columns.iter().map(|column| {
+                                let indicator = match &sort {
+                                    Some(sort) if sort.column == *column && sort.descending => " ▼",
+                                    Some(sort) if sort.column == *column => " ▲",
+                                    _ => "",
+                                };
+                                let clicked = column.clone();
+                                let current = sort.clone();
+                                view! {
+                                    <button
+                                        type="button"
+                                        class="log-cell log-table-sort"
+                                        title=format!("Sort visible rows by {column}")
+                                        on:click=move |_| {
+                                            if let Some((_, set_sort)) = column_sort {
+                                                set_sort.set(next_sort(current.as_ref(), &clicked));
+                                            }
+                                        }
+                                    >
+                                        {column.clone()}
+                                        {indicator}
+                                    </button>
+                                }
+                            }).collect_view()}
+                        </div>
+                    });
                     view! {
                         <div class="line-numbers">
+                            {has_header.then(|| view! { <div class="log-table-header"></div> })}
                             { lines.iter().map(|line| {
                                 let line_number = line.number;
+                                let marker = gutter_marker(line.style.as_ref()).map(|(glyph, color)| view! {
+                                    <span class="gutter-marker" style=color aria-hidden="true">{glyph}</span>
+                                });
                                 view! {
                                     <div
                                         on:click=move |_| select_line(line_number)
                                     >
+                                        {marker}
                                         <b>{line_number}</b>
                                     </div>
                                 }
@@ -617,17 +886,29 @@ pub fn ContentLines(context: LogViewContext) -> impl IntoView {
                             on:keydown=on_key_down on:keyup=on_key_up
                             on:wheel=on_wheel
                         >
+                            {table_header}
                             { lines.into_iter().map(|line| {
                                 let line_number = line.number;
                                 let line_text = line.text;
                                 let line_style = line.style;
-                                let decorations = line_decorations_for_row(&decorations_by_line, line_number);
+                                let mut decorations = rule_span_decorations(&line.spans);
+                                decorations.extend(line_decorations_for_row(&decorations_by_line, line_number));
+                                let cells = (!columns.is_empty())
+                                    .then(|| column_cells(&line.fields, &columns))
+                                    .flatten();
+                                let continuation = line.continuation;
+                                let times = time_cells(line.time.as_ref(), shown_times);
                                 view! {
                                     <LogLineRow
                                         line_number=line_number
                                         line_text=line_text
                                         line_style=line_style
+                                        rule_spans=line.spans
                                         decorations=decorations
+                                        mark_colors=mark_colors.clone()
+                                        cells=cells
+                                        times=times
+                                        continuation=continuation
                                         selected_line=selected_line
                                         select_line=select_line_callback
                                     />
@@ -646,16 +927,20 @@ mod tests {
     use std::collections::HashMap;
 
     use super::{
-        can_auto_enable_global_follow, can_mutate_global_follow_state, is_at_end,
-        is_editable_target, is_handled_key, keyboard_target_line, line_decorations_for_row,
-        line_style_css_variables, search_segment_class, should_handle_focus_request,
-        should_restore_focus, tail_update_for_navigation, visual_color_css, wheel_lines_to_jump,
-        wheel_target_line, TailEndComparison, TailNavigationUpdate, VisualColorRole, ARROW_DOWN,
-        ARROW_UP, PAGE_DOWN, PAGE_UP,
+        can_auto_enable_global_follow, can_mutate_global_follow_state, column_cells, gutter_marker,
+        is_at_end, is_editable_target, is_handled_key, keyboard_target_line,
+        line_decorations_for_row, line_style_css_variables, mark_segment_style, next_sort,
+        rule_span_css_variables, search_segment_class, should_handle_focus_request,
+        should_restore_focus, sorted_rows, tail_update_for_navigation, time_cells, time_headers,
+        wheel_lines_to_jump, wheel_target_line, TailEndComparison, TailNavigationUpdate,
+        ARROW_DOWN, ARROW_UP, PAGE_DOWN, PAGE_UP,
     };
-    use crate::components::context::SelectionSource;
+    use crate::components::context::{ColumnSort, SelectionSource, TimeColumns};
     use crate::components::line_decorations::{DecorationKind, LineDecoration};
-    use logmancer_core::{LineStyleIntent, VisualColor};
+    use logmancer_core::{
+        GutterIcon, GutterMarker, LineStyleIntent, LineTime, PageLine, StyledSpan, TextAttributes,
+        VisualColor,
+    };
 
     fn decoration(start: usize, end: usize, kind: DecorationKind) -> LineDecoration {
         LineDecoration { start, end, kind }
@@ -715,6 +1000,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn mark_segments_carry_the_background_of_their_page_slot() {
+        let mark_colors = vec![
+            VisualColor("cyan".to_string()),
+            VisualColor("not a token".to_string()),
+        ];
+
+        assert_eq!(
+            search_segment_class(DecorationKind::MarkCurrent(0)),
+            "highlight-mark highlight-mark-current"
+        );
+        assert_eq!(
+            mark_segment_style(DecorationKind::Mark(0), &mark_colors),
+            Some("--highlight-mark-background: var(--visual-color-cyan-background)".to_string())
+        );
+        assert_eq!(
+            mark_segment_style(DecorationKind::Mark(1), &mark_colors),
+            None
+        );
+        assert_eq!(
+            mark_segment_style(DecorationKind::Mark(5), &mark_colors),
+            None
+        );
+        assert_eq!(
+            mark_segment_style(DecorationKind::SearchMatch, &mark_colors),
+            None
+        );
+    }
+
     #[test]
     fn row_decorations_are_cloned_for_original_line_number() {
         let mut decorations_by_line = HashMap::new();
@@ -738,36 +1052,12 @@ mod tests {
         assert_eq!(decorations, Vec::<LineDecoration>::new());
     }
 
-    #[test]
-    fn canonical_visual_tokens_map_to_role_appropriate_shades() {
-        let mappings = [
-            ("red", "#b91c1c", "#fee2e2"),
-            ("orange", "#c2410c", "#ffedd5"),
-            ("yellow", "#a16207", "#fef9c3"),
-            ("green", "#15803d", "#dcfce7"),
-            ("cyan", "#0e7490", "#cffafe"),
-            ("blue", "#1d4ed8", "#dbeafe"),
-            ("purple", "#7e22ce", "#f3e8ff"),
-            ("gray", "#4b5563", "#f3f4f6"),
-        ];
-
-        for (token, foreground, background) in mappings {
-            assert_eq!(
-                visual_color_css(&VisualColor(token.to_string()), VisualColorRole::Foreground),
-                Some(foreground)
-            );
-            assert_eq!(
-                visual_color_css(&VisualColor(token.to_string()), VisualColorRole::Background),
-                Some(background)
-            );
-        }
-    }
-
     #[test]
     fn default_absent_or_unknown_visual_tokens_leave_row_unstyled() {
         let style = LineStyleIntent {
             foreground: Some(VisualColor("default".to_string())),
             background: Some(VisualColor("hotpink; background: url(bad)".to_string())),
+            ..LineStyleIntent::default()
         };
 
         assert_eq!(line_style_css_variables(None), None);
@@ -775,15 +1065,184 @@ mod tests {
     }
 
     #[test]
-    fn known_visual_tokens_render_without_forwarding_unknown_tokens() {
+    fn palette_tokens_render_their_variables_without_forwarding_malformed_tokens() {
         let style = LineStyleIntent {
-            foreground: Some(VisualColor("unknown".to_string())),
+            foreground: Some(VisualColor("not a token".to_string())),
             background: Some(VisualColor("yellow".to_string())),
+            ..LineStyleIntent::default()
+        };
+
+        assert_eq!(
+            line_style_css_variables(Some(&style)),
+            Some("--log-line-background: var(--visual-color-yellow-background)".to_string())
+        );
+    }
+
+    #[test]
+    fn text_attributes_and_gutter_markers_render_fixed_declarations() {
+        let style = LineStyleIntent {
+            foreground: Some(VisualColor("red".to_string())),
+            background: None,
+            attributes: TextAttributes {
+                bold: true,
+                underline: true,
+                strikethrough: true,
+                ..TextAttributes::default()
+            },
+            gutter: Some(GutterMarker {
+                icon: GutterIcon::Flag,
+                color: None,
+            }),
         };
 
         assert_eq!(
             line_style_css_variables(Some(&style)),
-            Some("--log-line-background: #fef9c3".to_string())
+            Some(
+                "font-weight: 700; text-decoration-line: underline line-through; \
+                 --log-line-foreground: var(--visual-color-red-foreground)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            gutter_marker(Some(&style)),
+            Some((
+                "⚑",
+                Some("color: var(--visual-color-red-foreground)".to_string())
+            ))
+        );
+        assert_eq!(gutter_marker(None), None);
+    }
+
+    #[test]
+    fn rule_spans_render_their_own_palette_variables() {
+        let spans = vec![StyledSpan {
+            start: 0,
+            end: 4,
+            style: LineStyleIntent {
+                foreground: Some(VisualColor("cyan".to_string())),
+                background: Some(VisualColor("gray".to_string())),
+                ..LineStyleIntent::default()
+            },
+        }];
+
+        assert_eq!(
+            search_segment_class(DecorationKind::RuleSpan(0)),
+            "visual-rule-span"
+        );
+        assert_eq!(
+            rule_span_css_variables(DecorationKind::RuleSpan(0), &spans),
+            Some(
+                "--visual-span-foreground: var(--visual-color-cyan-foreground); \
+                 --visual-span-background: var(--visual-color-gray-background)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            rule_span_css_variables(DecorationKind::RuleSpan(1), &spans),
+            None
+        );
+        assert_eq!(
+            rule_span_css_variables(DecorationKind::SearchMatch, &spans),
+            None
+        );
+    }
+
+    #[test]
+    fn table_rows_blank_missing_fields_and_keep_unparsed_lines_as_text() {
+        let fields = vec![
+            ("level".to_string(), "error".to_string()),
+            ("http.status".to_string(), "503".to_string()),
+        ];
+        let columns = vec![
+            "http.status".to_string(),
+            "user".to_string(),
+            "level".to_string(),
+        ];
+        assert_eq!(
+            column_cells(&fields, &columns),
+            Some(vec!["503".to_string(), String::new(), "error".to_string()])
+        );
+        assert_eq!(column_cells(&[], &columns), None);
+    }
+
+    #[test]
+    fn time_cells_follow_the_shown_columns_and_blank_lines_without_a_timestamp() {
+        let time = LineTime {
+            timestamp: "2026-10-03 12:00:00.250".to_string(),
+            since_previous_ms: Some(1_250),
+            since_reference_ms: Some(-123_000),
+        };
+        let all = TimeColumns {
+            timestamp: true,
+            since_previous: true,
+            since_reference: true,
+        };
+        let texts = |cells: Vec<(&str, String)>| -> Vec<String> {
+            cells.into_iter().map(|(_, text)| text).collect()
+        };
+        assert_eq!(
+            texts(time_cells(Some(&time), all)),
+            vec!["2026-10-03 12:00:00.250", "+1.250s", "-2m03s"]
+        );
+        assert_eq!(texts(time_cells(None, all)), vec![""; 3]);
+        assert_eq!(
+            time_headers(all),
+            vec![
+                ("log-time log-time--timestamp", "Time"),
+                ("log-time", "Δ previous"),
+                ("log-time", "Δ reference")
+            ]
+        );
+
+        let deltas = TimeColumns {
+            since_previous: true,
+            ..TimeColumns::default()
+        };
+        assert_eq!(
+            time_cells(Some(&time), deltas),
+            vec![("log-time", "+1.250s".to_string())]
+        );
+        assert_eq!(time_headers(deltas), vec![("log-time", "Δ previous")]);
+        assert!(time_cells(Some(&time), TimeColumns::default()).is_empty());
+        assert!(!TimeColumns::default().any());
+    }
+
+    #[test]
+    fn sorting_orders_visible_records_and_keeps_their_continuation_lines() {
+        let row = |number: usize, duration: Option<&str>, continuation: bool| PageLine {
+            number,
+            text: String::new(),
+            style: None,
+            spans: Vec::new(),
+            fields: duration
+                .map(|duration| vec![("duration".to_string(), duration.to_string())])
+                .unwrap_or_default(),
+            continuation,
+            time: None,
+        };
+        let lines = vec![
+            row(1, Some("1.5s"), false),
+            row(2, None, true),
+            row(3, None, false),
+            row(4, Some("20ms"), false),
+            row(5, Some("900ms"), false),
+        ];
+        let columns = vec!["duration".to_string()];
+        let numbers = |sort: Option<ColumnSort>| {
+            sorted_rows(lines.clone(), &columns, sort.as_ref())
+                .iter()
+                .map(|line| line.number)
+                .collect::<Vec<_>>()
+        };
+        let ascending = next_sort(None, "duration");
+        assert_eq!(numbers(ascending.clone()), [4, 5, 1, 2, 3]);
+        let descending = next_sort(ascending.as_ref(), "duration");
+        assert_eq!(numbers(descending.clone()), [1, 2, 5, 4, 3]);
+        assert_eq!(next_sort(descending.as_ref(), "duration"), None);
+        assert_eq!(
+            numbers(next_sort(None, "hidden")),
+            [1, 2, 3, 4, 5],
+            "columns that are not shown leave page order"
         );
     }
 
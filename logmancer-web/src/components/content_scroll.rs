@@ -1,4 +1,5 @@
 use crate::components::context::{LogFileContext, LogViewContext};
+use crate::components::density_map::DensityStrip;
 use crate::components::diagnostics::{scroll_trace, scroll_trace_enabled};
 use crate::components::layout::{
     LOG_LINE_HEIGHT_PX, SCROLLBAR_ARROW_MAX_PIXEL_DELTA, SCROLL_LINE_JUMP,
@@ -34,9 +35,15 @@ fn scrollbar_target_line(
     }
 }
 
+/// Virtual scrollbar for a pane. When `density_refresh` is set, a density strip of the
+/// whole file is drawn beside it; only source-line panes can map buckets to start lines.
 #[component]
-pub fn ContentScroll(context: LogViewContext) -> impl IntoView {
+pub fn ContentScroll(
+    context: LogViewContext,
+    #[prop(optional)] density_refresh: Option<ReadSignal<u64>>,
+) -> impl IntoView {
     let LogFileContext { set_tail, .. } = use_context().expect("");
+    let density_context = context.clone();
 
     let LogViewContext {
         set_start_line,
@@ -228,6 +235,13 @@ pub fn ContentScroll(context: LogViewContext) -> impl IntoView {
     });
 
     view! {
+        {density_refresh.map(|refresh_generation| view! {
+            <DensityStrip
+                context=density_context
+                page_result=page_result
+                refresh_generation=refresh_generation
+            />
+        })}
         <div node_ref=scroll_ref class="scrollbar" on:scroll=on_scroll>
             <div class="spacer" node_ref=spacer_ref>
                 <Transition>
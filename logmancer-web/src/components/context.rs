@@ -1,5 +1,5 @@
 use leptos::prelude::{LocalResource, Memo, ReadSignal, ServerFnError, WriteSignal};
-use logmancer_core::PageResult;
+use logmancer_core::{PageResult, SearchMatch};
 
 #[derive(Clone)]
 pub struct Port(pub u16);
@@ -11,6 +11,52 @@ pub struct LogFileContext {
     pub set_follow: WriteSignal<bool>,
     pub tail: ReadSignal<bool>,
     pub set_tail: WriteSignal<bool>,
+    /// Bumped whenever the filter pane applies or clears a filter.
+    pub filter_generation: ReadSignal<u64>,
+    pub set_filter_generation: WriteSignal<u64>,
+}
+
+/// Fields of the open file's log format, shared by the format bar, both panes and the filter.
+#[derive(Clone)]
+pub struct LogFieldsContext {
+    /// Field paths shown as columns; empty for the plain text view.
+    pub columns: ReadSignal<Vec<String>>,
+    pub set_columns: WriteSignal<Vec<String>>,
+    /// Field paths discovered in the first lines of the file with its format.
+    pub fields: ReadSignal<Vec<String>>,
+    pub set_fields: WriteSignal<Vec<String>>,
+    /// Column the visible rows of the table view are ordered by.
+    pub sort: ReadSignal<Option<ColumnSort>>,
+    pub set_sort: WriteSignal<Option<ColumnSort>>,
+}
+
+/// Time columns shown before the text of each line in both panes.
+#[derive(Clone)]
+pub struct TimeColumnsContext {
+    pub columns: ReadSignal<TimeColumns>,
+    pub set_columns: WriteSignal<TimeColumns>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeColumns {
+    /// Timestamp of the line in the display time zone.
+    pub timestamp: bool,
+    /// Time elapsed since the nearest line above with a timestamp.
+    pub since_previous: bool,
+    /// Time elapsed since the reference line.
+    pub since_reference: bool,
+}
+
+impl TimeColumns {
+    pub fn any(self) -> bool {
+        self.timestamp || self.since_previous || self.since_reference
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnSort {
+    pub column: String,
+    pub descending: bool,
 }
 
 #[derive(Clone)]
@@ -49,6 +95,37 @@ pub struct SearchCommandContext {
     pub previous_request: ReadSignal<u64>,
     pub navigation_in_flight: ReadSignal<bool>,
     pub set_navigation_in_flight: WriteSignal<bool>,
+    pub select_request: ReadSignal<Option<SearchMatch>>,
+    pub request_select: WriteSignal<Option<SearchMatch>>,
+}
+
+/// Requests to pin the current search query as a persistent highlight mark.
+#[derive(Clone)]
+pub struct MarkCommandContext {
+    pub add_request: ReadSignal<u64>,
+    pub request_add: WriteSignal<u64>,
+}
+
+/// Requests from the visual rules drawer to move the main pane to the next or previous line
+/// matched by a rule of the active profile.
+#[derive(Clone)]
+pub struct RuleNavigationContext {
+    pub request: ReadSignal<Option<RuleNavigationRequest>>,
+    pub request_navigate: WriteSignal<Option<RuleNavigationRequest>>,
+}
+
+/// Requests from the SQL drawer to reveal and select a line, counted from 1, in the main pane.
+#[derive(Clone)]
+pub struct LineJumpContext {
+    pub request: ReadSignal<Option<usize>>,
+    pub request_jump: WriteSignal<Option<usize>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuleNavigationRequest {
+    /// Rule index within the active profile, as listed by the rule counts.
+    pub rule: usize,
+    pub forward: bool,
 }
 
 #[derive(Clone)]
@@ -0,0 +1,270 @@
+use crate::browser_api_client::fetch_density_map;
+use crate::components::context::{LogFileContext, LogViewContext};
+use crate::components::visual_palette::visual_color_css;
+use leptos::prelude::*;
+use leptos::{component, view, IntoView};
+use logmancer_core::{DensityBucket, DensityMap, LineStyleIntent, PageResult, VisualColorRole};
+use std::time::Duration;
+
+/// Number of buckets requested for the minimap; each one is a thin row of the strip.
+const DENSITY_MAP_BUCKETS: usize = 100;
+
+/// Rule that styles the most lines of the bucket, preferring higher priority rules on ties.
+fn dominant_rule(bucket: &DensityBucket) -> Option<usize> {
+    bucket
+        .rule_lines
+        .iter()
+        .enumerate()
+        .filter(|(_, lines)| **lines > 0)
+        .fold(
+            None,
+            |best: Option<(usize, usize)>, (rule, lines)| match best {
+                Some((_, best_lines)) if best_lines >= *lines => best,
+                _ => Some((rule, *lines)),
+            },
+        )
+        .map(|(rule, _)| rule)
+}
+
+/// Opacity for a count relative to the busiest bucket; any hit stays visible.
+fn density_level(count: usize, max: usize) -> f64 {
+    if count == 0 || max == 0 {
+        return 0.0;
+    }
+    0.3 + 0.7 * count as f64 / max as f64
+}
+
+pub(super) fn rule_stripe_color(style: &LineStyleIntent) -> Option<String> {
+    style
+        .foreground
+        .as_ref()
+        .and_then(|token| visual_color_css(token, VisualColorRole::Foreground))
+        .or_else(|| {
+            style
+                .background
+                .as_ref()
+                .and_then(|token| visual_color_css(token, VisualColorRole::Background))
+        })
+}
+
+/// First line to show when jumping to `bucket`, keeping a full page on screen at the end.
+fn density_target_line(bucket: &DensityBucket, total_lines: usize, page_size: usize) -> usize {
+    bucket.start_line.min(total_lines.saturating_sub(page_size))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct DensityCell {
+    bucket: DensityBucket,
+    rule_color: Option<String>,
+    filter_level: f64,
+    search_level: f64,
+}
+
+fn density_cells(map: &DensityMap) -> Vec<DensityCell> {
+    let max_search = map
+        .buckets
+        .iter()
+        .map(|bucket| bucket.search_matches)
+        .max()
+        .unwrap_or(0);
+    let max_filter = map
+        .buckets
+        .iter()
+        .map(|bucket| bucket.filter_matches)
+        .max()
+        .unwrap_or(0);
+    map.buckets
+        .iter()
+        .map(|bucket| DensityCell {
+            bucket: bucket.clone(),
+            rule_color: dominant_rule(bucket)
+                .and_then(|rule| map.rule_styles.get(rule))
+                .and_then(rule_stripe_color),
+            filter_level: density_level(bucket.filter_matches, max_filter),
+            search_level: density_level(bucket.search_matches, max_search),
+        })
+        .collect()
+}
+
+fn density_cell_title(bucket: &DensityBucket) -> String {
+    let styled = bucket.rule_lines.iter().sum::<usize>();
+    format!(
+        "Lines {}-{}: {} search matches, {} filter matches, {} styled lines",
+        bucket.start_line + 1,
+        bucket.end_line,
+        bucket.search_matches,
+        bucket.filter_matches,
+        styled
+    )
+}
+
+/// Heat strip of search matches, filter matches and visual rules drawn beside the scrollbar.
+#[component]
+pub fn DensityStrip(
+    context: LogViewContext,
+    page_result: ReadSignal<Option<PageResult>>,
+    refresh_generation: ReadSignal<u64>,
+) -> impl IntoView {
+    let LogFileContext {
+        file_id,
+        set_tail,
+        filter_generation,
+        ..
+    } = use_context().expect("LogFileContext not found");
+    let LogViewContext {
+        set_start_line,
+        page_size,
+        ..
+    } = context;
+
+    let (refresh_tick, set_refresh_tick) = signal(0_u64);
+    // Only refetch when something that feeds the counts changed, not on every scroll.
+    let page_key = Memo::new(move |_| {
+        page_result.get().map(|page| {
+            let search = page
+                .search
+                .map(|search| (search.query, search.total_matches));
+            (page.total_lines, search)
+        })
+    });
+
+    let density = LocalResource::new(move || {
+        refresh_generation.track();
+        filter_generation.track();
+        refresh_tick.track();
+        let file_id = file_id.get();
+        let has_page = page_key.get().is_some();
+        async move {
+            if !has_page {
+                return Ok(None);
+            }
+            fetch_density_map(file_id, DENSITY_MAP_BUCKETS)
+                .await
+                .map(Some)
+        }
+    });
+
+    view! {
+        <div class="density-map" aria-hidden="true">
+            <Transition>
+                { move || Suspend::new(async move {
+                    density.await.map(|map| map.map(|map| {
+                        if map.is_indexing {
+                            set_timeout(
+                                move || set_refresh_tick.update(|tick| *tick = tick.wrapping_add(1)),
+                                Duration::from_secs(1),
+                            );
+                        }
+                        let total_lines = map.total_lines;
+                        density_cells(&map).into_iter().map(|cell| {
+                            let title = density_cell_title(&cell.bucket);
+                            let bucket = cell.bucket;
+                            view! {
+                                <div
+                                    class="density-map__cell"
+                                    title=title
+                                    on:click=move |_| {
+                                        set_tail.set(false);
+                                        set_start_line.set(density_target_line(
+                                            &bucket,
+                                            total_lines,
+                                            page_size.get_untracked(),
+                                        ));
+                                    }
+                                >
+                                    <span
+                                        class="density-map__rule"
+                                        style=cell.rule_color.map(|color| format!("background: {color}"))
+                                    ></span>
+                                    <span
+                                        class="density-map__filter"
+                                        style=format!("opacity: {:.2}", cell.filter_level)
+                                    ></span>
+                                    <span
+                                        class="density-map__search"
+                                        style=format!("opacity: {:.2}", cell.search_level)
+                                    ></span>
+                                </div>
+                            }
+                        }).collect_view()
+                    }))
+                })}
+            </Transition>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{density_cells, density_level, density_target_line, dominant_rule};
+    use logmancer_core::{DensityBucket, DensityMap, LineStyleIntent, VisualColor};
+
+    fn bucket(start_line: usize, search: usize, rule_lines: Vec<usize>) -> DensityBucket {
+        DensityBucket {
+            start_line,
+            end_line: start_line + 10,
+            search_matches: search,
+            filter_matches: 0,
+            rule_lines,
+        }
+    }
+
+    #[test]
+    fn dominant_rule_prefers_most_lines_then_priority() {
+        assert_eq!(dominant_rule(&bucket(0, 0, vec![0, 0])), None);
+        assert_eq!(dominant_rule(&bucket(0, 0, vec![1, 4, 2])), Some(1));
+        assert_eq!(dominant_rule(&bucket(0, 0, vec![3, 3])), Some(0));
+    }
+
+    #[test]
+    fn density_level_keeps_sparse_buckets_visible() {
+        assert_eq!(density_level(0, 10), 0.0);
+        assert_eq!(density_level(10, 10), 1.0);
+        assert!(density_level(1, 1000) >= 0.3);
+    }
+
+    #[test]
+    fn target_line_keeps_last_page_full() {
+        assert_eq!(density_target_line(&bucket(40, 0, vec![]), 100, 20), 40);
+        assert_eq!(density_target_line(&bucket(90, 0, vec![]), 100, 20), 80);
+        assert_eq!(density_target_line(&bucket(0, 0, vec![]), 5, 20), 0);
+    }
+
+    #[test]
+    fn cells_color_rules_and_scale_search_against_busiest_bucket() {
+        let map = DensityMap {
+            total_lines: 20,
+            buckets: vec![bucket(0, 4, vec![2, 0]), bucket(10, 0, vec![0, 1])],
+            rule_styles: vec![
+                LineStyleIntent {
+                    foreground: Some(VisualColor("red".to_string())),
+                    background: None,
+                    ..LineStyleIntent::default()
+                },
+                LineStyleIntent {
+                    foreground: Some(VisualColor("default".to_string())),
+                    background: Some(VisualColor("yellow".to_string())),
+                    ..LineStyleIntent::default()
+                },
+            ],
+            search_active: true,
+            filter_active: false,
+            rule_lines_sampled: false,
+            is_indexing: false,
+        };
+
+        let cells = density_cells(&map);
+
+        assert_eq!(
+            cells[0].rule_color.as_deref(),
+            Some("var(--visual-color-red-foreground)")
+        );
+        assert_eq!(cells[0].search_level, 1.0);
+        assert_eq!(
+            cells[1].rule_color.as_deref(),
+            Some("var(--visual-color-yellow-background)")
+        );
+        assert_eq!(cells[1].search_level, 0.0);
+        assert_eq!(cells[1].filter_level, 0.0);
+    }
+}
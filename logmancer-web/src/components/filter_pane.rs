@@ -2,7 +2,8 @@ use crate::browser_api_client::{apply_filter as apply_filter_fetch, fetch_filter
 use crate::components::content_lines::ContentLines;
 use crate::components::content_scroll::ContentScroll;
 use crate::components::context::{
-    ActivePaneContext, LogFileContext, LogViewContext, SelectionContext, SelectionSource,
+    ActivePaneContext, LogFieldsContext, LogFileContext, LogViewContext, SelectionContext,
+    SelectionSource,
 };
 use crate::components::layout::LOG_LINE_HEIGHT_PX;
 use crate::components::pane_index_progress::PaneIndexProgress;
@@ -13,15 +14,52 @@ use leptos::prelude::*;
 use leptos::task::spawn_local;
 use leptos::{component, view, IntoView};
 
+/// Select value that filters with a field query; field paths never take this form.
+const QUERY_TARGET: &str = "(query)";
+
+/// What the filter text is tested against, from the value of the target select.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum FilterTarget {
+    Line,
+    Query,
+    Field(String),
+}
+
+impl FilterTarget {
+    fn from_value(value: &str) -> Self {
+        match value {
+            "" => FilterTarget::Line,
+            QUERY_TARGET => FilterTarget::Query,
+            field => FilterTarget::Field(field.to_string()),
+        }
+    }
+
+    fn placeholder(&self) -> &'static str {
+        match self {
+            FilterTarget::Line => "Filter (press Enter)",
+            FilterTarget::Query => "Query like level >= WARN and duration > 250ms (press Enter)",
+            FilterTarget::Field(_) => "Regex or comparison like >100ms (press Enter)",
+        }
+    }
+}
+
 #[component]
 pub fn FilterPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
-    let LogFileContext { file_id, .. } = use_context().expect("LogFileContext not found");
+    let LogFileContext {
+        file_id,
+        set_filter_generation,
+        ..
+    } = use_context().expect("LogFileContext not found");
 
     let div_ref = NodeRef::<Div>::new();
     let (content_width, set_content_width) = signal(2048_f64);
     let (content_height, set_content_height) = signal(1080_f64);
 
     let (filter_text, set_filter_text) = signal(String::new());
+    // Value of the target select: empty for the whole line, a field path or QUERY_TARGET.
+    let (filter_field, set_filter_field) = signal(String::new());
+    let (filter_error, set_filter_error) = signal(String::new());
+    let log_fields = use_context::<LogFieldsContext>().map(|context| context.fields);
     let (filter_applied, set_filter_applied) = signal(false);
     let (indexing_progress, set_indexing_progress) = signal(0_f64);
     let SelectionContext {
@@ -70,6 +108,7 @@ pub fn FilterPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
     let on_input = move |ev: leptos::ev::Event| {
         let value = event_target_value(&ev);
         set_filter_text.set(value);
+        set_filter_error.set(String::new());
     };
 
     let apply_current_filter = move || {
@@ -77,17 +116,29 @@ pub fn FilterPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
         if !text.is_empty() {
             let file_id = file_id.get();
             let text_clone = text.clone();
+            let (field, query) = match FilterTarget::from_value(&filter_field.get()) {
+                FilterTarget::Line => (None, false),
+                FilterTarget::Query => (None, true),
+                FilterTarget::Field(field) => (Some(field), false),
+            };
 
             spawn_local(async move {
-                apply_filter_fetch(file_id, text_clone).await.ok();
+                if let Err(message) = apply_filter_fetch(file_id, text_clone, field, query).await {
+                    set_filter_error.set(message);
+                    return;
+                }
+                set_filter_error.set(String::new());
                 set_filter_applied.set(true);
                 set_indexing_progress.set(0.0);
                 // Reset scroll position when filter changes
                 set_start_line.set(0);
+                set_filter_generation.update(|generation| *generation = generation.wrapping_add(1));
             });
         } else {
+            set_filter_error.set(String::new());
             set_filter_applied.set(false);
             set_indexing_progress.set(0.0);
+            set_filter_generation.update(|generation| *generation = generation.wrapping_add(1));
         }
     };
 
@@ -131,10 +182,28 @@ pub fn FilterPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
             class:active-pane=move || active_pane.get() == SelectionSource::Filter
         >
             <div class="filter-input-container">
+                {move || {
+                    let fields = log_fields.map(|fields| fields.get()).unwrap_or_default();
+                    (!fields.is_empty()).then(|| view! {
+                        <select
+                            class="filter-field"
+                            aria-label="Filter field"
+                            prop:value=filter_field
+                            on:change=move |event| set_filter_field.set(event_target_value(&event))
+                        >
+                            <option value="">"Whole line"</option>
+                            <option value=QUERY_TARGET>"Query"</option>
+                            {fields.into_iter().map(|path| view! {
+                                <option value=path.clone()>{path.clone()}</option>
+                            }).collect_view()}
+                        </select>
+                    })
+                }}
                 <input
                     type="text"
                     class="filter-input"
-                    placeholder="Filter (press Enter)"
+                    class:filter-input--invalid=move || !filter_error.get().is_empty()
+                    placeholder=move || FilterTarget::from_value(&filter_field.get()).placeholder()
                     value=filter_text
                     on:input=on_input
                     on:keydown=on_key_down
@@ -151,6 +220,7 @@ pub fn FilterPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
                 >
                     {move || if filter_text.get().is_empty() && filter_applied.get() { "Clear" } else { "Search" }}
                 </button>
+                <span class="filter-error" role="alert">{filter_error}</span>
             </div>
             <PaneIndexProgress
                 context=log_view_context.clone()
@@ -164,3 +234,19 @@ pub fn FilterPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_select_values_choose_line_query_or_field() {
+        assert_eq!(FilterTarget::from_value(""), FilterTarget::Line);
+        assert_eq!(FilterTarget::from_value(QUERY_TARGET), FilterTarget::Query);
+        assert_eq!(
+            FilterTarget::from_value("http.status"),
+            FilterTarget::Field("http.status".to_string())
+        );
+        assert!(FilterTarget::Query.placeholder().starts_with("Query"));
+    }
+}
@@ -1,8 +1,13 @@
-use logmancer_core::PageSearchResult;
+use logmancer_core::{PageMarkResult, PageSearchResult, StyledSpan};
 use std::collections::HashMap;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum DecorationKind {
+    /// Span of a match-scoped visual rule, at this position in `PageLine::spans`.
+    RuleSpan(usize),
+    /// Occurrence of the mark at this position in `PageResult::marks`.
+    Mark(usize),
+    MarkCurrent(usize),
     SearchMatch,
     SearchCurrent,
 }
@@ -10,6 +15,9 @@ pub(crate) enum DecorationKind {
 impl DecorationKind {
     fn precedence(self) -> u8 {
         match self {
+            Self::RuleSpan(_) => 2,
+            Self::Mark(_) => 4,
+            Self::MarkCurrent(_) => 6,
             Self::SearchMatch => 10,
             Self::SearchCurrent => 40,
         }
@@ -132,13 +140,52 @@ pub(crate) fn search_decorations_by_line(
     decorations_by_line
 }
 
+/// Adds the decorations of every mark on the page, keyed like `search_decorations_by_line`.
+pub(crate) fn extend_with_mark_decorations(
+    decorations_by_line: &mut HashMap<usize, Vec<LineDecoration>>,
+    marks: &[PageMarkResult],
+) {
+    for (slot, mark) in marks.iter().enumerate() {
+        for mark_match in &mark.search.page_matches {
+            decorations_by_line
+                .entry(mark_match.line_index + 1)
+                .or_default()
+                .push(LineDecoration {
+                    start: mark_match.start,
+                    end: mark_match.end,
+                    kind: if mark.search.current.as_ref() == Some(mark_match) {
+                        DecorationKind::MarkCurrent(slot)
+                    } else {
+                        DecorationKind::Mark(slot)
+                    },
+                });
+        }
+    }
+}
+
+/// Decorations of the visual-rule spans of one line. Earlier spans come from higher
+/// priority rules and win where spans overlap, since equal kinds keep the first decoration.
+pub(crate) fn rule_span_decorations(spans: &[StyledSpan]) -> Vec<LineDecoration> {
+    spans
+        .iter()
+        .enumerate()
+        .map(|(index, span)| LineDecoration {
+            start: span.start,
+            end: span.end,
+            kind: DecorationKind::RuleSpan(index),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        search_decorations_by_line, split_line_segments, DecorationKind, LineDecoration,
-        RenderSegment,
+        extend_with_mark_decorations, rule_span_decorations, search_decorations_by_line,
+        split_line_segments, DecorationKind, LineDecoration, RenderSegment,
+    };
+    use logmancer_core::{
+        LineStyleIntent, PageMarkResult, PageSearchResult, SearchMatch, StyledSpan, VisualColor,
     };
-    use logmancer_core::{PageSearchResult, SearchMatch};
 
     fn segment<'a>(text: &'a str, kind: Option<DecorationKind>) -> RenderSegment<'a> {
         RenderSegment { text, kind }
@@ -313,4 +360,90 @@ mod tests {
             Some(&vec![decoration(1, 2, DecorationKind::SearchMatch)])
         );
     }
+
+    #[test]
+    fn search_spans_take_precedence_over_overlapping_marks() {
+        assert_eq!(
+            split_line_segments(
+                "req-7 failed",
+                &[
+                    decoration(0, 12, DecorationKind::Mark(0)),
+                    decoration(0, 5, DecorationKind::MarkCurrent(1)),
+                    decoration(6, 12, DecorationKind::SearchMatch),
+                ],
+            ),
+            vec![
+                segment("req-7", Some(DecorationKind::MarkCurrent(1))),
+                segment(" ", Some(DecorationKind::Mark(0))),
+                segment("failed", Some(DecorationKind::SearchMatch)),
+            ]
+        );
+    }
+
+    #[test]
+    fn mark_decorations_use_page_slot_and_current_match() {
+        let current = search_match(0, 6, 9, 1);
+        let mark = |query: &str, search: PageSearchResult| PageMarkResult {
+            id: 9,
+            color: VisualColor("cyan".to_string()),
+            search: PageSearchResult {
+                query: query.to_string(),
+                ..search
+            },
+        };
+        let marks = vec![
+            mark(
+                "foo",
+                page_search_result(None, vec![search_match(1, 0, 3, 0)]),
+            ),
+            mark(
+                "bar",
+                page_search_result(
+                    Some(current.clone()),
+                    vec![search_match(0, 0, 3, 0), current],
+                ),
+            ),
+        ];
+        let mut grouped =
+            search_decorations_by_line(&page_search_result(None, vec![search_match(0, 0, 1, 0)]));
+
+        extend_with_mark_decorations(&mut grouped, &marks);
+
+        assert_eq!(
+            grouped.get(&1),
+            Some(&vec![
+                decoration(0, 1, DecorationKind::SearchMatch),
+                decoration(0, 3, DecorationKind::Mark(1)),
+                decoration(6, 9, DecorationKind::MarkCurrent(1)),
+            ])
+        );
+        assert_eq!(
+            grouped.get(&2),
+            Some(&vec![decoration(0, 3, DecorationKind::Mark(0))])
+        );
+    }
+
+    #[test]
+    fn rule_spans_sit_below_search_and_keep_rule_priority_on_overlap() {
+        let span = |start, end| StyledSpan {
+            start,
+            end,
+            style: LineStyleIntent {
+                foreground: Some(VisualColor("cyan".to_string())),
+                background: None,
+                ..LineStyleIntent::default()
+            },
+        };
+        let mut decorations = rule_span_decorations(&[span(0, 8), span(4, 12)]);
+        decorations.push(decoration(6, 9, DecorationKind::SearchMatch));
+
+        assert_eq!(
+            split_line_segments("user=bob ok!", &decorations),
+            vec![
+                segment("user=b", Some(DecorationKind::RuleSpan(0))),
+                segment("ob ", Some(DecorationKind::SearchMatch)),
+                segment("ok!", Some(DecorationKind::RuleSpan(1))),
+            ]
+        );
+    }
 }
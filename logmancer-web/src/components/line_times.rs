@@ -0,0 +1,209 @@
+#[cfg(target_arch = "wasm32")]
+use crate::browser_api_client::{fetch_time_settings, set_time_reference, set_time_zone};
+use crate::components::context::{
+    LogFileContext, SelectionContext, TimeColumns, TimeColumnsContext,
+};
+use leptos::context::use_context;
+use leptos::prelude::*;
+use leptos::{component, view, IntoView};
+use logmancer_core::TimeSettings;
+
+/// Offset of the browser's time zone, from the minutes JavaScript's `getTimezoneOffset`
+/// reports behind UTC.
+#[cfg(any(target_arch = "wasm32", test))]
+fn browser_time_zone(minutes_behind_utc: i32) -> String {
+    let minutes = -minutes_behind_utc;
+    let sign = if minutes < 0 { '-' } else { '+' };
+    let minutes = minutes.abs();
+    format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// IANA zone the browser runs in, such as `Europe/Madrid`, as `Intl` resolves it.
+#[cfg(target_arch = "wasm32")]
+fn browser_zone_name() -> Option<String> {
+    let format = js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new());
+    js_sys::Reflect::get(&format.resolved_options(), &"timeZone".into())
+        .ok()?
+        .as_string()
+}
+
+fn reference_label(settings: &TimeSettings) -> String {
+    match (settings.reference_line, &settings.reference_timestamp) {
+        (Some(line), Some(timestamp)) => format!("Reference: line {line} at {timestamp}"),
+        (Some(line), None) => format!("Reference: line {line}"),
+        (None, _) => "No reference line".to_string(),
+    }
+}
+
+/// Time columns, display time zone and reference line of the open file.
+#[component]
+pub fn LineTimes(on_changed: Callback<()>) -> impl IntoView {
+    let LogFileContext { file_id, .. } = use_context().expect("LogFileContext not found");
+    let TimeColumnsContext {
+        columns,
+        set_columns,
+    } = use_context().expect("TimeColumnsContext not found");
+    let SelectionContext {
+        selected_original_line,
+        ..
+    } = use_context().expect("SelectionContext not found");
+    let (settings, set_settings) = signal(None::<TimeSettings>);
+    let (zone, set_zone) = signal(String::new());
+    let (message, set_message) = signal(String::new());
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (file_id, set_settings, set_message);
+
+    #[cfg(target_arch = "wasm32")]
+    Effect::new(move |_| {
+        let file_id = file_id.get();
+        if file_id.is_empty() {
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            match fetch_time_settings(file_id).await {
+                Ok(current) => {
+                    set_zone.set(current.time_zone.clone());
+                    set_settings.set(Some(current));
+                }
+                Err(error) => set_message.set(error),
+            }
+        });
+    });
+
+    let apply_zone = move |_time_zone: String| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let file_id = file_id.get_untracked();
+            leptos::task::spawn_local(async move {
+                match set_time_zone(file_id, _time_zone).await {
+                    Ok(current) => {
+                        set_zone.set(current.time_zone.clone());
+                        set_settings.set(Some(current));
+                        set_message.set(String::new());
+                        on_changed.run(());
+                    }
+                    Err(error) => set_message.set(error),
+                }
+            });
+        }
+    };
+
+    let apply_reference = move |_line: Option<usize>| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let file_id = file_id.get_untracked();
+            leptos::task::spawn_local(async move {
+                match set_time_reference(file_id, _line).await {
+                    Ok(current) => {
+                        set_settings.set(Some(current));
+                        set_message.set(String::new());
+                        on_changed.run(());
+                    }
+                    Err(error) => set_message.set(error),
+                }
+            });
+        }
+    };
+
+    // Pages are drawn with the time columns chosen when they were fetched.
+    let toggle = move |update: fn(&mut TimeColumns, bool), shown: bool| {
+        set_columns.update(|columns| update(columns, shown));
+        on_changed.run(());
+    };
+
+    view! {
+        <div class="line-times">
+            <fieldset class="line-times__columns">
+                <legend>"Times"</legend>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || columns.get().timestamp
+                        on:change=move |event| toggle(|columns, shown| columns.timestamp = shown, event_target_checked(&event))
+                    />
+                    "Timestamp"
+                </label>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || columns.get().since_previous
+                        on:change=move |event| toggle(|columns, shown| columns.since_previous = shown, event_target_checked(&event))
+                    />
+                    "Since previous"
+                </label>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || columns.get().since_reference
+                        on:change=move |event| toggle(|columns, shown| columns.since_reference = shown, event_target_checked(&event))
+                    />
+                    "Since reference"
+                </label>
+            </fieldset>
+            <form class="line-times__zone" on:submit=move |event| {
+                event.prevent_default();
+                apply_zone(zone.get_untracked());
+            }>
+                <label>"Zone"
+                    <input
+                        type="text"
+                        size="14"
+                        placeholder="UTC"
+                        title="UTC, an offset such as +02:00 or an IANA zone such as Europe/Madrid"
+                        prop:value=move || zone.get()
+                        on:input=move |event| set_zone.set(event_target_value(&event))
+                        on:change=move |event| apply_zone(event_target_value(&event))
+                    />
+                </label>
+                <button type="button" title="Use this browser's time zone" on:click=move |_| {
+                    #[cfg(target_arch = "wasm32")]
+                    apply_zone(browser_zone_name().unwrap_or_else(|| {
+                        browser_time_zone(js_sys::Date::new_0().get_timezone_offset() as i32)
+                    }));
+                }>"Local"</button>
+            </form>
+            <span class="line-times__reference">
+                {move || settings.get().map(|settings| reference_label(&settings))}
+            </span>
+            <button
+                type="button"
+                disabled=move || selected_original_line.get().is_none()
+                on:click=move |_| apply_reference(selected_original_line.get_untracked())
+            >"Set reference to selected line"</button>
+            <button
+                type="button"
+                disabled=move || settings.get().and_then(|settings| settings.reference_line).is_none()
+                on:click=move |_| apply_reference(None)
+            >"Clear reference"</button>
+            <span class="line-times__status" role="status">{message}</span>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browser_offsets_read_as_zones_ahead_of_utc() {
+        assert_eq!(browser_time_zone(-120), "+02:00");
+        assert_eq!(browser_time_zone(330), "-05:30");
+        assert_eq!(browser_time_zone(0), "+00:00");
+    }
+
+    #[test]
+    fn reference_labels_name_the_line_and_its_timestamp() {
+        let mut settings = TimeSettings {
+            time_zone: "UTC".to_string(),
+            reference_line: None,
+            reference_timestamp: None,
+        };
+        assert_eq!(reference_label(&settings), "No reference line");
+        settings.reference_line = Some(42);
+        settings.reference_timestamp = Some("2026-10-03 12:00:00.250".to_string());
+        assert_eq!(
+            reference_label(&settings),
+            "Reference: line 42 at 2026-10-03 12:00:00.250"
+        );
+    }
+}
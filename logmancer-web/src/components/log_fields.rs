@@ -0,0 +1,222 @@
+#[cfg(target_arch = "wasm32")]
+use crate::browser_api_client::{
+    fetch_file_info, fetch_log_fields, fetch_log_format, set_log_columns, set_log_format,
+};
+use crate::components::context::{LogFieldsContext, LogFileContext};
+use leptos::context::use_context;
+use leptos::prelude::*;
+use leptos::{component, view, IntoView};
+use logmancer_core::{FormatDetection, LogFormatStatus};
+
+/// Select value of the format switcher; the empty value stops parsing the file.
+#[cfg(any(target_arch = "wasm32", test))]
+fn chosen_format(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.is_empty())
+}
+
+/// Note on the format detected when the file was opened; a format below the confidence
+/// threshold is only suggested.
+fn detection_label(detection: &FormatDetection) -> String {
+    let percent = (detection.confidence * 100.0).round();
+    if detection.is_applied() {
+        format!("Detected {} ({percent}%)", detection.format)
+    } else {
+        format!("Possibly {} ({percent}%)", detection.format)
+    }
+}
+
+/// Columns after showing or hiding `path`; newly shown fields go last.
+#[cfg(any(target_arch = "wasm32", test))]
+fn toggled_columns(columns: &[String], path: &str, shown: bool) -> Vec<String> {
+    let mut columns: Vec<String> = columns
+        .iter()
+        .filter(|column| column.as_str() != path)
+        .cloned()
+        .collect();
+    if shown {
+        columns.push(path.to_string());
+    }
+    columns
+}
+
+/// Fetches the fields of the file's format into the shared context.
+#[cfg(target_arch = "wasm32")]
+fn load_fields(
+    file_id: String,
+    set_fields: WriteSignal<Vec<String>>,
+    set_message: WriteSignal<String>,
+) {
+    leptos::task::spawn_local(async move {
+        match fetch_log_fields(file_id).await {
+            Ok(discovery) => {
+                set_fields.set(
+                    discovery
+                        .fields
+                        .into_iter()
+                        .map(|field| field.path)
+                        .collect(),
+                );
+                set_message.set(String::new());
+            }
+            Err(error) => set_message.set(error),
+        }
+    });
+}
+
+/// Format switcher and column picker for the open file.
+#[component]
+pub fn LogFields(on_changed: Callback<()>) -> impl IntoView {
+    let LogFileContext { file_id, .. } = use_context().expect("LogFileContext not found");
+    let LogFieldsContext {
+        columns,
+        set_columns,
+        fields,
+        set_fields,
+        ..
+    } = use_context().expect("LogFieldsContext not found");
+    let (status, set_status) = signal(None::<LogFormatStatus>);
+    let (detection, set_detection) = signal(None::<FormatDetection>);
+    let (message, set_message) = signal(String::new());
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (
+        file_id,
+        set_status,
+        set_detection,
+        set_columns,
+        set_fields,
+        set_message,
+        on_changed,
+    );
+
+    #[cfg(target_arch = "wasm32")]
+    Effect::new(move |_| {
+        let file_id = file_id.get();
+        if file_id.is_empty() {
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            if let Ok(info) = fetch_file_info(file_id.clone()).await {
+                set_detection.set(info.detected_format);
+            }
+            match fetch_log_format(file_id.clone()).await {
+                Ok(current) => {
+                    set_columns.set(current.columns.clone());
+                    set_status.set(Some(current));
+                    load_fields(file_id, set_fields, set_message);
+                }
+                Err(error) => set_message.set(error),
+            }
+        });
+    });
+
+    let switch_format = move |_value: String| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let file_id = file_id.get_untracked();
+            leptos::task::spawn_local(async move {
+                match set_log_format(file_id.clone(), chosen_format(&_value)).await {
+                    Ok(current) => {
+                        set_columns.set(current.columns.clone());
+                        set_fields.set(Vec::new());
+                        set_status.set(Some(current));
+                        load_fields(file_id, set_fields, set_message);
+                        on_changed.run(());
+                    }
+                    Err(error) => set_message.set(error),
+                }
+            });
+        }
+    };
+
+    let toggle_column = move |_path: String, _shown: bool| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let file_id = file_id.get_untracked();
+            let requested = toggled_columns(&columns.get_untracked(), &_path, _shown);
+            leptos::task::spawn_local(async move {
+                match set_log_columns(file_id, requested).await {
+                    Ok(current) => {
+                        set_columns.set(current.columns.clone());
+                        set_status.set(Some(current));
+                        set_message.set(String::new());
+                        on_changed.run(());
+                    }
+                    Err(error) => set_message.set(error),
+                }
+            });
+        }
+    };
+
+    view! {
+        <div class="log-fields">
+            {move || status.get().map(|status| {
+                let active = status.active.clone().unwrap_or_default();
+                view! {
+                    <label>"Format"
+                        <select prop:value=active on:change=move |event| switch_format(event_target_value(&event))>
+                            <option value="">"Plain text"</option>
+                            {status.formats.iter().map(|name| view! {
+                                <option value=name.clone()>{name.clone()}</option>
+                            }).collect_view()}
+                        </select>
+                    </label>
+                }
+            })}
+            {move || detection.get().map(|detection| view! {
+                <span class="log-fields__detected">{detection_label(&detection)}</span>
+            })}
+            {move || (!fields.get().is_empty()).then(|| view! {
+                <fieldset class="log-fields__columns">
+                    <legend>"Columns"</legend>
+                    {fields.get().into_iter().map(|path| {
+                        let shown = columns.get().contains(&path);
+                        let toggled = path.clone();
+                        view! {
+                            <label>
+                                <input
+                                    type="checkbox"
+                                    prop:checked=shown
+                                    on:change=move |event| toggle_column(toggled.clone(), event_target_checked(&event))
+                                />
+                                {path}
+                            </label>
+                        }
+                    }).collect_view()}
+                </fieldset>
+            })}
+            <span class="log-fields__status" role="status">{message}</span>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_select_value_stops_parsing() {
+        assert_eq!(chosen_format(""), None);
+        assert_eq!(chosen_format("json").as_deref(), Some("json"));
+    }
+
+    #[test]
+    fn detections_below_the_threshold_are_only_suggested() {
+        let detection = |confidence| FormatDetection {
+            format: "csv".to_string(),
+            confidence,
+        };
+        assert_eq!(detection_label(&detection(0.916)), "Detected csv (92%)");
+        assert_eq!(detection_label(&detection(0.4)), "Possibly csv (40%)");
+    }
+
+    #[test]
+    fn shown_columns_go_last_and_hidden_ones_are_removed() {
+        let columns = vec!["level".to_string(), "msg".to_string()];
+        assert_eq!(
+            toggled_columns(&columns, "http.status", true),
+            ["level", "msg", "http.status"]
+        );
+        assert_eq!(toggled_columns(&columns, "level", false), ["msg"]);
+        assert_eq!(toggled_columns(&columns, "level", true), ["msg", "level"]);
+    }
+}
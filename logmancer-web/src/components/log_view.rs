@@ -1,12 +1,18 @@
 #[cfg(target_arch = "wasm32")]
-use crate::browser_api_client::fetch_file_info;
+use crate::browser_api_client::{fetch_file_info, wait_for_visual_rules_change};
 use crate::components::context::{
-    ActivePaneContext, LogContentFocusContext, LogFileContext, SearchCommandContext,
-    SearchUiContext, SelectionContext, SelectionSource,
+    ActivePaneContext, ColumnSort, LineJumpContext, LogContentFocusContext, LogFieldsContext,
+    LogFileContext, MarkCommandContext, RuleNavigationContext, RuleNavigationRequest,
+    SearchCommandContext, SearchUiContext, SelectionContext, SelectionSource, TimeColumns,
+    TimeColumnsContext,
 };
 use crate::components::filter_pane::FilterPane;
+use crate::components::line_times::LineTimes;
+use crate::components::log_fields::LogFields;
 use crate::components::main_pane::MainPane;
 use crate::components::search_panel::SearchPanel;
+use crate::components::sql_query::SqlQuery;
+use crate::components::visual_palette::VisualPaletteStyle;
 use crate::components::{AppBar, VisualRules};
 #[cfg(target_arch = "wasm32")]
 use leptos::ev::{keydown, KeyboardEvent};
@@ -18,6 +24,13 @@ use leptos::{component, view, IntoView};
 use leptos_router::hooks::use_params_map;
 #[cfg(target_arch = "wasm32")]
 use leptos_use::use_event_listener;
+use logmancer_core::SearchMatch;
+#[cfg(target_arch = "wasm32")]
+use std::time::Duration;
+
+/// Pause before following visual rules changes again after the server could not be reached.
+#[cfg(target_arch = "wasm32")]
+const VISUAL_RULES_FOLLOW_RETRY: Duration = Duration::from_secs(5);
 
 #[cfg(any(target_arch = "wasm32", test))]
 fn is_editable_element(tag_name: &str, content_editable: Option<&str>) -> bool {
@@ -71,6 +84,40 @@ fn next_refresh_generation(current: u64) -> u64 {
     current.wrapping_add(1)
 }
 
+/// Whether a followed revision means the rules changed since the page last saw them; the
+/// first answer only tells the page where it starts.
+#[cfg(any(target_arch = "wasm32", test))]
+fn visual_rules_changed(known: Option<u64>, revision: u64) -> bool {
+    known.is_some_and(|known| known != revision)
+}
+
+/// Long-polls the visual rules revision and refreshes the panes when the rules change outside
+/// this page, such as a hand edit of the rules file or a save from another window. Stops once
+/// the view is disposed.
+#[cfg(target_arch = "wasm32")]
+fn follow_visual_rules(known: Option<u64>, set_refresh_generation: WriteSignal<u64>) {
+    leptos::task::spawn_local(async move {
+        let changes = wait_for_visual_rules_change(known).await;
+        if set_refresh_generation.is_disposed() {
+            return;
+        }
+        match changes {
+            Ok(revision) => {
+                if visual_rules_changed(known, revision) {
+                    set_refresh_generation.update(|generation| {
+                        *generation = next_refresh_generation(*generation);
+                    });
+                }
+                follow_visual_rules(Some(revision), set_refresh_generation);
+            }
+            Err(_) => set_timeout(
+                move || follow_visual_rules(known, set_refresh_generation),
+                VISUAL_RULES_FOLLOW_RETRY,
+            ),
+        }
+    });
+}
+
 #[component]
 pub fn LogView() -> impl IntoView {
     let file_id = Memo::new(move |_| use_params_map().get().get("id").unwrap_or_default());
@@ -91,17 +138,27 @@ pub fn LogView() -> impl IntoView {
     let (search_next_request, request_search_next) = signal(0_u64);
     let (search_previous_request, request_search_previous) = signal(0_u64);
     let (search_navigation_in_flight, set_search_navigation_in_flight) = signal(false);
+    let (search_select_request, request_search_select) = signal(None::<SearchMatch>);
+    let (mark_add_request, request_mark_add) = signal(0_u64);
+    let (rule_navigation_request, request_rule_navigation) = signal(None::<RuleNavigationRequest>);
     #[cfg(not(target_arch = "wasm32"))]
     let _ = (&request_search_next, &request_search_previous);
     let (log_content_focus_request, request_log_content_focus) = signal(0_u64);
     let log_view_ref: NodeRef<html::Div> = NodeRef::new();
     let (visual_rules_open, set_visual_rules_open) = signal(false);
     let visual_rules_button_ref: NodeRef<html::Button> = NodeRef::new();
+    let (line_jump_request, request_line_jump) = signal(None::<usize>);
+    let (sql_open, set_sql_open) = signal(false);
+    let sql_button_ref: NodeRef<html::Button> = NodeRef::new();
     let (visual_rules_refresh_generation, set_visual_rules_refresh_generation) = signal(0_u64);
+    let (filter_generation, set_filter_generation) = signal(0_u64);
     let (file_path, set_file_path) = signal(file_id.get_untracked());
     #[cfg(not(target_arch = "wasm32"))]
     let _ = set_file_path;
 
+    #[cfg(target_arch = "wasm32")]
+    follow_visual_rules(None, set_visual_rules_refresh_generation);
+
     #[cfg(target_arch = "wasm32")]
     Effect::new(move |_| {
         let current_file_id = file_id.get();
@@ -212,12 +269,41 @@ pub fn LogView() -> impl IntoView {
         set_filter_height_percent.set(clamped_filter_percent);
     };
 
+    let (log_columns, set_log_columns) = signal(Vec::<String>::new());
+    let (log_fields, set_log_fields) = signal(Vec::<String>::new());
+    let (log_sort, set_log_sort) = signal(None::<ColumnSort>);
+    provide_context(LogFieldsContext {
+        columns: log_columns,
+        set_columns: set_log_columns,
+        fields: log_fields,
+        set_fields: set_log_fields,
+        sort: log_sort,
+        set_sort: set_log_sort,
+    });
+    // Pages are drawn with the order chosen when they were fetched, so sorting redraws them.
+    Effect::new(move |previous: Option<()>| {
+        log_sort.track();
+        if previous.is_some() {
+            set_visual_rules_refresh_generation.update(|generation| {
+                *generation = next_refresh_generation(*generation);
+            });
+        }
+    });
+
+    let (time_columns, set_time_columns) = signal(TimeColumns::default());
+    provide_context(TimeColumnsContext {
+        columns: time_columns,
+        set_columns: set_time_columns,
+    });
+
     provide_context(LogFileContext {
         file_id,
         tail,
         set_tail,
         follow,
         set_follow,
+        filter_generation,
+        set_filter_generation,
     });
 
     provide_context(SelectionContext {
@@ -252,6 +338,23 @@ pub fn LogView() -> impl IntoView {
         previous_request: search_previous_request,
         navigation_in_flight: search_navigation_in_flight,
         set_navigation_in_flight: set_search_navigation_in_flight,
+        select_request: search_select_request,
+        request_select: request_search_select,
+    });
+
+    provide_context(MarkCommandContext {
+        add_request: mark_add_request,
+        request_add: request_mark_add,
+    });
+
+    provide_context(RuleNavigationContext {
+        request: rule_navigation_request,
+        request_navigate: request_rule_navigation,
+    });
+
+    provide_context(LineJumpContext {
+        request: line_jump_request,
+        request_jump: request_line_jump,
     });
 
     provide_context(LogContentFocusContext {
@@ -287,7 +390,21 @@ pub fn LogView() -> impl IntoView {
                 path=file_path.get()
                 open_visual_rules=Callback::new(move |_| set_visual_rules_open.set(true))
                 visual_rules_button_ref=visual_rules_button_ref
+                open_sql=Callback::new(move |_| set_sql_open.set(true))
+                sql_button_ref=sql_button_ref
             />
+            // A format change also moves profile auto-selection, so it redraws like a rules save.
+            <LogFields on_changed=Callback::new(move |_| {
+                set_visual_rules_refresh_generation.update(|generation| {
+                    *generation = next_refresh_generation(*generation);
+                });
+            }) />
+            // Time settings are read when pages are fetched, so changing them redraws the panes.
+            <LineTimes on_changed=Callback::new(move |_| {
+                set_visual_rules_refresh_generation.update(|generation| {
+                    *generation = next_refresh_generation(*generation);
+                });
+            }) />
             <div
                 class=move || {
                     if active_pane.get() == SelectionSource::Main {
@@ -320,10 +437,13 @@ pub fn LogView() -> impl IntoView {
                 <FilterPane refresh_generation=visual_rules_refresh_generation />
             </div>
             <SearchPanel />
+            <SqlQuery open=sql_open set_open=set_sql_open invoker_ref=sql_button_ref />
+            <VisualPaletteStyle refresh_generation=visual_rules_refresh_generation />
             <VisualRules
                 open=visual_rules_open
                 set_open=set_visual_rules_open
                 invoker_ref=visual_rules_button_ref
+                refresh_generation=visual_rules_refresh_generation
                 on_saved=Callback::new(move |_| {
                     set_visual_rules_refresh_generation.update(|generation| {
                         *generation = next_refresh_generation(*generation);
@@ -338,6 +458,7 @@ pub fn LogView() -> impl IntoView {
 mod tests {
     use super::{
         app_bar_path, is_editable_element, next_refresh_generation, should_handle_viewer_shortcut,
+        visual_rules_changed,
     };
     use logmancer_core::FileInfo;
 
@@ -365,6 +486,7 @@ mod tests {
             path: "/var/log/service.log".to_string(),
             total_lines: 12,
             indexing_progress: 1.0,
+            detected_format: None,
         };
 
         assert_eq!(
@@ -380,4 +502,11 @@ mod tests {
         assert_eq!(next_refresh_generation(1), 2);
         assert_eq!(next_refresh_generation(u64::MAX), 0);
     }
+
+    #[test]
+    fn only_revisions_after_the_first_followed_one_refresh_the_page() {
+        assert!(!visual_rules_changed(None, 7));
+        assert!(!visual_rules_changed(Some(7), 7));
+        assert!(visual_rules_changed(Some(7), 8));
+    }
 }
@@ -1,14 +1,17 @@
 use crate::browser_api_client::{
-    apply_search, clear_search, fetch_page, search_next, search_previous,
+    apply_search, clear_search, fetch_page, search_next, search_previous, search_select,
+    visual_rule_next, visual_rule_previous,
 };
 use crate::components::auto_scroll_status::AutoScrollStatus;
 use crate::components::content_lines::ContentLines;
 use crate::components::content_scroll::ContentScroll;
 use crate::components::context::{
-    ActivePaneContext, LogContentFocusContext, LogFileContext, LogViewContext,
-    SearchCommandContext, SearchUiContext, SelectionContext, SelectionSource,
+    ActivePaneContext, LineJumpContext, LogContentFocusContext, LogFileContext, LogViewContext,
+    RuleNavigationContext, SearchCommandContext, SearchUiContext, SelectionContext,
+    SelectionSource,
 };
 use crate::components::layout::LOG_LINE_HEIGHT_PX;
+use crate::components::marks_bar::MarksBar;
 use crate::components::pane_index_progress::PaneIndexProgress;
 use crate::components::search_status::format_page_search_status;
 use leptos::context::use_context;
@@ -112,12 +115,21 @@ pub fn MainPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
         previous_request: search_previous_request,
         navigation_in_flight: search_navigation_in_flight,
         set_navigation_in_flight: set_search_navigation_in_flight,
+        select_request: search_select_request,
         ..
     } = use_context().expect("SearchCommandContext not found");
     let LogContentFocusContext {
         request_focus: request_log_content_focus,
         ..
     } = use_context().expect("LogContentFocusContext not found");
+    let RuleNavigationContext {
+        request: rule_navigation_request,
+        ..
+    } = use_context().expect("RuleNavigationContext not found");
+    let LineJumpContext {
+        request: line_jump_request,
+        ..
+    } = use_context().expect("LineJumpContext not found");
 
     let div_ref = NodeRef::<Div>::new();
     let (content_width, set_content_width) = signal(2048_f64);
@@ -263,6 +275,77 @@ pub fn MainPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
         submit_search();
     });
 
+    Effect::new(move || {
+        let Some(search_match) = search_select_request.get() else {
+            return;
+        };
+        let file_id = file_id.get_untracked();
+        let max_lines = page_size.get_untracked();
+
+        spawn_local(async move {
+            match search_select(
+                file_id,
+                search_match.line_index,
+                search_match.start,
+                max_lines,
+            )
+            .await
+            {
+                Ok(page) => {
+                    set_search_status.set(format_page_search_status(&page));
+                    apply_search_page_result(
+                        page,
+                        set_tail,
+                        set_follow,
+                        set_start_line,
+                        set_selected_original_line,
+                        set_selected_line_source,
+                    );
+                }
+                Err(_) => set_search_status.set("Match unavailable".to_string()),
+            }
+        });
+    });
+
+    Effect::new(move || {
+        let Some(request) = rule_navigation_request.get() else {
+            return;
+        };
+        let file_id = file_id.get_untracked();
+        let max_lines = page_size.get_untracked();
+
+        spawn_local(async move {
+            let result = if request.forward {
+                visual_rule_next(file_id, request.rule, max_lines).await
+            } else {
+                visual_rule_previous(file_id, request.rule, max_lines).await
+            };
+            if let Ok(navigation) = result {
+                set_tail.set(false);
+                set_follow.set(false);
+                set_start_line.set(navigation.page.start_line);
+                set_start_line.notify();
+                set_selected_line_source.set(SelectionSource::Main);
+                set_selected_original_line.set(navigation.line_index.map(|line| line + 1));
+            }
+        });
+    });
+
+    Effect::new(move || {
+        let Some(line_number) = line_jump_request.get() else {
+            return;
+        };
+        set_tail.set(false);
+        set_follow.set(false);
+        set_start_line.set(reveal_start_line_for_selected_line(
+            line_number,
+            page_size.get_untracked(),
+        ));
+        set_start_line.notify();
+        set_selected_line_source.set(SelectionSource::Main);
+        set_selected_original_line.set(Some(line_number));
+    });
+
     Effect::new(move || {
         let request = search_clear_request.get();
         if request == 0 {
@@ -342,9 +425,13 @@ pub fn MainPane(refresh_generation: ReadSignal<u64>) -> impl IntoView {
                 hidden=Signal::derive(move || indexing_progress.get() >= 1.0)
                 variant_class="progress-bar--global"
             />
+            <MarksBar context=log_view_context.clone() />
             <div node_ref=div_ref class="content">
                 <ContentLines context=log_view_context.clone() />
-                <ContentScroll context=log_view_context.clone() />
+                <ContentScroll
+                    context=log_view_context.clone()
+                    density_refresh=refresh_generation
+                />
             </div>
             <AutoScrollStatus />
         </div>
@@ -376,6 +463,7 @@ mod tests {
             total_lines: 0,
             indexing_progress: 1.0,
             search,
+            marks: Vec::new(),
         }
     }
 
@@ -0,0 +1,220 @@
+use crate::browser_api_client::{add_mark, clear_marks, mark_next, mark_previous, remove_mark};
+use crate::components::context::{
+    LogFileContext, LogViewContext, MarkCommandContext, SearchUiContext, SelectionSource,
+};
+use crate::components::search_status::format_search_status;
+use leptos::context::use_context;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos::{component, view, IntoView};
+use logmancer_core::{PageMarkResult, PageResult};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MarkNavigationDirection {
+    Next,
+    Previous,
+}
+
+fn mark_chip_label(mark: &PageMarkResult) -> String {
+    format_search_status(&mark.search.display_status())
+}
+
+fn mark_swatch_class(mark: &PageMarkResult) -> String {
+    format!("marks-bar__swatch marks-bar__swatch--{}", mark.color.0)
+}
+
+fn selected_mark_line_from_page(page: &PageResult, mark_id: u64) -> Option<usize> {
+    page.marks
+        .iter()
+        .find(|mark| mark.id == mark_id)
+        .and_then(|mark| mark.search.current.as_ref())
+        .map(|mark_match| mark_match.line_index + 1)
+}
+
+/// Chips for the persistent highlight marks of the main pane, each with its own navigation.
+#[component]
+pub fn MarksBar(context: LogViewContext) -> impl IntoView {
+    let LogFileContext {
+        file_id,
+        set_tail,
+        set_follow,
+        ..
+    } = use_context().expect("LogFileContext not found");
+    let SearchUiContext {
+        query: search_query,
+        set_status: set_search_status,
+        ..
+    } = use_context().expect("SearchUiContext not found");
+    let MarkCommandContext { add_request, .. } =
+        use_context().expect("MarkCommandContext not found");
+    let LogViewContext {
+        set_start_line,
+        page_size,
+        log_page,
+        set_selected_line,
+        set_selected_line_source,
+        ..
+    } = context;
+
+    let (marks, set_marks) = signal(Vec::<PageMarkResult>::new());
+
+    Effect::new(move || {
+        if add_request.get() == 0 {
+            return;
+        }
+        let query = search_query.get_untracked().trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+
+        let file_id = file_id.get_untracked();
+        spawn_local(async move {
+            match add_mark(file_id, query.clone(), None).await {
+                Ok(_) => {
+                    set_search_status.set(format!("Marked {query}"));
+                    set_start_line.notify();
+                }
+                Err(_) => set_search_status.set("Mark failed".to_string()),
+            }
+        });
+    });
+
+    let navigate = move |mark_id: u64, direction: MarkNavigationDirection| {
+        let file_id = file_id.get_untracked();
+        let max_lines = page_size.get_untracked();
+        spawn_local(async move {
+            let result = match direction {
+                MarkNavigationDirection::Next => mark_next(file_id, mark_id, max_lines).await,
+                MarkNavigationDirection::Previous => {
+                    mark_previous(file_id, mark_id, max_lines).await
+                }
+            };
+            if let Ok(page) = result {
+                set_tail.set(false);
+                set_follow.set(false);
+                set_start_line.set(page.start_line);
+                set_start_line.notify();
+                set_selected_line_source.set(SelectionSource::Main);
+                set_selected_line.set(selected_mark_line_from_page(&page, mark_id));
+            }
+        });
+    };
+
+    let remove = move |mark_id: u64| {
+        let file_id = file_id.get_untracked();
+        spawn_local(async move {
+            remove_mark(file_id, mark_id).await.ok();
+            set_start_line.notify();
+        });
+    };
+
+    let clear = move |_| {
+        let file_id = file_id.get_untracked();
+        spawn_local(async move {
+            clear_marks(file_id).await.ok();
+            set_start_line.notify();
+        });
+    };
+
+    view! {
+        <Transition>
+            { move || Suspend::new(async move {
+                log_page.await.map(|page_result| {
+                    set_marks.set(page_result.marks);
+                })
+            })}
+        </Transition>
+        <div class="marks-bar" class:marks-bar--empty=move || marks.get().is_empty()>
+            <For
+                each=move || marks.get()
+                key=|mark| (mark.id, mark_chip_label(mark))
+                children=move |mark| {
+                    let mark_id = mark.id;
+                    view! {
+                        <span class="marks-bar__chip">
+                            <span class=mark_swatch_class(&mark)></span>
+                            <span class="marks-bar__label">{mark_chip_label(&mark)}</span>
+                            <button
+                                type="button"
+                                aria-label="Previous mark occurrence"
+                                on:click=move |_| navigate(mark_id, MarkNavigationDirection::Previous)
+                            >"‹"</button>
+                            <button
+                                type="button"
+                                aria-label="Next mark occurrence"
+                                on:click=move |_| navigate(mark_id, MarkNavigationDirection::Next)
+                            >"›"</button>
+                            <button
+                                type="button"
+                                aria-label="Remove mark"
+                                on:click=move |_| remove(mark_id)
+                            >"×"</button>
+                        </span>
+                    }
+                }
+            />
+            <button type="button" class="marks-bar__clear" on:click=clear>"Clear marks"</button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mark_chip_label, mark_swatch_class, selected_mark_line_from_page};
+    use logmancer_core::{PageMarkResult, PageResult, PageSearchResult, SearchMatch, VisualColor};
+
+    fn mark(id: u64, current: Option<SearchMatch>) -> PageMarkResult {
+        PageMarkResult {
+            id,
+            color: VisualColor("green".to_string()),
+            search: PageSearchResult {
+                query: "req-7".to_string(),
+                total_matches: 4,
+                total_matches_final: true,
+                is_indexing: false,
+                first: None,
+                current,
+                page_matches: Vec::new(),
+            },
+        }
+    }
+
+    fn search_match(line_index: usize, ordinal: usize) -> SearchMatch {
+        SearchMatch {
+            line_index,
+            start: 0,
+            end: 5,
+            ordinal,
+        }
+    }
+
+    #[test]
+    fn mark_chip_reuses_search_status_wording() {
+        assert_eq!(
+            mark_chip_label(&mark(1, Some(search_match(3, 1)))),
+            "req-7 2/4"
+        );
+        assert_eq!(
+            mark_swatch_class(&mark(1, None)),
+            "marks-bar__swatch marks-bar__swatch--green"
+        );
+    }
+
+    #[test]
+    fn selected_mark_line_uses_the_navigated_mark_only() {
+        let page = PageResult {
+            lines: Vec::new(),
+            start_line: 0,
+            total_lines: 10,
+            indexing_progress: 1.0,
+            search: None,
+            marks: vec![
+                mark(1, Some(search_match(2, 0))),
+                mark(2, Some(search_match(7, 0))),
+            ],
+        };
+
+        assert_eq!(selected_mark_line_from_page(&page, 2), Some(8));
+        assert_eq!(selected_mark_line_from_page(&page, 3), None);
+    }
+}
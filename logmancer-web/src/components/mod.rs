@@ -4,18 +4,27 @@ mod auto_scroll_status;
 mod content_lines;
 mod content_scroll;
 mod context;
+mod density_map;
 mod diagnostics;
 mod filter_pane;
 mod home;
 mod layout;
 pub(crate) mod line_decorations;
+mod line_times;
+mod log_fields;
 mod log_view;
 mod main_pane;
+mod marks_bar;
 mod pane_index_progress;
 mod progress_bar;
 mod search_panel;
+mod search_results;
 mod search_status;
 mod server_file_spotlight;
+mod sql_query;
+mod visual_palette;
+mod visual_rule_conditions;
+mod visual_rule_counts;
 mod visual_rule_editor;
 mod visual_rules;
 
@@ -1,4 +1,5 @@
-use crate::components::context::{SearchCommandContext, SearchUiContext};
+use crate::components::context::{MarkCommandContext, SearchCommandContext, SearchUiContext};
+use crate::components::search_results::SearchResults;
 use leptos::ev::KeyboardEvent;
 use leptos::html::Input;
 use leptos::prelude::*;
@@ -22,11 +23,27 @@ pub fn SearchPanel() -> impl IntoView {
         request_clear,
         ..
     } = use_context().expect("SearchCommandContext not found");
+    let MarkCommandContext {
+        request_add: request_mark_add,
+        ..
+    } = use_context().expect("MarkCommandContext not found");
     let input_ref = NodeRef::<Input>::new();
+    let (results_open, set_results_open) = signal(false);
 
     let close_panel = move || request_close.update(|request| *request = request.saturating_add(1));
 
+    let pin_mark = move || {
+        if !query.get_untracked().trim().is_empty() {
+            request_mark_add.update(|request| *request = request.saturating_add(1));
+        }
+    };
+
     let on_key_down = move |ev: KeyboardEvent| match ev.key().as_str() {
+        "Enter" if ev.alt_key() => {
+            ev.prevent_default();
+            ev.stop_propagation();
+            pin_mark();
+        }
         "Enter" => {
             ev.prevent_default();
             ev.stop_propagation();
@@ -88,6 +105,22 @@ pub fn SearchPanel() -> impl IntoView {
                 on:keydown=on_key_down
             />
             <span class="search-panel__status" aria-live="polite">{move || status.get()}</span>
+            <button
+                type="button"
+                class="search-panel__mark"
+                class:search-panel__mark--active=results_open
+                title="List all matches"
+                aria-pressed=move || results_open.get().to_string()
+                tabindex=move || if visible.get() { "0" } else { "-1" }
+                on:click=move |_| set_results_open.update(|open| *open = !*open)
+            >"All"</button>
+            <button
+                type="button"
+                class="search-panel__mark"
+                title="Keep highlighting this term (Alt+Enter)"
+                tabindex=move || if visible.get() { "0" } else { "-1" }
+                on:click=move |_| pin_mark()
+            >"Mark"</button>
             <button
                 type="button"
                 class="search-panel__close"
@@ -95,6 +128,7 @@ pub fn SearchPanel() -> impl IntoView {
                 tabindex=move || if visible.get() { "0" } else { "-1" }
                 on:click=move |_| close_panel()
             >"x"</button>
+            <SearchResults visible=Signal::derive(move || visible.get() && results_open.get()) />
         </div>
     }
 }
@@ -0,0 +1,209 @@
+use crate::browser_api_client::fetch_search_hits;
+use crate::components::context::{LogFileContext, SearchCommandContext, SearchUiContext};
+use crate::components::line_decorations::{split_line_segments, DecorationKind, LineDecoration};
+use leptos::prelude::*;
+use leptos::{component, view, IntoView};
+use logmancer_core::{SearchHit, SearchHitsPage};
+use std::time::Duration;
+
+const SEARCH_RESULTS_PAGE_SIZE: usize = 100;
+
+fn results_range_label(page: &SearchHitsPage) -> String {
+    if page.hits.is_empty() {
+        return if page.is_indexing {
+            "No matches yet".to_string()
+        } else {
+            "No matches".to_string()
+        };
+    }
+
+    let first = page.offset + 1;
+    let last = page.offset + page.hits.len();
+    let total_suffix = if page.total_matches_final { "" } else { "+" };
+    format!("{first}-{last} of {}{total_suffix}", page.total_matches)
+}
+
+fn previous_offset(offset: usize) -> Option<usize> {
+    (offset > 0).then(|| offset.saturating_sub(SEARCH_RESULTS_PAGE_SIZE))
+}
+
+fn next_offset(page: &SearchHitsPage) -> Option<usize> {
+    let next = page.offset + page.hits.len();
+    (!page.hits.is_empty() && next < page.total_matches).then_some(next)
+}
+
+fn hit_decoration(hit: &SearchHit) -> LineDecoration {
+    LineDecoration {
+        start: hit.search_match.start,
+        end: hit.search_match.end,
+        kind: DecorationKind::SearchMatch,
+    }
+}
+
+#[component]
+fn SearchHitRow(hit: SearchHit) -> impl IntoView {
+    let SearchCommandContext { request_select, .. } =
+        use_context().expect("SearchCommandContext not found");
+    let decoration = hit_decoration(&hit);
+    let segments = split_line_segments(&hit.text, &[decoration])
+        .into_iter()
+        .map(|segment| (segment.text.to_string(), segment.kind.is_some()))
+        .collect::<Vec<_>>();
+    let search_match = hit.search_match;
+
+    view! {
+        <li
+            class="search-results__hit"
+            on:click=move |_| request_select.set(Some(search_match.clone()))
+        >
+            <b class="search-results__line">{hit.line_number}</b>
+            <span class="search-results__text">
+                {segments.into_iter().map(|(text, matched)| {
+                    if matched {
+                        view! { <mark class="search-match">{text}</mark> }.into_any()
+                    } else {
+                        view! { <span>{text}</span> }.into_any()
+                    }
+                }).collect_view()}
+            </span>
+        </li>
+    }
+}
+
+/// "Find all" list of the active search hits; clicking a hit makes it the current match.
+#[component]
+pub fn SearchResults(visible: Signal<bool>) -> impl IntoView {
+    let LogFileContext { file_id, .. } = use_context().expect("LogFileContext not found");
+    let SearchUiContext { status, .. } = use_context().expect("SearchUiContext not found");
+    let SearchCommandContext { submit_request, .. } =
+        use_context().expect("SearchCommandContext not found");
+
+    let (offset, set_offset) = signal(0_usize);
+    let (refresh_tick, set_refresh_tick) = signal(0_u64);
+
+    Effect::new(move || {
+        submit_request.track();
+        set_offset.set(0);
+    });
+
+    let hits = LocalResource::new(move || {
+        status.track();
+        refresh_tick.track();
+        let visible = visible.get();
+        let file_id = file_id.get();
+        let offset = offset.get();
+        async move {
+            if !visible {
+                return Ok(None);
+            }
+            fetch_search_hits(file_id, offset, SEARCH_RESULTS_PAGE_SIZE).await
+        }
+    });
+
+    view! {
+        <div class="search-results" class:search-results--hidden=move || !visible.get()>
+            <Transition>
+                { move || Suspend::new(async move {
+                    hits.await.map(|page| {
+                        let Some(page) = page else {
+                            return view! {
+                                <div class="search-results__empty">"No active search"</div>
+                            }.into_any();
+                        };
+                        if page.is_indexing {
+                            // Matches are still being merged; refresh until the count is final.
+                            set_timeout(
+                                move || set_refresh_tick.update(|tick| *tick = tick.wrapping_add(1)),
+                                Duration::from_secs(1),
+                            );
+                        }
+                        let previous = previous_offset(page.offset);
+                        let next = next_offset(&page);
+                        view! {
+                            <div class="search-results__header">
+                                <span>{results_range_label(&page)}</span>
+                                <button
+                                    type="button"
+                                    disabled=previous.is_none()
+                                    on:click=move |_| {
+                                        if let Some(previous) = previous {
+                                            set_offset.set(previous);
+                                        }
+                                    }
+                                >"Previous"</button>
+                                <button
+                                    type="button"
+                                    disabled=next.is_none()
+                                    on:click=move |_| {
+                                        if let Some(next) = next {
+                                            set_offset.set(next);
+                                        }
+                                    }
+                                >"Next"</button>
+                            </div>
+                            <ol class="search-results__list">
+                                {page.hits.into_iter().map(|hit| view! { <SearchHitRow hit=hit /> }).collect_view()}
+                            </ol>
+                        }.into_any()
+                    })
+                })}
+            </Transition>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_offset, previous_offset, results_range_label, SEARCH_RESULTS_PAGE_SIZE};
+    use logmancer_core::{SearchHit, SearchHitsPage, SearchMatch};
+
+    fn hits_page(
+        offset: usize,
+        hits: usize,
+        total_matches: usize,
+        final_count: bool,
+    ) -> SearchHitsPage {
+        SearchHitsPage {
+            query: "timeout".to_string(),
+            offset,
+            hits: (0..hits)
+                .map(|index| SearchHit {
+                    line_number: offset + index + 1,
+                    text: "timeout".to_string(),
+                    search_match: SearchMatch {
+                        line_index: offset + index,
+                        start: 0,
+                        end: 7,
+                        ordinal: offset + index,
+                    },
+                })
+                .collect(),
+            total_matches,
+            total_matches_final: final_count,
+            is_indexing: !final_count,
+        }
+    }
+
+    #[test]
+    fn range_label_marks_counts_that_may_still_grow() {
+        assert_eq!(
+            results_range_label(&hits_page(100, 100, 250, true)),
+            "101-200 of 250"
+        );
+        assert_eq!(results_range_label(&hits_page(0, 3, 3, false)), "1-3 of 3+");
+        assert_eq!(
+            results_range_label(&hits_page(0, 0, 0, false)),
+            "No matches yet"
+        );
+        assert_eq!(results_range_label(&hits_page(0, 0, 0, true)), "No matches");
+    }
+
+    #[test]
+    fn pagination_offsets_stop_at_both_ends() {
+        assert_eq!(previous_offset(0), None);
+        assert_eq!(previous_offset(SEARCH_RESULTS_PAGE_SIZE + 5), Some(5));
+        assert_eq!(next_offset(&hits_page(0, 100, 250, true)), Some(100));
+        assert_eq!(next_offset(&hits_page(200, 50, 250, true)), None);
+        assert_eq!(next_offset(&hits_page(0, 0, 0, false)), None);
+    }
+}
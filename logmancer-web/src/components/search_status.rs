@@ -7,7 +7,7 @@ pub fn format_page_search_status(page: &PageResult) -> String {
         .unwrap_or_default()
 }
 
-fn format_search_status(status: &SearchDisplayStatus) -> String {
+pub(crate) fn format_search_status(status: &SearchDisplayStatus) -> String {
     let mut text = if status.total_matches == 0 {
         if status.is_indexing {
             format!("{} no matches yet", status.query)
@@ -0,0 +1,190 @@
+#[cfg(target_arch = "wasm32")]
+use crate::browser_api_client::run_sql;
+use crate::components::context::{LineJumpContext, LogFileContext};
+use leptos::html;
+use leptos::prelude::*;
+use leptos::{component, view, IntoView};
+use logmancer_core::{SqlResult, MAX_SQL_ROWS};
+
+const SQL_PLACEHOLDER: &str = "SELECT level, count(*) FROM log GROUP BY level";
+
+/// Text of one result cell; SQL NULL is spelled out so that it differs from empty text.
+fn sql_cell_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "NULL".to_string(),
+        serde_json::Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn sql_result_summary(result: &SqlResult) -> String {
+    match result.rows.len() {
+        _ if result.truncated => format!("First {MAX_SQL_ROWS} rows"),
+        1 => "1 row".to_string(),
+        rows => format!("{rows} rows"),
+    }
+}
+
+/// Ctrl+Enter or Cmd+Enter runs the query; a plain Enter adds a line to it.
+fn runs_query(key: &str, ctrl: bool, meta: bool) -> bool {
+    key == "Enter" && (ctrl || meta)
+}
+
+/// Drawer running SQL over the open file's `log` table; a row that selects `log_line` opens
+/// its source line in the main pane.
+#[component]
+pub fn SqlQuery(
+    open: ReadSignal<bool>,
+    set_open: WriteSignal<bool>,
+    invoker_ref: NodeRef<html::Button>,
+) -> impl IntoView {
+    let LogFileContext { file_id, .. } = use_context().expect("LogFileContext not found");
+    let LineJumpContext { request_jump, .. } = use_context().expect("LineJumpContext not found");
+    let (query, set_query) = signal(String::new());
+    let (result, set_result) = signal(None::<SqlResult>);
+    let (error, set_error) = signal(None::<String>);
+    let (running, set_running) = signal(false);
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (file_id, set_result, set_running);
+
+    let run = move || {
+        let query = query.get_untracked();
+        if query.trim().is_empty() || running.get_untracked() {
+            return;
+        }
+        set_error.set(None);
+        #[cfg(target_arch = "wasm32")]
+        {
+            let file_id = file_id.get_untracked();
+            set_running.set(true);
+            leptos::task::spawn_local(async move {
+                match run_sql(file_id, query).await {
+                    Ok(rows) => set_result.set(Some(rows)),
+                    Err(message) => set_error.set(Some(message)),
+                }
+                set_running.set(false);
+            });
+        }
+    };
+    let close = move || {
+        set_open.set(false);
+        if let Some(invoker) = invoker_ref.get() {
+            request_animation_frame(move || {
+                _ = invoker.focus();
+            });
+        }
+    };
+
+    view! {
+        <div data-viewer-shortcuts="ignore" style="display: contents">
+            <aside
+                class=move || if open.get() { "sql-drawer" } else { "sql-drawer sql-drawer--closed" }
+                aria-label="SQL query"
+                on:keydown=move |event: leptos::ev::KeyboardEvent| {
+                    if event.key() == "Escape" {
+                        event.prevent_default();
+                        close();
+                    }
+                }
+            >
+                <header><h2>"SQL"</h2><button type="button" on:click=move |_| close()>"Close"</button></header>
+                <form class="sql-drawer__form" on:submit=move |event| {
+                    event.prevent_default();
+                    run();
+                }>
+                    <textarea
+                        class="sql-drawer__query"
+                        class:sql-drawer__query--invalid=move || error.get().is_some()
+                        aria-label="SQL query"
+                        placeholder=SQL_PLACEHOLDER
+                        spellcheck="false"
+                        rows="4"
+                        prop:value=move || query.get()
+                        on:input=move |event| set_query.set(event_target_value(&event))
+                        on:keydown=move |event: leptos::ev::KeyboardEvent| {
+                            if runs_query(&event.key(), event.ctrl_key(), event.meta_key()) {
+                                event.prevent_default();
+                                run();
+                            }
+                        }
+                    ></textarea>
+                    <button type="submit" disabled=move || running.get()>
+                        {move || if running.get() { "Running…" } else { "Run" }}
+                    </button>
+                </form>
+                {move || error.get().map(|message| view! {
+                    <p class="sql-drawer__error" role="alert">{message}</p>
+                })}
+                {move || result.get().map(|result| {
+                    let summary = sql_result_summary(&result);
+                    view! {
+                        <p class="sql-drawer__summary" role="status">{summary}</p>
+                        <div class="sql-results">
+                            <table>
+                                <thead>
+                                    <tr>
+                                        {result.columns.iter().map(|column| view! { <th>{column.clone()}</th> }).collect_view()}
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {result.rows.into_iter().map(|row| {
+                                        let line = row.line;
+                                        view! {
+                                            <tr
+                                                class:sql-results__row--linked=line.is_some()
+                                                title=line.map(|line| format!("Go to line {line}"))
+                                                on:click=move |_| {
+                                                    if line.is_some() {
+                                                        request_jump.set(line);
+                                                    }
+                                                }
+                                            >
+                                                {row.values.iter().map(|value| view! {
+                                                    <td class:sql-results__null=value.is_null()>{sql_cell_text(value)}</td>
+                                                }).collect_view()}
+                                            </tr>
+                                        }
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        </div>
+                    }
+                })}
+            </aside>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{runs_query, sql_cell_text, sql_result_summary};
+    use logmancer_core::{SqlResult, SqlRow};
+    use serde_json::json;
+
+    #[test]
+    fn cells_spell_out_null_and_summaries_count_rows() {
+        assert_eq!(sql_cell_text(&json!(null)), "NULL");
+        assert_eq!(sql_cell_text(&json!("GET /")), "GET /");
+        assert_eq!(sql_cell_text(&json!(1500)), "1500");
+        assert_eq!(sql_cell_text(&json!(0.25)), "0.25");
+
+        let row = SqlRow {
+            line: Some(2),
+            values: vec![json!(2)],
+        };
+        let mut result = SqlResult {
+            columns: vec!["log_line".to_string()],
+            rows: vec![row.clone()],
+            truncated: false,
+        };
+        assert_eq!(sql_result_summary(&result), "1 row");
+        result.rows.push(row);
+        assert_eq!(sql_result_summary(&result), "2 rows");
+        result.truncated = true;
+        assert_eq!(sql_result_summary(&result), "First 1000 rows");
+
+        assert!(runs_query("Enter", true, false));
+        assert!(runs_query("Enter", false, true));
+        assert!(!runs_query("Enter", false, false));
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(target_arch = "wasm32")]
+use crate::browser_api_client::fetch_visual_rules;
+use leptos::prelude::*;
+#[cfg(any(target_arch = "wasm32", test))]
+use logmancer_core::{validate_palette_color, PaletteColor};
+use logmancer_core::{VisualColor, VisualColorRole, VisualPalette, VisualTheme};
+
+/// CSS custom property a palette token resolves through.
+fn palette_variable(token: &str, role: VisualColorRole) -> String {
+    let role = match role {
+        VisualColorRole::Foreground => "foreground",
+        VisualColorRole::Background => "background",
+    };
+    format!("--visual-color-{token}-{role}")
+}
+
+/// CSS color of a palette token, read from the variables [`VisualPaletteStyle`] defines for the
+/// built-in and custom tokens alike; `default` and malformed tokens have none.
+pub(crate) fn visual_color_css(token: &VisualColor, role: VisualColorRole) -> Option<String> {
+    let name = token.0.as_str();
+    let well_formed = !name.is_empty()
+        && name.chars().all(|character| {
+            character.is_ascii_lowercase() || character.is_ascii_digit() || character == '-'
+        });
+    (well_formed && name != "default").then(|| format!("var({})", palette_variable(name, role)))
+}
+
+/// Palette of the custom colors that pass validation, in order.
+#[cfg(any(target_arch = "wasm32", test))]
+pub(crate) fn valid_palette(colors: &[PaletteColor]) -> VisualPalette {
+    let mut valid: Vec<PaletteColor> = Vec::with_capacity(colors.len());
+    for color in colors {
+        if validate_palette_color(color, &valid).is_ok() {
+            valid.push(color.clone());
+        }
+    }
+    VisualPalette::new(valid)
+}
+
+/// Light-theme variables of every palette token.
+pub(crate) fn palette_stylesheet(palette: &VisualPalette) -> String {
+    let mut declarations = Vec::new();
+    for token in palette.tokens() {
+        for role in [VisualColorRole::Foreground, VisualColorRole::Background] {
+            if let Some(color) =
+                palette.resolve(&VisualColor(token.to_string()), role, VisualTheme::Light)
+            {
+                declarations.push(format!(
+                    "{}: {}",
+                    palette_variable(token, role),
+                    color.to_hex()
+                ));
+            }
+        }
+    }
+    format!(":root {{ {}; }}", declarations.join("; "))
+}
+
+/// Stylesheet of the saved palette, reloaded whenever the visual rules are saved.
+#[component]
+pub fn VisualPaletteStyle(refresh_generation: ReadSignal<u64>) -> impl IntoView {
+    let (palette, set_palette) = signal(VisualPalette::default());
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (refresh_generation, set_palette);
+
+    #[cfg(target_arch = "wasm32")]
+    Effect::new(move |_| {
+        refresh_generation.track();
+        leptos::task::spawn_local(async move {
+            if let Ok(response) = fetch_visual_rules().await {
+                set_palette.set(valid_palette(&response.envelope.palette));
+            }
+        });
+    });
+
+    view! { <style>{move || palette.with(palette_stylesheet)}</style> }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logmancer_core::PaletteVariant;
+
+    fn teal(light_foreground: &str) -> PaletteColor {
+        PaletteColor {
+            name: "teal".to_string(),
+            light: PaletteVariant {
+                foreground: light_foreground.to_string(),
+                background: "#ccfbf1".to_string(),
+            },
+            dark: PaletteVariant {
+                foreground: "#2dd4bf".to_string(),
+                background: "#134e4a".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn tokens_read_their_palette_variable_and_malformed_tokens_have_none() {
+        assert_eq!(
+            visual_color_css(
+                &VisualColor("teal".to_string()),
+                VisualColorRole::Background
+            ),
+            Some("var(--visual-color-teal-background)".to_string())
+        );
+        for token in ["default", "", "hotpink; background: url(bad)", "Red"] {
+            assert_eq!(
+                visual_color_css(&VisualColor(token.to_string()), VisualColorRole::Foreground),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn canonical_visual_tokens_define_role_appropriate_shades() {
+        let stylesheet = palette_stylesheet(&VisualPalette::default());
+        let mappings = [
+            ("red", "#b91c1c", "#fee2e2"),
+            ("orange", "#c2410c", "#ffedd5"),
+            ("yellow", "#a16207", "#fef9c3"),
+            ("green", "#15803d", "#dcfce7"),
+            ("cyan", "#0e7490", "#cffafe"),
+            ("blue", "#1d4ed8", "#dbeafe"),
+            ("purple", "#7e22ce", "#f3e8ff"),
+            ("gray", "#4b5563", "#f3f4f6"),
+        ];
+
+        for (token, foreground, background) in mappings {
+            assert!(
+                stylesheet.contains(&format!("--visual-color-{token}-foreground: {foreground}"))
+            );
+            assert!(
+                stylesheet.contains(&format!("--visual-color-{token}-background: {background}"))
+            );
+        }
+    }
+
+    #[test]
+    fn custom_colors_define_their_light_variant_unless_they_fail_validation() {
+        let stylesheet = palette_stylesheet(&valid_palette(&[teal("#0f766e")]));
+        assert!(stylesheet.contains("--visual-color-teal-foreground: #0f766e"));
+        assert!(stylesheet.contains("--visual-color-teal-background: #ccfbf1"));
+
+        let unreadable = valid_palette(&[teal("#fde68a")]);
+        assert!(unreadable.custom().is_empty());
+        assert!(!palette_stylesheet(&unreadable).contains("teal"));
+    }
+}
@@ -0,0 +1,420 @@
+use leptos::prelude::*;
+use logmancer_core::{FieldOperator, FieldPredicate, ManagedVisualRule, VisualMatcher};
+
+const CONDITION_KINDS: [(&str, &str); 7] = [
+    ("text", "Contains text"),
+    ("regex", "Matches regex"),
+    ("lines", "Line numbers"),
+    ("field", "Field"),
+    ("all", "All of"),
+    ("any", "Any of"),
+    ("not", "Not"),
+];
+
+const FIELD_OPERATORS: [(FieldOperator, &str, &str); 7] = [
+    (FieldOperator::Eq, "eq", "="),
+    (FieldOperator::Ne, "ne", "≠"),
+    (FieldOperator::Lt, "lt", "<"),
+    (FieldOperator::Le, "le", "≤"),
+    (FieldOperator::Gt, "gt", ">"),
+    (FieldOperator::Ge, "ge", "≥"),
+    (FieldOperator::Contains, "contains", "contains"),
+];
+
+fn condition_kind(matcher: &VisualMatcher) -> &'static str {
+    match matcher {
+        VisualMatcher::Text(_) => "text",
+        VisualMatcher::Regex(_) => "regex",
+        VisualMatcher::Lines { .. } => "lines",
+        VisualMatcher::Field(_) => "field",
+        VisualMatcher::All(_) => "all",
+        VisualMatcher::Any(_) => "any",
+        VisualMatcher::Not(_) => "not",
+    }
+}
+
+/// Condition at `path`, a list of child positions from the root; `Not` has one child.
+fn condition_at<'a>(matcher: &'a VisualMatcher, path: &[usize]) -> Option<&'a VisualMatcher> {
+    let Some((index, rest)) = path.split_first() else {
+        return Some(matcher);
+    };
+    let child = match matcher {
+        VisualMatcher::All(children) | VisualMatcher::Any(children) => children.get(*index)?,
+        VisualMatcher::Not(child) if *index == 0 => child,
+        _ => return None,
+    };
+    condition_at(child, rest)
+}
+
+fn condition_at_mut<'a>(
+    matcher: &'a mut VisualMatcher,
+    path: &[usize],
+) -> Option<&'a mut VisualMatcher> {
+    let Some((index, rest)) = path.split_first() else {
+        return Some(matcher);
+    };
+    let child = match matcher {
+        VisualMatcher::All(children) | VisualMatcher::Any(children) => children.get_mut(*index)?,
+        VisualMatcher::Not(child) if *index == 0 => child,
+        _ => return None,
+    };
+    condition_at_mut(child, rest)
+}
+
+/// Kind and child count of a condition; the editor re-renders a node only when this changes,
+/// so typing into a pattern keeps the input focused.
+fn condition_shape(matcher: &VisualMatcher, path: &[usize]) -> (&'static str, usize) {
+    match condition_at(matcher, path) {
+        Some(condition) => {
+            let children = match condition {
+                VisualMatcher::All(children) | VisualMatcher::Any(children) => children.len(),
+                _ => 0,
+            };
+            (condition_kind(condition), children)
+        }
+        None => ("text", 0),
+    }
+}
+
+/// Converts a condition to another kind, keeping what carries over: the pattern between text
+/// and regex, the children between groups, and the current condition as the first child of a
+/// new group or as the negated condition.
+fn condition_of_kind(kind: &str, current: VisualMatcher) -> VisualMatcher {
+    match (kind, current) {
+        ("text", VisualMatcher::Text(pattern) | VisualMatcher::Regex(pattern)) => {
+            VisualMatcher::Text(pattern)
+        }
+        ("regex", VisualMatcher::Text(pattern) | VisualMatcher::Regex(pattern)) => {
+            VisualMatcher::Regex(pattern)
+        }
+        ("text", _) => VisualMatcher::Text(String::new()),
+        ("regex", _) => VisualMatcher::Regex(String::new()),
+        ("lines", current @ VisualMatcher::Lines { .. }) => current,
+        ("lines", _) => VisualMatcher::Lines {
+            first: 1,
+            last: None,
+        },
+        ("field", current @ VisualMatcher::Field(_)) => current,
+        ("field", _) => VisualMatcher::Field(FieldPredicate {
+            field: "level".to_string(),
+            operator: FieldOperator::Eq,
+            value: String::new(),
+        }),
+        ("all", VisualMatcher::All(children) | VisualMatcher::Any(children)) => {
+            VisualMatcher::All(children)
+        }
+        ("any", VisualMatcher::All(children) | VisualMatcher::Any(children)) => {
+            VisualMatcher::Any(children)
+        }
+        ("all", current) => VisualMatcher::All(vec![current]),
+        ("any", current) => VisualMatcher::Any(vec![current]),
+        ("not", current @ VisualMatcher::Not(_)) => current,
+        ("not", current) => VisualMatcher::Not(Box::new(current)),
+        (_, current) => current,
+    }
+}
+
+fn add_condition(matcher: &mut VisualMatcher, path: &[usize]) {
+    if let Some(VisualMatcher::All(children) | VisualMatcher::Any(children)) =
+        condition_at_mut(matcher, path)
+    {
+        children.push(VisualMatcher::Text(String::new()));
+    }
+}
+
+/// Removes the group member at `path`; the root and the child of a `Not` stay in place.
+fn remove_condition(matcher: &mut VisualMatcher, path: &[usize]) {
+    let Some((index, parent)) = path.split_last() else {
+        return;
+    };
+    if let Some(VisualMatcher::All(children) | VisualMatcher::Any(children)) =
+        condition_at_mut(matcher, parent)
+    {
+        if *index < children.len() {
+            children.remove(*index);
+        }
+    }
+}
+
+fn condition_pattern(matcher: &VisualMatcher) -> String {
+    match matcher {
+        VisualMatcher::Text(pattern) | VisualMatcher::Regex(pattern) => pattern.clone(),
+        _ => String::new(),
+    }
+}
+
+fn set_condition_pattern(matcher: &mut VisualMatcher, value: String) {
+    if let VisualMatcher::Text(pattern) | VisualMatcher::Regex(pattern) = matcher {
+        *pattern = value;
+    }
+}
+
+fn line_bound_text(matcher: &VisualMatcher, last: bool) -> String {
+    match matcher {
+        VisualMatcher::Lines { first, .. } if !last => first.to_string(),
+        VisualMatcher::Lines {
+            last: Some(line), ..
+        } => line.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Sets a line range bound; an unparsable first line becomes 0, which validation reports,
+/// and a blank last line leaves the range open.
+fn set_line_bound(matcher: &mut VisualMatcher, last_bound: bool, value: &str) {
+    if let VisualMatcher::Lines { first, last } = matcher {
+        let value = value.trim();
+        if last_bound {
+            *last = value.parse().ok();
+        } else {
+            *first = value.parse().unwrap_or(0);
+        }
+    }
+}
+
+fn field_predicate(matcher: &VisualMatcher) -> Option<&FieldPredicate> {
+    match matcher {
+        VisualMatcher::Field(predicate) => Some(predicate),
+        _ => None,
+    }
+}
+
+fn operator_selection(operator: FieldOperator) -> &'static str {
+    FIELD_OPERATORS
+        .iter()
+        .find(|(candidate, _, _)| *candidate == operator)
+        .map(|(_, value, _)| *value)
+        .unwrap_or("eq")
+}
+
+fn operator_from_selection(value: &str) -> FieldOperator {
+    FIELD_OPERATORS
+        .iter()
+        .find(|(_, candidate, _)| *candidate == value)
+        .map(|(operator, _, _)| *operator)
+        .unwrap_or(FieldOperator::Eq)
+}
+
+fn child_path(path: &[usize], index: usize) -> Vec<usize> {
+    let mut child = path.to_vec();
+    child.push(index);
+    child
+}
+
+/// Editor of the condition at `path` of the draft rule's matcher, nesting an editor per
+/// child condition.
+pub(super) fn condition_editor(
+    draft: ReadSignal<ManagedVisualRule>,
+    set_draft: WriteSignal<ManagedVisualRule>,
+    path: Vec<usize>,
+) -> AnyView {
+    let path = StoredValue::new(path);
+    let shape = Memo::new(move |_| {
+        draft.with(|rule| path.with_value(|path| condition_shape(&rule.matcher, path)))
+    });
+    let read = move |value: fn(&VisualMatcher) -> String| {
+        draft.with(|rule| {
+            path.with_value(|path| {
+                condition_at(&rule.matcher, path)
+                    .map(value)
+                    .unwrap_or_default()
+            })
+        })
+    };
+    let edit = move |change: &dyn Fn(&mut VisualMatcher)| {
+        set_draft.update(|rule| {
+            path.with_value(|path| {
+                if let Some(condition) = condition_at_mut(&mut rule.matcher, path) {
+                    change(condition);
+                }
+            })
+        })
+    };
+    let body = move || {
+        match shape.get() {
+        ("text" | "regex", _) => view! {
+            <input placeholder="Pattern" value=move || read(condition_pattern) on:input=move |event| {
+                let value = event_target_value(&event);
+                edit(&|condition| set_condition_pattern(condition, value.clone()));
+            } />
+        }
+        .into_any(),
+        ("lines", _) => view! {
+            <span class="visual-rule-condition__range">
+                <input type="number" min="1" aria-label="First line" value=move || read(|condition| line_bound_text(condition, false)) on:change=move |event| {
+                    let value = event_target_value(&event);
+                    edit(&|condition| set_line_bound(condition, false, &value));
+                } />
+                "to"
+                <input type="number" min="1" aria-label="Last line" placeholder="end" value=move || read(|condition| line_bound_text(condition, true)) on:change=move |event| {
+                    let value = event_target_value(&event);
+                    edit(&|condition| set_line_bound(condition, true, &value));
+                } />
+            </span>
+        }
+        .into_any(),
+        ("field", _) => view! {
+            <span class="visual-rule-condition__field">
+                <input aria-label="Field name" placeholder="level" value=move || read(|condition| field_predicate(condition).map(|predicate| predicate.field.clone()).unwrap_or_default()) on:input=move |event| {
+                    let value = event_target_value(&event);
+                    edit(&|condition| if let VisualMatcher::Field(predicate) = condition { predicate.field = value.clone(); });
+                } />
+                <select aria-label="Comparison" prop:value=move || read(|condition| field_predicate(condition).map(|predicate| operator_selection(predicate.operator)).unwrap_or("eq").to_string()) on:change=move |event| {
+                    let operator = operator_from_selection(&event_target_value(&event));
+                    edit(&|condition| if let VisualMatcher::Field(predicate) = condition { predicate.operator = operator; });
+                }>
+                    {FIELD_OPERATORS.into_iter().map(|(_, value, label)| view! {
+                        <option value=value>{label}</option>
+                    }).collect_view()}
+                </select>
+                <input aria-label="Field value" placeholder="WARN" value=move || read(|condition| field_predicate(condition).map(|predicate| predicate.value.clone()).unwrap_or_default()) on:input=move |event| {
+                    let value = event_target_value(&event);
+                    edit(&|condition| if let VisualMatcher::Field(predicate) = condition { predicate.value = value.clone(); });
+                } />
+            </span>
+        }
+        .into_any(),
+        ("not", _) => condition_editor(draft, set_draft, path.with_value(|path| child_path(path, 0))),
+        (_, count) => view! {
+            <ul>
+                {(0..count).map(|index| view! {
+                    <li>
+                        {condition_editor(draft, set_draft, path.with_value(|path| child_path(path, index)))}
+                        <button type="button" on:click=move |_| set_draft.update(|rule| {
+                            path.with_value(|path| remove_condition(&mut rule.matcher, &child_path(path, index)));
+                        })>"Remove"</button>
+                    </li>
+                }).collect_view()}
+            </ul>
+            <button type="button" on:click=move |_| set_draft.update(|rule| {
+                path.with_value(|path| add_condition(&mut rule.matcher, path));
+            })>"Add condition"</button>
+        }
+        .into_any(),
+    }
+    };
+    view! {
+        <div class="visual-rule-condition">
+            <select aria-label="Condition" prop:value=move || shape.get().0.to_string() on:change=move |event| {
+                let kind = event_target_value(&event);
+                edit(&|condition| *condition = condition_of_kind(&kind, condition.clone()));
+            }>
+                {CONDITION_KINDS.into_iter().map(|(value, label)| view! {
+                    <option value=value>{label}</option>
+                }).collect_view()}
+            </select>
+            {body}
+        </div>
+    }
+    .into_any()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(pattern: &str) -> VisualMatcher {
+        VisualMatcher::Text(pattern.to_string())
+    }
+
+    fn unretried_errors() -> VisualMatcher {
+        VisualMatcher::All(vec![
+            text("ERROR"),
+            VisualMatcher::Not(Box::new(text("retry"))),
+        ])
+    }
+
+    #[test]
+    fn pattern_edit_preserves_matcher_variant() {
+        let mut regex = VisualMatcher::Regex("old".into());
+        let mut plain = text("old");
+
+        set_condition_pattern(&mut regex, "new".into());
+        set_condition_pattern(&mut plain, "new".into());
+
+        assert_eq!(regex, VisualMatcher::Regex("new".into()));
+        assert_eq!(plain, text("new"));
+    }
+
+    #[test]
+    fn paths_address_group_members_and_negated_conditions() {
+        let matcher = unretried_errors();
+
+        assert_eq!(condition_at(&matcher, &[]), Some(&matcher));
+        assert_eq!(condition_at(&matcher, &[1, 0]), Some(&text("retry")));
+        assert_eq!(condition_at(&matcher, &[2]), None);
+        assert_eq!(condition_at(&matcher, &[0, 0]), None);
+        assert_eq!(condition_shape(&matcher, &[]), ("all", 2));
+        assert_eq!(condition_shape(&matcher, &[1]), ("not", 0));
+    }
+
+    #[test]
+    fn kind_changes_keep_patterns_children_and_wrap_the_current_condition() {
+        assert_eq!(
+            condition_of_kind("regex", text("ERROR")),
+            VisualMatcher::Regex("ERROR".to_string())
+        );
+        assert_eq!(
+            condition_of_kind("all", text("ERROR")),
+            VisualMatcher::All(vec![text("ERROR")])
+        );
+        assert_eq!(
+            condition_of_kind("any", unretried_errors()),
+            VisualMatcher::Any(vec![
+                text("ERROR"),
+                VisualMatcher::Not(Box::new(text("retry"))),
+            ])
+        );
+        assert_eq!(
+            condition_of_kind("not", text("ERROR")),
+            VisualMatcher::Not(Box::new(text("ERROR")))
+        );
+        assert_eq!(
+            condition_of_kind("lines", text("ERROR")),
+            VisualMatcher::Lines {
+                first: 1,
+                last: None
+            }
+        );
+        assert_eq!(condition_of_kind("text", unretried_errors()), text(""));
+    }
+
+    #[test]
+    fn nested_edits_add_remove_and_update_conditions_in_place() {
+        let mut matcher = unretried_errors();
+
+        add_condition(&mut matcher, &[]);
+        if let Some(condition) = condition_at_mut(&mut matcher, &[2]) {
+            *condition = condition_of_kind("lines", condition.clone());
+            set_line_bound(condition, true, "200");
+            set_line_bound(condition, false, "x");
+        }
+        set_condition_pattern(
+            condition_at_mut(&mut matcher, &[1, 0]).unwrap(),
+            "retried".into(),
+        );
+        remove_condition(&mut matcher, &[0]);
+        remove_condition(&mut matcher, &[0, 0]);
+
+        assert_eq!(
+            matcher,
+            VisualMatcher::All(vec![
+                VisualMatcher::Not(Box::new(text("retried"))),
+                VisualMatcher::Lines {
+                    first: 0,
+                    last: Some(200)
+                },
+            ])
+        );
+        assert_eq!(line_bound_text(&matcher, false), "");
+        assert_eq!(
+            line_bound_text(condition_at(&matcher, &[1]).unwrap(), true),
+            "200"
+        );
+        assert_eq!(
+            operator_from_selection(operator_selection(FieldOperator::Ge)),
+            FieldOperator::Ge
+        );
+        assert_eq!(operator_from_selection("bogus"), FieldOperator::Eq);
+    }
+}
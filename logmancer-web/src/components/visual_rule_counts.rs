@@ -0,0 +1,119 @@
+use crate::browser_api_client::fetch_visual_rule_counts;
+use crate::components::context::{LogFileContext, RuleNavigationContext, RuleNavigationRequest};
+use crate::components::density_map::rule_stripe_color;
+use leptos::prelude::*;
+use leptos::{component, view, IntoView};
+use logmancer_core::RuleCount;
+use std::time::Duration;
+
+fn rule_count_name(index: usize, count: &RuleCount) -> String {
+    count
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("Rule {}", index + 1))
+}
+
+/// Matched lines of a rule; the count may still grow while the file is being scanned.
+fn matched_lines_label(count: &RuleCount, is_ready: bool) -> String {
+    let plural = if count.matched_lines == 1 { "" } else { "s" };
+    let pending = if is_ready { "" } else { "+" };
+    format!("{}{pending} line{plural}", count.matched_lines)
+}
+
+/// Lines matched by each rule of the profile the open file renders with, with buttons that
+/// move the main pane between them.
+#[component]
+pub(super) fn VisualRuleCounts(open: ReadSignal<bool>, refresh: Signal<u64>) -> impl IntoView {
+    let LogFileContext { file_id, .. } = use_context().expect("LogFileContext not found");
+    let RuleNavigationContext {
+        request_navigate, ..
+    } = use_context().expect("RuleNavigationContext not found");
+    let (poll_tick, set_poll_tick) = signal(0_u64);
+
+    let counts = LocalResource::new(move || {
+        refresh.track();
+        poll_tick.track();
+        let file_id = file_id.get();
+        let open = open.get();
+        async move {
+            if !open || file_id.is_empty() {
+                return Ok(None);
+            }
+            fetch_visual_rule_counts(file_id).await.map(Some)
+        }
+    });
+    let navigate = move |rule: usize, forward: bool| {
+        request_navigate.set(Some(RuleNavigationRequest { rule, forward }));
+    };
+
+    view! {
+        <section class="visual-rule-counts" aria-label="Rule matches in this file">
+            <h3>"Matches in this file"</h3>
+            <Transition>
+                { move || Suspend::new(async move {
+                    counts.await.map(|status| status.map(|status| {
+                        if !status.is_ready {
+                            set_timeout(
+                                move || set_poll_tick.update(|tick| *tick = tick.wrapping_add(1)),
+                                Duration::from_secs(1),
+                            );
+                        }
+                        if status.rules.is_empty() {
+                            return view! { <p>"No enabled rules in this profile."</p> }.into_any();
+                        }
+                        let is_ready = status.is_ready;
+                        view! {
+                            <ul>
+                                {status.rules.into_iter().enumerate().map(|(index, count)| view! {
+                                    <li>
+                                        <span
+                                            class="visual-rule-counts__swatch"
+                                            style=rule_stripe_color(&count.style).map(|color| format!("background: {color}"))
+                                        ></span>
+                                        <span class="visual-rule-counts__name">{rule_count_name(index, &count)}</span>
+                                        <span class="visual-rule-counts__lines">{matched_lines_label(&count, is_ready)}</span>
+                                        <button
+                                            type="button"
+                                            aria-label="Previous line matched by this rule"
+                                            disabled=count.matched_lines == 0
+                                            on:click=move |_| navigate(index, false)
+                                        >"‹"</button>
+                                        <button
+                                            type="button"
+                                            aria-label="Next line matched by this rule"
+                                            disabled=count.matched_lines == 0
+                                            on:click=move |_| navigate(index, true)
+                                        >"›"</button>
+                                    </li>
+                                }).collect_view()}
+                            </ul>
+                        }.into_any()
+                    }))
+                })}
+            </Transition>
+        </section>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logmancer_core::LineStyleIntent;
+
+    fn count(name: Option<&str>, matched_lines: usize) -> RuleCount {
+        RuleCount {
+            name: name.map(str::to_string),
+            style: LineStyleIntent::default(),
+            matched_lines,
+            current_line_index: None,
+        }
+    }
+
+    #[test]
+    fn rule_counts_fall_back_to_rule_numbers_and_mark_growing_counts() {
+        assert_eq!(rule_count_name(0, &count(Some("OOM"), 1)), "OOM");
+        assert_eq!(rule_count_name(2, &count(None, 1)), "Rule 3");
+        assert_eq!(matched_lines_label(&count(None, 1), true), "1 line");
+        assert_eq!(matched_lines_label(&count(None, 40), false), "40+ lines");
+    }
+}
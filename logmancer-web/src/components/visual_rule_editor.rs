@@ -1,5 +1,9 @@
+use super::visual_rule_conditions::condition_editor;
 use leptos::prelude::*;
-use logmancer_core::{LineStyleIntent, ManagedVisualRule, VisualColor, VisualMatcher};
+use logmancer_core::{
+    GutterIcon, GutterMarker, LineStyleIntent, ManagedVisualRule, TextAttributes, VisualColor,
+    VisualMatcher, VisualRuleScope,
+};
 
 const VISUAL_COLOR_PALETTE: [(&str, &str); 9] = [
     ("default", "Default"),
@@ -13,6 +17,38 @@ const VISUAL_COLOR_PALETTE: [(&str, &str); 9] = [
     ("gray", "Gray"),
 ];
 
+const VISUAL_RULE_SCOPES: [(&str, &str); 3] = [
+    ("line", "Whole line"),
+    ("match", "Matched text"),
+    ("groups", "Capture groups"),
+];
+
+const GUTTER_ICONS: [(&str, &str); 6] = [
+    ("none", "None"),
+    ("dot", "Dot"),
+    ("flag", "Flag"),
+    ("star", "Star"),
+    ("warning", "Warning"),
+    ("error", "Error"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TextAttribute {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Dim,
+}
+
+const TEXT_ATTRIBUTES: [(TextAttribute, &str); 5] = [
+    (TextAttribute::Bold, "Bold"),
+    (TextAttribute::Italic, "Italic"),
+    (TextAttribute::Underline, "Underline"),
+    (TextAttribute::Strikethrough, "Strikethrough"),
+    (TextAttribute::Dim, "Dim"),
+];
+
 #[derive(Clone, Copy)]
 enum VisualColorRole {
     Foreground,
@@ -25,37 +61,118 @@ pub fn new_rule() -> ManagedVisualRule {
         enabled: true,
         matcher: VisualMatcher::Text(String::new()),
         case_sensitive: false,
+        scope: VisualRuleScope::Line,
         style: LineStyleIntent {
             foreground: None,
             background: None,
+            ..LineStyleIntent::default()
         },
     }
 }
 
-fn updated_matcher_pattern(matcher: VisualMatcher, value: String) -> VisualMatcher {
-    match matcher {
-        VisualMatcher::Text(_) => VisualMatcher::Text(value),
-        VisualMatcher::Regex(_) => VisualMatcher::Regex(value),
+fn scope_selection(scope: &VisualRuleScope) -> &'static str {
+    match scope {
+        VisualRuleScope::Line => "line",
+        VisualRuleScope::Match => "match",
+        VisualRuleScope::Groups(_) => "groups",
+    }
+}
+
+fn scope_groups_text(scope: &VisualRuleScope) -> String {
+    match scope {
+        VisualRuleScope::Groups(groups) => groups
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        _ => String::new(),
+    }
+}
+
+/// Builds the scope for a selection; unparsable group numbers are dropped so that
+/// validation reports an empty group list instead of silently styling the line.
+fn scope_from_selection(value: &str, groups: &str) -> VisualRuleScope {
+    match value {
+        "match" => VisualRuleScope::Match,
+        "groups" => VisualRuleScope::Groups(
+            groups
+                .split(',')
+                .filter_map(|group| group.trim().parse().ok())
+                .collect(),
+        ),
+        _ => VisualRuleScope::Line,
+    }
+}
+
+fn attribute_flag(attributes: &mut TextAttributes, attribute: TextAttribute) -> &mut bool {
+    match attribute {
+        TextAttribute::Bold => &mut attributes.bold,
+        TextAttribute::Italic => &mut attributes.italic,
+        TextAttribute::Underline => &mut attributes.underline,
+        TextAttribute::Strikethrough => &mut attributes.strikethrough,
+        TextAttribute::Dim => &mut attributes.dim,
+    }
+}
+
+fn attribute_enabled(mut attributes: TextAttributes, attribute: TextAttribute) -> bool {
+    *attribute_flag(&mut attributes, attribute)
+}
+
+fn gutter_selection(marker: Option<&GutterMarker>) -> &'static str {
+    match marker.map(|marker| marker.icon) {
+        None => "none",
+        Some(GutterIcon::Dot) => "dot",
+        Some(GutterIcon::Flag) => "flag",
+        Some(GutterIcon::Star) => "star",
+        Some(GutterIcon::Warning) => "warning",
+        Some(GutterIcon::Error) => "error",
     }
 }
 
-fn color_selection(color: Option<&VisualColor>) -> &str {
+/// Sets the gutter icon from a selection, keeping the marker color of an existing marker.
+fn update_rule_gutter(rule: &mut ManagedVisualRule, value: &str) {
+    let icon = match value {
+        "dot" => GutterIcon::Dot,
+        "flag" => GutterIcon::Flag,
+        "star" => GutterIcon::Star,
+        "warning" => GutterIcon::Warning,
+        "error" => GutterIcon::Error,
+        _ => {
+            rule.style.gutter = None;
+            return;
+        }
+    };
+    let color = rule.style.gutter.take().and_then(|marker| marker.color);
+    rule.style.gutter = Some(GutterMarker { icon, color });
+}
+
+/// Returns true when `value` is a built-in option or one of the palette's custom colors.
+fn is_color_option(value: &str, custom_colors: &[String]) -> bool {
+    VISUAL_COLOR_PALETTE
+        .iter()
+        .any(|(token, _)| *token == value)
+        || custom_colors.iter().any(|name| name == value)
+}
+
+fn color_selection<'a>(color: Option<&'a VisualColor>, custom_colors: &[String]) -> &'a str {
     color
         .map(|color| color.0.as_str())
-        .filter(|token| VISUAL_COLOR_PALETTE.iter().any(|(value, _)| value == token))
+        .filter(|token| is_color_option(token, custom_colors))
         .unwrap_or("default")
 }
 
-fn visual_color_from_selection(value: &str) -> Option<VisualColor> {
-    (value != "default"
-        && VISUAL_COLOR_PALETTE
-            .iter()
-            .any(|(token, _)| *token == value))
-    .then(|| VisualColor(value.to_string()))
+fn visual_color_from_selection(value: &str, custom_colors: &[String]) -> Option<VisualColor> {
+    (value != "default" && is_color_option(value, custom_colors))
+        .then(|| VisualColor(value.to_string()))
 }
 
-fn update_rule_color(rule: &mut ManagedVisualRule, role: VisualColorRole, value: &str) {
-    let color = visual_color_from_selection(value);
+fn update_rule_color(
+    rule: &mut ManagedVisualRule,
+    role: VisualColorRole,
+    value: &str,
+    custom_colors: &[String],
+) {
+    let color = visual_color_from_selection(value, custom_colors);
     match role {
         VisualColorRole::Foreground => rule.style.foreground = color,
         VisualColorRole::Background => rule.style.background = color,
@@ -65,10 +182,26 @@ fn update_rule_color(rule: &mut ManagedVisualRule, role: VisualColorRole, value:
 #[component]
 pub fn VisualRuleEditor(
     rule: ManagedVisualRule,
+    /// Names of the palette's custom colors, offered after the built-in ones.
+    custom_colors: Vec<String>,
     save: Callback<ManagedVisualRule>,
     close: Callback<()>,
 ) -> impl IntoView {
     let (draft, set_draft) = signal(rule);
+    let custom_colors = StoredValue::new(custom_colors);
+    let color_options = move || {
+        let builtin = VISUAL_COLOR_PALETTE
+            .into_iter()
+            .map(|(value, label)| (value.to_string(), label.to_string()));
+        let custom = custom_colors
+            .get_value()
+            .into_iter()
+            .map(|name| (name.clone(), name));
+        builtin
+            .chain(custom)
+            .map(|(value, label)| view! { <option value=value>{label}</option> })
+            .collect_view()
+    };
     let save_draft = move |_| save.run(draft.get());
     view! {
         <div class="visual-rules-modal-backdrop" role="presentation" on:keydown=move |event| {
@@ -81,29 +214,66 @@ pub fn VisualRuleEditor(
                         set_draft.update(|rule| rule.name = (!value.trim().is_empty()).then_some(value));
                     } />
                 </label>
-                <label>"Pattern"
-                    <input value=move || match draft.get().matcher { VisualMatcher::Text(value) | VisualMatcher::Regex(value) => value } on:input=move |event| {
+                <fieldset class="visual-rule-conditions">
+                    <legend>"Condition"</legend>
+                    {condition_editor(draft, set_draft, Vec::new())}
+                </fieldset>
+                <label>"Scope"
+                    <select prop:value=move || scope_selection(&draft.get().scope).to_string() on:change=move |event| {
                         let value = event_target_value(&event);
-                        set_draft.update(|rule| rule.matcher = updated_matcher_pattern(rule.matcher.clone(), value));
-                    } />
+                        set_draft.update(|rule| {
+                            let groups = match &rule.scope {
+                                VisualRuleScope::Groups(_) => scope_groups_text(&rule.scope),
+                                _ => "1".to_string(),
+                            };
+                            rule.scope = scope_from_selection(&value, &groups);
+                        });
+                    }>
+                        {VISUAL_RULE_SCOPES.into_iter().map(|(value, label)| view! {
+                            <option value=value>{label}</option>
+                        }).collect_view()}
+                    </select>
                 </label>
+                <Show when=move || matches!(draft.get().scope, VisualRuleScope::Groups(_))>
+                    <label>"Capture groups"
+                        <input placeholder="1,2" value=move || scope_groups_text(&draft.get().scope) on:change=move |event| {
+                            let value = event_target_value(&event);
+                            set_draft.update(|rule| rule.scope = scope_from_selection("groups", &value));
+                        } />
+                    </label>
+                </Show>
                 <label><input type="checkbox" checked=move || draft.get().enabled on:change=move |event| set_draft.update(|rule| rule.enabled = event_target_checked(&event)) />"Enabled"</label>
                 <label>"Foreground color"
-                    <select prop:value=move || color_selection(draft.get().style.foreground.as_ref()).to_string() on:change=move |event| {
+                    <select prop:value=move || custom_colors.with_value(|custom| color_selection(draft.get().style.foreground.as_ref(), custom).to_string()) on:change=move |event| {
                         let value = event_target_value(&event);
-                        set_draft.update(|rule| update_rule_color(rule, VisualColorRole::Foreground, &value));
+                        custom_colors.with_value(|custom| set_draft.update(|rule| update_rule_color(rule, VisualColorRole::Foreground, &value, custom)));
                     }>
-                        {VISUAL_COLOR_PALETTE.into_iter().map(|(value, label)| view! {
-                            <option value=value>{label}</option>
-                        }).collect_view()}
+                        {color_options()}
                     </select>
                 </label>
                 <label>"Background color"
-                    <select prop:value=move || color_selection(draft.get().style.background.as_ref()).to_string() on:change=move |event| {
+                    <select prop:value=move || custom_colors.with_value(|custom| color_selection(draft.get().style.background.as_ref(), custom).to_string()) on:change=move |event| {
+                        let value = event_target_value(&event);
+                        custom_colors.with_value(|custom| set_draft.update(|rule| update_rule_color(rule, VisualColorRole::Background, &value, custom)));
+                    }>
+                        {color_options()}
+                    </select>
+                </label>
+                <fieldset>
+                    <legend>"Text style"</legend>
+                    {TEXT_ATTRIBUTES.into_iter().map(|(attribute, label)| view! {
+                        <label><input type="checkbox" checked=move || attribute_enabled(draft.get().style.attributes, attribute) on:change=move |event| {
+                            let enabled = event_target_checked(&event);
+                            set_draft.update(|rule| *attribute_flag(&mut rule.style.attributes, attribute) = enabled);
+                        } />{label}</label>
+                    }).collect_view()}
+                </fieldset>
+                <label>"Gutter marker"
+                    <select prop:value=move || gutter_selection(draft.get().style.gutter.as_ref()).to_string() on:change=move |event| {
                         let value = event_target_value(&event);
-                        set_draft.update(|rule| update_rule_color(rule, VisualColorRole::Background, &value));
+                        set_draft.update(|rule| update_rule_gutter(rule, &value));
                     }>
-                        {VISUAL_COLOR_PALETTE.into_iter().map(|(value, label)| view! {
+                        {GUTTER_ICONS.into_iter().map(|(value, label)| view! {
                             <option value=value>{label}</option>
                         }).collect_view()}
                     </select>
@@ -124,36 +294,43 @@ mod tests {
             enabled: false,
             matcher: VisualMatcher::Regex("^ERROR".to_string()),
             case_sensitive: true,
+            scope: VisualRuleScope::Line,
             style: LineStyleIntent {
                 foreground: Some(VisualColor("purple".to_string())),
                 background: Some(VisualColor("default".to_string())),
+                ..LineStyleIntent::default()
             },
         }
     }
 
     #[test]
-    fn pattern_edit_preserves_matcher_variant() {
+    fn loaded_colors_convert_to_palette_selections_and_default_means_no_color() {
+        let rule = regex_rule();
+
+        assert_eq!(
+            color_selection(rule.style.foreground.as_ref(), &[]),
+            "purple"
+        );
         assert_eq!(
-            updated_matcher_pattern(VisualMatcher::Regex("old".into()), "new".into()),
-            VisualMatcher::Regex("new".into())
+            color_selection(rule.style.background.as_ref(), &[]),
+            "default"
         );
+        assert_eq!(visual_color_from_selection("default", &[]), None);
         assert_eq!(
-            updated_matcher_pattern(VisualMatcher::Text("old".into()), "new".into()),
-            VisualMatcher::Text("new".into())
+            visual_color_from_selection("cyan", &[]),
+            Some(VisualColor("cyan".to_string()))
         );
     }
 
     #[test]
-    fn loaded_colors_convert_to_palette_selections_and_default_means_no_color() {
-        let rule = regex_rule();
+    fn custom_palette_colors_are_selectable_only_while_the_palette_defines_them() {
+        let teal = VisualColor("teal".to_string());
+        let custom = ["teal".to_string()];
 
-        assert_eq!(color_selection(rule.style.foreground.as_ref()), "purple");
-        assert_eq!(color_selection(rule.style.background.as_ref()), "default");
-        assert_eq!(visual_color_from_selection("default"), None);
-        assert_eq!(
-            visual_color_from_selection("cyan"),
-            Some(VisualColor("cyan".to_string()))
-        );
+        assert_eq!(color_selection(Some(&teal), &custom), "teal");
+        assert_eq!(color_selection(Some(&teal), &[]), "default");
+        assert_eq!(visual_color_from_selection("teal", &custom), Some(teal));
+        assert_eq!(visual_color_from_selection("teal", &[]), None);
     }
 
     #[test]
@@ -161,8 +338,8 @@ mod tests {
         let original = regex_rule();
         let mut edited = original.clone();
 
-        update_rule_color(&mut edited, VisualColorRole::Foreground, "orange");
-        update_rule_color(&mut edited, VisualColorRole::Background, "blue");
+        update_rule_color(&mut edited, VisualColorRole::Foreground, "orange", &[]);
+        update_rule_color(&mut edited, VisualColorRole::Background, "blue", &[]);
 
         assert_eq!(edited.name, original.name);
         assert_eq!(edited.enabled, original.enabled);
@@ -177,4 +354,48 @@ mod tests {
             Some(VisualColor("blue".to_string()))
         );
     }
+
+    #[test]
+    fn scope_selections_round_trip_and_parse_group_lists() {
+        let groups = scope_from_selection("groups", " 1, 3,x");
+
+        assert_eq!(groups, VisualRuleScope::Groups(vec![1, 3]));
+        assert_eq!(scope_selection(&groups), "groups");
+        assert_eq!(scope_groups_text(&groups), "1,3");
+        assert_eq!(scope_from_selection("match", ""), VisualRuleScope::Match);
+        assert_eq!(scope_from_selection("line", "2"), VisualRuleScope::Line);
+        assert_eq!(scope_groups_text(&VisualRuleScope::Match), "");
+    }
+
+    #[test]
+    fn attribute_and_gutter_edits_keep_the_rest_of_the_style() {
+        let mut rule = regex_rule();
+
+        *attribute_flag(&mut rule.style.attributes, TextAttribute::Italic) = true;
+        update_rule_gutter(&mut rule, "star");
+        rule.style.gutter.as_mut().unwrap().color = Some(VisualColor("blue".to_string()));
+        update_rule_gutter(&mut rule, "warning");
+
+        assert!(attribute_enabled(
+            rule.style.attributes,
+            TextAttribute::Italic
+        ));
+        assert!(!attribute_enabled(
+            rule.style.attributes,
+            TextAttribute::Bold
+        ));
+        assert_eq!(gutter_selection(rule.style.gutter.as_ref()), "warning");
+        assert_eq!(
+            rule.style
+                .gutter
+                .as_ref()
+                .and_then(|marker| marker.color.clone()),
+            Some(VisualColor("blue".to_string()))
+        );
+        assert_eq!(rule.style.foreground, regex_rule().style.foreground);
+
+        update_rule_gutter(&mut rule, "none");
+        assert_eq!(rule.style.gutter, None);
+        assert_eq!(gutter_selection(None), "none");
+    }
 }
@@ -1,17 +1,141 @@
+use crate::api::commons::VisualRulesExportResponse;
 #[cfg(target_arch = "wasm32")]
-use crate::browser_api_client::{fetch_visual_rules, retry_visual_rules, save_visual_rules};
+use crate::browser_api_client::{
+    export_visual_rules_file, fetch_visual_profile, fetch_visual_rules, import_visual_rules_file,
+    retry_visual_rules, save_visual_rules, set_visual_profile,
+};
+use crate::components::context::LogFileContext;
+use crate::components::visual_rule_counts::VisualRuleCounts;
 use crate::components::visual_rule_editor::{new_rule, VisualRuleEditor};
 #[cfg(target_arch = "wasm32")]
 use crate::visual_rules_state::operation_status;
 use crate::visual_rules_state::VisualRulesEditorState;
 use leptos::html;
 use leptos::prelude::*;
-use logmancer_core::{ManagedVisualRule, VisualRulesEnvelope};
+use logmancer_core::{
+    validate_palette_color, ManagedVisualRule, PaletteColor, ValidationDiagnostic,
+    ValidationSeverity, VisualProfileStatus, VisualRulesEnvelope, VisualRulesFormat,
+};
+
+const TRANSFER_FORMATS: [(VisualRulesFormat, &str, &str); 3] = [
+    (VisualRulesFormat::Klogg, "klogg", "klogg / glogg"),
+    (VisualRulesFormat::Lnav, "lnav", "lnav"),
+    (VisualRulesFormat::Less, "less", "less"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PaletteField {
+    Name,
+    LightForeground,
+    LightBackground,
+    DarkForeground,
+    DarkBackground,
+}
+
+const PALETTE_SHADES: [(PaletteField, &str); 4] = [
+    (PaletteField::LightForeground, "Light text"),
+    (PaletteField::LightBackground, "Light background"),
+    (PaletteField::DarkForeground, "Dark text"),
+    (PaletteField::DarkBackground, "Dark background"),
+];
+
+fn palette_field(color: &PaletteColor, field: PaletteField) -> &str {
+    match field {
+        PaletteField::Name => &color.name,
+        PaletteField::LightForeground => &color.light.foreground,
+        PaletteField::LightBackground => &color.light.background,
+        PaletteField::DarkForeground => &color.dark.foreground,
+        PaletteField::DarkBackground => &color.dark.background,
+    }
+}
+
+fn set_palette_field(color: &mut PaletteColor, field: PaletteField, value: String) {
+    let target = match field {
+        PaletteField::Name => &mut color.name,
+        PaletteField::LightForeground => &mut color.light.foreground,
+        PaletteField::LightBackground => &mut color.light.background,
+        PaletteField::DarkForeground => &mut color.dark.foreground,
+        PaletteField::DarkBackground => &mut color.dark.background,
+    };
+    *target = value;
+}
+
+/// Why the color at `index` would be rejected on save, checked against the colors before it.
+fn palette_color_problem(palette: &[PaletteColor], index: usize) -> Option<String> {
+    validate_palette_color(palette.get(index)?, &palette[..index]).err()
+}
 
 fn drawer_should_handle_escape(key: &str) -> bool {
     key == "Escape"
 }
 
+/// Select value of the per-file switcher; the empty value follows auto-selection.
+#[cfg(any(target_arch = "wasm32", test))]
+fn pinned_profile(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.is_empty())
+}
+
+fn automatic_profile_label(status: &VisualProfileStatus) -> String {
+    match (&status.pinned, &status.auto_selected) {
+        (None, Some(name)) => format!("Automatic ({name})"),
+        _ => "Automatic".to_string(),
+    }
+}
+
+fn transfer_format(value: &str) -> VisualRulesFormat {
+    TRANSFER_FORMATS
+        .iter()
+        .find(|(_, key, _)| *key == value)
+        .map(|(format, _, _)| *format)
+        .unwrap_or(VisualRulesFormat::Klogg)
+}
+
+fn transfer_format_key(format: VisualRulesFormat) -> &'static str {
+    TRANSFER_FORMATS
+        .iter()
+        .find(|(candidate, _, _)| *candidate == format)
+        .map(|(_, key, _)| *key)
+        .unwrap_or("klogg")
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+fn imported_status(envelope: &VisualRulesEnvelope) -> String {
+    let rules = envelope.rules.len()
+        + envelope
+            .profiles
+            .iter()
+            .map(|profile| profile.rules.len())
+            .sum::<usize>();
+    let plural = if rules == 1 { "" } else { "s" };
+    match envelope.profiles.len() {
+        0 => format!("Imported {rules} rule{plural}; review and save to apply."),
+        profiles => format!(
+            "Imported {rules} rule{plural} in {} profiles; review and save to apply.",
+            profiles + 1
+        ),
+    }
+}
+
+fn diagnostic_text(diagnostic: &ValidationDiagnostic) -> String {
+    match diagnostic.severity {
+        ValidationSeverity::Warning => format!("Warning: {}", diagnostic.message),
+        ValidationSeverity::Error => format!("Error: {}", diagnostic.message),
+    }
+}
+
+/// `data:` URL that downloads `content` as plain text.
+fn download_href(content: &str) -> String {
+    let mut href = String::from("data:text/plain;charset=utf-8,");
+    for byte in content.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            href.push(char::from(byte));
+        } else {
+            href.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    href
+}
+
 #[cfg(any(target_arch = "wasm32", test))]
 fn notify_after_accepted_save(accepted: bool, notify: impl FnOnce()) {
     if accepted {
@@ -24,15 +148,58 @@ pub fn VisualRules(
     open: ReadSignal<bool>,
     set_open: WriteSignal<bool>,
     invoker_ref: NodeRef<html::Button>,
+    refresh_generation: ReadSignal<u64>,
     on_saved: Callback<()>,
 ) -> impl IntoView {
     #[cfg(not(target_arch = "wasm32"))]
-    let _ = on_saved;
+    let _ = (on_saved, refresh_generation);
     let (editor, set_editor) = signal(None::<usize>);
     let (state, set_state) = signal(VisualRulesEditorState::new(
         0,
         VisualRulesEnvelope::new(Vec::new()),
     ));
+    let (file_profile, set_file_profile) = signal(None::<VisualProfileStatus>);
+    let file_id = use_context::<LogFileContext>().map(|context| context.file_id);
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = (file_id, set_file_profile);
+    let (transfer, set_transfer) = signal(VisualRulesFormat::Klogg);
+    let (transfer_diagnostics, set_transfer_diagnostics) =
+        signal(Vec::<ValidationDiagnostic>::new());
+    let (export, set_export) = signal(None::<VisualRulesExportResponse>);
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = set_transfer_diagnostics;
+
+    #[cfg(target_arch = "wasm32")]
+    Effect::new(move |_| {
+        let Some(file_id) = file_id.map(|file_id| file_id.get()) else {
+            return;
+        };
+        if open.get() && !file_id.is_empty() {
+            leptos::task::spawn_local(async move {
+                match fetch_visual_profile(file_id).await {
+                    Ok(status) => set_file_profile.set(Some(status)),
+                    Err(error) => set_state.update(|state| state.save_failed(error)),
+                }
+            });
+        }
+    });
+    let switch_file_profile = move |_value: String| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let Some(file_id) = file_id.map(|file_id| file_id.get_untracked()) else {
+                return;
+            };
+            leptos::task::spawn_local(async move {
+                match set_visual_profile(file_id, pinned_profile(&_value)).await {
+                    Ok(status) => {
+                        set_file_profile.set(Some(status));
+                        on_saved.run(());
+                    }
+                    Err(error) => set_state.update(|state| state.save_failed(error)),
+                }
+            });
+        }
+    };
 
     #[cfg(target_arch = "wasm32")]
     Effect::new(move |_| {
@@ -53,6 +220,25 @@ pub fn VisualRules(
         }
     });
 
+    #[cfg(target_arch = "wasm32")]
+    Effect::new(move |previous: Option<()>| {
+        refresh_generation.track();
+        if previous.is_none() {
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            if let Ok(response) = fetch_visual_rules().await {
+                set_state.update(|state| {
+                    let message = operation_status(
+                        "Visual rules changed outside the editor.",
+                        &response.diagnostics,
+                    );
+                    state.saved_elsewhere(response.revision, response.envelope, message);
+                });
+            }
+        });
+    });
+
     let discard = move |_| set_state.update(VisualRulesEditorState::discard);
     let persist = move |_replace: bool| {
         #[cfg(target_arch = "wasm32")]
@@ -115,14 +301,79 @@ pub fn VisualRules(
             });
         }
     };
+    let import_file = move |_event: leptos::ev::Event| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use leptos::wasm_bindgen::JsCast;
+
+            let Some(input) = _event
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::HtmlInputElement>().ok())
+            else {
+                return;
+            };
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            input.set_value("");
+            let format = transfer.get_untracked();
+            leptos::task::spawn_local(async move {
+                let Some(content) = wasm_bindgen_futures::JsFuture::from(file.text())
+                    .await
+                    .ok()
+                    .and_then(|text| text.as_string())
+                else {
+                    set_state
+                        .update(|state| state.save_failed("Could not read the selected file."));
+                    return;
+                };
+                match import_visual_rules_file(format, content).await {
+                    Ok(response) => {
+                        set_transfer_diagnostics.set(response.diagnostics);
+                        set_export.set(None);
+                        set_editor.set(None);
+                        set_state.update(|state| {
+                            let message = imported_status(&response.envelope);
+                            state.import_draft(response.envelope, message);
+                        });
+                    }
+                    Err(error) => set_state.update(|state| state.save_failed(error)),
+                }
+            });
+        }
+    };
+    let export_rules = move |_| {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let format = transfer.get_untracked();
+            let envelope = state.get_untracked().envelope().clone();
+            leptos::task::spawn_local(async move {
+                match export_visual_rules_file(format, envelope).await {
+                    Ok(response) => {
+                        set_transfer_diagnostics.set(response.diagnostics.clone());
+                        set_export.set(Some(response));
+                    }
+                    Err(error) => set_state.update(|state| state.save_failed(error)),
+                }
+            });
+        }
+    };
     let apply_editor = move |rule: ManagedVisualRule| {
         set_state.update(|state| match editor.get_untracked() {
-            Some(index) if index < state.envelope().rules.len() => state.replace_rule(index, rule),
+            Some(index) if index < state.rules().len() => state.replace_rule(index, rule),
             _ => state.add(rule),
         });
         set_state.update(VisualRulesEditorState::close_editor_with_escape);
         set_editor.set(None);
     };
+    let update_palette = move |index: usize, field: PaletteField, value: String| {
+        set_state.update(|state| {
+            if let Some(mut color) = state.envelope().palette.get(index).cloned() {
+                set_palette_field(&mut color, field, value);
+                state.replace_palette_color(index, color);
+            }
+        });
+    };
     view! {
         <div data-viewer-shortcuts="ignore" style="display: contents">
             <aside class=move || if open.get() { "visual-rules-drawer" } else { "visual-rules-drawer visual-rules-drawer--closed" } aria-label="Visual rules" on:keydown=move |event: leptos::ev::KeyboardEvent| {
@@ -153,13 +404,76 @@ pub fn VisualRules(
                     }
                 }>"Close"</button></header>
                 <p role="status">{move || state.get().status().to_string()}</p>
+                {move || file_profile.get().map(|status| {
+                    let automatic = automatic_profile_label(&status);
+                    let pinned = status.pinned.clone().unwrap_or_default();
+                    view! {
+                        <label>"This file uses"
+                            <select prop:value=pinned on:change=move |event| switch_file_profile(event_target_value(&event))>
+                                <option value="">{automatic}</option>
+                                {status.profiles.iter().map(|name| view! {
+                                    <option value=name.clone()>{name.clone()}</option>
+                                }).collect_view()}
+                            </select>
+                        </label>
+                    }
+                })}
+                {file_id.map(|_| {
+                    // Saves and profile switches change the rules the counts follow.
+                    let refresh = Signal::derive(move || {
+                        file_profile.track();
+                        state.with(VisualRulesEditorState::revision)
+                    });
+                    view! { <VisualRuleCounts open refresh /> }
+                })}
+                <fieldset class="visual-rule-profiles">
+                    <legend>"Editing profile"</legend>
+                    <select prop:value=move || state.get().profile_index().map(|index| index.to_string()).unwrap_or_default() on:change=move |event| {
+                        let index = event_target_value(&event).parse::<usize>().ok();
+                        set_state.update(|state| state.select_profile(index));
+                        set_editor.set(None);
+                    }>
+                        <option value="">{logmancer_core::DEFAULT_VISUAL_PROFILE}</option>
+                        {move || state.get().envelope().profiles.iter().enumerate().map(|(index, profile)| view! {
+                            <option value=index.to_string()>{profile.name.clone()}</option>
+                        }).collect_view()}
+                    </select>
+                    <button type="button" on:click=move |_| {
+                        let count = state.get_untracked().envelope().profiles.len();
+                        set_state.update(|state| state.add_profile(format!("Profile {}", count + 1)));
+                        set_editor.set(None);
+                    }>"New profile"</button>
+                    {move || {
+                        let current = state.get();
+                        let profile = current.profile_index().and_then(|index| current.envelope().profiles.get(index).cloned())?;
+                        let auto_select = profile.auto_select.unwrap_or_default();
+                        let path_glob = auto_select.path_glob.unwrap_or_default();
+                        let format = auto_select.format.unwrap_or_default();
+                        let (glob_value, format_value) = (path_glob.clone(), format.clone());
+                        Some(view! {
+                            <label>"Name"<input type="text" prop:value=profile.name on:change=move |event| {
+                                set_state.update(|state| state.rename_profile(event_target_value(&event)));
+                            } /></label>
+                            <label>"Auto-select path glob"<input type="text" placeholder="access.log*" prop:value=path_glob on:change=move |event| {
+                                set_state.update(|state| state.set_profile_auto_select(&event_target_value(&event), &format_value));
+                            } /></label>
+                            <label>"Auto-select format"<input type="text" prop:value=format on:change=move |event| {
+                                set_state.update(|state| state.set_profile_auto_select(&glob_value, &event_target_value(&event)));
+                            } /></label>
+                            <button type="button" on:click=move |_| {
+                                set_state.update(VisualRulesEditorState::remove_profile);
+                                set_editor.set(None);
+                            }>"Delete profile"</button>
+                        })
+                    }}
+                </fieldset>
                 <button type="button" on:click=move |_| {
-                    let index = state.get_untracked().envelope().rules.len();
+                    let index = state.get_untracked().rules().len();
                     set_state.update(|state| state.add(new_rule()));
                     set_state.update(|state| state.open_editor(index));
                     set_editor.set(Some(index));
                 }>"Add"</button>
-                <ol>{move || state.get().envelope().rules.clone().into_iter().enumerate().map(|(index, rule)| view! {
+                <ol>{move || state.get().rules().to_vec().into_iter().enumerate().map(|(index, rule)| view! {
                     <li><button type="button" on:click=move |_| {
                         set_state.update(|state| state.open_editor(index));
                         set_editor.set(Some(index));
@@ -168,11 +482,67 @@ pub fn VisualRules(
                         <button type="button" on:click=move |_| set_state.update(|state| state.move_rule(index, 1))>"Move down"</button>
                         <button type="button" on:click=move |_| set_state.update(|state| state.remove(index))>"Remove"</button></li>
                 }).collect_view()}</ol>
+                <fieldset class="visual-rules-palette">
+                    <legend>"Palette"</legend>
+                    <ol>{move || {
+                        let palette = state.get().envelope().palette.clone();
+                        palette.iter().enumerate().map(|(index, color)| {
+                            let problem = palette_color_problem(&palette, index);
+                            let sample = problem.is_none().then(|| format!(
+                                "color: {}; background: {}",
+                                color.light.foreground, color.light.background
+                            ));
+                            view! {
+                                <li>
+                                    <label>"Name"<input type="text" title="Lowercase letters, digits and '-'" prop:value=color.name.clone() on:change=move |event| {
+                                        update_palette(index, PaletteField::Name, event_target_value(&event));
+                                    } /></label>
+                                    {PALETTE_SHADES.into_iter().map(|(field, label)| view! {
+                                        <label>{label}<input type="color" prop:value=palette_field(color, field).to_string() on:change=move |event| {
+                                            update_palette(index, field, event_target_value(&event));
+                                        } /></label>
+                                    }).collect_view()}
+                                    {sample.map(|style| view! { <span class="visual-rules-palette__sample" style=style>"Sample"</span> })}
+                                    <button type="button" on:click=move |_| set_state.update(|state| state.remove_palette_color(index))>"Remove"</button>
+                                    {problem.map(|message| view! { <p class="visual-rules-palette__problem">{message}</p> })}
+                                </li>
+                            }
+                        }).collect_view()
+                    }}</ol>
+                    <button type="button" on:click=move |_| set_state.update(VisualRulesEditorState::add_palette_color)>"Add color"</button>
+                </fieldset>
+                <fieldset class="visual-rules-transfer">
+                    <legend>"Import / export"</legend>
+                    <label>"Format"
+                        <select prop:value=move || transfer_format_key(transfer.get()) on:change=move |event| {
+                            set_transfer.set(transfer_format(&event_target_value(&event)));
+                            set_export.set(None);
+                        }>
+                            {TRANSFER_FORMATS.iter().map(|(_, key, label)| view! {
+                                <option value=*key>{*label}</option>
+                            }).collect_view()}
+                        </select>
+                    </label>
+                    <label>"Import file"<input type="file" on:change=import_file /></label>
+                    <button type="button" on:click=export_rules>"Export draft"</button>
+                    {move || export.get().map(|export| view! {
+                        <a download=export.file_name.clone() href=download_href(&export.content)>
+                            {format!("Download {}", export.file_name)}
+                        </a>
+                    })}
+                    <ul class="visual-rules-transfer__diagnostics">
+                        {move || transfer_diagnostics.get().iter().map(|diagnostic| view! {
+                            <li>{diagnostic_text(diagnostic)}</li>
+                        }).collect_view()}
+                    </ul>
+                </fieldset>
                 <footer><button type="button" on:click=discard>"Discard"</button><button type="button" on:click=reload>"Reload latest"</button><button type="button" disabled=move || !state.get().ordinary_save_allowed() on:click=move |_| persist(false)>"Save"</button><button type="button" on:click=move |_| persist(true)>"Replace"</button></footer>
             </aside>
             {move || editor.get().map(|index| {
-                let rule = state.get().envelope().rules.get(index).cloned().unwrap_or_else(new_rule);
-                view! { <VisualRuleEditor rule save=Callback::new(apply_editor) close=Callback::new(move |_| {
+                let current = state.get();
+                let rule = current.rules().get(index).cloned().unwrap_or_else(new_rule);
+                let custom_colors = current.envelope().palette.iter().map(|color| color.name.clone()).collect::<Vec<_>>();
+                view! { <VisualRuleEditor rule custom_colors save=Callback::new(apply_editor) close=Callback::new(move |_| {
                     set_state.update(VisualRulesEditorState::close_editor_with_escape);
                     set_editor.set(None);
                 }) /> }
@@ -186,6 +556,36 @@ mod tests {
     use super::*;
     use crate::visual_rules_state::VisualRulesFocusRequest;
 
+    #[test]
+    fn palette_fields_edit_one_shade_and_problems_name_the_failing_check() {
+        let mut state = VisualRulesEditorState::new(0, VisualRulesEnvelope::new(Vec::new()));
+        state.add_palette_color();
+        state.add_palette_color();
+        let mut palette = state.envelope().palette.clone();
+
+        assert_eq!(palette_color_problem(&palette, 0), None);
+        set_palette_field(
+            &mut palette[1],
+            PaletteField::DarkBackground,
+            "#6b7280".to_string(),
+        );
+        assert_eq!(
+            palette_field(&palette[1], PaletteField::DarkBackground),
+            "#6b7280"
+        );
+        assert_eq!(palette[1].dark.foreground, "#2dd4bf");
+        assert_eq!(
+            palette_color_problem(&palette, 1).as_deref(),
+            Some("dark background contrast 3.9:1 is below 4.5:1")
+        );
+        set_palette_field(&mut palette[1], PaletteField::Name, "custom-1".to_string());
+        assert_eq!(
+            palette_color_problem(&palette, 1).as_deref(),
+            Some("is defined more than once")
+        );
+        assert_eq!(palette_color_problem(&palette, 2), None);
+    }
+
     #[test]
     fn drawer_escape_is_handled_locally_and_restores_the_invoker_focus() {
         assert!(drawer_should_handle_escape("Escape"));
@@ -200,6 +600,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn file_profile_switcher_names_the_auto_selected_profile_when_not_pinned() {
+        let mut status = VisualProfileStatus {
+            active: "Access logs".to_string(),
+            pinned: None,
+            auto_selected: Some("Access logs".to_string()),
+            profiles: vec!["Default".to_string(), "Access logs".to_string()],
+        };
+
+        assert_eq!(automatic_profile_label(&status), "Automatic (Access logs)");
+        assert_eq!(pinned_profile(""), None);
+        assert_eq!(
+            pinned_profile("Access logs").as_deref(),
+            Some("Access logs")
+        );
+
+        status.pinned = Some("Access logs".to_string());
+        assert_eq!(automatic_profile_label(&status), "Automatic");
+    }
+
+    #[test]
+    fn transfer_helpers_round_trip_formats_and_describe_imports() {
+        for (format, key, _) in TRANSFER_FORMATS {
+            assert_eq!(transfer_format(transfer_format_key(format)), format);
+            assert_eq!(transfer_format_key(transfer_format(key)), key);
+        }
+        assert_eq!(
+            download_href("LESS = +/a b\n"),
+            "data:text/plain;charset=utf-8,LESS%20%3D%20%2B%2Fa%20b%0A"
+        );
+        assert_eq!(
+            diagnostic_text(&ValidationDiagnostic {
+                severity: ValidationSeverity::Error,
+                message: "rule 2 skipped: invalid regex".to_string(),
+            }),
+            "Error: rule 2 skipped: invalid regex"
+        );
+
+        let mut envelope = VisualRulesEnvelope::new(vec![new_rule()]);
+        assert_eq!(
+            imported_status(&envelope),
+            "Imported 1 rule; review and save to apply."
+        );
+        envelope.profiles.push(logmancer_core::VisualRuleProfile {
+            name: "Access logs".to_string(),
+            rules: vec![new_rule()],
+            auto_select: None,
+        });
+        assert_eq!(
+            imported_status(&envelope),
+            "Imported 2 rules in 2 profiles; review and save to apply."
+        );
+    }
+
     #[test]
     fn page_refresh_is_notified_only_for_an_accepted_save_response() {
         let mut notifications = 0;
@@ -30,7 +30,8 @@ fn visual_rules_path(
 mod tests {
     use super::{visual_rules_path, visual_rules_runtime, visual_rules_runtime_with_initial_file};
     use logmancer_core::{
-        LineStyleIntent, ManagedVisualRule, VisualColor, VisualMatcher, VisualRulesEnvelope,
+        LineStyleIntent, ManagedVisualRule, VisualColor, VisualMatcher, VisualRuleScope,
+        VisualRulesEnvelope,
     };
     use std::path::PathBuf;
 
@@ -71,9 +72,11 @@ mod tests {
                     enabled: true,
                     matcher: VisualMatcher::Text("ERROR".to_string()),
                     case_sensitive: true,
+                    scope: VisualRuleScope::Line,
                     style: LineStyleIntent {
                         foreground: Some(VisualColor("red".to_string())),
                         background: None,
+                        ..LineStyleIntent::default()
                     },
                 }]),
             )
@@ -126,6 +129,10 @@ mod tests {
     }
 }
 
+/// How often the server checks the visual rules file for hand edits.
+#[cfg(feature = "ssr")]
+const VISUAL_RULES_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
 #[cfg(feature = "ssr")]
 pub fn visual_rules_runtime(
     path: std::path::PathBuf,
@@ -133,7 +140,9 @@ pub fn visual_rules_runtime(
     std::sync::Arc<logmancer_core::LogRegistry>,
     std::sync::Arc<logmancer_core::VisualRulesManager>,
 ) {
-    use logmancer_core::{LogRegistry, NativeVisualRulesStore, VisualRulesManager};
+    use logmancer_core::{
+        LogFormatManager, LogRegistry, NativeVisualRulesStore, VisualRulesManager,
+    };
     use std::sync::Arc;
     use tracing::warn;
 
@@ -150,8 +159,15 @@ pub fn visual_rules_runtime(
     if let Err(error) = manager.load() {
         warn!(path = %path.display(), %error, "Could not load optional visual rules configuration");
     }
+    manager.watch(VISUAL_RULES_WATCH_INTERVAL);
+    let formats_path = path.with_file_name("log-formats.json");
+    let log_formats =
+        LogFormatManager::with_store(Arc::new(NativeVisualRulesStore::new(formats_path.clone())));
+    if let Err(error) = log_formats.load() {
+        warn!(path = %formats_path.display(), %error, "Could not load optional log formats configuration");
+    }
     (
-        Arc::new(LogRegistry::with_manager(manager.clone())),
+        Arc::new(LogRegistry::with_managers(manager.clone(), log_formats)),
         manager,
     )
 }
@@ -1,4 +1,7 @@
-use logmancer_core::{ManagedVisualRule, VisualRulesEnvelope};
+use logmancer_core::{
+    ManagedVisualRule, PaletteColor, PaletteVariant, ProfileAutoSelect, VisualRuleProfile,
+    VisualRulesEnvelope,
+};
 
 #[derive(Clone, Debug)]
 pub struct VisualRulesEditorState {
@@ -8,6 +11,8 @@ pub struct VisualRulesEditorState {
     status: String,
     drawer_is_open: bool,
     editor_index: Option<usize>,
+    /// Profile whose rules the drawer edits; `None` edits the default rules.
+    profile_index: Option<usize>,
     focus_target: VisualRulesFocusTarget,
     loaded_from_server: bool,
     operation_generation: u64,
@@ -35,6 +40,7 @@ impl VisualRulesEditorState {
             status: String::new(),
             drawer_is_open: false,
             editor_index: None,
+            profile_index: None,
             focus_target: VisualRulesFocusTarget::Invoker,
             loaded_from_server: false,
             operation_generation: 0,
@@ -109,6 +115,7 @@ impl VisualRulesEditorState {
         self.baseline = envelope.clone();
         if !has_local_edits {
             self.envelope = envelope;
+            self.keep_profile_in_range();
         } else {
             self.requires_replace = true;
             self.status = "Draft preserved; use Replace or Discard before saving.".to_string();
@@ -132,6 +139,7 @@ impl VisualRulesEditorState {
         self.baseline = envelope.clone();
         if !has_local_edits {
             self.envelope = envelope;
+            self.keep_profile_in_range();
         }
         self.requires_replace = has_local_edits;
         self.status = message.into();
@@ -143,26 +151,174 @@ impl VisualRulesEditorState {
         true
     }
 
+    /// Follows rules published outside the drawer, such as a hand edit of the rules file,
+    /// without an operation of its own. Revisions the drawer already has are ignored, and a
+    /// draft that differs from both the old and the new rules is kept for Replace or Discard.
+    pub fn saved_elsewhere(
+        &mut self,
+        revision: u64,
+        envelope: VisualRulesEnvelope,
+        message: impl Into<String>,
+    ) -> bool {
+        if !self.loaded_from_server || revision <= self.baseline_revision {
+            return false;
+        }
+        let has_local_edits = self.envelope != self.baseline && self.envelope != envelope;
+        self.baseline_revision = revision;
+        self.baseline = envelope.clone();
+        if !has_local_edits {
+            self.envelope = envelope;
+            self.keep_profile_in_range();
+        }
+        self.requires_replace = has_local_edits;
+        self.status = message.into();
+        if has_local_edits {
+            self.status
+                .push_str(" Draft preserved; use Replace or Discard before saving.");
+        }
+        true
+    }
+
+    pub fn profile_index(&self) -> Option<usize> {
+        self.profile_index
+    }
+
+    /// Rules of the profile being edited.
+    pub fn rules(&self) -> &[ManagedVisualRule] {
+        match self
+            .profile_index
+            .and_then(|index| self.envelope.profiles.get(index))
+        {
+            Some(profile) => &profile.rules,
+            None => &self.envelope.rules,
+        }
+    }
+
+    fn rules_mut(&mut self) -> &mut Vec<ManagedVisualRule> {
+        match self.profile_index {
+            Some(index) if index < self.envelope.profiles.len() => {
+                &mut self.envelope.profiles[index].rules
+            }
+            _ => &mut self.envelope.rules,
+        }
+    }
+
+    pub fn select_profile(&mut self, index: Option<usize>) {
+        self.profile_index = index.filter(|index| *index < self.envelope.profiles.len());
+        self.editor_index = None;
+    }
+
+    fn keep_profile_in_range(&mut self) {
+        self.profile_index = self
+            .profile_index
+            .filter(|index| *index < self.envelope.profiles.len());
+    }
+
+    pub fn add_profile(&mut self, name: impl Into<String>) {
+        self.envelope.profiles.push(VisualRuleProfile {
+            name: name.into(),
+            rules: Vec::new(),
+            auto_select: None,
+        });
+        self.select_profile(Some(self.envelope.profiles.len() - 1));
+    }
+
+    pub fn remove_profile(&mut self) {
+        if let Some(index) = self
+            .profile_index
+            .filter(|index| *index < self.envelope.profiles.len())
+        {
+            self.envelope.profiles.remove(index);
+        }
+        self.select_profile(None);
+    }
+
+    pub fn rename_profile(&mut self, name: impl Into<String>) {
+        if let Some(profile) = self
+            .profile_index
+            .and_then(|index| self.envelope.profiles.get_mut(index))
+        {
+            profile.name = name.into();
+        }
+    }
+
+    /// Blank inputs clear the corresponding criterion; clearing both disables auto-selection.
+    pub fn set_profile_auto_select(&mut self, path_glob: &str, format: &str) {
+        let criterion = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        let auto_select = ProfileAutoSelect {
+            path_glob: criterion(path_glob),
+            format: criterion(format),
+        };
+        if let Some(profile) = self
+            .profile_index
+            .and_then(|index| self.envelope.profiles.get_mut(index))
+        {
+            profile.auto_select =
+                (auto_select != ProfileAutoSelect::default()).then_some(auto_select);
+        }
+    }
+
+    /// Replaces the draft with converted rules; they are only persisted by a later save.
+    pub fn import_draft(&mut self, envelope: VisualRulesEnvelope, message: impl Into<String>) {
+        self.envelope = envelope;
+        self.profile_index = None;
+        self.editor_index = None;
+        self.status = message.into();
+    }
+
+    /// Adds a custom color under the first free `custom-N` name, starting from readable teal
+    /// shades.
+    pub fn add_palette_color(&mut self) {
+        let palette = &mut self.envelope.palette;
+        let name = (1..)
+            .map(|index| format!("custom-{index}"))
+            .find(|name| palette.iter().all(|color| color.name != *name))
+            .expect("a free palette name");
+        let variant = |foreground: &str, background: &str| PaletteVariant {
+            foreground: foreground.to_string(),
+            background: background.to_string(),
+        };
+        palette.push(PaletteColor {
+            name,
+            light: variant("#0f766e", "#ccfbf1"),
+            dark: variant("#2dd4bf", "#134e4a"),
+        });
+    }
+
+    pub fn replace_palette_color(&mut self, index: usize, color: PaletteColor) {
+        if let Some(existing) = self.envelope.palette.get_mut(index) {
+            *existing = color;
+        }
+    }
+
+    pub fn remove_palette_color(&mut self, index: usize) {
+        if index < self.envelope.palette.len() {
+            self.envelope.palette.remove(index);
+        }
+    }
+
     pub fn add(&mut self, rule: ManagedVisualRule) {
-        self.envelope.rules.push(rule);
+        self.rules_mut().push(rule);
     }
 
     pub fn replace_rule(&mut self, index: usize, rule: ManagedVisualRule) {
-        if let Some(existing) = self.envelope.rules.get_mut(index) {
+        if let Some(existing) = self.rules_mut().get_mut(index) {
             *existing = rule;
         }
     }
 
     pub fn remove(&mut self, index: usize) {
-        if index < self.envelope.rules.len() {
-            self.envelope.rules.remove(index);
+        let rules = self.rules_mut();
+        if index < rules.len() {
+            rules.remove(index);
         }
     }
 
     pub fn move_rule(&mut self, index: usize, direction: isize) {
+        let rules = self.rules_mut();
         let target = index.checked_add_signed(direction);
-        if let Some(target) = target.filter(|target| *target < self.envelope.rules.len()) {
-            self.envelope.rules.swap(index, target);
+        if let Some(target) = target.filter(|target| *target < rules.len()) {
+            rules.swap(index, target);
         }
     }
 
@@ -171,6 +327,7 @@ impl VisualRulesEditorState {
     pub fn discard(&mut self) {
         self.begin_operation();
         self.envelope = self.baseline.clone();
+        self.keep_profile_in_range();
         self.requires_replace = false;
         self.status = "Discarded unsaved visual rule changes.".to_string();
     }
@@ -204,6 +361,7 @@ impl VisualRulesEditorState {
         self.baseline = envelope.clone();
         if !has_later_edits {
             self.envelope = envelope;
+            self.keep_profile_in_range();
         }
         self.status = message.into();
         self.loaded_from_server = true;
@@ -225,7 +383,8 @@ pub fn operation_status(success: &str, diagnostics: &[String]) -> String {
 mod tests {
     use super::*;
     use logmancer_core::{
-        LineStyleIntent, ManagedVisualRule, VisualColor, VisualMatcher, VisualRulesEnvelope,
+        LineStyleIntent, ManagedVisualRule, VisualColor, VisualMatcher, VisualRuleScope,
+        VisualRulesEnvelope,
     };
 
     fn rule(name: &str, pattern: &str) -> ManagedVisualRule {
@@ -234,9 +393,11 @@ mod tests {
             enabled: true,
             matcher: VisualMatcher::Text(pattern.to_string()),
             case_sensitive: false,
+            scope: VisualRuleScope::Line,
             style: LineStyleIntent {
                 foreground: Some(VisualColor("red".to_string())),
                 background: None,
+                ..LineStyleIntent::default()
             },
         }
     }
@@ -277,6 +438,30 @@ mod tests {
         assert_eq!(state.envelope().rules, saved.rules);
     }
 
+    #[test]
+    fn palette_edits_pick_free_names_and_stay_in_the_draft_until_discarded() {
+        let mut state = VisualRulesEditorState::new(1, VisualRulesEnvelope::new(Vec::new()));
+        state.add_palette_color();
+        state.add_palette_color();
+        let mut renamed = state.envelope().palette[0].clone();
+        renamed.name = "teal".to_string();
+        state.replace_palette_color(0, renamed);
+        state.add_palette_color();
+
+        let names = state
+            .envelope()
+            .palette
+            .iter()
+            .map(|color| color.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["teal", "custom-2", "custom-1"]);
+
+        state.remove_palette_color(1);
+        assert_eq!(state.envelope().palette.len(), 2);
+        state.discard();
+        assert!(state.envelope().palette.is_empty());
+    }
+
     #[test]
     fn drawer_and_editor_escape_preserve_local_edits_and_restore_invoker() {
         let baseline = VisualRulesEnvelope::new(vec![rule("Errors", "ERROR")]);
@@ -373,6 +558,36 @@ mod tests {
         assert!(state.ordinary_save_allowed());
     }
 
+    #[test]
+    fn outside_changes_follow_the_baseline_and_keep_unsaved_drafts() {
+        let mut state = VisualRulesEditorState::new(0, VisualRulesEnvelope::new(Vec::new()));
+        let edited = VisualRulesEnvelope::new(vec![rule("Edited", "EDITED")]);
+        assert!(!state.saved_elsewhere(3, edited.clone(), "Changed."));
+        state.load_saved_once(2, VisualRulesEnvelope::new(Vec::new()));
+
+        assert!(state.saved_elsewhere(3, edited.clone(), "Changed."));
+        assert_eq!(state.envelope().rules, edited.rules);
+        assert!(state.ordinary_save_allowed());
+        assert!(!state.saved_elsewhere(3, edited.clone(), "Changed."));
+
+        state.add(rule("Draft", "DRAFT"));
+        let submitted = state.envelope().clone();
+        let save = state.begin_operation();
+        assert!(state.saved_elsewhere(4, submitted.clone(), "Changed."));
+        assert!(state.ordinary_save_allowed());
+        assert!(state.save_succeeded_for(save, 4, submitted, "Saved visual rules."));
+
+        state.add(rule("Later", "LATER"));
+        let latest = VisualRulesEnvelope::new(vec![rule("Latest", "LATEST")]);
+        assert!(state.saved_elsewhere(5, latest.clone(), "Changed."));
+        assert_eq!(state.revision(), 5);
+        assert_eq!(state.rules().len(), 3);
+        assert!(!state.ordinary_save_allowed());
+        assert!(state.status().contains("Replace or Discard"));
+        state.discard();
+        assert_eq!(state.envelope().rules, latest.rules);
+    }
+
     #[test]
     fn older_save_response_cannot_rollback_a_newer_reload() {
         let mut state = VisualRulesEditorState::new(1, VisualRulesEnvelope::new(Vec::new()));
@@ -387,6 +602,75 @@ mod tests {
         assert!(!state.ordinary_save_allowed());
     }
 
+    #[test]
+    fn profile_selection_scopes_rule_edits_and_discard_drops_unsaved_profiles() {
+        let baseline = VisualRulesEnvelope::new(vec![rule("Errors", "ERROR")]);
+        let mut state = VisualRulesEditorState::new(1, baseline);
+
+        state.add_profile("Access logs");
+        state.add(rule("Server errors", " 500 "));
+        state.set_profile_auto_select(" access.log* ", "");
+        state.move_rule(0, 1);
+
+        assert_eq!(state.profile_index(), Some(0));
+        assert_eq!(state.rules().len(), 1);
+        assert_eq!(state.envelope().rules.len(), 1);
+        let profile = &state.envelope().profiles[0];
+        assert_eq!(profile.rules[0].name.as_deref(), Some("Server errors"));
+        assert_eq!(
+            profile
+                .auto_select
+                .as_ref()
+                .and_then(|auto| auto.path_glob.as_deref()),
+            Some("access.log*")
+        );
+
+        state.set_profile_auto_select("", " ");
+        assert_eq!(state.envelope().profiles[0].auto_select, None);
+
+        state.select_profile(None);
+        assert_eq!(state.rules()[0].name.as_deref(), Some("Errors"));
+
+        state.select_profile(Some(0));
+        state.discard();
+
+        assert_eq!(state.profile_index(), None);
+        assert!(state.envelope().profiles.is_empty());
+    }
+
+    #[test]
+    fn removing_a_profile_returns_the_editor_to_the_default_rules() {
+        let mut state = VisualRulesEditorState::new(0, VisualRulesEnvelope::new(Vec::new()));
+        state.add_profile("Draft");
+        state.rename_profile("Access logs");
+        assert_eq!(state.envelope().profiles[0].name, "Access logs");
+
+        state.remove_profile();
+
+        assert_eq!(state.profile_index(), None);
+        assert!(state.envelope().profiles.is_empty());
+    }
+
+    #[test]
+    fn imported_rules_replace_the_draft_until_discarded() {
+        let baseline = VisualRulesEnvelope::new(vec![rule("Errors", "ERROR")]);
+        let mut state = VisualRulesEditorState::new(3, baseline.clone());
+        state.add_profile("Access logs");
+
+        state.import_draft(
+            VisualRulesEnvelope::new(vec![rule("Imported", "WARN")]),
+            "Imported 1 rule.",
+        );
+
+        assert_eq!(state.profile_index(), None);
+        assert_eq!(state.rules()[0].name.as_deref(), Some("Imported"));
+        assert_eq!(state.status(), "Imported 1 rule.");
+        assert!(state.ordinary_save_allowed());
+
+        state.discard();
+        assert_eq!(state.envelope(), &baseline);
+    }
+
     #[test]
     fn operation_status_surfaces_persistence_diagnostics() {
         assert_eq!(